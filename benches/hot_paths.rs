@@ -0,0 +1,183 @@
+//! Benchmarks for the clustering, R-tree, merging, and database query code.
+//!
+//! Run with `cargo bench --features testutil`.
+
+use chrono::{DateTime, Duration, Utc};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use satfire::{
+    testutil::{random_fires, PixelGrid, SyntheticRng, SyntheticScan},
+    BoundingBox, ClusterDatabase, Connectivity, Coord, FireList, FireListView, Satellite, Sector,
+};
+
+const SEED: u64 = 20210801;
+
+fn scan_start() -> DateTime<Utc> {
+    "2021-08-01T12:00:00Z".parse().unwrap()
+}
+
+fn conus() -> BoundingBox {
+    BoundingBox {
+        ll: Coord {
+            lat: 25.0,
+            lon: -125.0,
+        },
+        ur: Coord {
+            lat: 50.0,
+            lon: -65.0,
+        },
+    }
+}
+
+fn bench_clustering(c: &mut Criterion) {
+    // About the size of a full disk image with a busy day's worth of fire detections.
+    let grid = PixelGrid::new(
+        Coord {
+            lat: 80.0,
+            lon: -170.0,
+        },
+        0.02,
+    );
+    let scan = SyntheticScan::random(grid, 5_424, 5_424, 2.0e-4, &mut SyntheticRng::new(SEED));
+    let start = scan_start();
+
+    c.bench_function("cluster full disk", |b| {
+        b.iter_batched(
+            || scan.clone(),
+            |scan| {
+                scan.into_cluster_list(
+                    Satellite::G17,
+                    Sector::FULL,
+                    start,
+                    start + Duration::minutes(10),
+                    Connectivity::Eight,
+                )
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_clustering_dense(c: &mut Criterion) {
+    // About 5,000 fire pixels packed into a small area, so the clusters are large.
+    let grid = PixelGrid::new(
+        Coord {
+            lat: 40.0,
+            lon: -120.0,
+        },
+        0.02,
+    );
+    let scan = SyntheticScan::random(grid, 120, 120, 0.42, &mut SyntheticRng::new(SEED));
+    let start = scan_start();
+
+    c.bench_function("cluster 5,000 dense points", |b| {
+        b.iter_batched(
+            || scan.clone(),
+            |scan| {
+                scan.into_cluster_list(
+                    Satellite::G17,
+                    Sector::CONUS,
+                    start,
+                    start + Duration::minutes(5),
+                    Connectivity::Eight,
+                )
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_hilbert_view(c: &mut Criterion) {
+    let make_fires = || {
+        random_fires(
+            conus(),
+            10_000,
+            3,
+            0.02,
+            0.0,
+            scan_start(),
+            &mut SyntheticRng::new(SEED),
+        )
+    };
+
+    c.bench_function("build view of 10,000 fires", |b| {
+        b.iter_batched(
+            make_fires,
+            |mut fires| black_box(FireListView::new(&mut fires).is_some()),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_merge_fires(c: &mut Criterion) {
+    let make_fires = || {
+        random_fires(
+            conus(),
+            10_000,
+            3,
+            0.02,
+            0.25,
+            scan_start(),
+            &mut SyntheticRng::new(SEED),
+        )
+    };
+
+    c.bench_function("merge 10,000 fires", |b| {
+        b.iter_batched(
+            make_fires,
+            |mut fires| {
+                let mut merged_away = FireList::new();
+                fires.merge_fires(&mut merged_away)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_query_clusters(c: &mut Criterion) {
+    // Two weeks of CONUS scans every 5 minutes.
+    let grid = PixelGrid::new(
+        Coord {
+            lat: 50.0,
+            lon: -125.0,
+        },
+        0.02,
+    );
+    let mut rng = SyntheticRng::new(SEED);
+    let db = ClusterDatabase::connect(":memory:").unwrap();
+    {
+        let mut add = db.prepare_to_add_clusters().unwrap();
+        for i in 0..(14 * 24 * 12) {
+            let start = scan_start() + Duration::minutes(5 * i);
+            let scan = SyntheticScan::random(grid, 3_000, 1_250, 2.0e-5, &mut rng);
+            let clusters = scan.into_cluster_list(
+                Satellite::G17,
+                Sector::CONUS,
+                start,
+                start + Duration::minutes(5),
+                Connectivity::Eight,
+            );
+            add.add(clusters).unwrap();
+        }
+    }
+
+    let start = scan_start() + Duration::days(7);
+    let end = start + Duration::hours(6);
+
+    c.bench_function("query 6 hours of clusters from 2 weeks", |b| {
+        b.iter(|| {
+            let mut query = db.query_clusters(None, None, start, end, conus()).unwrap();
+            let rows = query.rows().unwrap();
+            black_box(rows.count())
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_clustering,
+    bench_clustering_dense,
+    bench_hilbert_view,
+    bench_merge_fires,
+    bench_query_clusters
+);
+criterion_main!(benches);
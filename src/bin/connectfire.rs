@@ -3,8 +3,9 @@ use clap::Parser;
 use crossbeam_channel::{bounded, Receiver, Sender};
 use log::{error, info, warn};
 use satfire::{
-    BoundingBox, ClusterDatabase, Coord, Fire, FireList, FireListUpdateResult, FireListView,
-    FiresDatabase, SatFireResult, Satellite,
+    group_by_scan, parse_time_zone, BoundingBox, CentroidMethod, ClusterDatabase, Coord,
+    ExclusionZones, Fire, FireList, FireListUpdateResult, FireListView, FiresDatabase,
+    OutputTimeZone, SatFireConfig, SatFireResult, Satellite, Sector, StalenessConfig,
 };
 use simple_logger::SimpleLogger;
 use std::{
@@ -38,7 +39,14 @@ static SHUT_DOWN: AtomicBool = AtomicBool::new(false);
 #[derive(Debug, Parser)]
 #[clap(bin_name = "connectfire")]
 #[clap(author, version, about)]
-struct ConnectFireOptions {
+struct ConnectFireOptionsInit {
+    /// The path to a TOML configuration file.
+    ///
+    /// The cluster_store_file, fires_store_file, and region settings are used from the file.
+    /// Command line arguments and environment variables override the values in the file.
+    #[clap(long)]
+    config: Option<PathBuf>,
+
     /// The start date, do not try to connect clusters before this date.
     #[clap(short, long)]
     #[clap(parse(try_from_str=parse_datetime))]
@@ -50,31 +58,151 @@ struct ConnectFireOptions {
     end: Option<DateTime<Utc>>,
 
     /// Bounding Box where as bottom_lat,left_lon,top_lat,right_lon
+    ///
+    /// A left_lon greater than right_lon wraps around the antimeridian. If this is not specified,
+    /// then the region from the configuration file is used, and if that isn't available the whole
+    /// globe.
     #[clap(parse(try_from_str=parse_bbox))]
-    #[clap(default_value_t=BoundingBox{ll:Coord{lat: -90.0, lon: -180.0}, ur:Coord{lat: 90.0, lon: 180.0}})]
-    bbox: BoundingBox,
+    bbox: Option<BoundingBox>,
 
     /// The path to the database file with the clusters.
     ///
     /// If this is not specified, then the program will check for it in the "CLUSTER_DB"
-    /// environment variable.
+    /// environment variable, and then the configuration file.
     #[clap(short, long)]
     #[clap(env = "CLUSTER_DB")]
-    clusters_store_file: PathBuf,
+    clusters_store_file: Option<PathBuf>,
 
     /// The path to the database file with the fires and associations.
     ///
     /// If this is not specified, then the program will check for it in the "FIRES_DB"
-    /// environment variable.
+    /// environment variable, and then the configuration file.
     #[clap(short, long)]
     #[clap(env = "FIRES_DB")]
-    fires_store_file: PathBuf,
+    fires_store_file: Option<PathBuf>,
+
+    /// How to calculate the fire centroid stored in the database, "uniform" or "power".
+    ///
+    /// The stored centroid is used for spatial queries of the fires. A power weighted centroid
+    /// better represents the most active part of a fire.
+    #[clap(long)]
+    #[clap(parse(try_from_str=parse_centroid_method))]
+    #[clap(default_value = "uniform")]
+    centroid: CentroidMethod,
+
+    /// The path to a file listing zones around known persistent heat sources.
+    ///
+    /// Clusters with a centroid in one of these zones are not connected into fires. Each line of
+    /// the file is a zone as "lat, lon, radius" with the radius in meters, and anything after a
+    /// "#" is a comment.
+    #[clap(long)]
+    exclusion_zones: Option<PathBuf>,
+
+    /// The path to a file of named regions used to label the fires.
+    ///
+    /// Each stored fire is labeled with the name of the first region containing its centroid, and
+    /// fires outside all of them are left unlabeled. Each line of the file is a region as
+    /// "name, min_lat, min_lon, max_lat, max_lon", and anything after a "#" is a comment.
+    #[clap(long)]
+    regions: Option<PathBuf>,
+
+    /// The maximum lifetime of a fire in days.
+    ///
+    /// A fire first observed this long ago is closed even if it is still burning, and any later
+    /// clusters at the same location start a new fire. This keeps a fixed heat source from looking
+    /// like a single fire that burned for months. Fires are checked once an hour, so they may
+    /// exceed this by up to an hour. By default there is no limit.
+    #[clap(long)]
+    max_fire_lifetime: Option<u32>,
+
+    /// The maximum number of days a fire can go without being observed before it is closed.
+    ///
+    /// Slow burning fires, like peat fires, may go undetected for long periods. The default is 30
+    /// days.
+    #[clap(long)]
+    max_quiet_days: Option<u32>,
+
+    /// Fires with at least this many pixels are considered noise and closed.
+    ///
+    /// The default of 1000 is tuned for the CONUS sector.
+    #[clap(long)]
+    noise_pixel_cap: Option<usize>,
+
+    /// A fire that hasn't been observed for more than a day is closed once it has been quiet for
+    /// longer than it burned multiplied by this factor.
+    ///
+    /// Larger values keep fires around longer. The default is 1.
+    #[clap(long)]
+    quiet_burn_factor: Option<f64>,
+
+    /// Show times in reports in this time zone, like "America/Denver", or at a fixed offset from
+    /// UTC, like "-06" or "+05:30".
+    ///
+    /// Times are always stored in UTC, this only changes how they are displayed. Named zones
+    /// follow daylight saving time.
+    #[clap(long)]
+    #[clap(parse(try_from_str=parse_time_zone))]
+    time_zone: Option<OutputTimeZone>,
+
+    /// Connect the fires and print the summary stats without changing the fires database.
+    ///
+    /// This is useful for trying out the parameters on a new region before storing the results.
+    #[clap(long)]
+    dry_run: bool,
 
     /// Verbose output
     #[clap(short, long)]
     verbose: bool,
 }
 
+#[derive(Debug)]
+struct ConnectFireOptionsChecked {
+    /// The start date, do not try to connect clusters before this date.
+    start: Option<DateTime<Utc>>,
+
+    /// The end date, do not try to connect clusters after this date.
+    end: Option<DateTime<Utc>>,
+
+    /// Only connect clusters in this region.
+    bbox: BoundingBox,
+
+    /// The path to the database file with the clusters.
+    clusters_store_file: PathBuf,
+
+    /// The path to the database file with the fires and associations.
+    fires_store_file: PathBuf,
+
+    /// How to calculate the fire centroid stored in the database.
+    centroid: CentroidMethod,
+
+    /// Skip clusters in these zones.
+    exclusion_zones: ExclusionZones,
+
+    /// Label the fires with the name of the first of these regions that contains them.
+    regions: Vec<(String, BoundingBox)>,
+
+    /// The maximum lifetime of a fire.
+    max_fire_lifetime: Option<Duration>,
+
+    /// The maximum time a fire can go without being observed.
+    max_quiet: Option<Duration>,
+
+    /// Fires with at least this many pixels are noise.
+    noise_pixel_cap: Option<usize>,
+
+    /// The factor applied to the duration of a fire when comparing it to its quiet time.
+    quiet_burn_factor: Option<f64>,
+
+    /// The time zone used to display times.
+    time_zone: OutputTimeZone,
+
+    /// Don't change the fires database.
+    dry_run: bool,
+
+    /// Verbose output
+    verbose: bool,
+}
+
 /// Parse a bounding box argument.
 fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
     let corners: Vec<_> = bbox_str.split(',').collect();
@@ -88,10 +216,11 @@ fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
     let max_lat = corners[2].parse()?;
     let max_lon = corners[3].parse()?;
 
-    if min_lat >= max_lat || min_lon >= max_lon {
+    // A box with min_lon greater than max_lon wraps around the antimeridian.
+    if min_lat >= max_lat || min_lon == max_lon {
         return Err(format!(
             concat!(
-                "Minimum Lat/Lon must be less than Maximum Lat/Lon:",
+                "Minimum Lat must be less than Maximum Lat and the Lons must differ:",
                 " min_lat={} max_lat={} min_lon={} max_lon={}"
             ),
             min_lat, max_lat, min_lon, max_lon
@@ -99,7 +228,12 @@ fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
         .into());
     }
 
-    if min_lat < -90.0 || max_lat > 90.0 || min_lon < -180.0 || max_lon > 180.0 {
+    let lon_range = -180.0..=180.0;
+    if min_lat < -90.0
+        || max_lat > 90.0
+        || !lon_range.contains(&min_lon)
+        || !lon_range.contains(&max_lon)
+    {
         return Err(format!(
             concat!(
                 "Lat/Lon are out of range (-90.0 to 90.0 and -180.0 to 180.0):",
@@ -122,6 +256,42 @@ fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
     Ok(BoundingBox { ll, ur })
 }
 
+/// Parse a list of named regions, one "name, min_lat, min_lon, max_lat, max_lon" per line.
+fn parse_regions(text: &str) -> SatFireResult<Vec<(String, BoundingBox)>> {
+    let mut regions = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (name, bbox) = line
+            .split_once(',')
+            .ok_or_else(|| format!("line {}: expected a name and 4 coordinates", i + 1))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("line {}: missing region name", i + 1).into());
+        }
+
+        let bbox_str: String = bbox.chars().filter(|c| !c.is_whitespace()).collect();
+        let bbox = parse_bbox(&bbox_str).map_err(|err| format!("line {}: {}", i + 1, err))?;
+
+        regions.push((name.to_owned(), bbox));
+    }
+
+    Ok(regions)
+}
+
+/// Parse a centroid method argument.
+fn parse_centroid_method(method: &str) -> SatFireResult<CentroidMethod> {
+    match method {
+        "uniform" => Ok(CentroidMethod::Uniform),
+        "power" => Ok(CentroidMethod::PowerWeighted),
+        _ => Err(format!("Centroid method must be uniform or power: {}", method).into()),
+    }
+}
+
 /// Parse a command line datetime
 fn parse_datetime(dt_str: &str) -> SatFireResult<DateTime<Utc>> {
     const TIME_FORMAT: &str = "%Y-%m-%d-%H:%M:%S";
@@ -131,7 +301,7 @@ fn parse_datetime(dt_str: &str) -> SatFireResult<DateTime<Utc>> {
     Ok(DateTime::from_utc(naive, Utc))
 }
 
-impl Display for ConnectFireOptions {
+impl Display for ConnectFireOptionsChecked {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         writeln!(f, "\n")?; // yes, two blank lines.
         writeln!(
@@ -140,17 +310,60 @@ impl Display for ConnectFireOptions {
             self.clusters_store_file.display()
         )?;
         writeln!(f, "  Fires Database: {}", self.fires_store_file.display())?;
+        writeln!(f, "  Fire Centroids: {:?}", self.centroid)?;
+        writeln!(f, "    Bounding Box: {}", self.bbox)?;
+        writeln!(f, " Exclusion Zones: {}", self.exclusion_zones.len())?;
+        writeln!(f, "         Regions: {}", self.regions.len())?;
+        if let Some(max_fire_lifetime) = self.max_fire_lifetime {
+            writeln!(f, "   Max Lifetime : {} days", max_fire_lifetime.num_days())?;
+        }
+        if let Some(max_quiet) = self.max_quiet {
+            writeln!(f, "  Max Quiet Time: {} days", max_quiet.num_days())?;
+        }
+        if let Some(noise_pixel_cap) = self.noise_pixel_cap {
+            writeln!(f, " Noise Pixel Cap: {}", noise_pixel_cap)?;
+        }
+        if let Some(quiet_burn_factor) = self.quiet_burn_factor {
+            writeln!(f, "    Burn Factor : {}", quiet_burn_factor)?;
+        }
+        writeln!(f, "       Time Zone: {}", self.time_zone)?;
+        if self.dry_run {
+            writeln!(
+                f,
+                "         Dry Run: the fires database will not be changed"
+            )?;
+        }
         writeln!(f, "\n")?; // yes, two blank lines.
 
         Ok(())
     }
 }
 
+impl ConnectFireOptionsChecked {
+    /// The thresholds used to decide when a fire is no longer burning.
+    fn staleness(&self) -> StalenessConfig {
+        let mut staleness = StalenessConfig::default();
+        if let Some(max_fire_lifetime) = self.max_fire_lifetime {
+            staleness = staleness.max_lifetime(max_fire_lifetime);
+        }
+        if let Some(max_quiet) = self.max_quiet {
+            staleness = staleness.max_quiet(max_quiet);
+        }
+        if let Some(noise_pixel_cap) = self.noise_pixel_cap {
+            staleness = staleness.noise_pixel_cap(noise_pixel_cap);
+        }
+        if let Some(quiet_burn_factor) = self.quiet_burn_factor {
+            staleness = staleness.quiet_burn_factor(quiet_burn_factor);
+        }
+        staleness
+    }
+}
+
 /// Get the command line arguments and check them.
 ///
 /// If there is missing data, try to fill it in with environment variables.
-fn parse_args() -> SatFireResult<ConnectFireOptions> {
-    let opts = ConnectFireOptions::parse();
+fn parse_args() -> SatFireResult<ConnectFireOptionsChecked> {
+    let opts = check_args(ConnectFireOptionsInit::parse())?;
 
     if opts.verbose {
         info!(target:"startup", "{}", opts);
@@ -159,6 +372,95 @@ fn parse_args() -> SatFireResult<ConnectFireOptions> {
     Ok(opts)
 }
 
+/// Merge the command line arguments with the configuration file, if any, and check them.
+fn check_args(init: ConnectFireOptionsInit) -> SatFireResult<ConnectFireOptionsChecked> {
+    let ConnectFireOptionsInit {
+        config,
+        start,
+        end,
+        bbox,
+        clusters_store_file,
+        fires_store_file,
+        centroid,
+        exclusion_zones,
+        regions,
+        max_fire_lifetime,
+        max_quiet_days,
+        noise_pixel_cap,
+        quiet_burn_factor,
+        time_zone,
+        dry_run,
+        verbose,
+    } = init;
+
+    let config = match config {
+        Some(path) => SatFireConfig::from_file(path)?,
+        None => SatFireConfig::default(),
+    };
+
+    let bbox = bbox.or(config.region).unwrap_or(BoundingBox {
+        ll: Coord {
+            lat: -90.0,
+            lon: -180.0,
+        },
+        ur: Coord {
+            lat: 90.0,
+            lon: 180.0,
+        },
+    });
+    let clusters_store_file = clusters_store_file
+        .or(config.cluster_store_file)
+        .ok_or("No clusters store file specified")?;
+    let fires_store_file = fires_store_file
+        .or(config.fires_store_file)
+        .ok_or("No fires store file specified")?;
+    let exclusion_zones = match exclusion_zones {
+        Some(path) => ExclusionZones::from_file(path)?,
+        None => ExclusionZones::new(),
+    };
+    let regions = match regions {
+        Some(path) => parse_regions(&std::fs::read_to_string(path)?)?,
+        None => vec![],
+    };
+    let max_fire_lifetime = match max_fire_lifetime {
+        Some(0) => return Err("The maximum fire lifetime must be at least 1 day".into()),
+        Some(days) => Some(Duration::days(i64::from(days))),
+        None => None,
+    };
+    let max_quiet = match max_quiet_days {
+        Some(0) => return Err("The maximum quiet time must be at least 1 day".into()),
+        Some(days) => Some(Duration::days(i64::from(days))),
+        None => None,
+    };
+    if noise_pixel_cap == Some(0) {
+        return Err("The noise pixel cap must be at least 1".into());
+    }
+    if let Some(factor) = quiet_burn_factor {
+        if !(factor > 0.0 && factor.is_finite()) {
+            return Err(format!("The quiet burn factor must be positive: {}", factor).into());
+        }
+    }
+    let time_zone = time_zone.unwrap_or_default();
+
+    Ok(ConnectFireOptionsChecked {
+        start,
+        end,
+        bbox,
+        clusters_store_file,
+        fires_store_file,
+        centroid,
+        exclusion_zones,
+        regions,
+        max_fire_lifetime,
+        max_quiet,
+        noise_pixel_cap,
+        quiet_burn_factor,
+        time_zone,
+        dry_run,
+        verbose,
+    })
+}
+
 /*-------------------------------------------------------------------------------------------------
  *                                    Stats for this run.
  *-----------------------------------------------------------------------------------------------*/
@@ -169,6 +471,8 @@ struct FireStats {
     sat: Satellite,
     max_active: usize,
     largest_pixels_list: usize,
+    /// The time zone the times of the fires are shown in.
+    time_zone: OutputTimeZone,
 }
 
 impl Display for FireStats {
@@ -181,21 +485,21 @@ impl Display for FireStats {
 
         if let Some(ref longest) = self.longest_duration {
             writeln!(f, "   -- Longest Duration Fire --")?;
-            writeln!(f, "{}", longest)?;
+            writeln!(f, "{}", longest.display_in(self.time_zone))?;
         } else {
             writeln!(f, "No longest duration fire for stats.")?;
         }
 
         if let Some(ref longest) = self.longest_pixel_list {
             writeln!(f, "   -- Longest PixelList Fire --")?;
-            writeln!(f, "{}", longest)?;
+            writeln!(f, "{}", longest.display_in(self.time_zone))?;
         } else {
             writeln!(f, "No longest PixelList fire for stats.")?;
         }
 
         if let Some(ref hottest) = self.hottest {
             writeln!(f, "   -- Hottest Fire --")?;
-            writeln!(f, "{}", hottest)?;
+            writeln!(f, "{}", hottest.display_in(self.time_zone))?;
         } else {
             writeln!(f, "No hottest fire for stats.")?;
         }
@@ -205,7 +509,7 @@ impl Display for FireStats {
 }
 
 impl FireStats {
-    fn new(sat: Satellite) -> Self {
+    fn new(sat: Satellite, time_zone: OutputTimeZone) -> Self {
         FireStats {
             longest_duration: None,
             longest_pixel_list: None,
@@ -213,6 +517,7 @@ impl FireStats {
             sat,
             max_active: 0,
             largest_pixels_list: 0,
+            time_zone,
         }
     }
 
@@ -290,34 +595,60 @@ impl FireStats {
 /*-------------------------------------------------------------------------------------------------
  *                                   Processing For A Satellite
  *-----------------------------------------------------------------------------------------------*/
-fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>(
-    fires_db_store: P1,
+/// Warn about scans further apart than expected, fires may be missed or split across the gaps.
+fn warn_about_scan_gaps(
+    db: &ClusterDatabase,
+    sat: Satellite,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> SatFireResult<()> {
+    for sector in Sector::iter() {
+        for (prev, next) in db.scan_gaps(sat, sector, start, end)? {
+            warn!(target: sat.name(), "Gap in {} scans from {} to {}, {} minutes apart instead of {}",
+                sector, prev, next, (next - prev).num_minutes(),
+                sector.nominal_cadence_at(prev).num_minutes());
+        }
+    }
+
+    Ok(())
+}
+
+fn process_rows_for_satellite<P2: AsRef<Path>, P3: AsRef<Path>>(
+    db: FiresDatabase,
     clusters_db_store: P2,
     sat: Satellite,
     area: BoundingBox,
+    exclusion_zones: &ExclusionZones,
+    staleness: StalenessConfig,
     start: Option<DateTime<Utc>>,
     end: Option<DateTime<Utc>>,
     kmz_path: P3,
+    time_zone: OutputTimeZone,
     to_db_filler: Sender<DatabaseMessage>,
     verbose: bool,
-) -> SatFireResult<()> {
-    let db = FiresDatabase::connect(fires_db_store.as_ref())?;
-
-    let mut current_fires = db.ongoing_fires(sat)?;
+) -> SatFireResult<FireStats> {
+    let mut current_fires = db.ongoing_fires_with_staleness(sat, &staleness)?;
+
+    // Resume strictly after the last scan that was fully processed, so no cluster gets connected
+    // twice. Databases from before checkpoints were stored fall back to the last observation.
+    let resume = match db.checkpoint(sat)? {
+        Some(checkpoint) => Some(checkpoint + Duration::seconds(1)),
+        None => db.last_observed(sat),
+    };
 
-    let start = match (start, db.last_observed(sat)) {
+    let start = match (start, resume) {
         (Some(start), None) => start,
-        (None, Some(last_observed)) => last_observed,
-        (Some(start), Some(last_observed)) => {
-            if last_observed < start {
+        (None, Some(resume)) => resume,
+        (Some(start), Some(resume)) => {
+            if resume < start {
                 panic!(
                     "Database already started before but not complete up to: {}",
                     start
                 );
             }
 
-            if last_observed > start {
-                last_observed
+            if resume > start {
+                resume
             } else {
                 start
             }
@@ -339,7 +670,9 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
     let mut old_fires = FireList::new();
 
     let db = ClusterDatabase::connect(clusters_db_store.as_ref())?;
-    let mut stats = FireStats::new(sat);
+    let mut stats = FireStats::new(sat, time_zone);
+
+    warn_about_scan_gaps(&db, sat, start, end)?;
 
     let mut rows = db.query_clusters(Some(sat), None, start, end, area)?;
     let rows = rows.rows()?;
@@ -351,36 +684,25 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
         Utc,
     );
     let mut last_merge = current_time_step;
+    let mut last_processed: Option<DateTime<Utc>> = None;
 
     let mut num_absorbed = 0;
     let mut num_new = 0;
-    let current_group = vec![];
-    for (group_time, group) in rows
-        .map(|cluster| cluster.expect("Database error getting row."))
-        .scan(current_group, |group, cluster| {
-            let start = cluster.start;
-
-            if start != current_time_step {
-                let mut next_group = Vec::with_capacity(group.capacity());
-                std::mem::swap(&mut next_group, group);
-
-                let group_time = current_time_step;
-                current_time_step = start;
-
-                group.push(cluster);
+    let rows = rows.filter_map(|cluster| match cluster {
+        Ok(cluster) => Some(cluster),
+        Err(err) => {
+            warn!(target: sat.name(), "Error retrieving cluster - {}", err);
+            None
+        }
+    });
+    for (group_time, mut group) in group_by_scan(rows) {
+        current_time_step = group_time;
+        group.retain(|cluster| !exclusion_zones.contains(cluster.centroid));
 
-                Some(Some((group_time, next_group)))
-            } else {
-                group.push(cluster);
-                Some(None)
-            }
-        })
-        .filter_map(|val| val)
-    {
         if group_time - last_merge > Duration::hours(1) {
             // Only merge once per hour to speed things up.
             let num_merged = current_fires.merge_fires(&mut old_fires);
-            let num_old = current_fires.drain_stale_fires(&mut old_fires, group_time);
+            let num_old = current_fires.drain_stale_fires(&mut old_fires, group_time, &staleness);
             last_merge = group_time;
 
             let largest_pixel_list_size = current_fires
@@ -391,15 +713,29 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
 
             if verbose {
                 info!(target: sat.name(), "{:>23}, {:>8}, {:>6}, {:>4}, {:>4}, {:>6}, {:>6}",
-                    group_time, num_absorbed, num_merged, num_old, num_new, current_fires.len(), 
+                    group_time, num_absorbed, num_merged, num_old, num_new, current_fires.len(),
                     largest_pixel_list_size);
             }
 
             num_absorbed = 0;
             num_new = 0;
 
+            // The checkpoint says every scan up to last_processed is done, so the fires still going
+            // have to be stored with it or their clusters are lost if the run never finishes. They
+            // get stored again, along with any later associations, when they're sent next time.
+            let mut ongoing = FireList::new();
+            for fire in current_fires.iter().chain(new_fires.iter()) {
+                ongoing.add_fire(fire.clone());
+            }
+
+            let finished = std::mem::take(&mut old_fires);
             to_db_filler
-                .send(DatabaseMessage::Fires(std::mem::take(&mut old_fires)))
+                .send(DatabaseMessage::fires(
+                    finished,
+                    ongoing,
+                    sat,
+                    last_processed,
+                ))
                 .expect("Error sending Fires message to database:");
 
             if SHUT_DOWN.load(Ordering::SeqCst) {
@@ -412,9 +748,19 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
 
         stats.update(&current_fires);
 
+        // Clusters that bridge two or more fires get those fires merged right away instead of
+        // waiting for the periodic merge.
+        let mut bridged = vec![];
+
         if let Some(mut view) = FireListView::new(&mut current_fires) {
             for cluster in group {
                 let clusterid = cluster.rowid;
+
+                let matches = view.matching_fires(&cluster);
+                if matches.len() > 1 {
+                    bridged.push(matches);
+                }
+
                 let fireid = match view.update(cluster) {
                     FireListUpdateResult::NoMatch(cluster) => {
                         let fireid = NEXT_WILDFIRE_ID.fetch_add(1, Ordering::SeqCst);
@@ -435,6 +781,8 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
                     }
                 }
             }
+
+            current_fires.merge_bridged_fires(&bridged, &mut old_fires);
         } else {
             for cluster in group {
                 let clusterid = cluster.rowid;
@@ -450,13 +798,15 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
                 }
             }
         }
+
+        last_processed = Some(group_time);
     }
 
     let num_merged = current_fires.merge_fires(&mut old_fires);
-    let num_old = current_fires.drain_stale_fires(&mut old_fires, current_time_step);
+    let num_old = current_fires.drain_stale_fires(&mut old_fires, current_time_step, &staleness);
     let num_new = current_fires.extend(&mut new_fires);
 
-    current_fires.save_kmz(Duration::days(1), kmz_path)?;
+    current_fires.save_kmz(Duration::days(1), kmz_path, time_zone)?;
 
     let largest_pixel_list_size = current_fires
         .iter()
@@ -466,25 +816,27 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
 
     if verbose {
         info!(target: sat.name(), "{:>23}, {:>8}, {:>6}, {:>4}, {:>4}, {:>6}, {:>6}",
-            current_time_step, num_absorbed, num_merged, num_old, num_new, current_fires.len(), 
+            current_time_step, num_absorbed, num_merged, num_old, num_new, current_fires.len(),
             largest_pixel_list_size);
     }
 
-    old_fires.extend(&mut current_fires);
     stats.update(&old_fires);
+    stats.update(&current_fires);
 
-    assert!(current_fires.is_empty());
     assert!(new_fires.is_empty());
 
+    // The fires still going are picked up again by the next run, so they're stored no matter how
+    // long they have lasted so far.
     to_db_filler
-        .send(DatabaseMessage::Fires(old_fires))
+        .send(DatabaseMessage::fires(
+            old_fires,
+            current_fires,
+            sat,
+            last_processed,
+        ))
         .map_err(|_| "Undable to send to_db_filler".to_owned())?;
 
-    if verbose {
-        info!(target: "stats", "{}", stats);
-    }
-
-    Ok(())
+    Ok(stats)
 }
 
 /*-------------------------------------------------------------------------------------------------
@@ -492,20 +844,73 @@ fn process_rows_for_satellite<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>
  *-----------------------------------------------------------------------------------------------*/
 enum DatabaseMessage {
     Fires(FireList),
+    /// Finished fires and fires still going to store along with the start time of the last scan
+    /// fully processed before sending them, which is stored in the same transaction. Every fire
+    /// still going with clusters from before that scan has to be in the second list.
+    Checkpoint((FireList, FireList, Satellite, DateTime<Utc>)),
     Association((u64, u64)),
 }
 
+impl DatabaseMessage {
+    /// Send the `finished` and `ongoing` fires with a checkpoint if any scans have been processed
+    /// yet. Otherwise the ongoing fires haven't changed since they were loaded, so only the
+    /// finished ones are sent.
+    fn fires(
+        finished: FireList,
+        ongoing: FireList,
+        sat: Satellite,
+        last_processed: Option<DateTime<Utc>>,
+    ) -> Self {
+        match last_processed {
+            Some(scan_start) => DatabaseMessage::Checkpoint((finished, ongoing, sat, scan_start)),
+            None => DatabaseMessage::Fires(finished),
+        }
+    }
+}
+
 fn database_filler(
     db_store: PathBuf,
+    centroid: CentroidMethod,
+    regions: Vec<(String, BoundingBox)>,
     messages: Receiver<DatabaseMessage>,
+    dry_run: bool,
 ) -> JoinHandle<SatFireResult<()>> {
     thread::spawn(move || {
+        if dry_run {
+            let mut num_fires = 0;
+            let mut num_associations = 0;
+            for message in messages {
+                match message {
+                    DatabaseMessage::Fires(fires) => num_fires += fires.len(),
+                    DatabaseMessage::Checkpoint((fires, ongoing, _, _)) => {
+                        num_fires += fires.len() + ongoing.len()
+                    }
+                    DatabaseMessage::Association(_) => num_associations += 1,
+                }
+            }
+
+            info!(target: "dry run", "Would have stored {} fires and {} associations.",
+                num_fires, num_associations);
+            return Ok(());
+        }
+
         let db = FiresDatabase::connect(db_store)?;
-        let mut add_fire = db.prepare_to_add_fires()?;
+        let mut add_fire = db.prepare_to_add_fires_with_centroid(centroid)?;
+        if !regions.is_empty() {
+            add_fire.set_region_classifier(move |coord| {
+                regions
+                    .iter()
+                    .find(|(_, bbox)| bbox.contains_coord(coord, 0.0))
+                    .map(|(name, _)| name.clone())
+            });
+        }
 
         for message in messages {
             match message {
                 DatabaseMessage::Fires(fires) => add_fire.add_fires(&fires)?,
+                DatabaseMessage::Checkpoint((fires, ongoing, sat, scan_start)) => {
+                    add_fire.add_fires_with_checkpoint(&fires, &ongoing, sat, scan_start)?
+                }
                 DatabaseMessage::Association((fireid, clusterid)) => {
                     add_fire.add_association(fireid, clusterid)
                 }
@@ -516,6 +921,19 @@ fn database_filler(
     })
 }
 
+/// Open the fires database, read only for a dry run.
+///
+/// A dry run with a fires database that doesn't exist yet starts from an empty in memory database.
+fn connect_fires_database(path: &Path, dry_run: bool) -> SatFireResult<FiresDatabase> {
+    if !dry_run {
+        FiresDatabase::connect(path)
+    } else if path.exists() {
+        FiresDatabase::connect_read_only(path)
+    } else {
+        FiresDatabase::connect(":memory:")
+    }
+}
+
 /*-------------------------------------------------------------------------------------------------
  *                                       Signal Handlers
  *-----------------------------------------------------------------------------------------------*/
@@ -543,8 +961,10 @@ fn main() -> SatFireResult<()> {
 
     let opts = parse_args()?;
 
-    FiresDatabase::initialize(&opts.fires_store_file)?;
-    let fires_db = FiresDatabase::connect(&opts.fires_store_file)?;
+    if !opts.dry_run {
+        FiresDatabase::initialize(&opts.fires_store_file)?;
+    }
+    let fires_db = connect_fires_database(&opts.fires_store_file, opts.dry_run)?;
     let next_id = fires_db.next_wildfire_id()?;
     NEXT_WILDFIRE_ID.store(next_id, Ordering::SeqCst);
     drop(fires_db);
@@ -562,23 +982,28 @@ fn main() -> SatFireResult<()> {
             "scan start time", "Absorbed", "Merged", "Old", "New", "Active", "Most Pixels");
     }
 
+    let staleness = opts.staleness();
     for sat in Satellite::iter() {
         let mut kmz_path = opts.clusters_store_file.clone();
         kmz_path.set_file_name(sat.name());
         kmz_path.set_extension("kmz");
         let clusters_store_file = opts.clusters_store_file.clone();
-        let fires_store_file = opts.fires_store_file.clone();
+        let fires_db = connect_fires_database(&opts.fires_store_file, opts.dry_run)?;
         let send_to_db_filler = send_to_db_filler.clone();
+        let exclusion_zones = opts.exclusion_zones.clone();
 
         let jh = std::thread::spawn(move || {
             process_rows_for_satellite(
-                fires_store_file,
+                fires_db,
                 clusters_store_file,
                 sat,
                 opts.bbox,
+                &exclusion_zones,
+                staleness,
                 opts.start,
                 opts.end,
                 kmz_path,
+                opts.time_zone,
                 send_to_db_filler,
                 opts.verbose,
             )
@@ -588,15 +1013,666 @@ fn main() -> SatFireResult<()> {
     }
     drop(send_to_db_filler);
 
-    let jh_db_filler = database_filler(opts.fires_store_file, from_processing);
+    let jh_db_filler = database_filler(
+        opts.fires_store_file,
+        opts.centroid,
+        opts.regions,
+        from_processing,
+        opts.dry_run,
+    );
 
     jh_db_filler
         .join()
         .expect("Error joining the database filler thread.")?;
 
     for jh in jh_processing {
-        jh.join().expect("Error joining a processing thread.")?;
+        let stats = jh.join().expect("Error joining a processing thread.")?;
+
+        if opts.verbose || opts.dry_run {
+            info!(target: "stats", "{}", stats);
+        }
     }
 
     Ok(())
 }
+
+/*-------------------------------------------------------------------------------------------------
+ *                                             Tests
+ *-----------------------------------------------------------------------------------------------*/
+#[cfg(test)]
+mod test {
+    use super::*;
+    use satfire::{
+        testutil::{cluster_from_pixels, cluster_list, PixelGrid},
+        PixelList,
+    };
+
+    #[test]
+    fn test_config_file_with_overrides() {
+        // Make sure the environment doesn't override the config file.
+        std::env::remove_var("CLUSTER_DB");
+        std::env::remove_var("FIRES_DB");
+
+        let config_path =
+            std::env::temp_dir().join(format!("connectfire_test_{}.toml", std::process::id()));
+        std::fs::write(
+            &config_path,
+            concat!(
+                "cluster_store_file = \"/config/findfire.sqlite\"\n",
+                "fires_store_file = \"/config/connectfire.sqlite\"\n",
+                "region = [40.0, -125.0, 50.0, -110.0]\n",
+            ),
+        )
+        .unwrap();
+
+        let config_arg = config_path.to_str().unwrap();
+
+        let opts = check_args(
+            ConnectFireOptionsInit::try_parse_from(["connectfire", "--config", config_arg])
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            opts.clusters_store_file,
+            Path::new("/config/findfire.sqlite")
+        );
+        assert_eq!(
+            opts.fires_store_file,
+            Path::new("/config/connectfire.sqlite")
+        );
+        assert_eq!(opts.bbox.ll.lat, 40.0);
+        assert_eq!(opts.bbox.ur.lon, -110.0);
+
+        let opts = check_args(
+            ConnectFireOptionsInit::try_parse_from([
+                "connectfire",
+                "--config",
+                config_arg,
+                "--fires-store-file",
+                "/cli/connectfire.sqlite",
+                "30,-100,35,-90",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            opts.clusters_store_file,
+            Path::new("/config/findfire.sqlite")
+        );
+        assert_eq!(opts.fires_store_file, Path::new("/cli/connectfire.sqlite"));
+        assert_eq!(opts.bbox.ll.lat, 30.0);
+        assert_eq!(opts.bbox.ur.lon, -90.0);
+
+        std::fs::remove_file(&config_path).unwrap();
+
+        // Without a config file the paths are required and the region is the whole globe.
+        assert!(
+            check_args(ConnectFireOptionsInit::try_parse_from(["connectfire"]).unwrap()).is_err()
+        );
+        let opts = check_args(
+            ConnectFireOptionsInit::try_parse_from(["connectfire", "-c", "/a", "-f", "/b"])
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(opts.bbox.ll.lat, -90.0);
+        assert_eq!(opts.bbox.ur.lon, 180.0);
+    }
+
+    #[test]
+    fn test_parse_bbox() {
+        let bbox = parse_bbox("40.0,-125.0,50.0,-110.0").unwrap();
+        assert!(!bbox.wraps_antimeridian());
+
+        // Across the antimeridian from the western Pacific to the Aleutians.
+        let opts = ConnectFireOptionsInit::try_parse_from([
+            "connectfire",
+            "-c",
+            "/a.sqlite",
+            "-f",
+            "/b.sqlite",
+            "45.0,170.0,60.0,-160.0",
+        ])
+        .unwrap();
+        let bbox = opts.bbox.unwrap();
+        assert!(bbox.wraps_antimeridian());
+        assert!(bbox.contains_coord(
+            Coord {
+                lat: 52.0,
+                lon: 179.5
+            },
+            0.0
+        ));
+        assert!(bbox.contains_coord(
+            Coord {
+                lat: 52.0,
+                lon: -170.0
+            },
+            0.0
+        ));
+
+        assert!(parse_bbox("50.0,-125.0,40.0,-110.0").is_err());
+        assert!(parse_bbox("40.0,-125.0,50.0,-125.0").is_err());
+        assert!(parse_bbox("40.0,-125.0,50.0,-190.0").is_err());
+    }
+
+    /// The grid the test clusters are on, 0.02 degree pixels with the upper left corner of the
+    /// pixel in column 0 and row 0 at 45.02 N 120 W.
+    const GRID: PixelGrid = PixelGrid {
+        origin: Coord {
+            lat: 45.02,
+            lon: -120.0,
+        },
+        cell_size: 0.02,
+    };
+
+    /// A temporary directory with a clusters database for running connectfire in the tests.
+    ///
+    /// The directory is removed when this is dropped.
+    struct TestRun {
+        dir: PathBuf,
+        clusters_store: PathBuf,
+        kmz_path: PathBuf,
+    }
+
+    impl TestRun {
+        /// Create the directory and an empty clusters database, `name` must be unique to the test.
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("connectfire_{}_{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let clusters_store = dir.join("clusters.sqlite");
+            ClusterDatabase::initialize(&clusters_store).unwrap();
+            let kmz_path = dir.join("G17.kmz");
+
+            TestRun {
+                dir,
+                clusters_store,
+                kmz_path,
+            }
+        }
+
+        /// The path to a file in the temporary directory.
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.join(name)
+        }
+
+        /// Store a G17 CONUS scan starting at `start` with a cluster for each list of pixels.
+        fn add_scan(&self, start: DateTime<Utc>, clusters: Vec<PixelList>) {
+            let db = ClusterDatabase::connect(&self.clusters_store).unwrap();
+            let mut add = db.prepare_to_add_clusters().unwrap();
+
+            let clusters = clusters.into_iter().map(cluster_from_pixels).collect();
+            add.add(cluster_list(
+                Satellite::G17,
+                Sector::CONUS,
+                start,
+                start + Duration::minutes(4),
+                clusters,
+            ))
+            .unwrap();
+        }
+
+        /// Store clusters for two separate fires observed every half hour for four hours starting
+        /// at `t0`, and return the scan start times.
+        fn add_two_fires(&self, t0: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+            let scans: Vec<DateTime<Utc>> =
+                (0..=8).map(|i| t0 + Duration::minutes(30 * i)).collect();
+
+            for &start in &scans {
+                self.add_scan(
+                    start,
+                    vec![GRID.block(0, 0, 1, 1, 10.0), GRID.block(50, 0, 1, 1, 10.0)],
+                );
+            }
+
+            scans
+        }
+
+        /// Connect the G17 clusters from `start` to `end` anywhere outside the `exclusion_zones`
+        /// and send the results to `to_db_filler`.
+        fn process(
+            &self,
+            db: FiresDatabase,
+            exclusion_zones: &ExclusionZones,
+            start: Option<DateTime<Utc>>,
+            end: Option<DateTime<Utc>>,
+            to_db_filler: Sender<DatabaseMessage>,
+        ) -> FireStats {
+            process_rows_for_satellite(
+                db,
+                &self.clusters_store,
+                Satellite::G17,
+                BoundingBox {
+                    ll: Coord {
+                        lat: -90.0,
+                        lon: -180.0,
+                    },
+                    ur: Coord {
+                        lat: 90.0,
+                        lon: 180.0,
+                    },
+                },
+                exclusion_zones,
+                StalenessConfig::default(),
+                start,
+                end,
+                &self.kmz_path,
+                OutputTimeZone::default(),
+                to_db_filler,
+                false,
+            )
+            .unwrap()
+        }
+
+        /// Connect the G17 clusters from `start` to `end` and store the results in `fires_store`.
+        fn connect_and_store(
+            &self,
+            fires_store: &Path,
+            start: Option<DateTime<Utc>>,
+            end: DateTime<Utc>,
+        ) {
+            self.connect_with(fires_store, start, end, None, vec![]);
+        }
+
+        /// Like [TestRun::connect_and_store], but label the stored fires with `regions`, and if
+        /// `crash_after` is set, nothing sent after the first checkpoint at or after that scan
+        /// makes it to the database, as if the process died there.
+        fn connect_with(
+            &self,
+            fires_store: &Path,
+            start: Option<DateTime<Utc>>,
+            end: DateTime<Utc>,
+            crash_after: Option<DateTime<Utc>>,
+            regions: Vec<(String, BoundingBox)>,
+        ) {
+            let (to_forwarder, from_processing) = bounded(1024);
+            let (to_db_filler, to_store) = bounded(1024);
+            let filler = database_filler(
+                fires_store.to_owned(),
+                CentroidMethod::default(),
+                regions,
+                to_store,
+                false,
+            );
+
+            let forwarder = thread::spawn(move || {
+                let mut to_db_filler = Some(to_db_filler);
+                for message in from_processing {
+                    let crashed = match (&message, crash_after) {
+                        (DatabaseMessage::Checkpoint((_, _, _, scan_start)), Some(crash_after)) => {
+                            *scan_start >= crash_after
+                        }
+                        _ => false,
+                    };
+
+                    if let Some(sender) = &to_db_filler {
+                        sender.send(message).unwrap();
+                    }
+
+                    if crashed {
+                        to_db_filler = None;
+                    }
+                }
+            });
+
+            self.process(
+                FiresDatabase::connect(fires_store).unwrap(),
+                &ExclusionZones::new(),
+                start,
+                Some(end),
+                to_forwarder,
+            );
+            forwarder.join().unwrap();
+            filler.join().unwrap().unwrap();
+        }
+    }
+
+    impl Drop for TestRun {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Get the cluster ids associated with each fire, sorted so runs can be compared without
+    /// depending on the fire ids.
+    fn associated_clusters(fires_store: &Path) -> Vec<Vec<u64>> {
+        let conn = rusqlite::Connection::open(fires_store).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT fire_id, cluster_id FROM associations ORDER BY fire_id, cluster_id")
+            .unwrap();
+
+        let mut fires: Vec<(u64, Vec<u64>)> = vec![];
+        for pair in stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+        {
+            let (fire_id, cluster_id) = pair.unwrap();
+            match fires.last_mut() {
+                Some((last_id, clusters)) if *last_id == fire_id => clusters.push(cluster_id),
+                _ => fires.push((fire_id, vec![cluster_id])),
+            }
+        }
+
+        let mut clusters: Vec<Vec<u64>> = fires.into_iter().map(|(_, c)| c).collect();
+        clusters.sort_unstable();
+        clusters
+    }
+
+    #[test]
+    fn test_final_scan_clusters_are_associated() {
+        let run = TestRun::new("final_scan");
+        let fires_store = run.path("fires.sqlite");
+        FiresDatabase::initialize(&fires_store).unwrap();
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let t1 = t0 + Duration::minutes(5);
+
+        // One cluster in the first scan and two in the last, which used to be dropped.
+        run.add_scan(t0, vec![GRID.block(0, 0, 1, 1, 10.0)]);
+        run.add_scan(
+            t1,
+            vec![GRID.block(0, 0, 1, 1, 10.0), GRID.block(0, 750, 1, 1, 10.0)],
+        );
+
+        let (to_db_filler, from_processing) = bounded(1024);
+        run.process(
+            FiresDatabase::connect(&fires_store).unwrap(),
+            &ExclusionZones::new(),
+            Some(t0 - Duration::hours(1)),
+            Some(t1 + Duration::hours(1)),
+            to_db_filler,
+        );
+
+        let mut associated: Vec<u64> = from_processing
+            .iter()
+            .filter_map(|msg| match msg {
+                DatabaseMessage::Association((_fireid, clusterid)) => Some(clusterid),
+                DatabaseMessage::Fires(_) | DatabaseMessage::Checkpoint(_) => None,
+            })
+            .collect();
+        associated.sort_unstable();
+
+        assert_eq!(associated, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_exclusion_zones_are_not_connected() {
+        let run = TestRun::new("exclusion");
+        let fires_store = run.path("fires.sqlite");
+        FiresDatabase::initialize(&fires_store).unwrap();
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let scans = run.add_two_fires(t0);
+        let end = *scans.last().unwrap() + Duration::hours(1);
+
+        // A zone around the second fire, the first one is about 80 km away.
+        let mut exclusion_zones = ExclusionZones::new();
+        exclusion_zones.add(
+            Coord {
+                lat: 45.01,
+                lon: -118.99,
+            },
+            5_000.0,
+        );
+
+        let (to_db_filler, from_processing) = bounded(1024);
+        let filler = database_filler(
+            fires_store.clone(),
+            CentroidMethod::default(),
+            vec![],
+            from_processing,
+            false,
+        );
+        run.process(
+            FiresDatabase::connect(&fires_store).unwrap(),
+            &exclusion_zones,
+            Some(t0 - Duration::hours(1)),
+            Some(end),
+            to_db_filler,
+        );
+        filler.join().unwrap().unwrap();
+
+        // The first fire's cluster is stored first in each scan, so it has the odd ids.
+        let first_fire: Vec<u64> = (1..=17).step_by(2).collect();
+        assert_eq!(associated_clusters(&fires_store), vec![first_fire]);
+    }
+
+    #[test]
+    fn test_max_simultaneous_clusters_is_stored() {
+        let run = TestRun::new("msc");
+        let fires_store = run.path("fires.sqlite");
+        FiresDatabase::initialize(&fires_store).unwrap();
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let t1 = t0 + Duration::hours(2);
+
+        let cols = [0, 5, 10];
+
+        // One cluster covering three separated pixels, then each pixel as its own cluster in a
+        // later scan.
+        let mut all_pixels = PixelList::new();
+        for &col in &cols {
+            all_pixels.push(GRID.pixel(col, 0, 10.0));
+        }
+        run.add_scan(t0, vec![all_pixels]);
+        run.add_scan(
+            t1,
+            cols.iter()
+                .map(|&col| GRID.block(col, 0, 1, 1, 10.0))
+                .collect(),
+        );
+
+        let (to_db_filler, from_processing) = bounded(1024);
+        let filler = database_filler(
+            fires_store.clone(),
+            CentroidMethod::default(),
+            vec![],
+            from_processing,
+            false,
+        );
+        run.process(
+            FiresDatabase::connect(&fires_store).unwrap(),
+            &ExclusionZones::new(),
+            Some(t0 - Duration::hours(1)),
+            Some(t1 + Duration::hours(1)),
+            to_db_filler,
+        );
+        filler.join().unwrap().unwrap();
+
+        let fires: Vec<Fire> = FiresDatabase::connect_read_only(&fires_store)
+            .unwrap()
+            .query_fires(
+                None,
+                t0 - Duration::hours(1),
+                t1 + Duration::hours(1),
+                BoundingBox {
+                    ll: Coord {
+                        lat: 44.0,
+                        lon: -121.0,
+                    },
+                    ur: Coord {
+                        lat: 46.0,
+                        lon: -119.0,
+                    },
+                },
+            )
+            .unwrap()
+            .rows()
+            .unwrap()
+            .filter_map(Result::ok)
+            .collect();
+
+        assert_eq!(fires.len(), 1);
+        assert_eq!(fires[0].max_simultaneous_clusters(), 3);
+    }
+
+    #[test]
+    fn test_dry_run_leaves_fires_database_alone() {
+        let run = TestRun::new("dry");
+        let fires_store = run.path("fires.sqlite");
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        run.add_scan(t0, vec![GRID.block(0, 0, 1, 1, 10.0)]);
+
+        let (to_db_filler, from_processing) = bounded(1024);
+        let filler = database_filler(
+            fires_store.clone(),
+            CentroidMethod::default(),
+            vec![],
+            from_processing,
+            true,
+        );
+        let stats = run.process(
+            connect_fires_database(&fires_store, true).unwrap(),
+            &ExclusionZones::new(),
+            Some(t0 - Duration::hours(1)),
+            Some(t0 + Duration::hours(1)),
+            to_db_filler,
+        );
+        filler.join().unwrap().unwrap();
+
+        assert_eq!(stats.max_active, 1);
+        assert!(stats.hottest.is_some());
+        assert!(!fires_store.exists());
+    }
+
+    #[test]
+    fn test_resume_after_interruption() {
+        let run = TestRun::new("resume");
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let scans = run.add_two_fires(t0);
+
+        let start = Some(t0 - Duration::hours(1));
+        let end = t0 + Duration::hours(5);
+
+        let uninterrupted_store = run.path("uninterrupted.sqlite");
+        FiresDatabase::initialize(&uninterrupted_store).unwrap();
+        run.connect_and_store(&uninterrupted_store, start, end);
+
+        // Stop after the scan in the middle, then pick up from the checkpoint.
+        let interrupted = scans[4];
+        let resumed_store = run.path("resumed.sqlite");
+        FiresDatabase::initialize(&resumed_store).unwrap();
+        run.connect_and_store(&resumed_store, start, interrupted + Duration::minutes(4));
+        let checkpoint = FiresDatabase::connect(&resumed_store)
+            .unwrap()
+            .checkpoint(Satellite::G17)
+            .unwrap();
+
+        run.connect_and_store(&resumed_store, None, end);
+        let final_checkpoint = FiresDatabase::connect(&resumed_store)
+            .unwrap()
+            .checkpoint(Satellite::G17)
+            .unwrap();
+
+        let uninterrupted = associated_clusters(&uninterrupted_store);
+        let resumed = associated_clusters(&resumed_store);
+
+        assert_eq!(checkpoint, Some(interrupted));
+        assert_eq!(final_checkpoint, Some(scans[8]));
+
+        assert_eq!(uninterrupted.len(), 2);
+        assert_eq!(uninterrupted.iter().map(Vec::len).sum::<usize>(), 18);
+        assert_eq!(resumed, uninterrupted);
+    }
+
+    #[test]
+    fn test_resume_after_crash_at_checkpoint() {
+        let run = TestRun::new("crash");
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let scans = run.add_two_fires(t0);
+
+        // A third fire first seen in the last scan before the first hourly checkpoint, so it's
+        // only minutes old when the checkpoint is stored.
+        for &start in &scans[2..] {
+            run.add_scan(start, vec![GRID.block(100, 0, 1, 1, 10.0)]);
+        }
+
+        let start = Some(t0 - Duration::hours(1));
+        let end = t0 + Duration::hours(5);
+
+        let uninterrupted_store = run.path("uninterrupted.sqlite");
+        FiresDatabase::initialize(&uninterrupted_store).unwrap();
+        run.connect_and_store(&uninterrupted_store, start, end);
+
+        // Die right after the first hourly checkpoint, while all the fires are still going, so the
+        // final send never happens.
+        let crashed_store = run.path("crashed.sqlite");
+        FiresDatabase::initialize(&crashed_store).unwrap();
+        run.connect_with(&crashed_store, start, end, Some(scans[1]), vec![]);
+        let checkpoint = FiresDatabase::connect(&crashed_store)
+            .unwrap()
+            .checkpoint(Satellite::G17)
+            .unwrap();
+
+        run.connect_and_store(&crashed_store, None, end);
+
+        let uninterrupted = associated_clusters(&uninterrupted_store);
+        let resumed = associated_clusters(&crashed_store);
+
+        assert_eq!(checkpoint, Some(scans[2]));
+
+        assert_eq!(uninterrupted.len(), 3);
+        assert_eq!(resumed, uninterrupted);
+    }
+
+    #[test]
+    fn test_parse_regions() {
+        let regions = parse_regions(
+            "# name, min_lat, min_lon, max_lat, max_lon\n\
+             Idaho, 42.0, -117.2, 49.0, -111.0\n\
+             \n\
+             Aleutians, 50.0, 170.0, 56.0, -160.0 # wraps the antimeridian\n",
+        )
+        .unwrap();
+
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].0, "Idaho");
+        assert!(!regions[0].1.wraps_antimeridian());
+        assert_eq!(regions[1].0, "Aleutians");
+        assert!(regions[1].1.wraps_antimeridian());
+
+        let err = parse_regions("Idaho, 42.0, -117.2, 49.0, -111.0\n, 1.0, 2.0, 3.0, 4.0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("line 2:"), "{}", err);
+        assert!(parse_regions("Idaho, 42.0, -117.2, 49.0").is_err());
+        assert!(parse_regions("Idaho").is_err());
+    }
+
+    #[test]
+    fn test_fires_are_labeled_with_regions() {
+        let run = TestRun::new("regions");
+        let fires_store = run.path("fires.sqlite");
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        run.add_two_fires(t0);
+
+        // Only the fire near -120 is in the region, the one near -119 is outside all of them.
+        let regions = parse_regions("West, 40.0, -125.0, 50.0, -119.5").unwrap();
+
+        FiresDatabase::initialize(&fires_store).unwrap();
+        run.connect_with(
+            &fires_store,
+            Some(t0 - Duration::hours(1)),
+            t0 + Duration::hours(5),
+            None,
+            regions,
+        );
+
+        let conn = rusqlite::Connection::open(&fires_store).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT region FROM fires ORDER BY lon")
+            .unwrap();
+        let labels: Vec<Option<String>> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(labels, vec![Some("West".to_owned()), None]);
+    }
+}
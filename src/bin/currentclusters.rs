@@ -1,13 +1,15 @@
 use clap::Parser;
 use log::info;
 use satfire::{
-    BoundingBox, ClusterDatabase, Coord, Geo, KmlWriter, KmzFile, SatFireResult, Satellite, Sector,
+    BoundingBox, ClusterDatabase, Coord, Geo, KmlWriter, KmzFile, MaskCategory, SatFireResult,
+    Satellite, Sector,
 };
 use simple_logger::SimpleLogger;
 use std::{
     fmt::{self, Display, Write},
     path::PathBuf,
 };
+use strum::IntoEnumIterator;
 
 /*-------------------------------------------------------------------------------------------------
  *                                     Command Line Options
@@ -148,7 +150,7 @@ fn main() -> SatFireResult<()> {
     //
     // Load the data, the most recent clusters.
     //
-    let db = ClusterDatabase::connect(&opts.cluster_store_file)?;
+    let db = ClusterDatabase::connect_read_only(&opts.cluster_store_file)?;
     let latest = db.newest_scan_start(opts.sat, opts.sector)?;
     let latest_start = latest - chrono::Duration::seconds(1);
     let latest_end = latest + chrono::Duration::hours(1);
@@ -216,6 +218,18 @@ fn main() -> SatFireResult<()> {
             cluster.power, cluster.scan_angle, cluster.max_temperature,
         );
 
+        let mask_counts = cluster.pixels.mask_category_counts();
+        description.push_str("<p>");
+        for category in MaskCategory::iter().filter(|&c| mask_counts.get(c) > 0) {
+            let _ = write!(
+                &mut description,
+                "{}: {}<br/>",
+                category,
+                mask_counts.get(category)
+            );
+        }
+        description.push_str("</p>");
+
         kfile.start_folder(Some(&name), None, false)?;
 
         let centroid = cluster.pixels.centroid();
@@ -1,7 +1,7 @@
 use chrono::Duration;
 use clap::Parser;
 use log::info;
-use satfire::{FireList, FiresDatabase, SatFireResult, Satellite};
+use satfire::{parse_time_zone, FireList, FiresDatabase, OutputTimeZone, SatFireResult, Satellite};
 use simple_logger::SimpleLogger;
 use std::{
     cmp::Reverse,
@@ -45,6 +45,22 @@ struct CurrentFiresOptionsInit {
     #[clap(default_value_t=Satellite::G17)]
     sat: Satellite,
 
+    /// Write each day of fires to a separate document in the KMZ file.
+    ///
+    /// The main document links to the days by NetworkLink, which keeps very large exports small
+    /// enough for Google Earth to open.
+    #[clap(long)]
+    timesliced: bool,
+
+    /// Show times in the descriptions in this time zone, like "America/Denver", or at a fixed
+    /// offset from UTC, like "-06" or "+05:30".
+    ///
+    /// Times are always stored in UTC, this only changes how they are displayed. Named zones
+    /// follow daylight saving time.
+    #[clap(long)]
+    #[clap(parse(try_from_str=parse_time_zone))]
+    time_zone: Option<OutputTimeZone>,
+
     /// Verbose output
     #[clap(short, long)]
     verbose: bool,
@@ -67,6 +83,12 @@ struct CurrentFiresOptionsChecked {
     /// The satellite.
     sat: Satellite,
 
+    /// Split the output into a document per day.
+    timesliced: bool,
+
+    /// The time zone used to display times.
+    time_zone: OutputTimeZone,
+
     /// Verbose output
     verbose: bool,
 }
@@ -77,6 +99,8 @@ impl Display for CurrentFiresOptionsChecked {
         writeln!(f, "    Database: {}", self.fires_store_file.display())?;
         writeln!(f, "  Output KMZ: {}", self.kmz_file.display())?;
         writeln!(f, "   Satellite: {}", self.sat.name())?;
+        writeln!(f, " Time Sliced: {}", self.timesliced)?;
+        writeln!(f, "   Time Zone: {}", self.time_zone)?;
         writeln!(f, "\n")?; // yes, two blank lines.
 
         Ok(())
@@ -91,8 +115,11 @@ fn parse_args() -> SatFireResult<CurrentFiresOptionsChecked> {
         fires_store_file,
         kmz_file,
         sat,
+        timesliced,
+        time_zone,
         verbose,
     } = CurrentFiresOptionsInit::parse();
+    let time_zone = time_zone.unwrap_or_default();
 
     let kmz_file = match kmz_file {
         Some(v) => v,
@@ -107,6 +134,8 @@ fn parse_args() -> SatFireResult<CurrentFiresOptionsChecked> {
         fires_store_file,
         kmz_file,
         sat,
+        timesliced,
+        time_zone,
         verbose,
     };
 
@@ -128,7 +157,7 @@ fn main() -> SatFireResult<()> {
     //
     // Load the data, the most recent clusters.
     //
-    let db = FiresDatabase::connect(&opts.fires_store_file)?;
+    let db = FiresDatabase::connect_read_only(&opts.fires_store_file)?;
     let active_fires = db.ongoing_fires(opts.sat)?;
     drop(db);
 
@@ -140,7 +169,11 @@ fn main() -> SatFireResult<()> {
         info!("Retrieved {} fires.", active_fires.len());
     }
 
-    active_fires.save_kmz(Duration::days(1), &opts.kmz_file)?;
+    if opts.timesliced {
+        active_fires.save_kmz_timesliced(Duration::days(1), &opts.kmz_file, opts.time_zone)?;
+    } else {
+        active_fires.save_kmz(Duration::days(1), &opts.kmz_file, opts.time_zone)?;
+    }
 
     Ok(())
 }
@@ -2,18 +2,23 @@
 
 use chrono::{DateTime, Datelike, Timelike, Utc};
 use clap::Parser;
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
 use log::{debug, info, warn};
 use satfire::{
-    BoundingBox, Cluster, ClusterDatabase, ClusterList, Coord, Geo, KmlWriter, KmzFile,
-    SatFireResult, Satellite, Sector,
+    parse_time_zone, BoundingBox, Cluster, ClusterDatabase, ClusterList, ClusterQc, Connectivity,
+    Coord, Geo, KmlWriter, KmzFile, LoadOptions, MaskCategory, OutputTime, OutputTimeZone,
+    SatFireConfig, SatFireResult, Satellite, Sector, TerrainHeight,
 };
 use simple_logger::SimpleLogger;
 use std::{
     collections::HashMap,
     fmt::{self, Display, Formatter},
+    fs::OpenOptions,
+    io::{BufRead, BufWriter, Write},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
     thread::JoinHandle,
+    time::{Duration, Instant},
 };
 use strum::IntoEnumIterator;
 
@@ -42,56 +47,349 @@ use strum::IntoEnumIterator;
 /// findfire.kmz is output in the same location as the database file findfire.sqlite that has some
 /// summary statistics about the clusters and images that were analyzed during this run.
 ///
+/// Instead of, or as well as, the database the clusters can be appended to a JSON Lines file for
+/// tools that don't read SQLite. Without a database every file found is processed.
+///
+/// Instead of walking a directory, a list of files can be given with --files, which is handy for
+/// reprocessing particular files or running from a scheduler.
+///
 #[derive(Debug, Parser)]
 #[clap(bin_name = "findfire")]
 #[clap(author, version, about)]
 struct FindFireOptionsInit {
+    /// The path to a TOML configuration file.
+    ///
+    /// The cluster_store_file, data_dir, region, and loader_threads settings are used from the
+    /// file. Command line arguments and environment variables override the values in the file.
+    /// Without a region, clusters over North America are kept.
+    #[clap(long)]
+    config: Option<PathBuf>,
+
     /// The path to the cluster database file.
     ///
     /// If this is not specified, then the program will check for it in the "CLUSTER_DB"
-    /// environment variable.
+    /// environment variable, and then the configuration file. It is only optional when
+    /// --jsonl-out is given.
     #[clap(short, long)]
     #[clap(env = "CLUSTER_DB")]
-    cluster_store_file: PathBuf,
+    cluster_store_file: Option<PathBuf>,
+
+    /// Append the clusters to this JSON Lines file, one JSON object per cluster.
+    ///
+    /// Each object has the satellite, sector, scan start and end times, centroid, power, area,
+    /// maximum temperature, maximum scan angle, and number of pixels of a cluster. The file is
+    /// created if it doesn't exist. The options that depend on the database, like --new-only,
+    /// can't be used without one.
+    #[clap(long)]
+    jsonl_out: Option<PathBuf>,
 
     /// The path to a KMZ file to produce from this run.
     ///
     /// If this is not specified, then the program will create on automatically by replacing the
-    /// file extension on the store_file with "*.kmz".
+    /// file extension on the store_file, or the jsonl_out file without a store_file, with "*.kmz".
     #[clap(short, long)]
     kmz_file: Option<PathBuf>,
 
     /// The path to the data directory that will be walked to find new data.
     ///
     /// If this is not specified, then the program will check for it in the "SAT_ARCHIVE"
-    /// environment variable.
+    /// environment variable, and then the configuration file.
     #[clap(short, long)]
     #[clap(env = "SAT_ARCHIVE")]
-    data_dir: PathBuf,
+    data_dir: Option<PathBuf>,
+
+    /// Process these files instead of walking the data directory, use "-" to read paths from
+    /// standard input one per line.
+    ///
+    /// Every file name must parse as a GOES fire product. The files are loaded even if they are
+    /// already in the database, so --new-only, --retry-failed, and --sectors can't be used with
+    /// this.
+    #[clap(long, multiple_values = true)]
+    files: Vec<PathBuf>,
+
+    /// The number of threads to use for loading data files, the default is 4.
+    #[clap(long)]
+    loader_threads: Option<usize>,
 
     /// Only look for data newer than the most recent in the database.
+    ///
+    /// The newest scan processed for each satellite and sector is recorded at the end of every
+    /// run, and that is used if available. Otherwise the database is searched for the newest scan.
     #[clap(short, long)]
     new_only: bool,
 
+    /// Try to load files again even if they failed on a previous run.
+    ///
+    /// Files that fail to load are recorded in the database and skipped on later runs unless this
+    /// flag is set.
+    #[clap(long)]
+    retry_failed: bool,
+
+    /// How pixels are connected when grouping them into clusters.
+    ///
+    /// With 8 (the default) pixels that only touch diagonally are part of the same cluster, with 4
+    /// only pixels that share an edge are. Allowed values are 4 and 8.
+    #[clap(long)]
+    #[clap(parse(try_from_str=parse_connectivity))]
+    #[clap(default_value = "8")]
+    connectivity: Connectivity,
+
+    /// Remove clusters duplicated in overlapping sectors at the same time.
+    ///
+    /// When the same fire is seen in the CONUS and full disk sectors at the same time, only keep
+    /// the one from the sector with the more frequent updates. This holds all the clusters loaded
+    /// during a run in memory until the end of the run, so it is best suited to processing
+    /// modestly sized chunks of the archive.
+    #[clap(long)]
+    dedup_sectors: bool,
+
+    /// Store the cluster pixels in the database compressed.
+    ///
+    /// This makes the database much smaller for a small CPU cost. Databases can have a mix of
+    /// compressed and uncompressed pixels, and all versions of this program that understand
+    /// compressed pixels read both.
+    #[clap(long)]
+    compress_pixels: bool,
+
+    /// Optimize and vacuum the database at the end of the run to reclaim unused space.
+    ///
+    /// This can take a long time for a large database, and needs free disk space about the size
+    /// of the database while it runs. No other programs should be using the database.
+    #[clap(long)]
+    optimize: bool,
+
+    /// Switch the database to write-ahead logging so it can be read while it is being filled.
+    ///
+    /// The database stays in this mode afterwards. It is faster, but the last few scans added
+    /// may be lost if the computer crashes or loses power. It doesn't work on network file
+    /// systems.
+    #[clap(long)]
+    wal: bool,
+
+    /// Also export the clusters to a directory of newline delimited JSON files.
+    ///
+    /// The files are partitioned into satellite/sector/date subdirectories with one file per scan,
+    /// which is handy for incremental loading into other systems.
+    #[clap(long)]
+    export_dir: Option<PathBuf>,
+
+    /// Warn about clusters with a reported fire area this many times larger or smaller than the
+    /// area of their pixels.
+    ///
+    /// Near the limb the fire area in the file can be wildly inconsistent with the size of the
+    /// pixels. The suspect clusters are logged with their centroid, but they are still stored.
+    #[clap(long)]
+    warn_area_ratio: Option<f64>,
+
+    /// Drop clusters with fewer than this many pixels before storing them.
+    ///
+    /// Most single pixel clusters are noise, so this can be used to reject them. Scans where every
+    /// cluster is dropped are still recorded as processed.
+    #[clap(long)]
+    min_cluster_pixels: Option<usize>,
+
+    /// Only store this many of the most powerful clusters from each scan.
+    ///
+    /// This makes the database much smaller when only the major fires are of interest. It is
+    /// applied after the clusters outside the area of interest are removed. Scans where every
+    /// cluster is dropped are still recorded as processed.
+    #[clap(long)]
+    top_n: Option<usize>,
+
+    /// Use the published projection for the satellite when a file is missing it.
+    ///
+    /// Some older or non-standard files don't have all of the projection attributes. The GOES
+    /// projections are fixed, so they can still be geolocated with the documented values.
+    #[clap(long)]
+    default_projection: bool,
+
+    /// Correct the pixel locations for parallax assuming the fires are this many meters above
+    /// the ellipsoid.
+    ///
+    /// Pixels are located on the surface of the ellipsoid by default, which misplaces fires in
+    /// high terrain.
+    #[clap(long)]
+    terrain_height: Option<f64>,
+
+    /// Only process these sectors, a comma separated list like "FDCF,FDCC".
+    ///
+    /// The sectors are FDCF (full disk), FDCC (CONUS), FDCM1 and FDCM2 (the meso-sectors), or FDCM
+    /// for both meso-sectors. All sectors are processed by default.
+    #[clap(long)]
+    sectors: Option<String>,
+
+    /// Show times in reports in this time zone, like "America/Denver", or at a fixed offset from
+    /// UTC, like "-06" or "+05:30".
+    ///
+    /// Times are always stored in UTC, this only changes how they are displayed. Named zones
+    /// follow daylight saving time.
+    #[clap(long)]
+    #[clap(parse(try_from_str=parse_time_zone))]
+    time_zone: Option<OutputTimeZone>,
+
     /// Verbose output
     #[clap(short, long)]
     verbose: bool,
 }
 
+fn parse_connectivity(connectivity: &str) -> SatFireResult<Connectivity> {
+    match connectivity {
+        "4" => Ok(Connectivity::Four),
+        "8" => Ok(Connectivity::Eight),
+        _ => Err(format!("Connectivity must be 4 or 8: {}", connectivity).into()),
+    }
+}
+
+/// Expand "-" into the paths read from `stdin` and make sure every file looks like a GOES fire
+/// product.
+///
+/// If any files were requested the list can't come up empty, otherwise an empty standard input
+/// would quietly fall back to walking the data directory.
+fn collect_files<R: BufRead>(files: Vec<PathBuf>, stdin: R) -> SatFireResult<Vec<PathBuf>> {
+    let requested = !files.is_empty();
+    let mut collected = Vec::with_capacity(files.len());
+    let mut stdin = Some(stdin);
+
+    for path in files {
+        if path == Path::new("-") {
+            let lines = match stdin.take() {
+                Some(stdin) => stdin.lines(),
+                None => continue,
+            };
+            for line in lines {
+                let line = line?;
+                let line = line.trim();
+                if !line.is_empty() {
+                    collected.push(PathBuf::from(line));
+                }
+            }
+        } else {
+            collected.push(path);
+        }
+    }
+
+    for path in &collected {
+        let parsed = path.file_name().and_then(|fname| {
+            satfire::parse_satellite_description_from_file_name(&fname.to_string_lossy())
+        });
+        if parsed.is_none() {
+            return Err(format!("Not a satellite fire product: {}", path.display()).into());
+        }
+    }
+
+    if requested && collected.is_empty() {
+        return Err("No files to process from --files".into());
+    }
+
+    Ok(collected)
+}
+
+fn parse_sectors(sectors: &str) -> SatFireResult<Vec<Sector>> {
+    let mut parsed = vec![];
+
+    for name in sectors.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let name = name.to_ascii_uppercase();
+
+        // A name without its trailing number, like FDCM, selects all the numbered sectors.
+        let named: Vec<Sector> = Sector::iter()
+            .filter(|sector| {
+                sector.name() == name
+                    || sector
+                        .name()
+                        .strip_prefix(name.as_str())
+                        .is_some_and(|num| {
+                            !num.is_empty() && num.chars().all(|c| c.is_ascii_digit())
+                        })
+            })
+            .collect();
+
+        if named.is_empty() {
+            return Err(format!("Unknown sector: {}", name).into());
+        }
+
+        for sector in named {
+            if !parsed.contains(&sector) {
+                parsed.push(sector);
+            }
+        }
+    }
+
+    if parsed.is_empty() {
+        return Err("At least one sector must be selected".into());
+    }
+
+    Ok(parsed)
+}
+
 #[derive(Debug)]
 struct FindFireOptionsChecked {
     /// The path to the database file.
-    cluster_store_file: PathBuf,
+    cluster_store_file: Option<PathBuf>,
+
+    /// Append the clusters to this JSON Lines file.
+    jsonl_out: Option<PathBuf>,
 
     /// The path to a KMZ file to produce from this run.
     kmz_file: PathBuf,
 
     /// The path to the data directory that will be walked to find new data.
-    data_dir: PathBuf,
+    data_dir: Option<PathBuf>,
+
+    /// Process these files instead of walking the data directory.
+    files: Vec<PathBuf>,
+
+    /// Only keep clusters in this region.
+    region: BoundingBox,
+
+    /// The number of threads to use for loading data files.
+    loader_threads: usize,
 
     /// Only look for data newer than the most recent in the database.
     new_only: bool,
 
+    /// Try to load files again even if they failed on a previous run.
+    retry_failed: bool,
+
+    /// How pixels are connected when grouping them into clusters.
+    connectivity: Connectivity,
+
+    /// Remove clusters duplicated in overlapping sectors at the same time.
+    dedup_sectors: bool,
+
+    /// Store the cluster pixels in the database compressed.
+    compress_pixels: bool,
+
+    /// Optimize and vacuum the database at the end of the run.
+    optimize: bool,
+
+    /// Switch the database to write-ahead logging.
+    wal: bool,
+
+    /// Also export the clusters to a directory of newline delimited JSON files.
+    export_dir: Option<PathBuf>,
+
+    /// Warn about clusters with a reported fire area this many times larger or smaller than the
+    /// area of their pixels.
+    warn_area_ratio: Option<f64>,
+
+    /// Drop clusters with fewer than this many pixels before storing them.
+    min_cluster_pixels: Option<usize>,
+
+    /// Only store this many of the most powerful clusters from each scan.
+    top_n: Option<usize>,
+
+    /// Use the published projection for the satellite when a file is missing it.
+    default_projection: bool,
+
+    /// Correct the pixel locations for parallax with this terrain height in meters.
+    terrain_height: Option<f64>,
+
+    /// Only process these sectors.
+    sectors: Vec<Sector>,
+
+    /// The time zone used to display times.
+    time_zone: OutputTimeZone,
+
     /// Verbose output
     verbose: bool,
 }
@@ -100,18 +398,110 @@ struct FindFireOptionsChecked {
 ///
 /// If there is missing data, try to fill it in with environment variables.
 fn parse_args() -> SatFireResult<FindFireOptionsChecked> {
+    let opts = check_args(FindFireOptionsInit::parse())?;
+
+    Ok(opts)
+}
+
+/// Merge the command line arguments with the configuration file, if any, and check them.
+fn check_args(init: FindFireOptionsInit) -> SatFireResult<FindFireOptionsChecked> {
     let FindFireOptionsInit {
+        config,
         cluster_store_file,
+        jsonl_out,
         kmz_file,
         data_dir,
+        files,
+        loader_threads,
         new_only,
+        retry_failed,
+        connectivity,
+        dedup_sectors,
+        compress_pixels,
+        optimize,
+        wal,
+        export_dir,
+        warn_area_ratio,
+        min_cluster_pixels,
+        top_n,
+        default_projection,
+        terrain_height,
+        sectors,
+        time_zone,
         verbose,
-    } = FindFireOptionsInit::parse();
+    } = init;
+
+    let config = match config {
+        Some(path) => SatFireConfig::from_file(path)?,
+        None => SatFireConfig::default(),
+    };
+
+    let cluster_store_file = cluster_store_file.or(config.cluster_store_file);
+    if cluster_store_file.is_none() {
+        if jsonl_out.is_none() {
+            return Err("No cluster store file specified".into());
+        }
+
+        let needs_database = [
+            (new_only, "--new-only"),
+            (optimize, "--optimize"),
+            (wal, "--wal"),
+        ];
+        for (set, flag) in needs_database {
+            if set {
+                return Err(format!("{} requires a cluster store file", flag).into());
+            }
+        }
+    }
+    let files = collect_files(files, std::io::stdin().lock())?;
+    let data_dir = data_dir.or(config.data_dir);
+    if files.is_empty() {
+        if data_dir.is_none() {
+            return Err("No data directory specified".into());
+        }
+    } else {
+        let not_for_files = [
+            (new_only, "--new-only"),
+            (retry_failed, "--retry-failed"),
+            (sectors.is_some(), "--sectors"),
+        ];
+        for (set, flag) in not_for_files {
+            if set {
+                return Err(format!("{} can't be used with --files", flag).into());
+            }
+        }
+    }
+    let region = config.region.unwrap_or(DEFAULT_REGION);
+    let loader_threads = loader_threads
+        .or(config.loader_threads)
+        .unwrap_or(DEFAULT_LOADER_THREADS);
+
+    if loader_threads == 0 {
+        return Err("There must be at least 1 loader thread".into());
+    }
+
+    if let Some(ratio) = warn_area_ratio {
+        if ratio.is_nan() || ratio < 1.0 {
+            return Err(format!("The area ratio must be at least 1: {}", ratio).into());
+        }
+    }
+
+    let sectors = match sectors {
+        Some(sectors) => parse_sectors(&sectors)?,
+        None => Sector::iter().collect(),
+    };
+
+    let time_zone = time_zone.unwrap_or_default();
 
     let kmz_file = match kmz_file {
         Some(v) => v,
         None => {
-            let mut clone = cluster_store_file.clone();
+            // One of these is always set by now.
+            let mut clone = cluster_store_file
+                .as_ref()
+                .or(jsonl_out.as_ref())
+                .cloned()
+                .unwrap_or_default();
             clone.set_extension("kmz");
             clone
         }
@@ -119,9 +509,27 @@ fn parse_args() -> SatFireResult<FindFireOptionsChecked> {
 
     Ok(FindFireOptionsChecked {
         cluster_store_file,
+        jsonl_out,
         kmz_file,
         data_dir,
+        files,
+        region,
+        loader_threads,
         new_only,
+        retry_failed,
+        connectivity,
+        dedup_sectors,
+        compress_pixels,
+        optimize,
+        wal,
+        export_dir,
+        warn_area_ratio,
+        min_cluster_pixels,
+        top_n,
+        default_projection,
+        terrain_height,
+        sectors,
+        time_zone,
         verbose,
     })
 }
@@ -129,7 +537,19 @@ fn parse_args() -> SatFireResult<FindFireOptionsChecked> {
 /*-------------------------------------------------------------------------------------------------
  *                                            Main
  *-----------------------------------------------------------------------------------------------*/
-const NUM_LOADER_THREADS: u8 = 4;
+const DEFAULT_LOADER_THREADS: usize = 4;
+
+/// The clusters kept when the configuration file doesn't have a region, North America.
+const DEFAULT_REGION: BoundingBox = BoundingBox {
+    ll: Coord {
+        lat: 24.0,
+        lon: -177.0,
+    },
+    ur: Coord {
+        lat: 90.0,
+        lon: -50.0,
+    },
+};
 
 fn main() -> SatFireResult<()> {
     SimpleLogger::new().init()?;
@@ -140,64 +560,212 @@ fn main() -> SatFireResult<()> {
         info!(target: "startup", "{:#?}", opts);
     }
 
-    ClusterDatabase::initialize(&opts.cluster_store_file)?;
+    if let Some(ref store_file) = opts.cluster_store_file {
+        if opts.wal {
+            // The journal mode is saved in the database, so the other connections use it too.
+            drop(ClusterDatabase::connect_wal(store_file)?);
+        } else {
+            ClusterDatabase::initialize(store_file)?;
+        }
+
+        if opts.verbose {
+            let summary = ClusterDatabase::connect(store_file)?.summary()?;
+            info!(target: "startup", "Already in the database:\n{}", summary);
+        }
+    }
 
-    let (to_present_filter, from_dir_walker) = bounded(512);
     let (to_loader, from_present_filter) = bounded(512);
     let (to_db_writer, from_loader) = bounded(512);
 
-    let data_dir = &opts.data_dir;
-    let store_file = &opts.cluster_store_file;
+    let store_file = opts.cluster_store_file.as_ref();
     let verbose = opts.verbose;
     let only_new = opts.new_only;
+    let retry_failed = opts.retry_failed;
+
+    let sectors = &opts.sectors;
 
-    let walk_dir = dir_walker(data_dir, store_file, to_present_filter, only_new, verbose)?;
-    let filter_present = filter_already_processed(store_file, from_dir_walker, to_loader, verbose)?;
-    let loader = loader_threads(from_present_filter, to_db_writer, verbose)?;
+    let progress = if verbose {
+        Some(progress_reporter(PROGRESS_INTERVAL)?)
+    } else {
+        None
+    };
+
+    let mut file_sources = vec![];
+    match opts.data_dir {
+        Some(ref data_dir) if opts.files.is_empty() => {
+            let (to_present_filter, from_dir_walker) = bounded(512);
+
+            file_sources.push(dir_walker(
+                data_dir,
+                store_file,
+                to_present_filter,
+                only_new,
+                sectors.clone(),
+                verbose,
+            )?);
+            file_sources.extend(filter_already_processed(
+                store_file,
+                from_dir_walker,
+                to_loader,
+                retry_failed,
+                sectors.clone(),
+                verbose,
+            )?);
+        }
+        _ => file_sources.push(file_list_feeder(opts.files.clone(), to_loader)?),
+    }
+    let loader = loader_threads(
+        from_present_filter,
+        to_db_writer,
+        opts.loader_threads,
+        opts.connectivity,
+        ClusterQc::default(),
+        load_options(
+            opts.warn_area_ratio,
+            opts.default_projection,
+            opts.terrain_height,
+        ),
+        opts.min_cluster_pixels,
+        verbose,
+    )?;
     let db_filler = db_filler_thread(
-        &opts.cluster_store_file,
+        store_file,
         from_loader,
         &opts.kmz_file,
+        opts.region,
+        opts.dedup_sectors,
+        opts.compress_pixels,
+        opts.export_dir.clone(),
+        opts.jsonl_out.clone(),
+        opts.top_n,
+        opts.time_zone,
         opts.verbose,
     )?;
 
     db_filler.join().expect("Error joining db filler thread")?;
-    walk_dir.join().expect("Error joining dir walker thread")?;
 
-    for jh in filter_present {
-        jh.join().expect("Error joining filter thread")?;
+    for jh in file_sources {
+        jh.join()
+            .expect("Error joining dir walker or filter thread")?;
     }
 
     for jh in loader {
         jh.join().expect("Error joining loader thread")?;
     }
 
+    if let Some((stop_progress, jh)) = progress {
+        drop(stop_progress);
+        jh.join().expect("Error joining progress thread");
+    }
+
+    if let (true, Some(store_file)) = (opts.optimize, store_file) {
+        if verbose {
+            info!(target: "shutdown", "Optimizing {}", store_file.display());
+        }
+        ClusterDatabase::connect(store_file)?.optimize()?;
+    }
+
     Ok(())
 }
 
+/*-------------------------------------------------------------------------------------------------
+ *                                          Progress
+ *-----------------------------------------------------------------------------------------------*/
+/// How often to log progress in verbose mode.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The number of files sent to the loader threads.
+static NUM_FILES_QUEUED: AtomicU64 = AtomicU64::new(0);
+
+/// The number of files the loader threads have finished with, whether they loaded or not.
+static NUM_FILES_LOADED: AtomicU64 = AtomicU64::new(0);
+
+/// Set when the directory walk is complete, so the number of files queued is nearly final.
+static WALK_DONE: AtomicBool = AtomicBool::new(false);
+
+/// Describe the progress of the loader threads.
+///
+/// The ETA is only estimated once the directory walk is done, before that the total number of
+/// files isn't known.
+fn progress_message(loaded: u64, queued: u64, walk_done: bool, elapsed: Duration) -> String {
+    let elapsed = elapsed.as_secs_f64();
+    let rate = if elapsed > 0.0 {
+        loaded as f64 / elapsed
+    } else {
+        0.0
+    };
+
+    if !walk_done {
+        return format!(
+            "{} files processed, {:.1} files/s, {} found so far",
+            loaded, rate, queued
+        );
+    }
+
+    let remaining = queued.saturating_sub(loaded);
+    if rate > 0.0 {
+        let eta = (remaining as f64 / rate).round() as u64;
+        format!(
+            "{} of {} files processed, {:.1} files/s, ETA {}h {:02}m",
+            loaded,
+            queued,
+            rate,
+            eta / 3_600,
+            (eta % 3_600) / 60
+        )
+    } else {
+        format!("{} of {} files processed", loaded, queued)
+    }
+}
+
+/// Start a thread that logs progress every `interval` until the returned sender is dropped.
+fn progress_reporter(interval: Duration) -> SatFireResult<(Sender<()>, JoinHandle<()>)> {
+    let (stop, stopped) = bounded::<()>(0);
+    let start = Instant::now();
+
+    let jh = std::thread::Builder::new()
+        .name("findfire-progress".to_owned())
+        .spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                info!(target: "progress", "{}", progress_message(
+                    NUM_FILES_LOADED.load(Ordering::Relaxed),
+                    NUM_FILES_QUEUED.load(Ordering::Relaxed),
+                    WALK_DONE.load(Ordering::Relaxed),
+                    start.elapsed(),
+                ));
+            }
+        })?;
+
+    Ok((stop, jh))
+}
+
 /*-------------------------------------------------------------------------------------------------
  *                           Threads - Functions that start threads
  *-----------------------------------------------------------------------------------------------*/
 fn dir_walker<P: AsRef<Path>>(
     data_dir: P,
-    store_file: P,
+    store_file: Option<P>,
     to_db_present_filter: Sender<PathBuf>,
     only_new: bool,
+    sectors: Vec<Sector>,
     verbose: bool,
 ) -> SatFireResult<JoinHandle<SatFireResult<()>>> {
     let data_dir = data_dir.as_ref().to_path_buf();
 
     // Get the most recent version in the database if necessary
     let mut most_recent = HashMap::new();
-    if only_new {
+    if let (true, Some(store_file)) = (only_new, store_file) {
         let db = ClusterDatabase::connect(store_file)?;
 
         for sat in Satellite::iter() {
             let inner = most_recent.entry(sat).or_insert_with(HashMap::new);
             for sector in Sector::iter() {
-                let latest = db
-                    .newest_scan_start(sat, sector)
-                    .unwrap_or_else(|_| sat.operational());
+                let latest = match db.high_water_mark(sat, sector)? {
+                    Some(latest) => latest,
+                    None => db
+                        .newest_scan_start(sat, sector)
+                        .unwrap_or_else(|_| sat.operational()),
+                };
                 inner.insert(sector, latest);
 
                 if verbose {
@@ -214,7 +782,7 @@ fn dir_walker<P: AsRef<Path>>(
         }
     }
 
-    let standard_dir_filter = create_standard_dir_filter(most_recent, verbose);
+    let standard_dir_filter = create_standard_dir_filter(most_recent, sectors, verbose);
 
     let jh = std::thread::Builder::new()
         .name("findfire-walker".to_owned())
@@ -235,6 +803,29 @@ fn dir_walker<P: AsRef<Path>>(
                 to_db_present_filter.send(entry.into_path())?;
             }
 
+            WALK_DONE.store(true, Ordering::Relaxed);
+
+            Ok(())
+        })?;
+
+    Ok(jh)
+}
+
+/// Send a list of files straight to the loaders, without checking the database.
+fn file_list_feeder(
+    files: Vec<PathBuf>,
+    to_loader: Sender<PathBuf>,
+) -> SatFireResult<JoinHandle<SatFireResult<()>>> {
+    let jh = std::thread::Builder::new()
+        .name("findfire-files".to_owned())
+        .spawn(move || {
+            for path in files {
+                NUM_FILES_QUEUED.fetch_add(1, Ordering::Relaxed);
+                to_loader.send(path)?;
+            }
+
+            WALK_DONE.store(true, Ordering::Relaxed);
+
             Ok(())
         })?;
 
@@ -242,12 +833,14 @@ fn dir_walker<P: AsRef<Path>>(
 }
 
 fn filter_already_processed<P: AsRef<Path>>(
-    store_file: P,
+    store_file: Option<P>,
     from_dir_walker: Receiver<PathBuf>,
     to_loader: Sender<PathBuf>,
+    retry_failed: bool,
+    sectors: Vec<Sector>,
     verbose: bool,
 ) -> SatFireResult<Vec<JoinHandle<SatFireResult<()>>>> {
-    let store_file = store_file.as_ref().to_path_buf();
+    let store_file = store_file.map(|p| p.as_ref().to_path_buf());
 
     let mut handles = Vec::with_capacity(num_cpus::get());
 
@@ -255,23 +848,48 @@ fn filter_already_processed<P: AsRef<Path>>(
         let to_loader_clone = to_loader.clone();
         let from_dir_walker_clone = from_dir_walker.clone();
         let store_file_clone = store_file.clone();
+        let sectors = sectors.clone();
 
         let jh = std::thread::Builder::new()
             .name("findifre-filter".to_owned())
             .spawn(move || {
-                let db = ClusterDatabase::connect(store_file_clone)?;
-                let mut is_present = db.prepare_to_query_clusters_present()?;
+                // Without a database nothing has been processed before.
+                let db = store_file_clone.map(ClusterDatabase::connect).transpose()?;
+                let mut is_present = db
+                    .as_ref()
+                    .map(|db| db.prepare_to_query_clusters_present())
+                    .transpose()?;
 
                 for path in from_dir_walker_clone {
                     if let Some((sat, sector, start, end)) = path.file_name().and_then(|fname| {
                         satfire::parse_satellite_description_from_file_name(&fname.to_string_lossy())
                     }) {
-                        if !is_present.present(sat, sector, start, end)? {
+                        if !sectors.contains(&sector) {
+                            if verbose {
+                                info!(target: "filter", "sector not selected: {}", path.display());
+                            }
+                        } else if !retry_failed
+                            && is_present
+                                .as_mut()
+                                .map(|q| q.previously_failed(&path))
+                                .transpose()?
+                                .unwrap_or(false)
+                        {
+                            if verbose {
+                                info!(target: "filter", "previously failed: {}", path.display());
+                            }
+                        } else if !is_present
+                            .as_mut()
+                            .map(|q| q.present(sat, sector, start, end))
+                            .transpose()?
+                            .unwrap_or(false)
+                        {
                             if verbose {
                                 info!(target: "filter", "processing {} {} {}", sat, sector, start);
                                 debug!(target: "filter", "processing {} {} {} - {}", sat, sector, start, path.display());
                             }
 
+                            NUM_FILES_QUEUED.fetch_add(1, Ordering::Relaxed);
                             to_loader_clone.send(path)?;
                         } else if verbose {
                             info!(target: "filter", "already in db: {}", path.display());
@@ -287,35 +905,66 @@ fn filter_already_processed<P: AsRef<Path>>(
     Ok(handles)
 }
 
+/// The options for reading files when loading them.
+fn load_options(
+    warn_area_ratio: Option<f64>,
+    default_projection: bool,
+    terrain_height: Option<f64>,
+) -> LoadOptions {
+    let mut options = LoadOptions::default().use_default_projection(default_projection);
+    if let Some(ratio) = warn_area_ratio {
+        options = options.warn_area_ratio(ratio);
+    }
+    if let Some(meters) = terrain_height {
+        options = options.correct_parallax(TerrainHeight::Constant(meters));
+    }
+    options
+}
+
+#[allow(clippy::too_many_arguments)]
 fn loader_threads(
     from_db_present_filter: Receiver<PathBuf>,
-    to_db_writer: Sender<ClusterList>,
+    to_db_writer: Sender<DatabaseMessage>,
+    num_threads: usize,
+    connectivity: Connectivity,
+    qc: ClusterQc,
+    options: LoadOptions,
+    min_cluster_pixels: Option<usize>,
     verbose: bool,
 ) -> SatFireResult<Vec<JoinHandle<SatFireResult<()>>>> {
-    let mut jhs = Vec::with_capacity(NUM_LOADER_THREADS as usize);
+    let mut jhs = Vec::with_capacity(num_threads);
 
-    for _ in 0..NUM_LOADER_THREADS {
+    for _ in 0..num_threads {
         let from_db_present = from_db_present_filter.clone();
         let to_db_writer = to_db_writer.clone();
+        let qc = qc.clone();
+        let options = options.clone();
 
         let jh = std::thread::Builder::new()
             .name("findfire-load".to_owned())
             .spawn(move || {
                 for path in from_db_present {
-                    let mut clist = match ClusterList::from_file(&path) {
+                    let clist =
+                        ClusterList::from_file_with_options(&path, connectivity, &qc, &options);
+                    NUM_FILES_LOADED.fetch_add(1, Ordering::Relaxed);
+
+                    let mut clist = match clist {
                         Ok(clist) => clist,
                         Err(err) => {
                             if verbose {
                                 warn!(target: "loading", "({}) {}", err, path.display());
                             }
 
+                            to_db_writer.send(DatabaseMessage::Failed((path, err.to_string())))?;
                             continue;
                         }
                     };
 
-                    clist.filter(is_cluster_a_keeper);
+                    if let Some(min) = min_cluster_pixels {
+                        clist.filter_min_pixels(min);
+                    }
 
-                    to_db_writer.send(clist)?;
+                    to_db_writer.send(DatabaseMessage::Clusters((path, clist)))?;
                 }
 
                 Ok(())
@@ -327,40 +976,135 @@ fn loader_threads(
     Ok(jhs)
 }
 
+enum DatabaseMessage {
+    Clusters((PathBuf, ClusterList)),
+    Failed((PathBuf, String)),
+}
+
+#[allow(clippy::too_many_arguments)]
 fn db_filler_thread<P: AsRef<Path>>(
-    store_file: P,
-    from_loader: Receiver<ClusterList>,
+    store_file: Option<P>,
+    from_loader: Receiver<DatabaseMessage>,
     kmz_path: P,
+    bb: BoundingBox,
+    dedup_sectors: bool,
+    compress_pixels: bool,
+    export_dir: Option<PathBuf>,
+    jsonl_out: Option<PathBuf>,
+    top_n: Option<usize>,
+    time_zone: OutputTimeZone,
     verbose: bool,
 ) -> SatFireResult<JoinHandle<SatFireResult<()>>> {
-    let store_file = store_file.as_ref().to_path_buf();
+    let store_file = store_file.map(|p| p.as_ref().to_path_buf());
     let kmz_path = kmz_path.as_ref().to_path_buf();
 
     let jh = std::thread::Builder::new()
         .name("findfire-dbase".to_owned())
         .spawn(move || {
-            let bb = BoundingBox {
-                ll: Coord {
-                    lat: 24.0,
-                    lon: -177.0,
-                },
-                ur: Coord {
-                    lat: 90.0,
-                    lon: -50.0,
-                },
+            let db = store_file.map(ClusterDatabase::connect).transpose()?;
+            let mut add_stmt = match db {
+                Some(ref db) => {
+                    let mut add_stmt = db.prepare_to_add_clusters()?;
+                    add_stmt.set_compress_pixels(compress_pixels);
+                    Some(add_stmt)
+                }
+                None => None,
             };
 
-            let db = ClusterDatabase::connect(store_file)?;
-            let mut add_stmt = db.prepare_to_add_clusters()?;
+            let mut jsonl = match jsonl_out {
+                Some(path) => Some(BufWriter::new(
+                    OpenOptions::new().create(true).append(true).open(path)?,
+                )),
+                None => None,
+            };
 
             let mut cluster_stats: Option<ClusterStats> = None;
             let mut cluster_list_stats: Option<ClusterListStats> = None;
 
-            for mut cluster_list in from_loader {
+            let mut held_for_dedup: Vec<ClusterList> = vec![];
+            let mut held_paths: Vec<PathBuf> = vec![];
+            let mut newest_scans: HashMap<(Satellite, Sector), DateTime<Utc>> = HashMap::new();
+
+            for message in from_loader {
+                let (path, mut cluster_list) = match message {
+                    DatabaseMessage::Clusters(loaded) => loaded,
+                    DatabaseMessage::Failed((path, err)) => {
+                        if let Some(ref mut add_stmt) = add_stmt {
+                            add_stmt.add_failed(path, &err)?;
+                        }
+                        continue;
+                    }
+                };
+
+                if dedup_sectors {
+                    held_for_dedup.push(cluster_list);
+                    held_paths.push(path);
+                    continue;
+                }
+
                 cluster_list.filter_box(bb);
+                if let Some(n) = top_n {
+                    cluster_list.retain_top_n_by_power(n);
+                }
                 ClusterStats::update(&mut cluster_stats, &cluster_list);
                 ClusterListStats::update(&mut cluster_list_stats, &cluster_list);
-                add_stmt.add(cluster_list)?;
+                update_newest_scan(&mut newest_scans, &cluster_list);
+                if let Some(ref export_dir) = export_dir {
+                    cluster_list.export_ndjson(export_dir)?;
+                }
+                if let Some(ref mut jsonl) = jsonl {
+                    cluster_list.write_jsonl(jsonl)?;
+                }
+                if let Some(ref mut add_stmt) = add_stmt {
+                    add_stmt.add_from_file(path, cluster_list)?;
+                }
+            }
+
+            if dedup_sectors {
+                let num_removed = ClusterList::deduplicate_across_sectors(&mut held_for_dedup);
+                if verbose {
+                    info!(target: "dedup", "Removed {} duplicated clusters.", num_removed);
+                }
+
+                for mut cluster_list in held_for_dedup {
+                    cluster_list.filter_box(bb);
+                    if let Some(n) = top_n {
+                        cluster_list.retain_top_n_by_power(n);
+                    }
+                    ClusterStats::update(&mut cluster_stats, &cluster_list);
+                    ClusterListStats::update(&mut cluster_list_stats, &cluster_list);
+                    update_newest_scan(&mut newest_scans, &cluster_list);
+                    if let Some(ref export_dir) = export_dir {
+                        cluster_list.export_ndjson(export_dir)?;
+                    }
+                    if let Some(ref mut jsonl) = jsonl {
+                        cluster_list.write_jsonl(jsonl)?;
+                    }
+                    if let Some(ref mut add_stmt) = add_stmt {
+                        add_stmt.add(cluster_list)?;
+                    }
+                }
+
+                // The lists are reordered by the deduplication, so clear the failures for all the
+                // files once they're stored.
+                if let Some(ref mut add_stmt) = add_stmt {
+                    for path in held_paths {
+                        add_stmt.clear_failed(path)?;
+                    }
+                }
+            }
+
+            if let Some(ref mut jsonl) = jsonl {
+                jsonl.flush()?;
+            }
+
+            if let Some(ref db) = db {
+                for ((sat, sector), newest) in newest_scans {
+                    db.update_high_water_mark(sat, sector, newest)?;
+                    if verbose {
+                        info!(target: "stats", "High water mark {} {}: {}", sat, sector, newest);
+                    }
+                }
             }
 
             if let (Some(ref cluster_stats), Some(ref cluster_list_stats)) =
@@ -368,7 +1112,7 @@ fn db_filler_thread<P: AsRef<Path>>(
             {
                 save_cluster_stats_kmz(kmz_path, cluster_stats)?;
                 if verbose {
-                    info!(target: "stats", "{}", cluster_stats);
+                    info!(target: "stats", "{}", cluster_stats.display_in(time_zone));
                     info!(target: "stats", "{}", cluster_list_stats);
                 }
             }
@@ -379,20 +1123,32 @@ fn db_filler_thread<P: AsRef<Path>>(
     Ok(jh)
 }
 
+/// Keep track of the newest scan start time for each satellite and sector.
+fn update_newest_scan(
+    newest_scans: &mut HashMap<(Satellite, Sector), DateTime<Utc>>,
+    cluster_list: &ClusterList,
+) {
+    let key = (cluster_list.satellite(), cluster_list.sector());
+    let start = cluster_list.scan_start();
+
+    let newest = newest_scans.entry(key).or_insert(start);
+    if start > *newest {
+        *newest = start;
+    }
+}
+
 /*-------------------------------------------------------------------------------------------------
  *                             Cluster and Image Statistics
  *-----------------------------------------------------------------------------------------------*/
 
-/* Use this as the maximum value of the scan angle allowed for a cluster to be considered in the
+/* Only clusters with a scan angle below ClusterQc::default_max_scan_angle are considered in the
  * summary statistics. This is a QC tool, there are a lot of outliers on the limb of the Earth as
  * viewed by the GOES satellites, and the angles / geometry seem to have something to do with it.
  *
- * The value of 8.3 degrees is based on visual inspection of a graph of cluster power vs max scan
- * angle of the cluster member centroids. Based on the satellite product documentation
- * (https://www.goes-r.gov/products/docs/PUG-L2+-vol5.pdf) I calculated that the limb of the Earth
- * is at a scan angle of about 8.7 degrees.
+ * The cutoff is a little inside Satellite::limb_scan_angle, the limb of the Earth at a scan angle
+ * of about 8.7 degrees based on the satellite product documentation
+ * (https://www.goes-r.gov/products/docs/PUG-L2+-vol5.pdf).
  */
-const MAX_SCAN_ANGLE: f64 = 8.3;
 
 #[derive(Debug, Clone)]
 struct ClusterStat {
@@ -420,14 +1176,14 @@ impl ClusterStat {
     }
 }
 
-impl Display for ClusterStat {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+impl ClusterStat {
+    fn fmt_in(&self, f: &mut Formatter, zone: OutputTimeZone) -> Result<(), fmt::Error> {
         let centroid = self.fire.centroid();
 
         writeln!(f, "      satellite: {}", self.sat.name())?;
         writeln!(f, "         sector: {}", self.sector.name())?;
-        writeln!(f, "          start: {}", self.start)?;
-        writeln!(f, "            end: {}", self.end)?;
+        writeln!(f, "          start: {}", OutputTime(self.start, zone))?;
+        writeln!(f, "            end: {}", OutputTime(self.end, zone))?;
         writeln!(f, "            Lat: {:10.6}", centroid.lat)?;
         writeln!(f, "            Lon: {:11.6}", centroid.lon)?;
         writeln!(f, " Max Scan Angle: {:3.0}", self.fire.max_scan_angle())?;
@@ -469,7 +1225,7 @@ impl ClusterStats {
         let end = clusters.scan_end();
 
         for cluster in clusters.clusters() {
-            if cluster.max_scan_angle() >= MAX_SCAN_ANGLE {
+            if cluster.max_scan_angle() >= ClusterQc::default_max_scan_angle(sat) {
                 return;
             }
 
@@ -548,50 +1304,61 @@ fn u32_pct(num: u32, denom: u32) -> u32 {
     (num / denom * 100.0).round() as u32
 }
 
-impl Display for ClusterStats {
+impl ClusterStats {
+    /// Display the stats with the scan times shown in `zone`.
+    fn display_in(&self, zone: OutputTimeZone) -> ClusterStatsInTimeZone<'_> {
+        ClusterStatsInTimeZone(self, zone)
+    }
+}
+
+struct ClusterStatsInTimeZone<'a>(&'a ClusterStats, OutputTimeZone);
+
+impl Display for ClusterStatsInTimeZone<'_> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let ClusterStatsInTimeZone(stats, zone) = *self;
+
         writeln!(f, "\nIndividual Cluster Stats\n")?;
         writeln!(f, " Most Powerful:")?;
-        write!(f, "{}", &self.biggest_fire)?;
+        stats.biggest_fire.fmt_in(f, zone)?;
         writeln!(f, "       Hottest:")?;
-        write!(f, "{}", &self.hottest_fire)?;
+        stats.hottest_fire.fmt_in(f, zone)?;
         writeln!(f, "        Counts:")?;
-        writeln!(f, "         Total: {:10}", self.num_clusters)?;
-        writeln!(f, "Power <   1 MW: {:10}", self.num_power_lt_1mw)?;
-        writeln!(f, "Power <  10 MW: {:10}", self.num_power_lt_10mw)?;
-        writeln!(f, "Power < 100 MW: {:10}", self.num_power_lt_100mw)?;
-        writeln!(f, "Power <   1 GW: {:10}", self.num_power_lt_1gw)?;
-        writeln!(f, "Power <  10 GW: {:10}", self.num_power_lt_10gw)?;
-        writeln!(f, "Power < 100 GW: {:10}", self.num_power_lt_100gw)?;
+        writeln!(f, "         Total: {:10}", stats.num_clusters)?;
+        writeln!(f, "Power <   1 MW: {:10}", stats.num_power_lt_1mw)?;
+        writeln!(f, "Power <  10 MW: {:10}", stats.num_power_lt_10mw)?;
+        writeln!(f, "Power < 100 MW: {:10}", stats.num_power_lt_100mw)?;
+        writeln!(f, "Power <   1 GW: {:10}", stats.num_power_lt_1gw)?;
+        writeln!(f, "Power <  10 GW: {:10}", stats.num_power_lt_10gw)?;
+        writeln!(f, "Power < 100 GW: {:10}", stats.num_power_lt_100gw)?;
         writeln!(
             f,
             "  Pct <   1 MW: {:10}",
-            u32_pct(self.num_power_lt_1mw, self.num_clusters)
+            u32_pct(stats.num_power_lt_1mw, stats.num_clusters)
         )?;
         writeln!(
             f,
             "  Pct <  10 MW: {:10}",
-            u32_pct(self.num_power_lt_10mw, self.num_clusters)
+            u32_pct(stats.num_power_lt_10mw, stats.num_clusters)
         )?;
         writeln!(
             f,
             "  Pct < 100 MW: {:10}",
-            u32_pct(self.num_power_lt_100mw, self.num_clusters)
+            u32_pct(stats.num_power_lt_100mw, stats.num_clusters)
         )?;
         writeln!(
             f,
             "  Pct <   1 GW: {:10}",
-            u32_pct(self.num_power_lt_1gw, self.num_clusters)
+            u32_pct(stats.num_power_lt_1gw, stats.num_clusters)
         )?;
         writeln!(
             f,
             "  Pct <  10 GW: {:10}",
-            u32_pct(self.num_power_lt_10gw, self.num_clusters)
+            u32_pct(stats.num_power_lt_10gw, stats.num_clusters)
         )?;
         writeln!(
             f,
             "  Pct < 100 GW: {:10}",
-            u32_pct(self.num_power_lt_100gw, self.num_clusters)
+            u32_pct(stats.num_power_lt_100gw, stats.num_clusters)
         )?;
         Ok(())
     }
@@ -685,6 +1452,7 @@ impl Display for ClusterListStats {
  *-----------------------------------------------------------------------------------------------*/
 fn create_standard_dir_filter(
     most_recent_in_db: HashMap<Satellite, HashMap<Sector, DateTime<Utc>>>,
+    sectors: Vec<Sector>,
     verbose: bool,
 ) -> impl FnMut(&walkdir::DirEntry) -> bool {
     /* This filter assumes the data is stored in a directory tree like:
@@ -714,6 +1482,13 @@ fn create_standard_dir_filter(
                 None => return true,
             };
 
+            if !sector_dir_selected(&path, sector, &sectors) {
+                if verbose {
+                    info!(target:"directory-filter", "skipping {}", entry.path().display());
+                }
+                return false;
+            }
+
             let most_recent = match most_recent_in_db.get(&sat) {
                 Some(hm) => match hm.get(&sector) {
                     Some(mr) => *mr,
@@ -812,31 +1587,15 @@ fn create_standard_dir_filter(
     }
 }
 
-fn is_cluster_a_keeper(cluster: &Cluster) -> bool {
-    // Check if it meets our mask criteria
-    let keep_mask_criteria = cluster.pixels().pixels().iter().any(|pixel| {
-        match pixel.mask_flag.0 {
-            10   // good_fire_pixel
-            | 11 // saturated_fire_pixel
-            | 12 // cloud_contaminated_fire_pixel
-            | 13 // high_probability_fire_pixel
-            | 14 // medium_probability_fire_pixel
-
-            | 30 // temporally_filtered_good_fire_pixel
-            | 31 // temporally_filtered_saturated_fire_pixel
-            | 32 // temporally_filtered_cloud_contaminated_fire_pixel
-            | 33 // temporally_filtered_high_probability_fire_pixel
-            | 34 // temporally_filtered_medium_probablity_fire_pixel
-                => {
-                 true
-            }
-            _ => false
-        }
-    });
-
-    let scan_angle_criteria = cluster.max_scan_angle() < MAX_SCAN_ANGLE;
-
-    keep_mask_criteria && scan_angle_criteria
+/// Check if a directory for a sector may have files from any of the selected sectors.
+///
+/// Both meso-sectors are usually stored in the same FDCM directory, which
+/// [Sector::string_contains_sector] reports as [Sector::MESO1].
+fn sector_dir_selected(path: &str, sector: Sector, sectors: &[Sector]) -> bool {
+    sectors.contains(&sector)
+        || (sector == Sector::MESO1
+            && !path.contains(Sector::MESO1.name())
+            && sectors.contains(&Sector::MESO2))
 }
 
 /*-------------------------------------------------------------------------------------------------
@@ -884,6 +1643,14 @@ fn output_cluster_stat_kml<K: KmlWriter>(
         cluster.fire.max_temperature()
     );
 
+    let mask_counts = cluster.fire.mask_summary();
+    let description = MaskCategory::iter()
+        .filter(|&category| mask_counts.get(category) > 0)
+        .fold(description + "<p>", |description, category| {
+            description + &format!("{}: {}<br/>", category, mask_counts.get(category))
+        })
+        + "</p>";
+
     let centroid = cluster.fire.centroid();
 
     out.start_folder(Some(label), None, true)?;
@@ -898,3 +1665,273 @@ fn output_cluster_stat_kml<K: KmlWriter>(
 
     Ok(())
 }
+
+/*-------------------------------------------------------------------------------------------------
+ *                                             Tests
+ *-----------------------------------------------------------------------------------------------*/
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config_file_with_overrides() {
+        // Make sure the environment doesn't override the config file.
+        std::env::remove_var("CLUSTER_DB");
+        std::env::remove_var("SAT_ARCHIVE");
+
+        let config_path =
+            std::env::temp_dir().join(format!("findfire_test_{}.toml", std::process::id()));
+        std::fs::write(
+            &config_path,
+            concat!(
+                "cluster_store_file = \"/config/findfire.sqlite\"\n",
+                "data_dir = \"/config/archive\"\n",
+                "loader_threads = 2\n",
+                "region = [30.0, -125.0, 50.0, -100.0]\n",
+            ),
+        )
+        .unwrap();
+
+        let config_arg = config_path.to_str().unwrap();
+
+        let opts = check_args(
+            FindFireOptionsInit::try_parse_from(["findfire", "--config", config_arg]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            opts.cluster_store_file.as_deref(),
+            Some(Path::new("/config/findfire.sqlite"))
+        );
+        assert_eq!(opts.kmz_file, Path::new("/config/findfire.kmz"));
+        assert_eq!(opts.data_dir.as_deref(), Some(Path::new("/config/archive")));
+        assert_eq!(opts.loader_threads, 2);
+        assert_eq!(opts.region.ll.lat, 30.0);
+        assert_eq!(opts.region.ur.lon, -100.0);
+
+        let opts = check_args(
+            FindFireOptionsInit::try_parse_from([
+                "findfire",
+                "--config",
+                config_arg,
+                "--data-dir",
+                "/cli/archive",
+                "--loader-threads",
+                "8",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            opts.cluster_store_file.as_deref(),
+            Some(Path::new("/config/findfire.sqlite"))
+        );
+        assert_eq!(opts.data_dir.as_deref(), Some(Path::new("/cli/archive")));
+        assert_eq!(opts.loader_threads, 8);
+
+        std::fs::remove_file(&config_path).unwrap();
+
+        // Without a config file the paths are required.
+        assert!(check_args(FindFireOptionsInit::try_parse_from(["findfire"]).unwrap()).is_err());
+        let opts = check_args(
+            FindFireOptionsInit::try_parse_from(["findfire", "-c", "/a.sqlite", "-d", "/b"])
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(opts.loader_threads, DEFAULT_LOADER_THREADS);
+        assert_eq!(opts.sectors, Sector::iter().collect::<Vec<_>>());
+
+        // A JSON Lines file can stand in for the database.
+        let opts = check_args(
+            FindFireOptionsInit::try_parse_from([
+                "findfire",
+                "--jsonl-out",
+                "/out/clusters.jsonl",
+                "-d",
+                "/b",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(opts.cluster_store_file.is_none());
+        assert_eq!(
+            opts.jsonl_out.as_deref(),
+            Some(Path::new("/out/clusters.jsonl"))
+        );
+        assert_eq!(opts.kmz_file, Path::new("/out/clusters.kmz"));
+
+        // But not for the options that only make sense with a database.
+        assert!(check_args(
+            FindFireOptionsInit::try_parse_from([
+                "findfire",
+                "--jsonl-out",
+                "/out/clusters.jsonl",
+                "-d",
+                "/b",
+                "--new-only",
+            ])
+            .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_list_mode() {
+        const FILE: &str =
+            "/archive/OR_ABI-L2-FDCC-M6_G17_s20212130101177_e20212130103550_c20212130104114.nc";
+
+        // No data directory is needed with a list of files.
+        let opts = check_args(
+            FindFireOptionsInit::try_parse_from(["findfire", "-c", "/a.sqlite", "--files", FILE])
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(opts.files, vec![PathBuf::from(FILE)]);
+
+        let opts = check_args(
+            FindFireOptionsInit::try_parse_from([
+                "findfire",
+                "-c",
+                "/a.sqlite",
+                "-d",
+                "/b",
+                "--files",
+                FILE,
+                FILE,
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(opts.files.len(), 2);
+
+        // Every file has to look like a fire product.
+        assert!(check_args(
+            FindFireOptionsInit::try_parse_from([
+                "findfire",
+                "-c",
+                "/a.sqlite",
+                "--files",
+                FILE,
+                "/archive/notes.txt",
+            ])
+            .unwrap(),
+        )
+        .is_err());
+
+        // The options for filtering the directory walk don't apply.
+        assert!(check_args(
+            FindFireOptionsInit::try_parse_from([
+                "findfire",
+                "-c",
+                "/a.sqlite",
+                "--files",
+                FILE,
+                "--new-only",
+            ])
+            .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_list_from_stdin() {
+        const FILE: &str =
+            "/archive/OR_ABI-L2-FDCC-M6_G17_s20212130101177_e20212130103550_c20212130104114.nc";
+
+        let stdin = format!("{}\n\n  {}  \n", FILE, FILE);
+        let files = collect_files(vec![PathBuf::from("-")], stdin.as_bytes()).unwrap();
+        assert_eq!(files, vec![PathBuf::from(FILE), PathBuf::from(FILE)]);
+
+        // Asking for files and getting none is an error, not a directory walk.
+        assert!(collect_files(vec![PathBuf::from("-")], "\n".as_bytes()).is_err());
+        assert!(collect_files(vec![], "".as_bytes()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_terrain_height_option() {
+        let opts = check_args(
+            FindFireOptionsInit::try_parse_from([
+                "findfire",
+                "-c",
+                "/a.sqlite",
+                "-d",
+                "/b",
+                "--terrain-height",
+                "1500",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(opts.terrain_height, Some(1500.0));
+
+        let options = load_options(None, false, opts.terrain_height);
+        let coord = Coord {
+            lat: 45.0,
+            lon: -115.0,
+        };
+        assert_eq!(options.parallax_terrain().unwrap().height_at(coord), 1500.0);
+        assert!(load_options(None, false, None).parallax_terrain().is_none());
+    }
+
+    #[test]
+    fn test_progress_message() {
+        let minute = Duration::from_secs(60);
+
+        assert_eq!(
+            progress_message(120, 300, false, minute),
+            "120 files processed, 2.0 files/s, 300 found so far"
+        );
+        // 7,200 files left at 2 files per second is an hour.
+        assert_eq!(
+            progress_message(120, 7_320, true, minute),
+            "120 of 7320 files processed, 2.0 files/s, ETA 1h 00m"
+        );
+        assert_eq!(
+            progress_message(0, 10, true, minute),
+            "0 of 10 files processed"
+        );
+    }
+
+    #[test]
+    fn test_sector_selection() {
+        assert_eq!(
+            parse_sectors("FDCF, fdcc").unwrap(),
+            vec![Sector::FULL, Sector::CONUS]
+        );
+        assert_eq!(
+            parse_sectors("FDCM,FDCM2").unwrap(),
+            vec![Sector::MESO1, Sector::MESO2]
+        );
+        assert!(parse_sectors("FDCX").is_err());
+        assert!(parse_sectors(",").is_err());
+
+        let full_disk = parse_sectors("FDCF").unwrap();
+        assert!(sector_dir_selected(
+            "/G17/ABI-L2-FDCF",
+            Sector::FULL,
+            &full_disk
+        ));
+        assert!(!sector_dir_selected(
+            "/G17/ABI-L2-FDCC",
+            Sector::CONUS,
+            &full_disk
+        ));
+        assert!(!sector_dir_selected(
+            "/G17/ABI-L2-FDCM",
+            Sector::MESO1,
+            &full_disk
+        ));
+
+        // Both meso-sectors share a directory.
+        let meso2 = parse_sectors("FDCM2").unwrap();
+        assert!(sector_dir_selected(
+            "/G17/ABI-L2-FDCM",
+            Sector::MESO1,
+            &meso2
+        ));
+        assert!(!sector_dir_selected(
+            "/G17/ABI-L2-FDCM1",
+            Sector::MESO1,
+            &meso2
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+use clap::Parser;
+use log::info;
+use satfire::{write_fire_time_series_csv, JointFiresClusterDatabases, SatFireResult};
+use simple_logger::SimpleLogger;
+use std::{
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+/*-------------------------------------------------------------------------------------------------
+ *                                     Command Line Options
+ *-----------------------------------------------------------------------------------------------*/
+
+/// Select a single fire based on its identification fire_id value (perhaps by viewing output from
+/// showfires) and output its time series, one row per scan, as CSV.
+#[derive(Debug, Parser)]
+#[clap(bin_name = "firetimeseries")]
+#[clap(author, version, about)]
+struct FireTimeSeriesOptions {
+    /// The fire_id of the fire to export.
+    fire_id: u64,
+
+    /// The path to a CSV file to produce from this fire.
+    ///
+    /// If this is not specified, then the CSV is written to standard output.
+    csv_file: Option<PathBuf>,
+
+    /// The path to the database file with the clusters.
+    ///
+    /// If this is not specified, then the program will check for it in the "CLUSTER_DB"
+    /// environment variable.
+    #[clap(short, long)]
+    #[clap(env = "CLUSTER_DB")]
+    clusters_store_file: PathBuf,
+
+    /// The path to the database file with the fires and associations.
+    ///
+    /// If this is not specified, then the program will check for it in the "FIRES_DB"
+    /// environment variable.
+    #[clap(short, long)]
+    #[clap(env = "FIRES_DB")]
+    fires_store_file: PathBuf,
+
+    /// Verbose output
+    #[clap(short, long)]
+    verbose: bool,
+}
+
+impl Display for FireTimeSeriesOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "\n")?; // yes, two blank lines.
+        writeln!(
+            f,
+            "Cluster Database: {}",
+            self.clusters_store_file.display()
+        )?;
+        writeln!(f, "  Fires Database: {}", self.fires_store_file.display())?;
+        match self.csv_file {
+            Some(ref csv_file) => writeln!(f, "        CSV File: {}", csv_file.display())?,
+            None => writeln!(f, "        CSV File: <stdout>")?,
+        }
+        writeln!(f, "\n")?; // yes, two blank lines.
+
+        Ok(())
+    }
+}
+
+/// Get the command line arguments and check them.
+///
+/// If there is missing data, try to fill it in with environment variables.
+fn parse_args() -> SatFireResult<FireTimeSeriesOptions> {
+    let opts = FireTimeSeriesOptions::parse();
+
+    if opts.verbose {
+        info!(target:"startup", "{}", opts);
+    }
+
+    Ok(opts)
+}
+
+/*-------------------------------------------------------------------------------------------------
+ *                                             Main
+ *-----------------------------------------------------------------------------------------------*/
+fn main() -> SatFireResult<()> {
+    SimpleLogger::new().init()?;
+
+    let opts = parse_args()?;
+
+    let dbs =
+        JointFiresClusterDatabases::connect(&opts.clusters_store_file, &opts.fires_store_file)?;
+
+    let mut query = dbs.single_fire_query()?;
+    let rows = query.run(opts.fire_id)?.filter_map(Result::ok);
+
+    let writer: Box<dyn Write> = match opts.csv_file {
+        Some(ref csv_file) => Box::new(BufWriter::new(File::create(csv_file)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let num_rows = write_fire_time_series_csv(writer, rows)?;
+
+    if opts.verbose {
+        info!("Wrote {} scans for fire {}", num_rows, opts.fire_id);
+    }
+
+    Ok(())
+}
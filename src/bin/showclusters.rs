@@ -48,6 +48,8 @@ struct ShowClustersOptionsInit {
     end: DateTime<Utc>,
 
     /// Bounding Box where as bottom_lat,left_lon,top_lat,right_lon
+    ///
+    /// A left_lon greater than right_lon wraps around the antimeridian.
     #[clap(parse(try_from_str=parse_bbox))]
     #[clap(default_value_t=BoundingBox{ll:Coord{lat: 44.0, lon: -116.5}, ur:Coord{lat: 49.5, lon: -104.0}})]
     bbox: BoundingBox,
@@ -70,10 +72,11 @@ fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
     let max_lat = corners[2].parse()?;
     let max_lon = corners[3].parse()?;
 
-    if min_lat >= max_lat || min_lon >= max_lon {
+    // A box with min_lon greater than max_lon wraps around the antimeridian.
+    if min_lat >= max_lat || min_lon == max_lon {
         return Err(format!(
             concat!(
-                "Minimum Lat/Lon must be less than Maximum Lat/Lon:",
+                "Minimum Lat must be less than Maximum Lat and the Lons must differ:",
                 " min_lat={} max_lat={} min_lon={} max_lon={}"
             ),
             min_lat, max_lat, min_lon, max_lon
@@ -81,7 +84,12 @@ fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
         .into());
     }
 
-    if min_lat < -90.0 || max_lat > 90.0 || min_lon < -180.0 || max_lon > 180.0 {
+    let lon_range = -180.0..=180.0;
+    if min_lat < -90.0
+        || max_lat > 90.0
+        || !lon_range.contains(&min_lon)
+        || !lon_range.contains(&max_lon)
+    {
         return Err(format!(
             concat!(
                 "Lat/Lon are out of range (-90.0 to 90.0 and -180.0 to 180.0):",
@@ -198,7 +206,7 @@ fn main() -> SatFireResult<()> {
 
     let opts = parse_args()?;
 
-    let db = ClusterDatabase::connect(&opts.cluster_store_file)?;
+    let db = ClusterDatabase::connect_read_only(&opts.cluster_store_file)?;
     let mut kfile = KmzFile::new(&opts.kmz_file)?;
 
     for sat in Satellite::iter() {
@@ -2,7 +2,8 @@ use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use clap::Parser;
 use log::info;
 use satfire::{
-    BoundingBox, Coord, FiresDatabase, Geo, KmlWriter, KmzFile, SatFireResult, Satellite,
+    parse_time_zone, BoundingBox, Coord, FiresDatabase, Geo, KmlWriter, KmzFile, OutputTime,
+    OutputTimeZone, SatFireResult, Satellite,
 };
 use simple_logger::SimpleLogger;
 use std::{
@@ -47,6 +48,8 @@ struct ShowFiresOptionsInit {
     end: DateTime<Utc>,
 
     /// Bounding Box where as bottom_lat,left_lon,top_lat,right_lon
+    ///
+    /// A left_lon greater than right_lon wraps around the antimeridian.
     #[clap(parse(try_from_str=parse_bbox))]
     #[clap(default_value_t=BoundingBox{ll:Coord{lat: 44.0, lon: -116.5}, ur:Coord{lat: 49.5, lon: -104.0}})]
     bbox: BoundingBox,
@@ -56,6 +59,15 @@ struct ShowFiresOptionsInit {
     #[clap(default_value_t = 0)]
     minimum_days: i64,
 
+    /// Show times in reports in this time zone, like "America/Denver", or at a fixed offset from
+    /// UTC, like "-06" or "+05:30".
+    ///
+    /// Times are always stored in UTC, this only changes how they are displayed. Named zones
+    /// follow daylight saving time.
+    #[clap(long)]
+    #[clap(parse(try_from_str=parse_time_zone))]
+    time_zone: Option<OutputTimeZone>,
+
     /// Verbose output
     #[clap(short, long)]
     verbose: bool,
@@ -74,10 +86,11 @@ fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
     let max_lat = corners[2].parse()?;
     let max_lon = corners[3].parse()?;
 
-    if min_lat >= max_lat || min_lon >= max_lon {
+    // A box with min_lon greater than max_lon wraps around the antimeridian.
+    if min_lat >= max_lat || min_lon == max_lon {
         return Err(format!(
             concat!(
-                "Minimum Lat/Lon must be less than Maximum Lat/Lon:",
+                "Minimum Lat must be less than Maximum Lat and the Lons must differ:",
                 " min_lat={} max_lat={} min_lon={} max_lon={}"
             ),
             min_lat, max_lat, min_lon, max_lon
@@ -85,7 +98,12 @@ fn parse_bbox(bbox_str: &str) -> SatFireResult<BoundingBox> {
         .into());
     }
 
-    if min_lat < -90.0 || max_lat > 90.0 || min_lon < -180.0 || max_lon > 180.0 {
+    let lon_range = -180.0..=180.0;
+    if min_lat < -90.0
+        || max_lat > 90.0
+        || !lon_range.contains(&min_lon)
+        || !lon_range.contains(&max_lon)
+    {
         return Err(format!(
             concat!(
                 "Lat/Lon are out of range (-90.0 to 90.0 and -180.0 to 180.0):",
@@ -139,6 +157,9 @@ struct ShowFiresOptionsChecked {
 
     /// Bounding Box
     bbox: BoundingBox,
+
+    /// The time zone used to display times.
+    time_zone: OutputTimeZone,
 }
 
 impl Display for ShowFiresOptionsChecked {
@@ -149,6 +170,7 @@ impl Display for ShowFiresOptionsChecked {
         writeln!(f, "           Start: {}", self.start)?;
         writeln!(f, "             End: {}", self.end)?;
         writeln!(f, "Minimum Duration: {}", self.minimum_days)?;
+        writeln!(f, "       Time Zone: {}", self.time_zone)?;
         writeln!(
             f,
             "    Bounding Box: ({:.6}, {:.6}) <---> ({:.6}, {:.6})",
@@ -171,8 +193,10 @@ fn parse_args() -> SatFireResult<ShowFiresOptionsChecked> {
         end,
         minimum_days,
         bbox,
+        time_zone,
         verbose,
     } = ShowFiresOptionsInit::parse();
+    let time_zone = time_zone.unwrap_or_default();
 
     let kmz_file = match kmz_file {
         Some(v) => v,
@@ -190,6 +214,7 @@ fn parse_args() -> SatFireResult<ShowFiresOptionsChecked> {
         end,
         minimum_days: Duration::days(minimum_days),
         bbox,
+        time_zone,
         verbose,
     };
 
@@ -208,7 +233,7 @@ fn main() -> SatFireResult<()> {
 
     let opts = parse_args()?;
 
-    let db = FiresDatabase::connect(&opts.fires_store_file)?;
+    let db = FiresDatabase::connect_read_only(&opts.fires_store_file)?;
     let mut kfile = KmzFile::new(&opts.kmz_file)?;
 
     kfile.start_style(Some("fire"))?;
@@ -259,8 +284,8 @@ fn main() -> SatFireResult<()> {
                             "Num Pixels: {}<br/>",
                         ),
                         fire.id(),
-                        fire.first_observed(),
-                        fire.last_observed(),
+                        OutputTime(fire.first_observed(), opts.time_zone),
+                        OutputTime(fire.last_observed(), opts.time_zone),
                         &duration_buf,
                         fire.max_power(),
                         fire.max_temperature(),
@@ -1,13 +1,20 @@
 use crate::{
     end_time_from_file_name,
-    firesatimage::{FirePoint, SatFireImage},
+    firesatimage::{FirePoint, LoadOptions, SatFireImage},
     geo::{BoundingBox, Coord, Geo},
     pixel::PixelList,
-    satellite::{Satellite, Sector},
+    qc::ClusterQc,
+    satellite::{MaskCategoryCounts, Satellite, SatelliteTimeOffsets, Sector},
     start_time_from_file_name, SatFireResult,
 };
-use chrono::{DateTime, Utc};
-use std::path::Path;
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use rustc_hash::FxHashMap as HashMap;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
 
 /** Represents a spatially contiguous cluster of [Pixel](crate::Pixel) objects.
  *
@@ -18,12 +25,14 @@ use std::path::Path;
  * the aggregate properties only aggregate these paramters for the Pixels that report values.
  */
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cluster {
     /// Total (sum) of the fire power of the points in the cluster in megawatts.
     power: f64,
     /// Total (sum) of the fire area of the points in the cluster with area in square meters.
     area: f64,
     /// Maximum temperature of all the pixels in the cluster in Kelvin.
+    #[cfg_attr(feature = "serde", serde(rename = "max_temperature"))]
     max_temp: f64,
     /// The maximum scan angle of any point in this cluster
     max_scan_angle: f64,
@@ -72,6 +81,13 @@ impl Cluster {
         self.area
     }
 
+    /// Get the area on the ground covered by all pixels in the Cluster, square meters.
+    ///
+    /// This is calculated from the pixel corners, see [PixelList::total_geographic_area_m2].
+    pub fn total_geographic_area_m2(&self) -> f64 {
+        self.pixels.total_geographic_area_m2()
+    }
+
     /// Get the max fire temperature of all pixels in the Cluster that had a temperature in the
     /// file, Kelvin.
     pub fn max_temperature(&self) -> f64 {
@@ -83,6 +99,38 @@ impl Cluster {
         self.max_scan_angle
     }
 
+    /// Get the mean scan angle of the pixels in this cluster.
+    ///
+    /// Pixels without a valid scan angle are skipped. If there are no valid pixels, this returns
+    /// NaN.
+    pub fn mean_scan_angle(&self) -> f64 {
+        let (sum, count) = self
+            .pixels
+            .pixels()
+            .iter()
+            .filter(|p| p.scan_angle.is_finite())
+            .fold((0.0, 0), |(sum, count), p| (sum + p.scan_angle, count + 1));
+
+        sum / count as f64
+    }
+
+    /// Get the fire power weighted mean scan angle of the pixels in this cluster.
+    ///
+    /// Only pixels with a valid scan angle and a valid, positive fire power contribute. If there
+    /// are no such pixels, this returns NaN.
+    pub fn power_weighted_mean_scan_angle(&self) -> f64 {
+        let (sum, total_power) = self
+            .pixels
+            .pixels()
+            .iter()
+            .filter(|p| p.scan_angle.is_finite() && p.power.is_finite() && p.power > 0.0)
+            .fold((0.0, 0.0), |(sum, total_power), p| {
+                (sum + p.scan_angle * p.power, total_power + p.power)
+            });
+
+        sum / total_power
+    }
+
     /// Get the number of SFPixels in a Cluster.
     pub fn pixel_count(&self) -> usize {
         self.pixels.len()
@@ -93,6 +141,12 @@ impl Cluster {
         &self.pixels
     }
 
+    /// Count the pixels in the cluster in each [MaskCategory](crate::MaskCategory), a breakdown
+    /// of detection confidence.
+    pub fn mask_summary(&self) -> MaskCategoryCounts {
+        self.pixels.mask_category_counts()
+    }
+
     /// Add a fire point to this Cluster.
     fn add_fire_point(&mut self, fire_point: FirePoint) {
         let FirePoint { pixel, .. } = fire_point;
@@ -124,6 +178,20 @@ impl Geo for Cluster {
     }
 }
 
+/** How pixels on the satellite image grid are connected when grouping them into clusters.
+ *
+ * The default is [Connectivity::Eight], which matches the geometric adjacency used elsewhere in
+ * this crate where pixels touching only at a corner are considered adjacent.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Connectivity {
+    /// Only pixels sharing an edge (north, south, east, or west neighbors) are connected.
+    Four,
+    /// Pixels sharing an edge or a corner (including diagonal neighbors) are connected.
+    #[default]
+    Eight,
+}
+
 /** A collection of [Cluster](crate::Cluster) objects.
  *
  * This collection stores a list of Clusters that are related. Specifically, they all come from
@@ -142,6 +210,23 @@ pub struct ClusterList {
 }
 
 impl ClusterList {
+    /// Create a new ClusterList from its metadata and already formed Clusters.
+    pub(crate) fn new(
+        satellite: Satellite,
+        sector: Sector,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        clusters: Vec<Cluster>,
+    ) -> Self {
+        ClusterList {
+            satellite,
+            sector,
+            start,
+            end,
+            clusters,
+        }
+    }
+
     /// Get the name of the satellite.
     pub fn satellite(&self) -> Satellite {
         self.satellite
@@ -162,6 +247,23 @@ impl ClusterList {
         self.end
     }
 
+    /// Get the time from the start to the end of the scan.
+    pub fn scan_duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    /// Correct the scan start and end times with a clock correction table.
+    ///
+    /// The times in a ClusterList loaded from a file come from the file name, so this applies the
+    /// same corrections as [parse_satellite_description_from_file_name_with_offsets].
+    ///
+    /// [parse_satellite_description_from_file_name_with_offsets]:
+    /// crate::parse_satellite_description_from_file_name_with_offsets
+    pub fn apply_time_offsets(&mut self, offsets: &SatelliteTimeOffsets) {
+        self.start = offsets.apply(self.satellite, self.start);
+        self.end = offsets.apply(self.satellite, self.end);
+    }
+
     /// Get the Clusters.
     pub fn clusters(&self) -> &[Cluster] {
         &self.clusters
@@ -187,6 +289,50 @@ impl ClusterList {
             .retain(|cluster| cluster.max_scan_angle < max_scan_angle)
     }
 
+    /// Filter the ClusterList to only include fires with at least `min` pixels.
+    ///
+    /// Most single pixel detections are noise, so this is a simple way to reject them.
+    pub fn filter_min_pixels(&mut self, min: usize) {
+        self.clusters.retain(|cluster| cluster.pixels.len() >= min)
+    }
+
+    /// Filter the ClusterList to only include fires with a total power of at least `min_mw`
+    /// megawatts.
+    pub fn filter_min_power(&mut self, min_mw: f64) {
+        self.clusters.retain(|cluster| cluster.power >= min_mw)
+    }
+
+    /// Keep only the `n` most powerful clusters, sorted from the most to the least powerful.
+    ///
+    /// The sort is stable so clusters with the same power stay in their original order, and
+    /// clusters without a valid power sort last. The scan is still recorded if no clusters are
+    /// left.
+    pub fn retain_top_n_by_power(&mut self, n: usize) {
+        self.retain_top_n_by(n, |cluster| cluster.power)
+    }
+
+    /// Keep only the `n` hottest clusters, sorted from the hottest to the coolest.
+    ///
+    /// Like [ClusterList::retain_top_n_by_power], but using the maximum pixel temperature.
+    pub fn retain_top_n_by_temperature(&mut self, n: usize) {
+        self.retain_top_n_by(n, |cluster| cluster.max_temp)
+    }
+
+    fn retain_top_n_by<F: Fn(&Cluster) -> f64>(&mut self, n: usize, key: F) {
+        let key = |cluster: &Cluster| {
+            let val = key(cluster);
+            if val.is_nan() {
+                f64::NEG_INFINITY
+            } else {
+                val
+            }
+        };
+
+        self.clusters
+            .sort_by(|left, right| key(right).total_cmp(&key(left)));
+        self.clusters.truncate(n);
+    }
+
     /// Filter the ClusterList to only include fires for which the provided filter function returns
     /// true.
     pub fn filter<F: FnMut(&Cluster) -> bool>(&mut self, filter_func: F) {
@@ -210,11 +356,157 @@ impl ClusterList {
             .fold(0.0, |acc, cluster| acc + cluster.power)
     }
 
+    /// Find all the pairs of clusters in this list that are adjacent to or overlap each other.
+    ///
+    /// The pairs are the indexes into [ClusterList::clusters], with the smaller index first, and
+    /// are sorted. Clusters in a single scan shouldn't overlap, but clusters that were split apart
+    /// by QC or by [Connectivity::Four] can be adjacent.
+    pub fn adjacency(&self, eps: f64) -> Vec<(usize, usize)> {
+        let bboxes: Vec<_> = self.clusters.iter().map(|c| c.bounding_box()).collect();
+
+        let mut pairs = vec![];
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            for (j, other) in self.clusters.iter().enumerate().skip(i + 1) {
+                if bboxes[i].overlap(&bboxes[j], eps)
+                    && cluster.pixels.adjacent_to_or_overlaps(&other.pixels, eps)
+                {
+                    pairs.push((i, j));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    /// Remove any clusters that duplicate a cluster in `other`.
+    ///
+    /// A cluster is considered a duplicate if any of its pixels overlaps a pixel of a cluster in
+    /// `other` by at least half the area of the smaller pixel, see
+    /// [Pixel::overlap_area_fraction](crate::Pixel::overlap_area_fraction).
+    /// All the sectors from a satellite are projected onto the same grid, so the same pixel from
+    /// different sectors usually has (nearly) exactly the same coordinates, but this also catches
+    /// pixels that are slightly offset. Pixels that only share an edge are not duplicates. No
+    /// checks are made on the satellite, sector, or scan times.
+    ///
+    /// Returns the number of clusters removed.
+    pub fn remove_duplicates_of(&mut self, other: &ClusterList) -> usize {
+        let starting_size = self.clusters.len();
+
+        self.clusters.retain(|cluster| {
+            !other.clusters.iter().any(|other_cluster| {
+                cluster
+                    .bounding_box()
+                    .overlap(&other_cluster.bounding_box(), DUPLICATE_FUDGE_FACTOR)
+                    && cluster.pixels.pixels().iter().any(|pixel| {
+                        other_cluster.pixels.pixels().iter().any(|other_pixel| {
+                            pixel.overlap_area_fraction(other_pixel, DUPLICATE_FUDGE_FACTOR)
+                                >= DUPLICATE_OVERLAP_FRACTION
+                        })
+                    })
+            })
+        });
+
+        starting_size - self.clusters.len()
+    }
+
+    /// Remove clusters that were observed in more than one sector at the same time.
+    ///
+    /// When sectors overlap, like the CONUS and full disk sectors, the same fire is often detected
+    /// in both of them and storing both double counts it. This finds lists from the same satellite
+    /// with overlapping scan times and removes the duplicated clusters from the sector with the
+    /// lower update frequency. Meso-sectors are preferred over CONUS, which is preferred over full
+    /// disk. Lists from sectors with the same preference are not compared to each other.
+    ///
+    /// The lists are sorted by their scan start time as a side effect.
+    ///
+    /// Returns the total number of clusters removed.
+    pub fn deduplicate_across_sectors(lists: &mut [ClusterList]) -> usize {
+        lists.sort_by_key(|list| list.start);
+
+        let mut num_removed = 0;
+        for i in 0..lists.len() {
+            let (left, right) = lists.split_at_mut(i + 1);
+            let list = &mut left[i];
+            let list_end = list.end;
+
+            // Sorted by start time, so once a list starts after this one ends, none of the
+            // remaining lists can overlap in time.
+            for other in right.iter_mut().take_while(|other| other.start <= list_end) {
+                if list.satellite != other.satellite || other.end < list.start {
+                    continue;
+                }
+
+                let list_rank = sector_dedup_rank(list.sector);
+                let other_rank = sector_dedup_rank(other.sector);
+
+                if list_rank < other_rank {
+                    num_removed += list.remove_duplicates_of(other);
+                } else if other_rank < list_rank {
+                    num_removed += other.remove_duplicates_of(list);
+                }
+            }
+        }
+
+        num_removed
+    }
+
     /// Analyze a file and return a ClusterList.
     ///
     /// The metadata is gleaned from the file name, so this program relies on the current naming
-    /// conventions of the NOAA big data program.
+    /// conventions of the NOAA big data program. Pixels are grouped into clusters using the
+    /// default [Connectivity::Eight].
     pub fn from_file<P: AsRef<Path>>(full_path: P) -> SatFireResult<ClusterList> {
+        Self::from_file_with_connectivity(full_path, Connectivity::default())
+    }
+
+    /// Analyze a file and return a ClusterList, grouping pixels using the given connectivity.
+    ///
+    /// See [ClusterList::from_file].
+    pub fn from_file_with_connectivity<P: AsRef<Path>>(
+        full_path: P,
+        connectivity: Connectivity,
+    ) -> SatFireResult<ClusterList> {
+        Self::load_file(
+            full_path,
+            connectivity,
+            &ClusterQc::default(),
+            &LoadOptions::default(),
+        )
+    }
+
+    /// Analyze a file and return a ClusterList with only the clusters that pass quality control.
+    ///
+    /// Only pixels with a DQF accepted by `qc` are grouped into clusters, and then only the
+    /// clusters that [ClusterQc::is_keeper] accepts are kept. See [ClusterList::from_file].
+    pub fn from_file_with_qc<P: AsRef<Path>>(
+        full_path: P,
+        connectivity: Connectivity,
+        qc: &ClusterQc,
+    ) -> SatFireResult<ClusterList> {
+        Self::from_file_with_options(full_path, connectivity, qc, &LoadOptions::default())
+    }
+
+    /// Analyze a file and return a ClusterList with only the clusters that pass quality control,
+    /// reading the file with these options.
+    ///
+    /// See [ClusterList::from_file_with_qc].
+    pub fn from_file_with_options<P: AsRef<Path>>(
+        full_path: P,
+        connectivity: Connectivity,
+        qc: &ClusterQc,
+        options: &LoadOptions,
+    ) -> SatFireResult<ClusterList> {
+        let mut clist = Self::load_file(full_path, connectivity, qc, options)?;
+        clist.filter(|cluster| qc.is_keeper(cluster));
+        Ok(clist)
+    }
+
+    fn load_file<P: AsRef<Path>>(
+        full_path: P,
+        connectivity: Connectivity,
+        qc: &ClusterQc,
+        options: &LoadOptions,
+    ) -> SatFireResult<ClusterList> {
         let path: &Path = full_path.as_ref();
         let fname = path
             .file_name()
@@ -230,9 +522,25 @@ impl ClusterList {
             start_time_from_file_name(&fname).ok_or_else(|| "No start time.".to_string())?;
         let end = end_time_from_file_name(&fname).ok_or_else(|| "No end time".to_string())?;
 
-        let fdata = SatFireImage::open(path)?;
-        let points = fdata.extract_fire_points()?;
-        let clusters: Vec<Cluster> = clusters_from_fire_points(points);
+        let default_projection = if options.uses_default_projection() {
+            Some(satellite)
+        } else {
+            None
+        };
+        let fdata = SatFireImage::builder(path)
+            .default_projection(default_projection)
+            .terrain_height(options.parallax_terrain().cloned())
+            .open()?;
+        let points = fdata.extract_fire_points(end - start, qc)?;
+        let clusters: Vec<Cluster> = clusters_from_fire_points(points, connectivity);
+
+        for cluster in clusters.iter().filter(|c| options.area_disagrees(c)) {
+            let centroid = cluster.centroid();
+            warn!(target: satellite.name(),
+                "Suspect cluster at ({:.4}, {:.4}) in {}: area {:.0} m², geographic area {:.0} m²",
+                centroid.lat, centroid.lon, fname, cluster.total_area(),
+                cluster.total_geographic_area_m2());
+        }
 
         Ok(ClusterList {
             satellite,
@@ -244,53 +552,670 @@ impl ClusterList {
     }
 }
 
-fn clusters_from_fire_points(mut points: Vec<FirePoint>) -> Vec<Cluster> {
+/*-------------------------------------------------------------------------------------------------
+ *                                     Partitioned Export
+ *-----------------------------------------------------------------------------------------------*/
+impl ClusterList {
+    /// Write the clusters to a newline delimited JSON file in a directory partitioned by satellite,
+    /// sector, and scan start date.
+    ///
+    /// The file is written to `root/<satellite>/<sector>/<YYYY-MM-DD>/<start>_<end>.ndjson`, where
+    /// the start and end times are formatted like `20210801T120000Z`, and any missing directories
+    /// are created. The lines are written by [ClusterList::write_jsonl]. An existing file for the
+    /// same scan is replaced.
+    ///
+    /// Returns the path to the file.
+    pub fn export_ndjson<P: AsRef<Path>>(&self, root: P) -> SatFireResult<PathBuf> {
+        const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+        let dir = root
+            .as_ref()
+            .join(self.satellite.name())
+            .join(self.sector.name())
+            .join(self.start.format("%Y-%m-%d").to_string());
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!(
+            "{}_{}.ndjson",
+            self.start.format(TIME_FORMAT),
+            self.end.format(TIME_FORMAT)
+        ));
+
+        let mut f = BufWriter::new(File::create(&path)?);
+        self.write_jsonl(&mut f)?;
+        f.flush()?;
+
+        Ok(path)
+    }
+
+    /// Write the clusters as JSON Lines, one JSON object per cluster.
+    ///
+    /// Each line is a JSON object with the keys satellite, sector, start_time, and end_time (Unix
+    /// timestamps), lat and lon (the centroid), power, max_temperature, area, max_scan_angle, and
+    /// num_pixels. Values that aren't finite are written as null. Nothing is written for an empty
+    /// list, so many lists can be appended to the same file.
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> SatFireResult<()> {
+        let json_f64 = |val: f64| {
+            if val.is_finite() {
+                val.to_string()
+            } else {
+                "null".to_owned()
+            }
+        };
+
+        for cluster in &self.clusters {
+            let Coord { lat, lon } = cluster.centroid();
+            writeln!(
+                writer,
+                concat!(
+                    r#"{{"satellite":"{}","sector":"{}","start_time":{},"end_time":{},"#,
+                    r#""lat":{},"lon":{},"power":{},"max_temperature":{},"area":{},"#,
+                    r#""max_scan_angle":{},"num_pixels":{}}}"#
+                ),
+                self.satellite.name(),
+                self.sector.name(),
+                self.start.timestamp(),
+                self.end.timestamp(),
+                json_f64(lat),
+                json_f64(lon),
+                json_f64(cluster.power),
+                json_f64(cluster.max_temp),
+                json_f64(cluster.area),
+                json_f64(cluster.max_scan_angle),
+                cluster.pixels.len(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Used when checking if pixels from different sectors are really the same pixel.
+const DUPLICATE_FUDGE_FACTOR: f64 = 1.0e-5;
+
+/// Pixels from different sectors that overlap by at least this fraction are the same pixel.
+const DUPLICATE_OVERLAP_FRACTION: f64 = 0.5;
+
+/// The preference for keeping clusters from a sector when removing duplicates, higher is better.
+fn sector_dedup_rank(sector: Sector) -> u8 {
+    match sector {
+        Sector::FULL => 0,
+        Sector::CONUS => 1,
+        Sector::MESO1 | Sector::MESO2 => 2,
+    }
+}
+
+/// Group fire points into clusters of points that are connected on the image grid.
+///
+/// The points are put into a hash map by their grid coordinates, so finding the neighbors of a
+/// point is a constant time lookup, and connected points are joined with a union-find. Clusters
+/// are in the order of their first point in `points`, and the points in each cluster keep their
+/// order from `points`.
+pub(crate) fn clusters_from_fire_points(
+    points: Vec<FirePoint>,
+    connectivity: Connectivity,
+) -> Vec<Cluster> {
+    const FOUR_NEIGHBORS: [(isize, isize); 2] = [(-1, 0), (0, -1)];
+    const EIGHT_NEIGHBORS: [(isize, isize); 4] = [(-1, 0), (-1, -1), (0, -1), (1, -1)];
+
+    let neighbors: &[(isize, isize)] = match connectivity {
+        Connectivity::Four => &FOUR_NEIGHBORS,
+        Connectivity::Eight => &EIGHT_NEIGHBORS,
+    };
+
+    let mut cells: HashMap<(isize, isize), usize> = HashMap::default();
+    cells.reserve(points.len());
+    for (i, fp) in points.iter().enumerate() {
+        cells.entry((fp.x, fp.y)).or_insert(i);
+    }
+
+    // Only half of the neighbors need to be checked, the other half check this point.
+    let mut components = UnionFind::new(points.len());
+    for (i, fp) in points.iter().enumerate() {
+        // Points in the same cell are the same pixel.
+        components.union(i, cells[&(fp.x, fp.y)]);
+
+        for (dx, dy) in neighbors {
+            if let Some(&j) = cells.get(&(fp.x + dx, fp.y + dy)) {
+                components.union(i, j);
+            }
+        }
+    }
+
+    let mut cluster_index: Vec<usize> = vec![usize::MAX; points.len()];
     let mut clusters: Vec<Cluster> = vec![];
-    let mut cluster_points: Vec<FirePoint> = Vec::with_capacity(20);
+    for (i, fp) in points.into_iter().enumerate() {
+        let root = components.find(i);
+        if cluster_index[root] == usize::MAX {
+            cluster_index[root] = clusters.len();
+            clusters.push(Cluster::default());
+        }
+
+        clusters[cluster_index[root]].add_fire_point(fp);
+    }
+
+    clusters
+}
+
+/// A disjoint set forest of indexes, used to find connected components.
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        UnionFind {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        // Path halving keeps the trees shallow.
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        // Always keep the smaller index as the root.
+        if a < b {
+            self.parent[b] = a;
+        } else if b < a {
+            self.parent[a] = b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        satellite::{MaskCategory, MaskCode},
+        testutil::PixelGrid,
+    };
+
+    /// The grid the test fire points are on, the pixel in column 0 and row 0 is centered on
+    /// 45 N 120 W.
+    const GRID: PixelGrid = PixelGrid {
+        origin: Coord {
+            lat: 45.01,
+            lon: -120.01,
+        },
+        cell_size: 0.02,
+    };
+
+    fn fire_point(x: isize, y: isize) -> FirePoint {
+        FirePoint {
+            pixel: GRID.pixel(x, y, 10.0),
+            x,
+            y,
+        }
+    }
+
+    #[test]
+    fn test_cluster_mean_scan_angle() {
+        let mut cluster = Cluster::default();
+        assert!(cluster.mean_scan_angle().is_nan());
+        assert!(cluster.power_weighted_mean_scan_angle().is_nan());
+
+        for (i, (scan_angle, power)) in [(2.0, 100.0), (4.0, 10.0), (8.0, 10.0), (6.0, f64::NAN)]
+            .into_iter()
+            .enumerate()
+        {
+            let mut fp = fire_point(i as isize, 0);
+            fp.pixel.scan_angle = scan_angle;
+            fp.pixel.power = power;
+            cluster.add_fire_point(fp);
+        }
+
+        assert_eq!(cluster.max_scan_angle(), 8.0);
+        assert!((cluster.mean_scan_angle() - 5.0).abs() < 1.0e-12);
+
+        // (2 * 100 + 4 * 10 + 8 * 10) / 120, the pixel with no power is skipped.
+        let expected = 320.0 / 120.0;
+        assert!((cluster.power_weighted_mean_scan_angle() - expected).abs() < 1.0e-12);
+    }
 
-    for i in 0..points.len() {
-        let fp = unsafe { points.get_unchecked_mut(i) };
+    #[test]
+    fn test_cluster_mask_summary() {
+        let mut cluster = Cluster::default();
+        assert_eq!(cluster.mask_summary().total(), 0);
 
-        if fp.x == isize::MIN && fp.y == isize::MIN {
-            continue;
+        for (i, mask) in [10, 30, 13, 34, 12].into_iter().enumerate() {
+            let mut fp = fire_point(i as isize, 0);
+            fp.pixel.mask_flag = MaskCode(mask);
+            cluster.add_fire_point(fp);
         }
 
-        cluster_points.push(*fp);
+        let summary = cluster.mask_summary();
+        assert_eq!(summary.total(), 5);
+        assert_eq!(summary.get(MaskCategory::Good), 2);
+        assert_eq!(summary.get(MaskCategory::HighProbability), 1);
+        assert_eq!(summary.get(MaskCategory::MediumProbability), 1);
+        assert_eq!(summary.get(MaskCategory::CloudContaminated), 1);
+        assert_eq!(summary.get(MaskCategory::LowProbability), 0);
+    }
+
+    #[test]
+    fn test_cluster_list_export_ndjson() {
+        let start: DateTime<Utc> = "2021-08-01T23:58:00Z".parse().unwrap();
+        let end = start + chrono::Duration::minutes(5);
+
+        let cluster_at = |x: isize, y: isize| {
+            let mut cluster = Cluster::default();
+            cluster.add_fire_point(fire_point(x, y));
+            cluster
+        };
+
+        let list = ClusterList::new(
+            Satellite::G17,
+            Sector::CONUS,
+            start,
+            end,
+            vec![cluster_at(0, 0), cluster_at(10, 10)],
+        );
+
+        let root = std::env::temp_dir().join(format!("satfire_ndjson_{}", std::process::id()));
+        let path = list.export_ndjson(&root).unwrap();
+
+        assert_eq!(
+            path,
+            root.join("G17/FDCC/2021-08-01/20210801T235800Z_20210802T000300Z.ndjson")
+        );
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), list.len());
+
+        for (line, cluster) in lines.iter().zip(list.clusters()) {
+            let Coord { lat, lon } = cluster.centroid();
+            let expected = format!(
+                concat!(
+                    r#"{{"satellite":"G17","sector":"FDCC","start_time":{},"end_time":{},"#,
+                    r#""lat":{},"lon":{},"power":{},"max_temperature":{},"area":{},"#,
+                    r#""max_scan_angle":{},"num_pixels":1}}"#
+                ),
+                start.timestamp(),
+                end.timestamp(),
+                lat,
+                lon,
+                cluster.total_power(),
+                cluster.max_temperature(),
+                cluster.total_area(),
+                cluster.max_scan_angle(),
+            );
+            assert_eq!(*line, expected);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_cluster_serde_round_trip() {
+        let mut cluster = Cluster::default();
+        cluster.add_fire_point(fire_point(0, 0));
+        cluster.add_fire_point(fire_point(1, 0));
 
-        fp.x = isize::MIN;
-        fp.y = isize::MIN;
+        let json = serde_json::to_string(&cluster).unwrap();
+        assert!(json.contains(r#""max_temperature":600.0"#));
 
-        for j in (i + 1)..points.len() {
-            let candidate = unsafe { points.get_unchecked_mut(j) };
+        let back: Cluster = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.total_power(), cluster.total_power());
+        assert_eq!(back.total_area(), cluster.total_area());
+        assert_eq!(back.max_temperature(), cluster.max_temperature());
+        assert_eq!(back.max_scan_angle(), cluster.max_scan_angle());
+        assert_eq!(back.pixel_count(), cluster.pixel_count());
+        assert!(back.centroid().is_close(cluster.centroid(), 1.0e-12));
+    }
+
+    #[test]
+    fn test_cluster_list_retain_top_n() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let cluster_with = |power: f64, max_temp: f64| {
+            let mut cluster = Cluster::default();
+            cluster.add_fire_point(fire_point(0, 0));
+            cluster.power = power;
+            cluster.max_temp = max_temp;
+            cluster
+        };
+
+        let make_list = || {
+            ClusterList::new(
+                Satellite::G17,
+                Sector::CONUS,
+                start,
+                start,
+                vec![
+                    cluster_with(30.0, 500.0),
+                    cluster_with(10.0, 900.0),
+                    cluster_with(50.0, 600.0),
+                    cluster_with(20.0, 800.0),
+                    cluster_with(40.0, 700.0),
+                ],
+            )
+        };
+
+        let mut list = make_list();
+        list.retain_top_n_by_power(3);
+        let powers: Vec<_> = list.clusters().iter().map(|c| c.total_power()).collect();
+        assert_eq!(powers, vec![50.0, 40.0, 30.0]);
+
+        let mut list = make_list();
+        list.retain_top_n_by_temperature(2);
+        let temps: Vec<_> = list
+            .clusters()
+            .iter()
+            .map(|c| c.max_temperature())
+            .collect();
+        assert_eq!(temps, vec![900.0, 800.0]);
+
+        // Asking for more than there are keeps them all.
+        let mut list = make_list();
+        list.retain_top_n_by_power(10);
+        assert_eq!(list.len(), 5);
+
+        // Ties keep their original order, and missing powers go last.
+        let mut list = ClusterList::new(
+            Satellite::G17,
+            Sector::CONUS,
+            start,
+            start,
+            vec![
+                cluster_with(f64::NAN, 1.0),
+                cluster_with(10.0, 2.0),
+                cluster_with(10.0, 3.0),
+            ],
+        );
+        list.retain_top_n_by_power(3);
+        let temps: Vec<_> = list
+            .clusters()
+            .iter()
+            .map(|c| c.max_temperature())
+            .collect();
+        assert_eq!(temps, vec![2.0, 3.0, 1.0]);
+
+        let mut list = make_list();
+        list.retain_top_n_by_power(0);
+        assert!(list.is_empty());
+    }
 
-            if candidate.x == isize::MIN && candidate.y == isize::MIN {
-                continue;
+    #[test]
+    fn test_cluster_list_scan_duration() {
+        let start: DateTime<Utc> = "2021-08-01T12:01:17Z".parse().unwrap();
+        let end = start + Duration::seconds(170);
+
+        let list = ClusterList::new(Satellite::G17, Sector::CONUS, start, end, vec![]);
+        assert_eq!(list.scan_duration(), Duration::seconds(170));
+        assert_eq!(list.scan_start() + list.scan_duration(), list.scan_end());
+    }
+
+    #[test]
+    fn test_cluster_list_write_jsonl() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + chrono::Duration::minutes(5);
+
+        let mut cluster = Cluster::default();
+        cluster.add_fire_point(fire_point(0, 0));
+        cluster.add_fire_point(fire_point(0, 1));
+
+        let list = ClusterList::new(Satellite::G16, Sector::FULL, start, end, vec![cluster]);
+        let empty = ClusterList::new(Satellite::G16, Sector::FULL, start, end, vec![]);
+
+        let mut buf = Vec::new();
+        list.write_jsonl(&mut buf).unwrap();
+        empty.write_jsonl(&mut buf).unwrap();
+        list.write_jsonl(&mut buf).unwrap();
+
+        let text = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], lines[1]);
+        assert!(lines[0].starts_with(&format!(
+            r#"{{"satellite":"G16","sector":"FDCF","start_time":{},"end_time":{},"#,
+            start.timestamp(),
+            end.timestamp()
+        )));
+        assert!(lines[0].ends_with(r#""num_pixels":2}"#));
+    }
+
+    #[test]
+    fn test_cluster_list_filter_min_size() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let cluster_at = |points: &[(isize, isize)]| {
+            let mut cluster = Cluster::default();
+            for &(x, y) in points {
+                cluster.add_fire_point(fire_point(x, y));
             }
+            cluster
+        };
 
-            let mut adjacent = false;
-            for cluster_point in &cluster_points {
-                let dx = (cluster_point.x - candidate.x).abs();
-                let dy = (cluster_point.y - candidate.y).abs();
+        let make_list = || {
+            ClusterList::new(
+                Satellite::G17,
+                Sector::CONUS,
+                start,
+                start + chrono::Duration::minutes(5),
+                vec![
+                    cluster_at(&[(0, 0)]),
+                    cluster_at(&[(5, 0), (6, 0)]),
+                    cluster_at(&[(10, 0), (11, 0), (10, 1)]),
+                    cluster_at(&[(20, 20)]),
+                ],
+            )
+        };
 
-                if dx <= 1 && dy <= 1 {
-                    adjacent = true;
-                    break;
-                }
+        let mut by_pixels = make_list();
+        by_pixels.filter_min_pixels(2);
+        let sizes: Vec<usize> = by_pixels
+            .clusters()
+            .iter()
+            .map(|c| c.pixel_count())
+            .collect();
+        assert_eq!(sizes, vec![2, 3]);
+
+        // Every pixel has 10 MW.
+        let mut by_power = make_list();
+        by_power.filter_min_power(25.0);
+        assert_eq!(by_power.len(), 1);
+        assert_eq!(by_power.clusters()[0].pixel_count(), 3);
+
+        let mut all = make_list();
+        all.filter_min_pixels(1);
+        all.filter_min_power(0.0);
+        assert_eq!(all.len(), 4);
+    }
+
+    #[test]
+    fn test_cluster_list_adjacency() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let cluster_at = |points: &[(isize, isize)]| {
+            let mut cluster = Cluster::default();
+            for &(x, y) in points {
+                cluster.add_fire_point(fire_point(x, y));
             }
+            cluster
+        };
+
+        let list = ClusterList::new(
+            Satellite::G17,
+            Sector::CONUS,
+            start,
+            start + chrono::Duration::minutes(5),
+            vec![
+                cluster_at(&[(0, 0), (1, 0)]),
+                // Shares an edge with the first cluster.
+                cluster_at(&[(2, 0)]),
+                // Only touches the second cluster at a corner.
+                cluster_at(&[(3, 1), (3, 2)]),
+                // Isolated.
+                cluster_at(&[(10, 10)]),
+                // Shares an edge with the first cluster.
+                cluster_at(&[(0, -1)]),
+            ],
+        );
+
+        assert_eq!(list.adjacency(1.0e-5), vec![(0, 1), (0, 4), (1, 2)]);
+    }
 
-            if adjacent {
-                cluster_points.push(*candidate);
-                candidate.x = isize::MIN;
-                candidate.y = isize::MIN;
+    #[test]
+    fn test_deduplicate_across_sectors() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let minutes = chrono::Duration::minutes;
+
+        let cluster_at = |x: isize, y: isize| {
+            let mut cluster = Cluster::default();
+            cluster.add_fire_point(fire_point(x, y));
+            cluster
+        };
+
+        // Shift the pixel east by a fraction of its 0.02 degree width.
+        let shifted_cluster_at = |x: isize, y: isize, fraction: f64| {
+            let mut point = fire_point(x, y);
+            for corner in [
+                &mut point.pixel.ul,
+                &mut point.pixel.ll,
+                &mut point.pixel.lr,
+                &mut point.pixel.ur,
+            ] {
+                corner.lon += 0.02 * fraction;
             }
-        }
 
-        let mut curr_clust = Cluster::default();
-        cluster_points
-            .drain(..)
-            .for_each(|cp| curr_clust.add_fire_point(cp));
-        clusters.push(curr_clust);
+            let mut cluster = Cluster::default();
+            cluster.add_fire_point(point);
+            cluster
+        };
+
+        let mut lists = vec![
+            // A full disk scan that sees four fires.
+            ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                start + minutes(10),
+                vec![
+                    cluster_at(0, 0),
+                    cluster_at(10, 10),
+                    cluster_at(20, 20),
+                    cluster_at(30, 30),
+                ],
+            ),
+            // A CONUS scan during the full disk scan that sees one of the same fires, another one
+            // right next to a full disk fire, which is not a duplicate, and two with pixels that
+            // only partially overlap the full disk pixels. The one that mostly overlaps is a
+            // duplicate.
+            ClusterList::new(
+                Satellite::G17,
+                Sector::CONUS,
+                start + minutes(1),
+                start + minutes(6),
+                vec![
+                    cluster_at(0, 0),
+                    cluster_at(11, 10),
+                    shifted_cluster_at(20, 20, 0.25),
+                    shifted_cluster_at(30, 30, 0.75),
+                ],
+            ),
+            // A different satellite sees the same fire, but that is not a duplicate.
+            ClusterList::new(
+                Satellite::G16,
+                Sector::CONUS,
+                start + minutes(1),
+                start + minutes(6),
+                vec![cluster_at(0, 0)],
+            ),
+            // A later CONUS scan isn't at the same time as the full disk scan.
+            ClusterList::new(
+                Satellite::G17,
+                Sector::CONUS,
+                start + minutes(11),
+                start + minutes(16),
+                vec![cluster_at(10, 10)],
+            ),
+        ];
+
+        let num_removed = ClusterList::deduplicate_across_sectors(&mut lists);
+        assert_eq!(num_removed, 2);
+
+        let counts: Vec<_> = lists
+            .iter()
+            .map(|l| (l.satellite(), l.sector(), l.len()))
+            .collect();
+
+        assert_eq!(counts[0], (Satellite::G17, Sector::FULL, 2));
+        assert_eq!(counts[1], (Satellite::G17, Sector::CONUS, 4));
+        assert_eq!(counts[2], (Satellite::G16, Sector::CONUS, 1));
+        assert_eq!(counts[3], (Satellite::G17, Sector::CONUS, 1));
+
+        // The full disk clusters that were kept are the ones not seen by the CONUS scan and the one
+        // that only overlapped a CONUS pixel by a quarter.
+        let kept = lists[0].clusters()[0].centroid();
+        assert!(kept.is_close(fire_point(10, 10).pixel.centroid(), 1.0e-9));
+        let kept = lists[0].clusters()[1].centroid();
+        assert!(kept.is_close(fire_point(30, 30).pixel.centroid(), 1.0e-9));
     }
 
-    clusters
+    #[test]
+    fn test_clusters_diagonal_chain_connectivity() {
+        let diagonal_chain = || (0..4).map(|i| fire_point(i, i)).collect::<Vec<_>>();
+
+        let clusters = clusters_from_fire_points(diagonal_chain(), Connectivity::Eight);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].pixel_count(), 4);
+        assert!((clusters[0].total_power() - 40.0).abs() < 1.0e-12);
+
+        let clusters = clusters_from_fire_points(diagonal_chain(), Connectivity::Four);
+        assert_eq!(clusters.len(), 4);
+        assert!(clusters.iter().all(|c| c.pixel_count() == 1));
+
+        // Edge neighbors are connected either way.
+        let row = || (0..4).map(|i| fire_point(i, 0)).collect::<Vec<_>>();
+        assert_eq!(
+            clusters_from_fire_points(row(), Connectivity::Four).len(),
+            1
+        );
+        assert_eq!(
+            clusters_from_fire_points(row(), Connectivity::Eight).len(),
+            1
+        );
+
+        assert_eq!(Connectivity::default(), Connectivity::Eight);
+    }
+
+    #[test]
+    fn test_clusters_connected_through_later_points() {
+        // (2, 0) is only connected to the rest through (1, 1), which comes after it.
+        let points = vec![
+            fire_point(0, 0),
+            fire_point(2, 0),
+            fire_point(5, 0),
+            fire_point(0, 1),
+            fire_point(1, 1),
+        ];
+
+        let clusters = clusters_from_fire_points(points.clone(), Connectivity::Eight);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].pixel_count(), 4);
+        assert_eq!(clusters[1].pixel_count(), 1);
+        assert!(clusters[1].pixels().pixels()[0].approx_equal(&points[2].pixel, 0.0));
+
+        // The points keep their order within a cluster.
+        let expected = [0, 1, 3, 4];
+        for (pixel, &i) in clusters[0].pixels().pixels().iter().zip(&expected) {
+            assert!(pixel.approx_equal(&points[i].pixel, 0.0));
+        }
+
+        // Only (0, 0), (0, 1), and (1, 1) share edges.
+        let clusters = clusters_from_fire_points(points, Connectivity::Four);
+        let counts: Vec<_> = clusters.iter().map(|c| c.pixel_count()).collect();
+        assert_eq!(counts, vec![3, 1, 1]);
+    }
 }
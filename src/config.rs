@@ -0,0 +1,173 @@
+//! Configuration files shared by the programs in this crate.
+//!
+//! For scheduled runs it is handy to keep all the settings in one place instead of on the command
+//! line of every program. A configuration file is a TOML file with any of these keys, all of which
+//! are optional:
+//!
+//! ```toml
+//! cluster_store_file = "/path/to/findfire.sqlite"
+//! fires_store_file = "/path/to/connectfire.sqlite"
+//! data_dir = "/path/to/satellite/archive"
+//! # bottom_lat, left_lon, top_lat, right_lon
+//! region = [40.0, -125.0, 50.0, -110.0]
+//! loader_threads = 4
+//! ```
+//!
+//! Each program only uses the keys that apply to it, and command line flags (or environment
+//! variables) override the values in the file.
+
+use crate::{BoundingBox, Coord, SatFireResult};
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// The settings loaded from a configuration file.
+#[derive(Debug, Clone, Default)]
+pub struct SatFireConfig {
+    /// The path to the database file with the clusters.
+    pub cluster_store_file: Option<PathBuf>,
+    /// The path to the database file with the fires and associations.
+    pub fires_store_file: Option<PathBuf>,
+    /// The path to the directory with the satellite data archive.
+    pub data_dir: Option<PathBuf>,
+    /// Only process data in this region.
+    pub region: Option<BoundingBox>,
+    /// The number of threads to use for loading data files.
+    pub loader_threads: Option<usize>,
+}
+
+impl SatFireConfig {
+    /// Load a configuration file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> SatFireResult<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read config {}: {}", path.display(), err))?;
+
+        Self::from_toml_str(&text)
+            .map_err(|err| format!("Invalid config {}: {}", path.display(), err).into())
+    }
+
+    /// Parse the contents of a configuration file.
+    ///
+    /// Unknown keys are an error, so a misspelled setting isn't silently ignored.
+    pub fn from_toml_str(text: &str) -> SatFireResult<Self> {
+        let table: Table = text.parse()?;
+        let mut config = Self::default();
+
+        for (key, value) in table {
+            match key.as_str() {
+                "cluster_store_file" => config.cluster_store_file = Some(parse_path(&key, value)?),
+                "fires_store_file" => config.fires_store_file = Some(parse_path(&key, value)?),
+                "data_dir" => config.data_dir = Some(parse_path(&key, value)?),
+                "region" => config.region = Some(parse_region(value)?),
+                "loader_threads" => match value {
+                    Value::Integer(0) => return Err("loader_threads must be at least 1".into()),
+                    Value::Integer(threads) => {
+                        config.loader_threads = Some(usize::try_from(threads)?)
+                    }
+                    _ => return Err("loader_threads must be an integer".into()),
+                },
+                _ => return Err(format!("Unknown setting: {}", key).into()),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_path(key: &str, value: Value) -> SatFireResult<PathBuf> {
+    match value {
+        Value::String(path) => Ok(PathBuf::from(path)),
+        _ => Err(format!("{} must be a string", key).into()),
+    }
+}
+
+fn parse_region(value: Value) -> SatFireResult<BoundingBox> {
+    let corners: Vec<f64> = match value {
+        Value::Array(corners) => corners
+            .into_iter()
+            .map(|corner| match corner {
+                Value::Float(val) => Some(val),
+                Value::Integer(val) => Some(val as f64),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or("region must be an array of numbers")?,
+        _ => return Err("region must be an array of numbers".into()),
+    };
+
+    let [min_lat, min_lon, max_lat, max_lon] = <[f64; 4]>::try_from(corners)
+        .map_err(|_| "region must have 4 numbers, bottom_lat, left_lon, top_lat, right_lon")?;
+
+    // A region with min_lon greater than max_lon wraps around the antimeridian.
+    if min_lat >= max_lat || min_lon == max_lon {
+        return Err(
+            "region minimum lat must be less than maximum lat and the lons must differ".into(),
+        );
+    }
+
+    Ok(BoundingBox {
+        ll: Coord {
+            lat: min_lat,
+            lon: min_lon,
+        },
+        ur: Coord {
+            lat: max_lat,
+            lon: max_lon,
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config_from_toml_str() {
+        let config = SatFireConfig::from_toml_str(
+            r#"
+            cluster_store_file = "/data/findfire.sqlite"
+            region = [40.0, -125.0, 50.0, -110.0]
+            loader_threads = 2
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.cluster_store_file.as_deref(),
+            Some(Path::new("/data/findfire.sqlite"))
+        );
+        assert!(config.fires_store_file.is_none());
+        assert!(config.data_dir.is_none());
+        assert_eq!(config.loader_threads, Some(2));
+
+        let region = config.region.unwrap();
+        assert!(region.ll.is_close(
+            Coord {
+                lat: 40.0,
+                lon: -125.0
+            },
+            1.0e-12
+        ));
+        assert!(region.ur.is_close(
+            Coord {
+                lat: 50.0,
+                lon: -110.0
+            },
+            1.0e-12
+        ));
+
+        assert!(SatFireConfig::from_toml_str("").unwrap().region.is_none());
+        assert!(SatFireConfig::from_toml_str("region = [50.0, -125.0, 40.0, -110.0]").is_err());
+
+        let config = SatFireConfig::from_toml_str("region = [45.0, 170.0, 60.0, -160.0]").unwrap();
+        assert!(config.region.unwrap().wraps_antimeridian());
+        assert!(SatFireConfig::from_toml_str("loader_threads = 0").is_err());
+        assert!(SatFireConfig::from_toml_str("not_a_setting = 1").is_err());
+        assert!(SatFireConfig::from_toml_str("region = [40.0, -125.0, 50.0]").is_err());
+        assert!(SatFireConfig::from_toml_str("data_dir = 5").is_err());
+
+        // Whole numbers are fine for the corners.
+        let config = SatFireConfig::from_toml_str("region = [40, -125, 50, -110]").unwrap();
+        assert_eq!(config.region.unwrap().ur.lon, -110.0);
+    }
+}
@@ -1,16 +1,249 @@
 use crate::{
     cluster::ClusterList,
-    fire::{Fire, FireList},
-    geo::{BoundingBox, Coord, Geo},
+    fire::{CentroidMethod, Fire, FireList, StalenessConfig},
+    geo::{lat_lon_cell_area, BoundingBox, Coord, Ellipsoid, Geo},
     pixel::PixelList,
-    satellite::{Satellite, Sector},
-    SatFireResult,
+    satellite::{MaskCategory, MaskCategoryCounts, Satellite, Sector},
+    OutputTimeZone, SatFireResult,
 };
-use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Timelike, Utc};
 use log::{info, warn};
-use rusqlite::{Connection, OpenFlags, ToSql};
+use rusqlite::{params_from_iter, Connection, OpenFlags, ToSql};
 use rustc_hash::FxHashMap as HashMap;
-use std::path::Path;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use strum::IntoEnumIterator;
+
+/// An in-memory least recently used cache of [ClusterDatabase::query_clusters] results.
+///
+/// This is meant for interactive programs that repeatedly query the same regions and times. It
+/// can be shared between threads, but queries that aren't cached are run one at a time on the
+/// single underlying connection. Results are shared, not copied, so they are returned in an
+/// [Arc]. The cache doesn't know when the database is updated, so call
+/// [CachedClusterDatabase::clear] to make sure new clusters are seen.
+pub struct CachedClusterDatabase {
+    db: Mutex<ClusterDatabase>,
+    cache: Mutex<ClusterQueryCache>,
+    num_queries: AtomicUsize,
+}
+
+static_assertions::assert_impl_all!(CachedClusterDatabase: Send, Sync);
+
+type ClusterQueryKey = (Option<Satellite>, Option<Sector>, i64, i64, [u64; 4]);
+
+struct ClusterQueryCache {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<ClusterQueryKey, (u64, Arc<Vec<ClusterDatabaseClusterRow>>)>,
+}
+
+impl CachedClusterDatabase {
+    /// Wrap a database connection with a cache that holds the results of up to `capacity`
+    /// queries.
+    pub fn new(db: ClusterDatabase, capacity: usize) -> Self {
+        let cache = ClusterQueryCache {
+            capacity,
+            clock: 0,
+            entries: HashMap::default(),
+        };
+
+        CachedClusterDatabase {
+            db: Mutex::new(db),
+            cache: Mutex::new(cache),
+            num_queries: AtomicUsize::new(0),
+        }
+    }
+
+    /// Query clusters, see [ClusterDatabase::query_clusters].
+    ///
+    /// If the same query was recently run, the cached result is returned without querying the
+    /// database.
+    pub fn query_clusters(
+        &self,
+        sat: Option<Satellite>,
+        sect: Option<Sector>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        area: BoundingBox,
+    ) -> SatFireResult<Arc<Vec<ClusterDatabaseClusterRow>>> {
+        let key = (
+            sat,
+            sect,
+            start.timestamp(),
+            end.timestamp(),
+            [
+                area.ll.lat.to_bits(),
+                area.ll.lon.to_bits(),
+                area.ur.lat.to_bits(),
+                area.ur.lon.to_bits(),
+            ],
+        );
+
+        if let Some(rows) = self.lock_cache().get(&key) {
+            return Ok(rows);
+        }
+
+        let rows = {
+            let db = self
+                .db
+                .lock()
+                .map_err(|_| "Cluster database lock poisoned")?;
+            self.num_queries.fetch_add(1, Ordering::Relaxed);
+
+            let mut query = db.query_clusters(sat, sect, start, end, area)?;
+            let rows = query.rows()?.collect::<SatFireResult<Vec<_>>>()?;
+            Arc::new(rows)
+        };
+
+        self.lock_cache().insert(key, Arc::clone(&rows));
+
+        Ok(rows)
+    }
+
+    /// The number of queries that have actually been run on the database, as opposed to being
+    /// answered from the cache.
+    pub fn num_database_queries(&self) -> usize {
+        self.num_queries.load(Ordering::Relaxed)
+    }
+
+    /// Remove everything from the cache.
+    pub fn clear(&self) {
+        self.lock_cache().entries.clear();
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, ClusterQueryCache> {
+        // The cache is always left in a valid state, so it's fine to keep using it if another
+        // thread panicked while holding the lock.
+        self.cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ClusterQueryCache {
+    fn get(&mut self, key: &ClusterQueryKey) -> Option<Arc<Vec<ClusterDatabaseClusterRow>>> {
+        self.clock += 1;
+        let clock = self.clock;
+
+        self.entries.get_mut(key).map(|(last_used, rows)| {
+            *last_used = clock;
+            Arc::clone(rows)
+        })
+    }
+
+    fn insert(&mut self, key: ClusterQueryKey, rows: Arc<Vec<ClusterDatabaseClusterRow>>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (last_used, _))| *last_used)
+                .map(|(key, _)| *key);
+
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(key, (self.clock, rows));
+    }
+}
+
+/// The number of clusters in a database and the times they cover, see [ClusterDatabase::summary].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DatabaseSummary {
+    /// The number of clusters for every satellite and sector with any clusters, sorted by
+    /// satellite and then sector name.
+    pub counts: Vec<(Satellite, Sector, u64)>,
+    /// The earliest scan start time of any cluster, `None` if the database is empty.
+    pub first_scan_start: Option<DateTime<Utc>>,
+    /// The latest scan start time of any cluster, `None` if the database is empty.
+    pub last_scan_start: Option<DateTime<Utc>>,
+}
+
+impl DatabaseSummary {
+    /// The total number of clusters for all satellites and sectors.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|&(_, _, count)| count).sum()
+    }
+}
+
+impl std::fmt::Display for DatabaseSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        for (sat, sector, count) in &self.counts {
+            writeln!(f, "{:>4} {:>6}: {:>10} clusters", sat, sector, count)?;
+        }
+        writeln!(f, "      Total: {:>10} clusters", self.total())?;
+
+        match (self.first_scan_start, self.last_scan_start) {
+            (Some(first), Some(last)) => write!(f, "  From {} to {}", first, last),
+            _ => write!(f, "  No scans"),
+        }
+    }
+}
+
+/// A problem found by [FiresDatabase::check_consistency].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsistencyIssue {
+    /// An association refers to a fire that isn't in the database.
+    MissingAssociatedFire { fire_id: u64, cluster_id: u64 },
+    /// A fire was merged into a fire that isn't in the database.
+    MissingMergedIntoFire { fire_id: u64, merged_into: u64 },
+    /// A fire was last observed before it was first observed.
+    InvalidTimeRange {
+        fire_id: u64,
+        first_observed: DateTime<Utc>,
+        last_observed: DateTime<Utc>,
+    },
+    /// The pixels of a fire couldn't be deserialized, or don't match the stored pixel count.
+    InvalidPixels { fire_id: u64, reason: String },
+}
+
+impl std::fmt::Display for ConsistencyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        use ConsistencyIssue::*;
+
+        match self {
+            MissingAssociatedFire {
+                fire_id,
+                cluster_id,
+            } => write!(
+                f,
+                "cluster {} is associated with missing fire {}",
+                cluster_id, fire_id
+            ),
+            MissingMergedIntoFire {
+                fire_id,
+                merged_into,
+            } => write!(
+                f,
+                "fire {} was merged into missing fire {}",
+                fire_id, merged_into
+            ),
+            InvalidTimeRange {
+                fire_id,
+                first_observed,
+                last_observed,
+            } => write!(
+                f,
+                "fire {} was last observed at {} before it was first observed at {}",
+                fire_id, last_observed, first_observed
+            ),
+            InvalidPixels { fire_id, reason } => {
+                write!(f, "fire {} has invalid pixels: {}", fire_id, reason)
+            }
+        }
+    }
+}
 
 /// Represents a connection to the database where ALL the information related to fires is stored.
 pub struct ClusterDatabase {
@@ -26,7 +259,7 @@ impl ClusterDatabase {
     pub fn initialize<P: AsRef<Path>>(path: P) -> SatFireResult<()> {
         let path = path.as_ref();
 
-        let _conn = Self::open_database_to_write(path)?;
+        let _conn = Self::open_database_to_write(path, false)?;
         Ok(())
     }
 
@@ -34,11 +267,47 @@ impl ClusterDatabase {
     pub fn connect<P: AsRef<Path>>(path: P) -> SatFireResult<Self> {
         let path = path.as_ref();
 
-        let conn = Self::open_database_to_write(path)?;
+        let conn = Self::open_database_to_write(path, false)?;
         Ok(ClusterDatabase { conn })
     }
 
-    fn open_database_to_write(path: &Path) -> SatFireResult<Connection> {
+    /// Open a connection to the database and switch it to write-ahead logging (WAL).
+    ///
+    /// In WAL mode readers don't block the writer and the writer doesn't block readers, so
+    /// analysis queries can run against a database while it is being filled. The journal mode is
+    /// stored in the database file, so once this is done every connection uses WAL. Connections
+    /// to a database in WAL mode, including those from [ClusterDatabase::connect], also use
+    /// `synchronous=NORMAL`. That is much faster, but the most recent transactions may be lost
+    /// if the computer loses power or crashes, though the database won't be corrupted. WAL does
+    /// not work for databases on network file systems.
+    pub fn connect_wal<P: AsRef<Path>>(path: P) -> SatFireResult<Self> {
+        let path = path.as_ref();
+
+        let conn = Self::open_database_to_write(path, true)?;
+        Ok(ClusterDatabase { conn })
+    }
+
+    /// Open a read only connection to an existing database.
+    ///
+    /// This never changes the database, so it works for files on read only media, and for files
+    /// created by older versions of this crate that are missing some of the newer tables. Queries
+    /// that depend on a missing table behave as if it were empty.
+    pub fn connect_read_only<P: AsRef<Path>>(path: P) -> SatFireResult<Self> {
+        let conn = open_database_read_only(path.as_ref())?;
+        Ok(ClusterDatabase { conn })
+    }
+
+    /// Update the query planner statistics and rebuild the database file to reclaim free space.
+    ///
+    /// This needs exclusive access to the database, so it should only be run when no other
+    /// connections are open, e.g. after all the threads of a program are done with it. Rebuilding
+    /// a large database takes a while and temporarily needs as much free disk space as the
+    /// database file itself.
+    pub fn optimize(&self) -> SatFireResult<()> {
+        optimize_database(&self.conn)
+    }
+
+    fn open_database_to_write(path: &Path, wal: bool) -> SatFireResult<Connection> {
         let conn = rusqlite::Connection::open_with_flags(
             path,
             OpenFlags::SQLITE_OPEN_READ_WRITE
@@ -48,9 +317,21 @@ impl ClusterDatabase {
 
         // A 5-second busy time out is WAY too much. If we hit this something has gone terribly wrong.
         conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        set_journal_mode(&conn, wal)?;
         const QUERY: &str = include_str!("database/create_cluster_db.sql");
         conn.execute_batch(QUERY)?;
 
+        // Databases created before the mask category counts were stored need the columns added.
+        for category in MaskCategory::iter() {
+            let column = category.column_name();
+            if !column_exists(&conn, "clusters", column)? {
+                conn.execute(
+                    &format!("ALTER TABLE clusters ADD COLUMN {} INTEGER", column),
+                    [],
+                )?;
+            }
+        }
+
         Ok(conn)
     }
 
@@ -81,18 +362,246 @@ impl ClusterDatabase {
         Ok(res)
     }
 
+    /// Get the scan start time of the newest scan recorded as processed for this satellite and
+    /// sector.
+    ///
+    /// This is the value stored by [ClusterDatabase::update_high_water_mark], which is much faster
+    /// than searching for the newest cluster with [ClusterDatabase::newest_scan_start]. Returns
+    /// `None` if nothing has been recorded yet.
+    pub fn high_water_mark(
+        &self,
+        satellite: Satellite,
+        sector: Sector,
+    ) -> SatFireResult<Option<DateTime<Utc>>> {
+        // Older databases don't have this table.
+        if !table_exists(&self.conn, "high_water_marks")? {
+            return Ok(None);
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT scan_start FROM high_water_marks WHERE satellite = ? AND sector = ?",
+        )?;
+
+        let mut rows = stmt.query([satellite.name(), sector.name()])?;
+        match rows.next()? {
+            Some(row) => {
+                let timestamp: i64 = row.get(0)?;
+                let naive = chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
+                Ok(Some(DateTime::<Utc>::from_utc(naive, Utc)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Record that all the scans for this satellite and sector up to `scan_start` have been
+    /// processed.
+    ///
+    /// The high-water-mark never moves backwards, if `scan_start` is older than the current value
+    /// this does nothing.
+    pub fn update_high_water_mark(
+        &self,
+        satellite: Satellite,
+        sector: Sector,
+        scan_start: DateTime<Utc>,
+    ) -> SatFireResult<()> {
+        const QUERY: &str = include_str!("database/update_high_water_mark.sql");
+
+        self.conn.execute(
+            QUERY,
+            [
+                &satellite.name() as &dyn ToSql,
+                &sector.name(),
+                &scan_start.timestamp(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Calculate how far behind the time `now` the newest scan is for every satellite and sector.
+    ///
+    /// Satellite and sector combinations without any data in the database are left out.
+    pub fn latency(&self, now: DateTime<Utc>) -> SatFireResult<Vec<(Satellite, Sector, Duration)>> {
+        const QUERY: &str = include_str!("database/query_newest_cluster_all.sql");
+        let mut stmt = self.conn.prepare(QUERY)?;
+
+        let mut latencies = Vec::with_capacity(12);
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let sat: String = row.get(0)?;
+            let sector: String = row.get(1)?;
+            let timestamp: i64 = row.get(2)?;
+
+            let sat = Satellite::string_contains_satellite(&sat).ok_or("Invalid satellite")?;
+            let sector = Sector::string_contains_sector(&sector).ok_or("Invalid sector")?;
+            let naive = chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
+            let newest = DateTime::<Utc>::from_utc(naive, Utc);
+
+            latencies.push((sat, sector, now - newest));
+        }
+
+        Ok(latencies)
+    }
+
+    /// Count the clusters for every satellite and sector, and find the range of scan start times.
+    ///
+    /// Scans without any clusters aren't counted or included in the time range.
+    pub fn summary(&self) -> SatFireResult<DatabaseSummary> {
+        const QUERY: &str = r#"
+            SELECT satellite, sector, COUNT(*), MIN(start_time), MAX(start_time)
+            FROM clusters
+            GROUP BY satellite, sector
+            ORDER BY satellite, sector"#;
+
+        let to_time = |timestamp: i64| -> SatFireResult<DateTime<Utc>> {
+            Utc.timestamp_opt(timestamp, 0)
+                .single()
+                .ok_or_else(|| format!("Invalid timestamp: {}", timestamp).into())
+        };
+
+        let mut summary = DatabaseSummary::default();
+
+        let mut stmt = self.conn.prepare(QUERY)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let sat: String = row.get(0)?;
+            let sector: String = row.get(1)?;
+            let count: u64 = row.get(2)?;
+            let first = to_time(row.get(3)?)?;
+            let last = to_time(row.get(4)?)?;
+
+            let sat = Satellite::string_contains_satellite(&sat).ok_or("Invalid satellite")?;
+            let sector = Sector::string_contains_sector(&sector).ok_or("Invalid sector")?;
+            summary.counts.push((sat, sector, count));
+
+            summary.first_scan_start =
+                Some(summary.first_scan_start.map_or(first, |f| f.min(first)));
+            summary.last_scan_start = Some(summary.last_scan_start.map_or(last, |l| l.max(last)));
+        }
+
+        Ok(summary)
+    }
+
+    /// Find the expected scan times between `start` and `end` without any data in the database.
+    ///
+    /// Scans are expected every `cadence` starting at `start`, see [Sector::nominal_cadence]. An
+    /// expected scan is present if a scan with or without clusters starts within half a cadence of
+    /// it, since the actual scan times drift from the schedule. Files that failed to load don't
+    /// count as data.
+    pub fn missing_scans(
+        &self,
+        sat: Satellite,
+        sector: Sector,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        cadence: Duration,
+    ) -> SatFireResult<Vec<DateTime<Utc>>> {
+        if cadence <= Duration::zero() {
+            return Err(format!("The cadence must be positive: {}", cadence).into());
+        }
+
+        let half_cadence = cadence.num_seconds() / 2;
+
+        const QUERY: &str = include_str!("database/query_scan_starts.sql");
+        let mut stmt = self.conn.prepare(QUERY)?;
+        let scan_starts: Vec<i64> = stmt
+            .query_map(
+                [
+                    &sat.name() as &dyn ToSql,
+                    &sector.name(),
+                    &(start.timestamp() - half_cadence),
+                    &(end.timestamp() + half_cadence),
+                ],
+                |row| row.get(0),
+            )?
+            .collect::<Result<_, _>>()?;
+
+        let mut missing = vec![];
+        let mut expected = start;
+        while expected < end {
+            let from = expected.timestamp() - half_cadence;
+            let to = expected.timestamp() + half_cadence;
+
+            // The first scan starting at or after the beginning of the window.
+            let idx = scan_starts.partition_point(|&scan_start| scan_start < from);
+            if !matches!(scan_starts.get(idx), Some(&scan_start) if scan_start <= to) {
+                missing.push(expected);
+            }
+
+            expected += cadence;
+        }
+
+        Ok(missing)
+    }
+
+    /// Find the pairs of consecutive scans between `start` and `end` that are further apart than
+    /// expected.
+    ///
+    /// Consecutive scans, with or without clusters, are further apart than expected when the
+    /// time between them is more than one and a half times the
+    /// [nominal cadence](Sector::nominal_cadence_at) at the earlier scan. Each gap is returned as
+    /// the start times of the scans on either side of it. Unlike [ClusterDatabase::missing_scans]
+    /// there is no gap before the first or after the last scan in the range.
+    pub fn scan_gaps(
+        &self,
+        sat: Satellite,
+        sector: Sector,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> SatFireResult<Vec<(DateTime<Utc>, DateTime<Utc>)>> {
+        const QUERY: &str = include_str!("database/query_scan_starts.sql");
+        let mut stmt = self.conn.prepare(QUERY)?;
+        let timestamps: Vec<i64> = stmt
+            .query_map(
+                [
+                    &sat.name() as &dyn ToSql,
+                    &sector.name(),
+                    &start.timestamp(),
+                    &end.timestamp(),
+                ],
+                |row| row.get(0),
+            )?
+            .collect::<Result<_, _>>()?;
+        let scan_starts: Vec<DateTime<Utc>> = timestamps
+            .into_iter()
+            .filter_map(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+            .collect();
+
+        let gaps = scan_starts
+            .windows(2)
+            .filter_map(|pair| {
+                let (prev, next) = (pair[0], pair[1]);
+                let allowed = sector.nominal_cadence_at(prev) * 3 / 2;
+                if next - prev > allowed {
+                    Some((prev, next))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Ok(gaps)
+    }
+
     /// Prepare to add cluster rows to the database.
     pub fn prepare_to_add_clusters(&self) -> SatFireResult<ClusterDatabaseAddCluster> {
-        const ADD_CLUSTER_QUERY: &str = include_str!("database/add_cluster.sql");
+        const ADD_CLUSTER_QUERY: &str = include_str!("database/add_cluster_with_mask_counts.sql");
         const ADD_NO_FIRE_QUERY: &str = include_str!("database/add_no_cluster.sql");
+        const ADD_FAILED_QUERY: &str = include_str!("database/add_failed_file.sql");
+        const DELETE_FAILED_QUERY: &str = include_str!("database/delete_failed_file.sql");
 
         let add_cluster_stmt = self.conn.prepare(ADD_CLUSTER_QUERY)?;
         let add_no_fire_stmt = self.conn.prepare(ADD_NO_FIRE_QUERY)?;
+        let add_failed_stmt = self.conn.prepare(ADD_FAILED_QUERY)?;
+        let delete_failed_stmt = self.conn.prepare(DELETE_FAILED_QUERY)?;
 
         Ok(ClusterDatabaseAddCluster {
             add_cluster_stmt,
             add_no_fire_stmt,
+            add_failed_stmt,
+            delete_failed_stmt,
             conn: &self.conn,
+            compress_pixels: false,
         })
     }
 
@@ -102,17 +611,29 @@ impl ClusterDatabase {
     ) -> SatFireResult<ClusterDatabaseQueryClusterPresent> {
         const QUERY_CLUSTER: &str = include_str!("database/query_num_clusters_present.sql");
         const QUERY_NO_FIRE: &str = include_str!("database/query_no_clusters.sql");
+        const QUERY_FAILED: &str = include_str!("database/query_failed_file.sql");
 
         let clusters_stmt = self.conn.prepare(QUERY_CLUSTER)?;
         let no_fire_stmt = self.conn.prepare(QUERY_NO_FIRE)?;
 
+        // Older databases don't have this table.
+        let failed_stmt = if table_exists(&self.conn, "failed_files")? {
+            Some(self.conn.prepare(QUERY_FAILED)?)
+        } else {
+            None
+        };
+
         Ok(ClusterDatabaseQueryClusterPresent {
             clusters_stmt,
             no_fire_stmt,
+            failed_stmt,
         })
     }
 
     /// Query clusters from the database.
+    ///
+    /// The clusters are ordered by scan start time, use [ClusterDatabaseQueryClusters::limit] and
+    /// [ClusterDatabaseQueryClusters::offset] to only get a page of the results.
     pub fn query_clusters(
         &self,
         sat: Option<Satellite>,
@@ -121,18 +642,6 @@ impl ClusterDatabase {
         end: DateTime<Utc>,
         area: BoundingBox,
     ) -> SatFireResult<ClusterDatabaseQueryClusters<'_>> {
-        let sat_select = if let Some(sat) = sat {
-            format!("AND satellite = '{}'", sat.name())
-        } else {
-            String::new()
-        };
-
-        let sector_select = if let Some(sect) = sect {
-            format!("AND sector = '{}'", sect.name())
-        } else {
-            String::new()
-        };
-
         let query = &format!(
             r#"SELECT
                  rowid,
@@ -148,73 +657,287 @@ impl ClusterDatabase {
                  lon,
                  pixels
                FROM clusters
-               WHERE
-                 start_time >= {} AND
-                 end_time <= {} AND
-                 lat >= {} AND lat <= {} AND
-                 lon >= {} AND lon <= {} {} {}
-               ORDER BY start_time ASC"#,
-            start.timestamp(),
-            end.timestamp(),
-            area.ll.lat,
-            area.ur.lat,
-            area.ll.lon,
-            area.ur.lon,
-            sat_select,
-            sector_select
+               WHERE {}
+               ORDER BY start_time ASC, rowid ASC
+               LIMIT ?1 OFFSET ?2"#,
+            cluster_query_filter(sat, sect, start, end, area)
         );
 
         let stmt = self.conn.prepare(query)?;
 
-        Ok(ClusterDatabaseQueryClusters { stmt })
+        Ok(ClusterDatabaseQueryClusters {
+            stmt,
+            limit: None,
+            offset: 0,
+        })
     }
-}
 
-pub struct ClusterDatabaseAddCluster<'a> {
-    add_cluster_stmt: rusqlite::Statement<'a>,
-    add_no_fire_stmt: rusqlite::Statement<'a>,
-    conn: &'a Connection,
-}
+    /// Count the clusters in each hour of the day over a time period and area.
+    ///
+    /// Element 0 of the result is for midnight up to 1 AM local time in `zone`, and so on. The
+    /// hour is that of the scan start of each cluster, in the offset from UTC in effect at that
+    /// time, so named zones follow daylight saving time. Clusters are selected the same way as
+    /// [ClusterDatabase::query_clusters] for all satellites and sectors.
+    pub fn diurnal_profile(
+        &self,
+        area: BoundingBox,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        zone: OutputTimeZone,
+    ) -> SatFireResult<[f64; 24]> {
+        self.diurnal_query("COUNT(*)", area, start, end, zone)
+    }
 
-impl<'a> ClusterDatabaseAddCluster<'a> {
-    /// Adds an entire ClusterList to the database.
-    pub fn add(&mut self, clist: ClusterList) -> SatFireResult<()> {
-        if clist.is_empty() {
-            self.add_no_fire(clist)
-        } else {
-            self.add_clusters(clist)
-        }
+    /// Sum the power in megawatts of the clusters in each hour of the day.
+    ///
+    /// See [ClusterDatabase::diurnal_profile].
+    pub fn diurnal_power_profile(
+        &self,
+        area: BoundingBox,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        zone: OutputTimeZone,
+    ) -> SatFireResult<[f64; 24]> {
+        self.diurnal_query("SUM(power)", area, start, end, zone)
     }
 
-    fn add_clusters(&mut self, clist: ClusterList) -> SatFireResult<()> {
-        self.conn.execute("BEGIN TRANSACTION", [])?;
+    fn diurnal_query(
+        &self,
+        aggregate: &str,
+        area: BoundingBox,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        zone: OutputTimeZone,
+    ) -> SatFireResult<[f64; 24]> {
+        // The offset can change during the period, so aggregate each scan and bin them here.
+        let filter = cluster_query_filter(None, None, start, end, area);
+        let query = format!(
+            r#"SELECT start_time, {}
+               FROM clusters WHERE {} GROUP BY start_time"#,
+            aggregate, filter
+        );
 
-        let satellite = clist.satellite();
-        let sector = clist.sector();
-        let scan_start = clist.scan_start().timestamp();
-        let scan_end = clist.scan_end().timestamp();
+        let mut profile = [0.0; 24];
 
-        for cluster in clist.take_clusters().into_iter() {
-            let Coord { lat, lon } = cluster.centroid();
-            let pixels = cluster.pixels().binary_serialize();
-            let power = cluster.total_power();
-            let maxt = cluster.max_temperature();
-            let area = cluster.total_area();
-            let angle = cluster.max_scan_angle();
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let scan_start = Utc.timestamp_opt(row.get(0)?, 0).unwrap();
+            let value: f64 = row.get(1)?;
 
-            self.add_cluster_stmt.execute([
-                &satellite.name() as &dyn ToSql,
-                &sector.name(),
-                &scan_start,
-                &scan_end,
-                &lat,
-                &lon,
-                &power,
-                &maxt,
-                &area,
-                &angle,
-                &pixels,
-            ])?;
+            let hour = scan_start.with_timezone(&zone.offset_at(scan_start)).hour();
+            profile[hour as usize] += value;
+        }
+
+        Ok(profile)
+    }
+
+    /// Count the pixels in each mask category over all the clusters matching a query.
+    ///
+    /// Clusters are selected the same way as [ClusterDatabase::query_clusters]. The counts are
+    /// stored with each cluster when it is added, so the pixels only need to be deserialized for
+    /// clusters added before the counts were stored. Those with corrupt pixels are skipped with a
+    /// warning.
+    pub fn mask_category_histogram(
+        &self,
+        sat: Option<Satellite>,
+        sect: Option<Sector>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        area: BoundingBox,
+    ) -> SatFireResult<MaskCategoryCounts> {
+        let filter = cluster_query_filter(sat, sect, start, end, area);
+        let mut counts = MaskCategoryCounts::new();
+
+        let has_counts = column_exists(&self.conn, "clusters", MaskCategory::Good.column_name())?;
+        let missing_counts = if has_counts {
+            let sums: Vec<String> = MaskCategory::iter()
+                .map(|category| format!("SUM({})", category.column_name()))
+                .collect();
+            let query = format!(
+                "SELECT {} FROM clusters WHERE {} AND {} IS NOT NULL",
+                sums.join(", "),
+                filter,
+                MaskCategory::Good.column_name()
+            );
+
+            self.conn.query_row(&query, [], |row| {
+                for (i, category) in MaskCategory::iter().enumerate() {
+                    // SUM is NULL when no rows match.
+                    let sum: Option<i64> = row.get(i)?;
+                    counts.add(category, sum.unwrap_or(0).max(0) as u64);
+                }
+                Ok(())
+            })?;
+
+            format!("AND {} IS NULL", MaskCategory::Good.column_name())
+        } else {
+            String::new()
+        };
+
+        let query = format!(
+            "SELECT cluster_id, pixels FROM clusters WHERE {} {}",
+            filter, missing_counts
+        );
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let rowid: u64 = row.get(0)?;
+            match PixelList::try_binary_deserialize(row.get_ref(1)?.as_blob()?) {
+                Ok(pixels) => counts.merge(&pixels.mask_category_counts()),
+                Err(err) => warn!("Skipping cluster {} with bad pixels - {}", rowid, err),
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Get all the clusters from a single scan.
+    ///
+    /// Unlike [ClusterDatabase::query_clusters], this selects clusters by exactly matching the
+    /// satellite, sector, scan start, and scan end instead of by a time range and location.
+    pub fn clusters_in_scan(
+        &self,
+        satellite: Satellite,
+        sector: Sector,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> SatFireResult<Vec<ClusterDatabaseClusterRow>> {
+        const QUERY: &str = include_str!("database/query_clusters_in_scan.sql");
+        let mut stmt = self.conn.prepare(QUERY)?;
+
+        let rows = stmt.query_and_then(
+            [
+                &satellite.name() as &dyn ToSql,
+                &sector.name(),
+                &start.timestamp(),
+                &end.timestamp(),
+            ],
+            query_row_to_cluster_row,
+        )?;
+
+        rows.filter_map(Result::transpose).collect()
+    }
+
+    /// Get the clusters with the given rowids.
+    ///
+    /// The clusters are returned sorted by rowid, and ids that aren't in the database are skipped.
+    pub fn clusters_by_ids(&self, ids: &[u64]) -> SatFireResult<Vec<ClusterDatabaseClusterRow>> {
+        let mut clusters = vec![];
+
+        for chunk in sorted_unique_ids(ids).chunks(MAX_IDS_PER_QUERY) {
+            let query = format!(
+                r#"SELECT
+                     rowid,
+                     satellite,
+                     sector,
+                     start_time,
+                     end_time,
+                     power,
+                     max_temperature,
+                     area,
+                     max_scan_angle,
+                     lat,
+                     lon,
+                     pixels
+                   FROM clusters
+                   WHERE rowid IN ({})"#,
+                in_clause_placeholders(chunk.len())
+            );
+
+            let mut stmt = self.conn.prepare(&query)?;
+            for row in stmt.query_and_then(params_from_iter(chunk), query_row_to_cluster_row)? {
+                if let Some(row) = row? {
+                    clusters.push(row);
+                }
+            }
+        }
+
+        clusters.sort_unstable_by_key(|row| row.rowid);
+
+        Ok(clusters)
+    }
+}
+
+pub struct ClusterDatabaseAddCluster<'a> {
+    add_cluster_stmt: rusqlite::Statement<'a>,
+    add_no_fire_stmt: rusqlite::Statement<'a>,
+    add_failed_stmt: rusqlite::Statement<'a>,
+    delete_failed_stmt: rusqlite::Statement<'a>,
+    conn: &'a Connection,
+    compress_pixels: bool,
+}
+
+impl<'a> ClusterDatabaseAddCluster<'a> {
+    /// Store the pixels of the clusters compressed, see [PixelList::binary_serialize_compressed].
+    ///
+    /// Compressed and uncompressed pixels can be mixed in the same database, so this only
+    /// affects clusters added after it is set. By default the pixels are not compressed.
+    pub fn set_compress_pixels(&mut self, compress: bool) {
+        self.compress_pixels = compress;
+    }
+
+    /// Adds an entire ClusterList to the database.
+    pub fn add(&mut self, clist: ClusterList) -> SatFireResult<()> {
+        if clist.is_empty() {
+            self.add_no_fire(clist)
+        } else {
+            self.add_clusters(clist)
+        }
+    }
+
+    /// Adds a ClusterList loaded from `path` to the database, and forgets any earlier failure to
+    /// load the file, see [ClusterDatabaseAddCluster::add_failed].
+    pub fn add_from_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        clist: ClusterList,
+    ) -> SatFireResult<()> {
+        self.add(clist)?;
+        self.clear_failed(path)
+    }
+
+    fn add_clusters(&mut self, clist: ClusterList) -> SatFireResult<()> {
+        self.conn.execute("BEGIN TRANSACTION", [])?;
+
+        let satellite = clist.satellite();
+        let sector = clist.sector();
+        let scan_start = clist.scan_start().timestamp();
+        let scan_end = clist.scan_end().timestamp();
+
+        for cluster in clist.take_clusters().into_iter() {
+            let Coord { lat, lon } = cluster.centroid();
+            let mask_counts = cluster.pixels().mask_category_counts();
+            let pixels = if self.compress_pixels {
+                cluster.pixels().binary_serialize_compressed()?
+            } else {
+                cluster.pixels().binary_serialize()
+            };
+            let power = cluster.total_power();
+            let maxt = cluster.max_temperature();
+            let area = cluster.total_area();
+            let angle = cluster.max_scan_angle();
+
+            self.add_cluster_stmt.execute([
+                &satellite.name() as &dyn ToSql,
+                &sector.name(),
+                &scan_start,
+                &scan_end,
+                &lat,
+                &lon,
+                &power,
+                &maxt,
+                &area,
+                &angle,
+                &pixels,
+                &mask_counts.get(MaskCategory::Good),
+                &mask_counts.get(MaskCategory::Saturated),
+                &mask_counts.get(MaskCategory::CloudContaminated),
+                &mask_counts.get(MaskCategory::HighProbability),
+                &mask_counts.get(MaskCategory::MediumProbability),
+                &mask_counts.get(MaskCategory::LowProbability),
+                &mask_counts.get(MaskCategory::Other),
+            ])?;
         }
 
         self.conn.execute("COMMIT", [])?;
@@ -237,11 +960,31 @@ impl<'a> ClusterDatabaseAddCluster<'a> {
 
         Ok(())
     }
+
+    /// Record a file that could not be loaded so it can be skipped in the future.
+    pub fn add_failed<P: AsRef<Path>>(&mut self, path: P, error: &str) -> SatFireResult<()> {
+        let path = path.as_ref().to_string_lossy();
+        let timestamp = Utc::now().timestamp();
+
+        self.add_failed_stmt
+            .execute([&path as &dyn ToSql, &error, &timestamp])?;
+
+        Ok(())
+    }
+
+    /// Forget that a file could not be loaded, usually because it has been loaded since.
+    pub fn clear_failed<P: AsRef<Path>>(&mut self, path: P) -> SatFireResult<()> {
+        let path = path.as_ref().to_string_lossy();
+        self.delete_failed_stmt.execute([&path])?;
+
+        Ok(())
+    }
 }
 
 pub struct ClusterDatabaseQueryClusterPresent<'a> {
     clusters_stmt: rusqlite::Statement<'a>,
     no_fire_stmt: rusqlite::Statement<'a>,
+    failed_stmt: Option<rusqlite::Statement<'a>>,
 }
 
 impl<'a> ClusterDatabaseQueryClusterPresent<'a> {
@@ -311,18 +1054,61 @@ impl<'a> ClusterDatabaseQueryClusterPresent<'a> {
         // clusters found, so it is present in the database, just with no clusters.
         Ok(no_fire > 0)
     }
+
+    /// Check to see if this file was previously recorded as having failed to load.
+    pub fn previously_failed<P: AsRef<Path>>(&mut self, path: P) -> SatFireResult<bool> {
+        let failed_stmt = match self.failed_stmt {
+            Some(ref mut stmt) => stmt,
+            None => return Ok(false),
+        };
+
+        let path = path.as_ref().to_string_lossy();
+
+        let num_failed: i64 = failed_stmt.query_row([path], |row| row.get(0))?;
+
+        Ok(num_failed > 0)
+    }
 }
 
 pub struct ClusterDatabaseQueryClusters<'a> {
     stmt: rusqlite::Statement<'a>,
+    limit: Option<usize>,
+    offset: usize,
 }
 
 impl<'a> ClusterDatabaseQueryClusters<'a> {
+    /// Only return up to `limit` rows.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` rows.
+    ///
+    /// Rows are skipped by the database before any are checked for corrupt pixels, so a page may
+    /// have fewer than [ClusterDatabaseQueryClusters::limit] rows even if it isn't the last.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
     /// Get an iterator over the rows
+    ///
+    /// Rows with corrupt pixels are skipped with a warning.
     pub fn rows(
         &mut self,
     ) -> SatFireResult<impl Iterator<Item = SatFireResult<ClusterDatabaseClusterRow>> + '_> {
-        Ok(self.stmt.query_and_then([], query_row_to_cluster_row)?)
+        // A negative limit means no limit in SQLite.
+        let limit = match self.limit {
+            Some(limit) => i64::try_from(limit)?,
+            None => -1,
+        };
+        let offset = i64::try_from(self.offset)?;
+
+        Ok(self
+            .stmt
+            .query_and_then([limit, offset], query_row_to_cluster_row)?
+            .filter_map(Result::transpose))
     }
 }
 
@@ -344,6 +1130,170 @@ pub struct ClusterDatabaseClusterRow {
 
 impl ClusterDatabaseClusterRow {}
 
+/// Group consecutive cluster rows with the same scan start time.
+///
+/// The rows are expected to be sorted by scan start time, as they are when returned from
+/// [ClusterDatabase::query_clusters]. Each group is yielded along with its scan start time, and
+/// every row ends up in exactly one group, including the rows in the final scan.
+pub fn group_by_scan<I>(
+    rows: I,
+) -> impl Iterator<Item = (DateTime<Utc>, Vec<ClusterDatabaseClusterRow>)>
+where
+    I: Iterator<Item = ClusterDatabaseClusterRow>,
+{
+    let mut rows = rows.peekable();
+
+    std::iter::from_fn(move || {
+        let first = rows.next()?;
+        let scan_start = first.start;
+
+        let mut group = vec![first];
+        while let Some(row) = rows.next_if(|row| row.start == scan_start) {
+            group.push(row);
+        }
+
+        Some((scan_start, group))
+    })
+}
+
+/// How far the power weighted centroid of a fire moved over its lifetime.
+///
+/// A stationary heat source, like a gas flare or a steel mill, will have a small path length and
+/// net displacement even after being observed for days, while a spreading wildfire moves. Some
+/// drift is expected even for a fixed source because the pixels that detect it change from scan to
+/// scan, so this is best used for comparing fires with similar lifetimes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CentroidDrift {
+    /// The total length, in meters, of the path traced by the centroid from scan to scan.
+    pub path_length: f64,
+    /// The distance, in meters, from the centroid in the first scan to the centroid in the last.
+    pub net_displacement: f64,
+    /// The number of scans the fire was observed in.
+    pub num_scans: usize,
+}
+
+impl CentroidDrift {
+    /// Calculate the drift from the time series of clusters associated with a fire.
+    ///
+    /// The rows are expected to be sorted by scan start time, as they are when returned from
+    /// [JointQuerySingleFire::run]. All the clusters in a scan are combined into a single power
+    /// weighted centroid for that scan.
+    pub fn from_time_series<I>(rows: I) -> Self
+    where
+        I: IntoIterator<Item = ClusterDatabaseClusterRow>,
+    {
+        let mut centroids = group_by_scan(rows.into_iter()).map(|(_, group)| {
+            let mut pixels = PixelList::new();
+            for pixel in group.iter().flat_map(|row| row.pixels.pixels()) {
+                pixels.push(*pixel);
+            }
+            pixels.power_weighted_centroid()
+        });
+
+        let mut drift = CentroidDrift {
+            path_length: 0.0,
+            net_displacement: 0.0,
+            num_scans: 0,
+        };
+
+        let first = match centroids.next() {
+            Some(first) => first,
+            None => return drift,
+        };
+        drift.num_scans = 1;
+
+        let mut prev = first;
+        for centroid in centroids {
+            drift.path_length += prev.distance_meters_with_ellipsoid(centroid, &Ellipsoid::GRS80);
+            drift.num_scans += 1;
+            prev = centroid;
+        }
+
+        drift.net_displacement = first.distance_meters_with_ellipsoid(prev, &Ellipsoid::GRS80);
+
+        drift
+    }
+}
+
+/// Calculate the fire radiative energy (FRE), megajoules, from the time series of clusters
+/// associated with a fire.
+///
+/// The FRE is the fire radiative power integrated over time with the trapezoidal rule. The rows
+/// are expected to be sorted by scan start time, as they are when returned from
+/// [JointQuerySingleFire::run]. The power of all the clusters in a scan is added together and
+/// assigned to the middle of the scan. A fire seen in only one scan has no duration to integrate
+/// over, so its FRE is 0. Divide by 1,000 for gigajoules.
+pub fn fire_radiative_energy<I>(rows: I) -> f64
+where
+    I: IntoIterator<Item = ClusterDatabaseClusterRow>,
+{
+    let mut scans = group_by_scan(rows.into_iter()).map(|(_, group)| {
+        let mid_scan = group[0].start + (group[0].end - group[0].start) / 2;
+        let power: f64 = group
+            .iter()
+            .map(|row| row.power)
+            .filter(|power| power.is_finite())
+            .sum();
+        (mid_scan, power)
+    });
+
+    let (mut prev_time, mut prev_power) = match scans.next() {
+        Some(scan) => scan,
+        None => return 0.0,
+    };
+
+    let mut energy = 0.0;
+    for (time, power) in scans {
+        let seconds = (time - prev_time).num_milliseconds() as f64 / 1000.0;
+        energy += 0.5 * (prev_power + power) * seconds;
+
+        prev_time = time;
+        prev_power = power;
+    }
+
+    energy
+}
+
+/// Calculate how the burned area of a fire grew over the time series of clusters associated with
+/// it.
+///
+/// The rows are expected to be sorted by scan start time, as they are when returned from
+/// [JointQuerySingleFire::run]. The pixels from each scan are merged into a composite the same way
+/// a [Fire](crate::Fire) keeps the maximum of every pixel, so the burned area never shrinks. Each
+/// scan gives one entry with the scan start time, the cumulative burned area in square meters, and
+/// the growth rate in square meters per hour since the previous scan. The growth rate in the first
+/// scan is 0.
+pub fn fire_growth_series<I>(rows: I) -> Vec<(DateTime<Utc>, f64, f64)>
+where
+    I: IntoIterator<Item = ClusterDatabaseClusterRow>,
+{
+    let mut composite = PixelList::new();
+    let mut series: Vec<(DateTime<Utc>, f64, f64)> = vec![];
+
+    for (start, group) in group_by_scan(rows.into_iter()) {
+        for row in &group {
+            composite.max_merge(&row.pixels);
+        }
+        let area = composite.total_area();
+
+        let rate = match series.last() {
+            Some(&(prev_start, prev_area, _)) => {
+                let hours = (start - prev_start).num_milliseconds() as f64 / 3_600_000.0;
+                if hours > 0.0 {
+                    (area - prev_area) / hours
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+
+        series.push((start, area, rate));
+    }
+
+    series
+}
+
 /// Represents a connection to the database where ALL the information related to fires is stored.
 pub struct FiresDatabase {
     conn: Connection,
@@ -370,6 +1320,23 @@ impl FiresDatabase {
         Ok(Self { conn })
     }
 
+    /// Open a read only connection to an existing database.
+    ///
+    /// This never changes the database, so it works for files on read only media, and for files
+    /// created by older versions of this crate that are missing some of the newer columns. Queries
+    /// that depend on a missing column fall back to a default value.
+    pub fn connect_read_only<P: AsRef<Path>>(path: P) -> SatFireResult<Self> {
+        let conn = open_database_read_only(path.as_ref())?;
+        Ok(Self { conn })
+    }
+
+    /// Update the query planner statistics and rebuild the database file to reclaim free space.
+    ///
+    /// See [ClusterDatabase::optimize], this also needs exclusive access to the database.
+    pub fn optimize(&self) -> SatFireResult<()> {
+        optimize_database(&self.conn)
+    }
+
     fn open_database_to_write(path: &Path) -> SatFireResult<Connection> {
         let conn = rusqlite::Connection::open_with_flags(
             path,
@@ -380,12 +1347,93 @@ impl FiresDatabase {
 
         // A 5-second busy time out is WAY too much. If we hit this something has gone terribly wrong.
         conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        set_journal_mode(&conn, false)?;
         const QUERY: &str = include_str!("database/create_fire_db.sql");
         conn.execute_batch(QUERY)?;
 
+        // Databases created before the region column was added need it added.
+        if !column_exists(&conn, "fires", "region")? {
+            conn.execute("ALTER TABLE fires ADD COLUMN region TEXT", [])?;
+        }
+
+        // Databases created before the max_simultaneous_clusters column was added need it added.
+        if !column_exists(&conn, "fires", "max_simultaneous_clusters")? {
+            conn.execute(
+                "ALTER TABLE fires ADD COLUMN max_simultaneous_clusters INTEGER",
+                [],
+            )?;
+        }
+
         Ok(conn)
     }
 
+    /// Get the region or jurisdiction name stored with a fire, if any.
+    ///
+    /// See [FiresDatabaseAddFire::set_region_classifier]. Databases created before regions were
+    /// stored return `None` for every fire.
+    pub fn fire_region(&self, fire_id: u64) -> SatFireResult<Option<String>> {
+        if !column_exists(&self.conn, "fires", "region")? {
+            return Ok(None);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT region FROM fires WHERE fire_id = ?")?;
+        let mut rows = stmt.query([fire_id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(row.get(0)?),
+            None => Ok(None),
+        }
+    }
+
+    /// Annotate a fire with a value, replacing any value already stored for the key.
+    ///
+    /// This is for computed or external attributes of a fire, like a confidence score or the name
+    /// of an incident, that aren't part of the fires table.
+    pub fn set_fire_metadata(&self, fire_id: u64, key: &str, value: &str) -> SatFireResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO fire_metadata (fire_id, key, value) VALUES (?, ?, ?)",
+            [&fire_id as &dyn ToSql, &key, &value],
+        )?;
+        Ok(())
+    }
+
+    /// Get the value stored for a key with [FiresDatabase::set_fire_metadata], if any.
+    ///
+    /// Databases created before metadata was stored return `None` for every fire.
+    pub fn get_fire_metadata(&self, fire_id: u64, key: &str) -> SatFireResult<Option<String>> {
+        if !table_exists(&self.conn, "fire_metadata")? {
+            return Ok(None);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT value FROM fire_metadata WHERE fire_id = ? AND key = ?")?;
+        let mut rows = stmt.query([&fire_id as &dyn ToSql, &key])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get all the metadata stored for a fire, sorted by key.
+    pub fn all_fire_metadata(&self, fire_id: u64) -> SatFireResult<Vec<(String, String)>> {
+        if !table_exists(&self.conn, "fire_metadata")? {
+            return Ok(vec![]);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM fire_metadata WHERE fire_id = ? ORDER BY key")?;
+        let metadata = stmt
+            .query_map([fire_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(metadata)
+    }
+
     /// Get the next id number for a wildfire.
     pub fn next_wildfire_id(&self) -> SatFireResult<u64> {
         const QUERY: &str = "SELECT IFNULL(MAX(fire_id) + 1, 1) FROM fires";
@@ -413,90 +1461,116 @@ impl FiresDatabase {
             .ok()
     }
 
+    /// Get the scan start time of the last scan fully processed for this satellite.
+    ///
+    /// This is stored by [FiresDatabaseAddFire::add_fires_with_checkpoint], and clusters from this
+    /// scan or older have already been connected into fires. Returns `None` if nothing has been
+    /// recorded yet.
+    pub fn checkpoint(&self, sat: Satellite) -> SatFireResult<Option<DateTime<Utc>>> {
+        // Older databases don't have this table.
+        if !table_exists(&self.conn, "checkpoints")? {
+            return Ok(None);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT scan_start FROM checkpoints WHERE satellite = ?")?;
+
+        let mut rows = stmt.query([sat.name()])?;
+        match rows.next()? {
+            Some(row) => {
+                let timestamp: i64 = row.get(0)?;
+                Ok(Utc.timestamp_opt(timestamp, 0).single())
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Get the fires that are still going.
     pub fn ongoing_fires(&self, sat: Satellite) -> SatFireResult<FireList> {
-        let latest = match self.last_observed(sat) {
+        self.ongoing_fires_with_staleness(sat, &StalenessConfig::default())
+    }
+
+    /// Get the fires that are still going, using `staleness` to decide which fires are done.
+    ///
+    /// Only fires observed within 175 days of the latest observation are considered.
+    pub fn ongoing_fires_with_staleness(
+        &self,
+        sat: Satellite,
+        staleness: &StalenessConfig,
+    ) -> SatFireResult<FireList> {
+        let mut query = self.iter_fires(sat)?;
+        let latest = match query.latest() {
             Some(ts) => ts,
             None => return Ok(FireList::new()),
         };
 
-        let earliest = latest - Duration::days(175);
-
         info!(target: sat.name(), "Latest fire observation => {}", latest);
 
-        const QUERY: &str = include_str!("database/query_most_recent_fires.sql");
-        let mut stmt = self.conn.prepare(QUERY)?;
-
+        let mut num_retrieved = 0;
         let mut fires = FireList::new();
 
-        stmt.query_and_then(
-            [&earliest.timestamp() as &dyn ToSql, &sat.name()],
-            |row| -> SatFireResult<Fire> {
-                let id: u64 = u64::try_from(row.get::<_, i64>(0)?)?;
-
-                let sat = match row.get_ref(1)? {
-                    rusqlite::types::ValueRef::Text(txt) => {
-                        let txt = unsafe { std::str::from_utf8_unchecked(txt) };
-                        Satellite::string_contains_satellite(txt).ok_or("Invalid sattelite")
-                    }
-                    _ => Err("sattelite not text"),
-                }?;
-
-                let first_observed: DateTime<Utc> = DateTime::from_utc(
-                    chrono::NaiveDateTime::from_timestamp_opt(row.get(2)?, 0).unwrap(),
-                    Utc,
-                );
-                let last_observed: DateTime<Utc> = DateTime::from_utc(
-                    chrono::NaiveDateTime::from_timestamp_opt(row.get(3)?, 0).unwrap(),
-                    Utc,
-                );
-
-                let max_power: f64 = row.get(4)?;
-                let max_temperature: f64 = row.get(5)?;
-
-                let area = match row.get_ref(6)? {
-                    rusqlite::types::ValueRef::Blob(bytes) => {
-                        let mut cursor = std::io::Cursor::new(bytes);
-                        Ok(PixelList::binary_deserialize(&mut cursor))
-                    }
-                    _ => Err("Invalid type in pixels column"),
-                }?;
-
-                Ok(Fire::new(
-                    first_observed,
-                    last_observed,
-                    max_power,
-                    max_temperature,
-                    id,
-                    area,
-                    sat,
-                    0,
-                ))
-            },
-        )?
-        .filter_map(|res| match res {
-            Ok(fire) => Some(fire),
-            Err(err) => {
-                warn!(target: sat.name(), "Error retrieving fire - {}", err);
-                None
-            }
-        })
-        .for_each(|fire| fires.add_fire(fire));
-
-        let mut waste = FireList::default();
-
-        info!(target: sat.name(), "Retrieved {} fires from database.", fires.len());
-
-        fires.drain_stale_fires(&mut waste, latest);
+        let rows = query.rows()?.inspect(|_| num_retrieved += 1);
+        staleness
+            .without_stale(rows, latest)
+            .filter_map(|res| match res {
+                Ok(fire) => Some(fire),
+                Err(err) => {
+                    warn!(target: sat.name(), "Error retrieving fire - {}", err);
+                    None
+                }
+            })
+            .for_each(|fire| fires.add_fire(fire));
 
+        info!(target: sat.name(), "Retrieved {} fires from database.", num_retrieved);
         info!(target: sat.name(), "Retrieved {} fires from database after filtering out stale fires.",
             fires.len());
 
         Ok(fires)
     }
 
+    /// Stream the fires from `sat` that may still be going, without loading them all into
+    /// memory.
+    ///
+    /// These are the fires [FiresDatabase::ongoing_fires] starts from, the ones that weren't
+    /// merged into another fire and were observed within 175 days of the latest observation. The
+    /// pixels of each fire are only deserialized when the iterator reaches it. Stale fires are
+    /// included, use [StalenessConfig::without_stale] with [FiresDatabaseIterFires::latest] to
+    /// drop them.
+    pub fn iter_fires(&self, sat: Satellite) -> SatFireResult<FiresDatabaseIterFires<'_>> {
+        let latest = self.last_observed(sat);
+        let earliest = latest
+            .map(|latest| (latest - Duration::days(175)).timestamp())
+            .unwrap_or(i64::MAX);
+
+        // Databases created before the max_simultaneous_clusters column was added select NULL.
+        let query = if column_exists(&self.conn, "fires", "max_simultaneous_clusters")? {
+            include_str!("database/query_most_recent_fires.sql")
+        } else {
+            include_str!("database/query_most_recent_fires_old_schema.sql")
+        };
+        let stmt = self.conn.prepare(query)?;
+
+        Ok(FiresDatabaseIterFires {
+            stmt,
+            sat,
+            earliest,
+            latest,
+        })
+    }
+
     /// Add fires and associations to clusters to the database.
     pub fn prepare_to_add_fires(&self) -> SatFireResult<FiresDatabaseAddFire> {
+        self.prepare_to_add_fires_with_centroid(CentroidMethod::default())
+    }
+
+    /// Prepare to add fires to the database, storing the centroid calculated with `centroid`.
+    ///
+    /// The stored centroid is the `lat` and `lon` used in spatial queries of the fires.
+    pub fn prepare_to_add_fires_with_centroid(
+        &self,
+        centroid: CentroidMethod,
+    ) -> SatFireResult<FiresDatabaseAddFire<'_>> {
         const FIRE_QUERY: &str = include_str!("database/add_fire.sql");
         const ASSOC_QUERY: &str = include_str!("database/add_association.sql");
 
@@ -509,6 +1583,9 @@ impl FiresDatabase {
             fire_stmt,
             assoc_stmt,
             associations,
+            centroid,
+            region_classifier: None,
+            compress_pixels: false,
         })
     }
 
@@ -535,26 +1612,24 @@ impl FiresDatabase {
                  last_observed,
                  max_power,
                  max_temperature,
-                 pixels
+                 pixels,
+                 {}
                FROM fires
                WHERE
                  ((first_observed <= {} AND last_observed >= {})
                  OR (first_observed >= {} AND first_observed <= {})
                  OR (last_observed >= {} AND last_observed <= {}))
                  AND
-                 lat >= {} AND lat <= {} AND
-                 lon >= {} AND lon <= {} {} 
+                 {} {} 
                ORDER BY first_observed ASC"#,
+            max_simultaneous_clusters_column(&self.conn)?,
             start.timestamp(),
             end.timestamp(),
             start.timestamp(),
             end.timestamp(),
             start.timestamp(),
             end.timestamp(),
-            area.ll.lat,
-            area.ur.lat,
-            area.ll.lon,
-            area.ur.lon,
+            area_filter(&area),
             sat_select,
         );
 
@@ -562,27 +1637,558 @@ impl FiresDatabase {
 
         Ok(FiresDatabaseQueryFires { stmt })
     }
-}
+
+    /// Count the fires that ignited and were extinguished in each time bucket.
+    ///
+    /// A fire ignites in the bucket with its first observation and is extinguished in the bucket
+    /// with its last observation. Fires that were merged into another fire didn't burn out, so
+    /// they are counted as ignitions but not as extinctions. Only fires with a centroid in `area`
+    /// are counted.
+    ///
+    /// Buckets are aligned to multiples of `bucket` since the Unix epoch, so daily buckets start at
+    /// midnight UTC. Every bucket from the one containing `start` through the one containing `end`
+    /// is returned in order as (bucket start, ignitions, extinctions), including empty buckets.
+    pub fn ignition_extinction_series(
+        &self,
+        area: BoundingBox,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: Duration,
+    ) -> SatFireResult<Vec<(DateTime<Utc>, u64, u64)>> {
+        let bucket = bucket.num_seconds();
+        if bucket <= 0 {
+            return Err("Bucket size must be at least one second".into());
+        }
+
+        let first_bucket = start.timestamp().div_euclid(bucket);
+        let last_bucket = end.timestamp().div_euclid(bucket);
+        if last_bucket < first_bucket {
+            return Ok(vec![]);
+        }
+
+        let mut series: Vec<(DateTime<Utc>, u64, u64)> = (first_bucket..=last_bucket)
+            .map(|b| (Utc.timestamp_opt(b * bucket, 0).unwrap(), 0, 0))
+            .collect();
+
+        let count_query = |column: &str, merged_select: &str| {
+            format!(
+                r#"SELECT {column} / {bucket}, COUNT(*)
+                   FROM fires
+                   WHERE
+                     {column} >= {} AND {column} < {} AND
+                     {} {merged_select}
+                   GROUP BY {column} / {bucket}"#,
+                first_bucket * bucket,
+                (last_bucket + 1) * bucket,
+                area_filter(&area),
+            )
+        };
+
+        let ignitions = count_query("first_observed", "");
+        let extinctions = count_query("last_observed", "AND merged_into = 0");
+
+        for (query, is_ignition) in [(ignitions, true), (extinctions, false)] {
+            let mut stmt = self.conn.prepare(&query)?;
+            let rows =
+                stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u64>(1)?)))?;
+
+            for row in rows {
+                let (b, count) = row?;
+                let entry = &mut series[(b - first_bucket) as usize];
+                if is_ignition {
+                    entry.1 = count;
+                } else {
+                    entry.2 = count;
+                }
+            }
+        }
+
+        Ok(series)
+    }
+
+    /// Check the database for internal inconsistencies.
+    ///
+    /// These can be caused by interrupted runs or by editing the database by hand. This checks that
+    /// every association refers to a fire in the database, that every fire that was merged was
+    /// merged into a fire in the database, that no fire was last observed before it was first
+    /// observed, and that the pixels of every fire can be deserialized. An empty list means no
+    /// issues were found.
+    pub fn check_consistency(&self) -> SatFireResult<Vec<ConsistencyIssue>> {
+        let mut issues = vec![];
+
+        let mut stmt = self.conn.prepare(
+            r#"SELECT fire_id, cluster_id FROM associations
+               WHERE fire_id NOT IN (SELECT fire_id FROM fires)
+               ORDER BY fire_id, cluster_id"#,
+        )?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (fire_id, cluster_id) = row?;
+            issues.push(ConsistencyIssue::MissingAssociatedFire {
+                fire_id,
+                cluster_id,
+            });
+        }
+
+        let mut stmt = self.conn.prepare(
+            r#"SELECT fire_id, merged_into FROM fires
+               WHERE merged_into != 0 AND merged_into NOT IN (SELECT fire_id FROM fires)
+               ORDER BY fire_id"#,
+        )?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (fire_id, merged_into) = row?;
+            issues.push(ConsistencyIssue::MissingMergedIntoFire {
+                fire_id,
+                merged_into,
+            });
+        }
+
+        let mut stmt = self.conn.prepare(
+            r#"SELECT fire_id, first_observed, last_observed FROM fires
+               WHERE first_observed > last_observed
+               ORDER BY fire_id"#,
+        )?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))? {
+            let (fire_id, first_observed, last_observed): (u64, i64, i64) = row?;
+            issues.push(ConsistencyIssue::InvalidTimeRange {
+                fire_id,
+                first_observed: Utc.timestamp_opt(first_observed, 0).unwrap(),
+                last_observed: Utc.timestamp_opt(last_observed, 0).unwrap(),
+            });
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT fire_id, num_pixels, pixels FROM fires ORDER BY fire_id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let fire_id: u64 = row.get(0)?;
+            let num_pixels: usize = row.get(1)?;
+            let blob = row.get_ref(2)?.as_blob()?;
+
+            let reason = match PixelList::try_binary_deserialize(blob) {
+                Ok(pixels) if pixels.len() != num_pixels => Some(format!(
+                    "{} pixels stored, but num_pixels is {}",
+                    pixels.len(),
+                    num_pixels
+                )),
+                Ok(_) => None,
+                Err(err) => Some(err.to_string()),
+            };
+
+            if let Some(reason) = reason {
+                issues.push(ConsistencyIssue::InvalidPixels { fire_id, reason });
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Import all the fires and associations from another fires database.
+    ///
+    /// The fire ids of the imported database are offset past the largest id already in this
+    /// database so they can't collide, and the `merged_into` references and associations are
+    /// rewritten with the new ids, as is any fire metadata. The cluster ids are kept as they are,
+    /// so `clusters_db` must be the cluster database the imported associations refer to.
+    /// Associations to clusters that aren't in it are dropped. Returns the offset that was added
+    /// to the imported fire ids.
+    pub fn import_from(&self, other: &Path, clusters_db: &Path) -> SatFireResult<u64> {
+        let offset: u64 =
+            self.conn
+                .query_row("SELECT IFNULL(MAX(fire_id), 0) FROM fires", [], |row| {
+                    row.get(0)
+                })?;
+
+        // Attaching a file that doesn't exist would create an empty database.
+        for path in [other, clusters_db] {
+            if !path.exists() {
+                return Err(format!("No such database: {}", path.display()).into());
+            }
+        }
+
+        self.conn.execute(
+            "ATTACH DATABASE ? AS imported_fires",
+            [other.to_string_lossy()],
+        )?;
+        if let Err(err) = self.conn.execute(
+            "ATTACH DATABASE ? AS imported_clusters",
+            [clusters_db.to_string_lossy()],
+        ) {
+            self.conn.execute("DETACH DATABASE imported_fires", [])?;
+            return Err(err.into());
+        }
+
+        self.conn.execute("BEGIN TRANSACTION", [])?;
+        let res = self.copy_imported_fires(offset);
+        match res {
+            Ok(_) => self.conn.execute("COMMIT", [])?,
+            Err(_) => self.conn.execute("ROLLBACK", [])?,
+        };
+
+        self.conn.execute("DETACH DATABASE imported_clusters", [])?;
+        self.conn.execute("DETACH DATABASE imported_fires", [])?;
+
+        let (num_fires, num_dropped) = res?;
+        info!(
+            "Imported {} fires from {}, dropped {} associations to missing clusters.",
+            num_fires,
+            other.display(),
+            num_dropped
+        );
+
+        Ok(offset)
+    }
+
+    /// Copy the attached imported_fires into this database, see [FiresDatabase::import_from].
+    ///
+    /// Returns the number of fires copied and the number of associations dropped.
+    fn copy_imported_fires(&self, offset: u64) -> SatFireResult<(usize, usize)> {
+        let imported_column = |column: &'static str| -> SatFireResult<&'static str> {
+            let exists: bool = self.conn.query_row(
+                r#"SELECT COUNT(*) > 0 FROM pragma_table_info('fires', 'imported_fires')
+                   WHERE name = ?"#,
+                [column],
+                |row| row.get(0),
+            )?;
+            Ok(if exists { column } else { "NULL" })
+        };
+        let region = imported_column("region")?;
+        let max_simultaneous_clusters = imported_column("max_simultaneous_clusters")?;
+
+        let num_fires = self.conn.execute(
+            &format!(
+                r#"INSERT INTO fires (
+                     fire_id, merged_into, satellite, first_observed, last_observed, lat, lon,
+                     max_power, max_temperature, num_pixels, pixels, region,
+                     max_simultaneous_clusters)
+                   SELECT
+                     fire_id + ?1,
+                     CASE WHEN merged_into = 0 THEN 0 ELSE merged_into + ?1 END,
+                     satellite, first_observed, last_observed, lat, lon,
+                     max_power, max_temperature, num_pixels, pixels, {},
+                     {}
+                   FROM imported_fires.fires"#,
+                region, max_simultaneous_clusters
+            ),
+            [offset],
+        )?;
+
+        let num_associations: usize = self.conn.query_row(
+            "SELECT COUNT(*) FROM imported_fires.associations",
+            [],
+            |row| row.get(0),
+        )?;
+        let num_imported = self.conn.execute(
+            r#"INSERT OR IGNORE INTO associations (fire_id, cluster_id)
+               SELECT fire_id + ?, cluster_id FROM imported_fires.associations
+               WHERE cluster_id IN (SELECT cluster_id FROM imported_clusters.clusters)"#,
+            [offset],
+        )?;
+
+        let has_metadata: bool = self.conn.query_row(
+            r#"SELECT COUNT(*) > 0 FROM imported_fires.sqlite_master
+               WHERE type = 'table' AND name = 'fire_metadata'"#,
+            [],
+            |row| row.get(0),
+        )?;
+        if has_metadata {
+            self.conn.execute(
+                r#"INSERT OR REPLACE INTO fire_metadata (fire_id, key, value)
+                   SELECT fire_id + ?, key, value FROM imported_fires.fire_metadata"#,
+                [offset],
+            )?;
+        }
+
+        Ok((num_fires, num_associations - num_imported))
+    }
+
+    /// Get the fires with the given fire ids.
+    ///
+    /// The fires are returned sorted by id, and ids that aren't in the database are skipped.
+    pub fn fires_by_ids(&self, ids: &[u64]) -> SatFireResult<Vec<Fire>> {
+        let mut fires = vec![];
+
+        for chunk in sorted_unique_ids(ids).chunks(MAX_IDS_PER_QUERY) {
+            let query = format!(
+                r#"SELECT
+                     fire_id,
+                     merged_into,
+                     satellite,
+                     first_observed,
+                     last_observed,
+                     max_power,
+                     max_temperature,
+                     pixels,
+                     {}
+                   FROM fires
+                   WHERE fire_id IN ({})"#,
+                max_simultaneous_clusters_column(&self.conn)?,
+                in_clause_placeholders(chunk.len())
+            );
+
+            let mut stmt = self.conn.prepare(&query)?;
+            for fire in stmt.query_and_then(params_from_iter(chunk), query_row_to_fire)? {
+                fires.push(fire?);
+            }
+        }
+
+        fires.sort_unstable_by_key(|fire| fire.id());
+
+        Ok(fires)
+    }
+
+    /// Get a single fire by its fire id, or `None` if it isn't in the database.
+    pub fn query_fire_by_id(&self, fire_id: u64) -> SatFireResult<Option<Fire>> {
+        Ok(self.fires_by_ids(&[fire_id])?.pop())
+    }
+
+    /// Follow the `merged_into` chain of a fire to the fire that it ultimately became part of.
+    ///
+    /// A fire that was never merged resolves to itself. It is an error if any fire in the chain
+    /// isn't in the database, or if the chain loops back on itself, which can only happen if the
+    /// database is corrupt.
+    pub fn resolve_final_fire(&self, fire_id: u64) -> SatFireResult<u64> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT merged_into FROM fires WHERE fire_id = ?")?;
+
+        let mut visited = vec![];
+        let mut current = fire_id;
+        loop {
+            if visited.contains(&current) {
+                return Err(format!(
+                    "cycle in merged_into chain of fire {}: {:?}",
+                    fire_id, visited
+                )
+                .into());
+            }
+            visited.push(current);
+
+            let mut rows = stmt.query([current])?;
+            let merged_into: u64 = match rows.next()? {
+                Some(row) => u64::try_from(row.get::<_, i64>(0)?)?,
+                None => {
+                    return Err(format!(
+                        "fire {} in merged_into chain of fire {} not in database",
+                        current, fire_id
+                    )
+                    .into())
+                }
+            };
+
+            if merged_into == 0 {
+                return Ok(current);
+            }
+            current = merged_into;
+        }
+    }
+
+    /// Get the fires that burned the longest.
+    ///
+    /// Only fires with a centroid in `area` and a duration of at least `min_duration` are
+    /// considered. If `sat` is `None`, fires from all satellites are considered. Fires that were
+    /// merged into another fire are not included. The result is sorted by duration, longest first,
+    /// and has at most `limit` fires.
+    pub fn longest_fires(
+        &self,
+        limit: usize,
+        sat: Option<Satellite>,
+        area: BoundingBox,
+        min_duration: Duration,
+    ) -> SatFireResult<Vec<Fire>> {
+        let sat_select = if let Some(sat) = sat {
+            format!("AND satellite = '{}'", sat.name())
+        } else {
+            String::new()
+        };
+
+        let query = &format!(
+            r#"SELECT
+                 fire_id,
+                 merged_into,
+                 satellite,
+                 first_observed,
+                 last_observed,
+                 max_power,
+                 max_temperature,
+                 pixels,
+                 {}
+               FROM fires
+               WHERE
+                 merged_into = 0 AND
+                 (last_observed - first_observed) >= {} AND
+                 {} {}
+               ORDER BY (last_observed - first_observed) DESC, fire_id ASC
+               LIMIT {}"#,
+            max_simultaneous_clusters_column(&self.conn)?,
+            min_duration.num_seconds(),
+            area_filter(&area),
+            sat_select,
+            limit,
+        );
+
+        let mut stmt = self.conn.prepare(query)?;
+        let rows = stmt.query_and_then([], query_row_to_fire)?;
+
+        rows.collect()
+    }
+
+    /// Calculate the total ground area touched by fire in a region during a time period.
+    ///
+    /// The pixels of all the fires that were burning during the time period are rasterized onto
+    /// a latitude-longitude grid and the areas of the unique grid cells are summed, so the areas
+    /// of fires that overlap each other are not double counted. A grid cell is counted if its
+    /// center is inside a fire pixel and inside `area`.
+    ///
+    /// # Arguments
+    /// * `area` - the region to calculate the burned area in.
+    /// * `start` - the start of the time period.
+    /// * `end` - the end of the time period.
+    /// * `resolution` - the size of the grid cells in degrees of latitude and longitude. This
+    ///   should be much smaller than a satellite pixel (about 0.02 degrees for GOES near nadir),
+    ///   0.002 degrees is a good value. Smaller values are more accurate but slower.
+    ///
+    /// # Returns
+    /// The area in square meters.
+    pub fn total_burned_area(
+        &self,
+        area: BoundingBox,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        resolution: f64,
+    ) -> SatFireResult<f64> {
+        use rustc_hash::FxHashSet as HashSet;
+
+        if resolution.is_nan() || resolution <= 0.0 {
+            return Err(format!("Invalid grid resolution: {}", resolution).into());
+        }
+
+        let mut cells: HashSet<(i64, i64)> = HashSet::default();
+
+        let mut query = self.query_fires(None, start, end, area)?;
+        for fire in query.rows()? {
+            let fire = fire?;
+
+            for pixel in fire.pixels().pixels() {
+                let bbox = pixel.bounding_box();
+                if !bbox.overlap(&area, 0.0) {
+                    continue;
+                }
+
+                let min_i = (bbox.ll.lat / resolution).floor() as i64;
+                let max_i = (bbox.ur.lat / resolution).floor() as i64;
+                let min_j = (bbox.ll.lon / resolution).floor() as i64;
+                let max_j = (bbox.ur.lon / resolution).floor() as i64;
+
+                for i in min_i..=max_i {
+                    for j in min_j..=max_j {
+                        let center = Coord {
+                            lat: (i as f64 + 0.5) * resolution,
+                            lon: (j as f64 + 0.5) * resolution,
+                        };
+
+                        if area.contains_coord(center, 0.0) && pixel.contains_coord(center, 1.0e-9)
+                        {
+                            cells.insert((i, j));
+                        }
+                    }
+                }
+            }
+        }
+
+        let total = cells
+            .into_iter()
+            .map(|(i, _)| {
+                let south = i as f64 * resolution;
+                lat_lon_cell_area(south, south + resolution, resolution, &Ellipsoid::GRS80)
+            })
+            .sum();
+
+        Ok(total)
+    }
+}
 
 pub struct FiresDatabaseAddFire<'a> {
     conn: &'a rusqlite::Connection,
     fire_stmt: rusqlite::Statement<'a>,
     assoc_stmt: rusqlite::Statement<'a>,
     associations: HashMap<u64, Vec<u64>>,
+    centroid: CentroidMethod,
+    region_classifier: Option<Box<dyn Fn(Coord) -> Option<String> + 'a>>,
+    compress_pixels: bool,
 }
 
 impl<'a> FiresDatabaseAddFire<'a> {
     /// Add a list of fires to the database.
+    ///
+    /// Only fires that lasted more than an hour are stored, a shorter fire that was stored before
+    /// while it was still going is removed along with its associations.
+    ///
+    /// Only the [satellite](Fire::satellite) of each fire is stored, not the rest of
+    /// [Fire::satellites] for fires merged across satellites.
     pub fn add_fires(&mut self, fires: &FireList) -> SatFireResult<()> {
-        let mut ids = Vec::with_capacity(fires.len());
+        self.add_fires_and_maybe_checkpoint(fires, &FireList::new(), None)
+    }
+
+    /// Add a list of fires to the database along with the fires that are still `ongoing`, and
+    /// record `scan_start` as the last scan fully processed for `sat`.
+    ///
+    /// The fires, their associations, and the checkpoint are committed in a single transaction, so
+    /// after a crash the checkpoint never claims more than what was stored. The checkpoint never
+    /// moves backwards. See [FiresDatabase::checkpoint].
+    ///
+    /// `fires` are handled the same as in [FiresDatabaseAddFire::add_fires]. Clusters from scans
+    /// up to `scan_start` are never connected again after resuming, so `ongoing` has to include
+    /// every fire still going with any of them, and they're all stored no matter how long they
+    /// have lasted so far. Storing an ongoing fire again later replaces it.
+    pub fn add_fires_with_checkpoint(
+        &mut self,
+        fires: &FireList,
+        ongoing: &FireList,
+        sat: Satellite,
+        scan_start: DateTime<Utc>,
+    ) -> SatFireResult<()> {
+        self.add_fires_and_maybe_checkpoint(fires, ongoing, Some((sat, scan_start)))
+    }
+
+    fn add_fires_and_maybe_checkpoint(
+        &mut self,
+        fires: &FireList,
+        ongoing: &FireList,
+        checkpoint: Option<(Satellite, DateTime<Utc>)>,
+    ) -> SatFireResult<()> {
+        const DELETE_FIRE_QUERY: &str = include_str!("database/delete_fire.sql");
+        const DELETE_ASSOC_QUERY: &str = include_str!("database/delete_fire_associations.sql");
+
+        let mut ids = Vec::with_capacity(fires.len() + ongoing.len());
 
         self.conn.execute("BEGIN TRANSACTION", [])?;
 
-        for fire in fires.iter().filter(|f| f.duration() > Duration::hours(1)) {
+        let (keep, short): (Vec<&Fire>, Vec<&Fire>) = fires
+            .iter()
+            .partition(|f| f.duration() > Duration::hours(1));
+
+        for fire in short {
+            self.conn
+                .prepare_cached(DELETE_FIRE_QUERY)?
+                .execute([fire.id()])?;
+            self.conn
+                .prepare_cached(DELETE_ASSOC_QUERY)?
+                .execute([fire.id()])?;
+        }
+
+        for fire in keep.into_iter().chain(ongoing.iter()) {
             ids.push(fire.id());
 
-            let Coord { lat, lon } = fire.centroid();
-            let pixels = fire.pixels().binary_serialize();
+            let Coord { lat, lon } = fire.centroid_by(self.centroid);
+            let pixels = if self.compress_pixels {
+                fire.pixels().binary_serialize_compressed()?
+            } else {
+                fire.pixels().binary_serialize()
+            };
+            let region = self
+                .region_classifier
+                .as_ref()
+                .and_then(|classify| classify(Coord { lat, lon }));
 
             self.fire_stmt.execute([
                 &fire.id() as &dyn ToSql,
@@ -596,6 +2202,8 @@ impl<'a> FiresDatabaseAddFire<'a> {
                 &fire.max_temperature(),
                 &fire.pixels().len(),
                 &pixels,
+                &region,
+                &fire.max_simultaneous_clusters(),
             ])?;
         }
 
@@ -606,11 +2214,38 @@ impl<'a> FiresDatabaseAddFire<'a> {
                 }
             }
         }
+
+        if let Some((sat, scan_start)) = checkpoint {
+            const QUERY: &str = include_str!("database/update_checkpoint.sql");
+            self.conn
+                .execute(QUERY, [&sat.name() as &dyn ToSql, &scan_start.timestamp()])?;
+        }
         self.conn.execute("COMMIT", [])?;
 
         Ok(())
     }
 
+    /// Set a function to label each fire with a region or jurisdiction name.
+    ///
+    /// The classifier is applied to the stored centroid of each fire as it is added to the
+    /// database, and the result is stored in the `region` column. This crate doesn't know about
+    /// any boundaries, so the classifier is expected to do the point-in-polygon test itself. If
+    /// it returns `None`, or no classifier is set, the region is left NULL.
+    pub fn set_region_classifier<F>(&mut self, classifier: F)
+    where
+        F: Fn(Coord) -> Option<String> + 'a,
+    {
+        self.region_classifier = Some(Box::new(classifier));
+    }
+
+    /// Store the pixels of the fires compressed, see [PixelList::binary_serialize_compressed].
+    ///
+    /// Compressed and uncompressed pixels can be mixed in the same database, so this only
+    /// affects fires added after it is set. By default the pixels are not compressed.
+    pub fn set_compress_pixels(&mut self, compress: bool) {
+        self.compress_pixels = compress;
+    }
+
     /// Add associations.
     pub fn add_association(&mut self, fireid: u64, clusterid: u64) {
         let cluster_ids = self.associations.entry(fireid).or_insert(vec![]);
@@ -618,57 +2253,38 @@ impl<'a> FiresDatabaseAddFire<'a> {
     }
 }
 
-pub struct FiresDatabaseQueryFires<'a> {
+pub struct FiresDatabaseIterFires<'a> {
     stmt: rusqlite::Statement<'a>,
+    sat: Satellite,
+    earliest: i64,
+    latest: Option<DateTime<Utc>>,
 }
 
-impl<'a> FiresDatabaseQueryFires<'a> {
+impl<'a> FiresDatabaseIterFires<'a> {
+    /// The latest observation of any fire from the satellite, or `None` if there aren't any.
+    ///
+    /// This is the time [FiresDatabase::ongoing_fires] judges the staleness of the fires at.
+    pub fn latest(&self) -> Option<DateTime<Utc>> {
+        self.latest
+    }
+
     /// Get an iterator over the rows
     pub fn rows(&mut self) -> SatFireResult<impl Iterator<Item = SatFireResult<Fire>> + '_> {
-        Ok(self.stmt.query_and_then([], |row| -> SatFireResult<Fire> {
-            let id: u64 = u64::try_from(row.get::<_, i64>(0)?)?;
-
-            let merged_into: u64 = u64::try_from(row.get::<_, i64>(1)?)?;
-
-            let sat = match row.get_ref(2)? {
-                rusqlite::types::ValueRef::Text(txt) => {
-                    let txt = unsafe { std::str::from_utf8_unchecked(txt) };
-                    Satellite::string_contains_satellite(txt).ok_or("Invalid sattelite")
-                }
-                _ => Err("sattelite not text"),
-            }?;
-
-            let first_observed: DateTime<Utc> = DateTime::from_utc(
-                chrono::NaiveDateTime::from_timestamp_opt(row.get(3)?, 0).unwrap(),
-                Utc,
-            );
-            let last_observed: DateTime<Utc> = DateTime::from_utc(
-                chrono::NaiveDateTime::from_timestamp_opt(row.get(4)?, 0).unwrap(),
-                Utc,
-            );
-
-            let max_power: f64 = row.get(5)?;
-            let max_temperature: f64 = row.get(6)?;
+        Ok(self.stmt.query_and_then(
+            [&self.earliest as &dyn ToSql, &self.sat.name()],
+            query_row_to_fire,
+        )?)
+    }
+}
 
-            let area = match row.get_ref(7)? {
-                rusqlite::types::ValueRef::Blob(bytes) => {
-                    let mut cursor = std::io::Cursor::new(bytes);
-                    Ok(PixelList::binary_deserialize(&mut cursor))
-                }
-                _ => Err("Invalid type in pixels column"),
-            }?;
+pub struct FiresDatabaseQueryFires<'a> {
+    stmt: rusqlite::Statement<'a>,
+}
 
-            Ok(Fire::new(
-                first_observed,
-                last_observed,
-                max_power,
-                max_temperature,
-                id,
-                area,
-                sat,
-                merged_into,
-            ))
-        })?)
+impl<'a> FiresDatabaseQueryFires<'a> {
+    /// Get an iterator over the rows
+    pub fn rows(&mut self) -> SatFireResult<impl Iterator<Item = SatFireResult<Fire>> + '_> {
+        Ok(self.stmt.query_and_then([], query_row_to_fire)?)
     }
 }
 
@@ -708,61 +2324,271 @@ pub struct JointQuerySingleFire<'a> {
 
 impl<'a> JointQuerySingleFire<'a> {
     /// Get an iterator over the rows
+    ///
+    /// Rows with corrupt pixels are skipped with a warning.
     pub fn run(
         &mut self,
         fire_id: u64,
     ) -> SatFireResult<impl Iterator<Item = SatFireResult<ClusterDatabaseClusterRow>> + '_> {
         Ok(self
             .stmt
-            .query_and_then([fire_id], query_row_to_cluster_row)?)
+            .query_and_then([fire_id], query_row_to_cluster_row)?
+            .filter_map(Result::transpose))
+    }
+
+    /// Calculate how far the centroid of a fire, including any fires merged into it, moved over
+    /// its lifetime.
+    pub fn centroid_drift(&mut self, fire_id: u64) -> SatFireResult<CentroidDrift> {
+        let rows = self.run(fire_id)?.collect::<SatFireResult<Vec<_>>>()?;
+        Ok(CentroidDrift::from_time_series(rows))
+    }
+
+    /// Calculate the fire radiative energy, megajoules, of a fire including any fires merged into
+    /// it. See [fire_radiative_energy].
+    pub fn fire_radiative_energy(&mut self, fire_id: u64) -> SatFireResult<f64> {
+        let rows = self.run(fire_id)?.collect::<SatFireResult<Vec<_>>>()?;
+        Ok(fire_radiative_energy(rows))
+    }
+
+    /// Calculate the burned area and its growth rate in each scan of a fire, including any fires
+    /// merged into it. See [fire_growth_series].
+    pub fn fire_growth_series(
+        &mut self,
+        fire_id: u64,
+    ) -> SatFireResult<Vec<(DateTime<Utc>, f64, f64)>> {
+        let rows = self.run(fire_id)?.collect::<SatFireResult<Vec<_>>>()?;
+        Ok(fire_growth_series(rows))
     }
 }
 
-fn query_row_to_cluster_row(row: &rusqlite::Row) -> SatFireResult<ClusterDatabaseClusterRow> {
-    let rowid: u64 = u64::try_from(row.get::<_, i64>(0)?)?;
-    let sat = match row.get_ref(1)? {
-        rusqlite::types::ValueRef::Text(txt) => {
-            let txt = unsafe { std::str::from_utf8_unchecked(txt) };
-            Satellite::string_contains_satellite(txt).ok_or("Invalid satellite")
-        }
-        _ => Err("satellite not text"),
-    }?;
+/// Switch to WAL mode if requested, and use `synchronous=NORMAL` if the database is in WAL mode.
+fn set_journal_mode(conn: &Connection, wal: bool) -> SatFireResult<()> {
+    let mode: String = if wal {
+        conn.query_row("PRAGMA journal_mode=WAL", [], |row| row.get(0))?
+    } else {
+        conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?
+    };
 
-    let sector = match row.get_ref(2)? {
+    if mode.eq_ignore_ascii_case("wal") {
+        conn.execute_batch("PRAGMA synchronous=NORMAL")?;
+    } else if wal {
+        warn!("Unable to use WAL journal mode, using {}", mode);
+    }
+
+    Ok(())
+}
+
+fn open_database_read_only(path: &Path) -> SatFireResult<Connection> {
+    let conn = rusqlite::Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+
+    Ok(conn)
+}
+
+/// Check if a table exists, older versions of the databases are missing some tables.
+fn table_exists(conn: &Connection, table: &str) -> SatFireResult<bool> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?",
+        [table],
+        |row| row.get(0),
+    )?)
+}
+
+/// Run `PRAGMA optimize` and `VACUUM` on a database.
+fn optimize_database(conn: &Connection) -> SatFireResult<()> {
+    conn.execute_batch("PRAGMA optimize; VACUUM;")?;
+    Ok(())
+}
+
+/// Build the WHERE clause selecting clusters for [ClusterDatabase::query_clusters].
+fn cluster_query_filter(
+    sat: Option<Satellite>,
+    sect: Option<Sector>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    area: BoundingBox,
+) -> String {
+    let sat_select = if let Some(sat) = sat {
+        format!("AND satellite = '{}'", sat.name())
+    } else {
+        String::new()
+    };
+
+    let sector_select = if let Some(sect) = sect {
+        format!("AND sector = '{}'", sect.name())
+    } else {
+        String::new()
+    };
+
+    format!(
+        r#"start_time >= {} AND
+           end_time <= {} AND
+           {} {} {}"#,
+        start.timestamp(),
+        end.timestamp(),
+        area_filter(&area),
+        sat_select,
+        sector_select
+    )
+}
+
+/// Build the SQL condition selecting rows with a `lat` and `lon` inside `area`.
+///
+/// A box that wraps around the antimeridian selects the longitudes on both sides of it.
+fn area_filter(area: &BoundingBox) -> String {
+    let lon_filter = if area.wraps_antimeridian() {
+        format!("(lon >= {} OR lon <= {})", area.ll.lon, area.ur.lon)
+    } else {
+        format!("lon >= {} AND lon <= {}", area.ll.lon, area.ur.lon)
+    };
+
+    format!(
+        "lat >= {} AND lat <= {} AND {}",
+        area.ll.lat, area.ur.lat, lon_filter
+    )
+}
+
+/// Check if a table has a column, older versions of the databases are missing some columns.
+fn column_exists(conn: &Connection, table: &str, column: &str) -> SatFireResult<bool> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = ?",
+        [table, column],
+        |row| row.get(0),
+    )?)
+}
+
+fn query_row_to_fire(row: &rusqlite::Row) -> SatFireResult<Fire> {
+    let id: u64 = u64::try_from(row.get::<_, i64>(0)?)?;
+
+    let merged_into: u64 = u64::try_from(row.get::<_, i64>(1)?)?;
+
+    let sat = match row.get_ref(2)? {
         rusqlite::types::ValueRef::Text(txt) => {
             let txt = unsafe { std::str::from_utf8_unchecked(txt) };
-            Sector::string_contains_sector(txt).ok_or("Invalid sector")
+            Satellite::string_contains_satellite(txt).ok_or("Invalid sattelite")
         }
-        _ => Err("sector not text"),
+        _ => Err("sattelite not text"),
     }?;
 
-    let start: DateTime<Utc> = DateTime::from_utc(
+    let first_observed: DateTime<Utc> = DateTime::from_utc(
         chrono::NaiveDateTime::from_timestamp_opt(row.get(3)?, 0).unwrap(),
         Utc,
     );
-    let end: DateTime<Utc> = DateTime::from_utc(
+    let last_observed: DateTime<Utc> = DateTime::from_utc(
         chrono::NaiveDateTime::from_timestamp_opt(row.get(4)?, 0).unwrap(),
         Utc,
     );
-    let power: f64 = row.get(5)?;
+
+    let max_power: f64 = row.get(5)?;
     let max_temperature: f64 = row.get(6)?;
-    let area: f64 = row.get(7)?;
-    let scan_angle: f64 = row.get(8)?;
-    let lat: f64 = row.get(9)?;
-    let lon: f64 = row.get(10)?;
-    let centroid = Coord { lat, lon };
 
-    let pixels = match row.get_ref(11)? {
-        rusqlite::types::ValueRef::Blob(bytes) => {
-            let mut cursor = std::io::Cursor::new(bytes);
-            Ok(PixelList::binary_deserialize(&mut cursor))
-        }
-        _ => Err("Invalid type in pixels column"),
+    let area = match row.get_ref(7)? {
+        rusqlite::types::ValueRef::Blob(bytes) => PixelList::try_binary_deserialize(bytes),
+        _ => Err("Invalid type in pixels column".into()),
     }?;
 
-    Ok(ClusterDatabaseClusterRow {
-        rowid,
-        sat,
+    let max_simultaneous_clusters: Option<u32> = row.get(8)?;
+
+    let mut fire = Fire::new(
+        first_observed,
+        last_observed,
+        max_power,
+        max_temperature,
+        id,
+        area,
+        sat,
+        merged_into,
+    );
+    fire.set_max_simultaneous_clusters(max_simultaneous_clusters.unwrap_or(1));
+
+    Ok(fire)
+}
+
+/// The most ids bound in a single `IN (...)` clause, safely below SQLite's limit on the number of
+/// parameters in a statement.
+const MAX_IDS_PER_QUERY: usize = 500;
+
+/// Sort and remove duplicates from a list of ids so each is only queried once.
+fn sorted_unique_ids(ids: &[u64]) -> Vec<u64> {
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Build the comma separated list of `?` parameters for an `IN (...)` clause.
+fn in_clause_placeholders(num: usize) -> String {
+    vec!["?"; num].join(", ")
+}
+
+/// The column to select for the maximum number of simultaneous clusters in a fire. Databases
+/// created before it was stored don't have the column, so select NULL instead.
+fn max_simultaneous_clusters_column(conn: &Connection) -> SatFireResult<&'static str> {
+    if column_exists(conn, "fires", "max_simultaneous_clusters")? {
+        Ok("max_simultaneous_clusters")
+    } else {
+        Ok("NULL")
+    }
+}
+
+/// Convert a row from the clusters table, returning `None` if the pixels are corrupt.
+///
+/// A partially written database shouldn't stop a whole run, so a row with a pixel BLOB that can't
+/// be deserialized is logged and skipped.
+fn query_row_to_cluster_row(
+    row: &rusqlite::Row,
+) -> SatFireResult<Option<ClusterDatabaseClusterRow>> {
+    let rowid: u64 = u64::try_from(row.get::<_, i64>(0)?)?;
+    let sat = match row.get_ref(1)? {
+        rusqlite::types::ValueRef::Text(txt) => {
+            let txt = unsafe { std::str::from_utf8_unchecked(txt) };
+            Satellite::string_contains_satellite(txt).ok_or("Invalid satellite")
+        }
+        _ => Err("satellite not text"),
+    }?;
+
+    let sector = match row.get_ref(2)? {
+        rusqlite::types::ValueRef::Text(txt) => {
+            let txt = unsafe { std::str::from_utf8_unchecked(txt) };
+            Sector::string_contains_sector(txt).ok_or("Invalid sector")
+        }
+        _ => Err("sector not text"),
+    }?;
+
+    let start: DateTime<Utc> = DateTime::from_utc(
+        chrono::NaiveDateTime::from_timestamp_opt(row.get(3)?, 0).unwrap(),
+        Utc,
+    );
+    let end: DateTime<Utc> = DateTime::from_utc(
+        chrono::NaiveDateTime::from_timestamp_opt(row.get(4)?, 0).unwrap(),
+        Utc,
+    );
+    let power: f64 = row.get(5)?;
+    let max_temperature: f64 = row.get(6)?;
+    let area: f64 = row.get(7)?;
+    let scan_angle: f64 = row.get(8)?;
+    let lat: f64 = row.get(9)?;
+    let lon: f64 = row.get(10)?;
+    let centroid = Coord { lat, lon };
+
+    let pixels = match row.get_ref(11)? {
+        rusqlite::types::ValueRef::Blob(bytes) => match PixelList::try_binary_deserialize(bytes) {
+            Ok(pixels) => pixels,
+            Err(err) => {
+                warn!(target: sat.name(), "Skipping cluster {} with bad pixels - {}", rowid, err);
+                return Ok(None);
+            }
+        },
+        _ => return Err("Invalid type in pixels column".into()),
+    };
+
+    Ok(Some(ClusterDatabaseClusterRow {
+        rowid,
+        sat,
         sector,
         start,
         end,
@@ -772,5 +2598,2140 @@ fn query_row_to_cluster_row(row: &rusqlite::Row) -> SatFireResult<ClusterDatabas
         scan_angle,
         centroid,
         pixels,
-    })
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        testutil::{cluster_from_pixels, PixelGrid},
+        Cluster, MaskCode,
+    };
+
+    /// A cluster with a single 0.02 degree pixel centered on `lat` and `lon`.
+    fn test_cluster(lat: f64, lon: f64, power: f64) -> Cluster {
+        let grid = PixelGrid::new(
+            Coord {
+                lat: lat + 0.01,
+                lon: lon - 0.01,
+            },
+            0.02,
+        );
+
+        cluster_from_pixels(grid.block(0, 0, 1, 1, power))
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_stored_fire_centroid_method() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(6);
+
+        let grid = PixelGrid::new(Coord {lat: 46.0, lon: -120.0}, 1.0);
+
+        // Almost all the power is in the eastern pixel.
+        let mut pixels = PixelList::new();
+        pixels.push(grid.pixel(0, 0, 10.0));
+        pixels.push(grid.pixel(1, 0, 90.0));
+
+        let mut fires = FireList::new();
+        fires.add_fire(Fire::new(start, end, 100.0, 600.0, 1, pixels, Satellite::G17, 0));
+
+        let stored_lon = |method: CentroidMethod| -> f64 {
+            let db = FiresDatabase::connect(":memory:").unwrap();
+            db.prepare_to_add_fires_with_centroid(method).unwrap().add_fires(&fires).unwrap();
+            db.conn
+                .query_row("SELECT lon FROM fires WHERE fire_id = 1", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        let uniform = stored_lon(CentroidMethod::Uniform);
+        let weighted = stored_lon(CentroidMethod::PowerWeighted);
+
+        assert!((uniform - -119.0).abs() < 1.0e-9);
+        assert!((weighted - -118.6).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_region_classifier() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(6);
+
+        let mut fires = FireList::new();
+        for (id, lat, lon) in [(1, 45.0, -120.0), (2, 45.0, 120.0), (3, -45.0, 120.0)] {
+            let mut pixels = PixelList::new();
+            pixels.push(test_cluster(lat, lon, 10.0).pixels().pixels()[0]);
+            fires.add_fire(Fire::new(
+                start,
+                end,
+                10.0,
+                600.0,
+                id,
+                pixels,
+                Satellite::G17,
+                0,
+            ));
+        }
+
+        let mut add_fire = db.prepare_to_add_fires().unwrap();
+        add_fire.set_region_classifier(|coord: Coord| {
+            if coord.lat < 0.0 {
+                // Pretend we don't have boundaries for the southern hemisphere.
+                None
+            } else if coord.lon < 0.0 {
+                Some("NW".to_owned())
+            } else {
+                Some("NE".to_owned())
+            }
+        });
+        add_fire.add_fires(&fires).unwrap();
+        drop(add_fire);
+
+        let region = |id: u64| -> Option<String> {
+            db.conn
+                .query_row("SELECT region FROM fires WHERE fire_id = ?", [id], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+
+        assert_eq!(region(1).as_deref(), Some("NW"));
+        assert_eq!(region(2).as_deref(), Some("NE"));
+        assert_eq!(region(3), None);
+        assert_eq!(db.fire_region(2).unwrap().as_deref(), Some("NE"));
+        assert_eq!(db.fire_region(3).unwrap(), None);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_total_burned_area_no_double_count() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(6);
+
+        let grid = PixelGrid::new(Coord {lat: 46.0, lon: -120.0}, 1.0);
+
+        // The second fire completely overlaps the first, and then some.
+        let pixels1 = grid.block(0, 0, 1, 1, 10.0);
+        let pixels2 = grid.block(0, 0, 2, 1, 10.0);
+
+        let mut fires = FireList::new();
+        fires.add_fire(Fire::new(start, end, 10.0, 600.0, 1, pixels1, Satellite::G17, 0));
+        fires.add_fire(Fire::new(start, end, 20.0, 600.0, 2, pixels2, Satellite::G17, 0));
+
+        db.prepare_to_add_fires().unwrap().add_fires(&fires).unwrap();
+
+        let region = BoundingBox {
+            ll: Coord {lat: 40.0, lon: -125.0},
+            ur: Coord {lat: 50.0, lon: -115.0},
+        };
+
+        let burned = db.total_burned_area(region, start, end, 0.1).unwrap();
+
+        // Two one degree by one degree pixels.
+        let expected = 2.0 * lat_lon_cell_area(45.0, 46.0, 1.0, &Ellipsoid::GRS80);
+        assert!((burned - expected).abs() / expected < 1.0e-6, "{} != {}", burned, expected);
+
+        // Only half of it is in this region.
+        let region = BoundingBox {
+            ll: Coord {lat: 40.0, lon: -119.0},
+            ur: Coord {lat: 50.0, lon: -115.0},
+        };
+        let burned = db.total_burned_area(region, start, end, 0.1).unwrap();
+        assert!((burned - expected / 2.0).abs() / expected < 1.0e-6);
+
+        // No fires before they started.
+        let burned = db
+            .total_burned_area(region, start - Duration::days(2), start - Duration::days(1), 0.1)
+            .unwrap();
+        assert_eq!(burned, 0.0);
+    }
+
+    #[test]
+    fn test_resolve_final_fire() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(6);
+
+        // 1 -> 2 -> 3, 4 stands alone, and 5 <-> 6 is a corrupt cycle.
+        let mut fires = FireList::new();
+        for (id, merged_into) in [(1, 2), (2, 3), (3, 0), (4, 0), (5, 6), (6, 5), (7, 99)] {
+            let mut pixels = PixelList::new();
+            pixels.push(test_cluster(45.0, -120.0, 10.0).pixels().pixels()[0]);
+            fires.add_fire(Fire::new(
+                start,
+                end,
+                10.0,
+                600.0,
+                id,
+                pixels,
+                Satellite::G17,
+                merged_into,
+            ));
+        }
+        db.prepare_to_add_fires()
+            .unwrap()
+            .add_fires(&fires)
+            .unwrap();
+
+        assert_eq!(db.resolve_final_fire(1).unwrap(), 3);
+        assert_eq!(db.resolve_final_fire(2).unwrap(), 3);
+        assert_eq!(db.resolve_final_fire(3).unwrap(), 3);
+        assert_eq!(db.resolve_final_fire(4).unwrap(), 4);
+        assert!(db.resolve_final_fire(5).is_err());
+        assert!(db.resolve_final_fire(7).is_err());
+        assert!(db.resolve_final_fire(42).is_err());
+
+        let fire = db.query_fire_by_id(2).unwrap().unwrap();
+        assert_eq!(fire.id(), 2);
+        assert_eq!(fire.merged_into(), 3);
+        assert!(db.query_fire_by_id(42).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_check_consistency() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(6);
+
+        let mut fires = FireList::new();
+        for id in 1..=4 {
+            let mut pixels = PixelList::new();
+            pixels.push(test_cluster(45.0, -120.0, 10.0).pixels().pixels()[0]);
+            fires.add_fire(Fire::new(
+                start,
+                end,
+                10.0,
+                600.0,
+                id,
+                pixels,
+                Satellite::G17,
+                0,
+            ));
+        }
+
+        let mut add_fire = db.prepare_to_add_fires().unwrap();
+        add_fire.add_association(1, 100);
+        add_fire.add_fires(&fires).unwrap();
+        drop(add_fire);
+
+        assert!(db.check_consistency().unwrap().is_empty());
+
+        let corrupt = |sql: &str| {
+            db.conn.execute(sql, []).unwrap();
+        };
+
+        corrupt("INSERT INTO associations (fire_id, cluster_id) VALUES (99, 101)");
+        corrupt("UPDATE fires SET merged_into = 98 WHERE fire_id = 1");
+        corrupt("UPDATE fires SET first_observed = last_observed + 60 WHERE fire_id = 2");
+        corrupt("UPDATE fires SET pixels = X'0102' WHERE fire_id = 3");
+        corrupt("UPDATE fires SET num_pixels = 2 WHERE fire_id = 4");
+
+        let issues = db.check_consistency().unwrap();
+        assert_eq!(issues.len(), 5);
+
+        assert_eq!(
+            issues[0],
+            ConsistencyIssue::MissingAssociatedFire {
+                fire_id: 99,
+                cluster_id: 101
+            }
+        );
+        assert_eq!(
+            issues[1],
+            ConsistencyIssue::MissingMergedIntoFire {
+                fire_id: 1,
+                merged_into: 98
+            }
+        );
+        assert_eq!(
+            issues[2],
+            ConsistencyIssue::InvalidTimeRange {
+                fire_id: 2,
+                first_observed: end + Duration::minutes(1),
+                last_observed: end,
+            }
+        );
+        assert!(matches!(
+            issues[3],
+            ConsistencyIssue::InvalidPixels { fire_id: 3, .. }
+        ));
+        assert!(matches!(
+            issues[4],
+            ConsistencyIssue::InvalidPixels { fire_id: 4, .. }
+        ));
+    }
+
+    #[test]
+    fn test_import_fires_database() {
+        let tmp = |name: &str| {
+            let path = std::env::temp_dir().join(format!(
+                "satfire_test_import_{}_{}.sqlite",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            path
+        };
+        let clusters_path = tmp("clusters");
+        let other_path = tmp("fires");
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(6);
+
+        // Clusters 1, 2, and 3.
+        {
+            let clusters = ClusterDatabase::connect(&clusters_path).unwrap();
+            let mut add = clusters.prepare_to_add_clusters().unwrap();
+            let first = vec![
+                test_cluster(45.0, -120.0, 10.0),
+                test_cluster(46.0, -121.0, 20.0),
+            ];
+            add.add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                end,
+                first,
+            ))
+            .unwrap();
+            let second = vec![test_cluster(45.0, -120.0, 30.0)];
+            add.add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                end,
+                end + Duration::minutes(10),
+                second,
+            ))
+            .unwrap();
+        }
+
+        let add = |db: &FiresDatabase, fires: &[(u64, u64)], assocs: &[(u64, u64)]| {
+            let mut list = FireList::new();
+            for &(id, merged_into) in fires {
+                let mut pixels = PixelList::new();
+                pixels.push(test_cluster(45.0, -120.0, 10.0).pixels().pixels()[0]);
+                list.add_fire(Fire::new(
+                    start,
+                    end,
+                    10.0,
+                    600.0,
+                    id,
+                    pixels,
+                    Satellite::G17,
+                    merged_into,
+                ));
+            }
+
+            let mut add_fire = db.prepare_to_add_fires().unwrap();
+            for &(fire_id, cluster_id) in assocs {
+                add_fire.add_association(fire_id, cluster_id);
+            }
+            add_fire.add_fires(&list).unwrap();
+        };
+
+        let db = FiresDatabase::connect(":memory:").unwrap();
+        add(&db, &[(1, 0), (2, 1)], &[(1, 1), (2, 2)]);
+
+        {
+            let other = FiresDatabase::connect(&other_path).unwrap();
+            add(
+                &other,
+                &[(1, 0), (2, 0), (3, 1)],
+                &[(1, 3), (2, 1), (3, 2), (3, 999)],
+            );
+            other.set_fire_metadata(3, "incident", "Dixie").unwrap();
+        }
+
+        let offset = db.import_from(&other_path, &clusters_path).unwrap();
+        assert_eq!(offset, 2);
+        assert_eq!(db.next_wildfire_id().unwrap(), 6);
+
+        let fires = db.fires_by_ids(&[1, 2, 3, 4, 5]).unwrap();
+        let merged: Vec<(u64, u64)> = fires.iter().map(|f| (f.id(), f.merged_into())).collect();
+        assert_eq!(merged, vec![(1, 0), (2, 1), (3, 0), (4, 0), (5, 3)]);
+
+        let mut stmt = db
+            .conn
+            .prepare("SELECT fire_id, cluster_id FROM associations ORDER BY fire_id, cluster_id")
+            .unwrap();
+        let assocs: Vec<(u64, u64)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(assocs, vec![(1, 1), (2, 2), (3, 3), (4, 1), (5, 2)]);
+        drop(stmt);
+
+        assert!(db.check_consistency().unwrap().is_empty());
+        assert_eq!(
+            db.get_fire_metadata(5, "incident").unwrap().as_deref(),
+            Some("Dixie")
+        );
+
+        // The imported databases are detached again, so they can be imported twice.
+        assert_eq!(db.import_from(&other_path, &clusters_path).unwrap(), 5);
+        assert_eq!(db.next_wildfire_id().unwrap(), 9);
+
+        assert!(db.import_from(&tmp("missing"), &clusters_path).is_err());
+
+        let _ = std::fs::remove_file(&clusters_path);
+        let _ = std::fs::remove_file(&other_path);
+    }
+
+    #[test]
+    fn test_fire_metadata() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        assert_eq!(db.get_fire_metadata(1, "incident").unwrap(), None);
+        assert!(db.all_fire_metadata(1).unwrap().is_empty());
+
+        db.set_fire_metadata(1, "incident", "Bootleg").unwrap();
+        db.set_fire_metadata(1, "confidence", "0.9").unwrap();
+        db.set_fire_metadata(2, "incident", "Dixie").unwrap();
+
+        assert_eq!(
+            db.get_fire_metadata(1, "incident").unwrap().as_deref(),
+            Some("Bootleg")
+        );
+        assert_eq!(
+            db.get_fire_metadata(2, "incident").unwrap().as_deref(),
+            Some("Dixie")
+        );
+        assert_eq!(db.get_fire_metadata(2, "confidence").unwrap(), None);
+
+        // Setting a key again replaces the value.
+        db.set_fire_metadata(1, "confidence", "0.95").unwrap();
+        assert_eq!(
+            db.all_fire_metadata(1).unwrap(),
+            vec![
+                ("confidence".to_string(), "0.95".to_string()),
+                ("incident".to_string(), "Bootleg".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_short_fires_are_stored_while_ongoing() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let first: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let second = first + Duration::minutes(10);
+
+        let mut fires = FireList::new();
+        let mut pixels = PixelList::new();
+        pixels.push(test_cluster(45.0, -120.0, 10.0).pixels().pixels()[0]);
+        fires.add_fire(Fire::new(
+            first,
+            second,
+            10.0,
+            600.0,
+            1,
+            pixels,
+            Satellite::G17,
+            0,
+        ));
+
+        let count = |table: &str| -> i64 {
+            db.conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+
+        let mut add_fire = db.prepare_to_add_fires().unwrap();
+        add_fire.add_association(1, 7);
+        add_fire
+            .add_fires_with_checkpoint(&FireList::new(), &fires, Satellite::G17, second)
+            .unwrap();
+        assert_eq!(count("fires"), 1);
+        assert_eq!(count("associations"), 1);
+
+        // Once it's done it turns out to be too short to keep.
+        add_fire.add_fires(&fires).unwrap();
+        assert_eq!(count("fires"), 0);
+        assert_eq!(count("associations"), 0);
+    }
+
+    #[test]
+    fn test_checkpoint_is_stored_with_fires() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let first: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let second = first + Duration::hours(1);
+
+        let mut fires = FireList::new();
+        let mut pixels = PixelList::new();
+        pixels.push(test_cluster(45.0, -120.0, 10.0).pixels().pixels()[0]);
+        fires.add_fire(Fire::new(
+            first,
+            second,
+            10.0,
+            600.0,
+            1,
+            pixels,
+            Satellite::G17,
+            0,
+        ));
+
+        assert_eq!(db.checkpoint(Satellite::G17).unwrap(), None);
+
+        let mut add_fire = db.prepare_to_add_fires().unwrap();
+        add_fire.add_fires(&fires).unwrap();
+        assert_eq!(db.checkpoint(Satellite::G17).unwrap(), None);
+
+        add_fire
+            .add_fires_with_checkpoint(&fires, &FireList::new(), Satellite::G17, second)
+            .unwrap();
+        add_fire
+            .add_fires_with_checkpoint(&FireList::new(), &FireList::new(), Satellite::G16, first)
+            .unwrap();
+        assert_eq!(db.checkpoint(Satellite::G17).unwrap(), Some(second));
+        assert_eq!(db.checkpoint(Satellite::G16).unwrap(), Some(first));
+
+        // It never moves backwards.
+        add_fire
+            .add_fires_with_checkpoint(&FireList::new(), &FireList::new(), Satellite::G17, first)
+            .unwrap();
+        assert_eq!(db.checkpoint(Satellite::G17).unwrap(), Some(second));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_iter_fires() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let now: DateTime<Utc> = "2021-08-31T00:00:00Z".parse().unwrap();
+        let days = Duration::days;
+
+        let pixels = || {
+            let mut pixels = PixelList::new();
+            pixels.push(test_cluster(45.0, -120.0, 10.0).pixels().pixels()[0]);
+            pixels
+        };
+
+        let mut fires = FireList::new();
+        for (id, first, last, sat, merged_into) in [
+            (1, now - days(3), now, Satellite::G17, 0),
+            (2, now - days(40), now - days(2), Satellite::G17, 0),
+            // Quiet for longer than it burned, so it's stale.
+            (3, now - days(12), now - days(10), Satellite::G17, 0),
+            // Merged into another fire.
+            (4, now - days(3), now - days(2), Satellite::G17, 1),
+            // Too long ago to be considered at all.
+            (5, now - days(400), now - days(300), Satellite::G17, 0),
+            // Another satellite.
+            (6, now - days(3), now, Satellite::G16, 0),
+        ] {
+            fires.add_fire(Fire::new(first, last, 10.0, 600.0, id, pixels(), sat, merged_into));
+        }
+        db.prepare_to_add_fires().unwrap().add_fires(&fires).unwrap();
+
+        let mut query = db.iter_fires(Satellite::G17).unwrap();
+        assert_eq!(query.latest(), Some(now));
+
+        let mut all: Vec<u64> = query.rows().unwrap().map(|f| f.unwrap().id()).collect();
+        all.sort_unstable();
+        assert_eq!(all, vec![1, 2, 3]);
+
+        let staleness = StalenessConfig::default();
+        let mut ongoing: Vec<u64> = staleness
+            .without_stale(query.rows().unwrap(), now)
+            .map(|f| f.unwrap().id())
+            .collect();
+        ongoing.sort_unstable();
+        assert_eq!(ongoing, vec![1, 2]);
+
+        let mut from_list: Vec<u64> = db.ongoing_fires(Satellite::G17).unwrap().iter().map(Fire::id).collect();
+        from_list.sort_unstable();
+        assert_eq!(from_list, ongoing);
+
+        let mut query = db.iter_fires(Satellite::G18).unwrap();
+        assert_eq!(query.latest(), None);
+        assert_eq!(query.rows().unwrap().count(), 0);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_ignition_extinction_series() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let day1: DateTime<Utc> = "2021-08-01T00:00:00Z".parse().unwrap();
+        let day2 = day1 + Duration::days(1);
+        let day3 = day1 + Duration::days(2);
+        let hours = Duration::hours;
+
+        let pixels = |lat: f64| {
+            let mut pixels = PixelList::new();
+            pixels.push(test_cluster(lat, -120.0, 10.0).pixels().pixels()[0]);
+            pixels
+        };
+
+        let mut fires = FireList::new();
+        for (id, first, last, lat, merged_into) in [
+            (1, day1 + hours(2), day1 + hours(6), 45.0, 0),
+            (2, day1 + hours(20), day3 + hours(1), 45.0, 0),
+            (3, day2 + hours(12), day3 + hours(23), 45.0, 0),
+            // Merged into fire 2, so it ignited but wasn't extinguished.
+            (4, day2 + hours(1), day2 + hours(5), 45.0, 2),
+            // Outside the area.
+            (5, day1 + hours(1), day1 + hours(5), 35.0, 0),
+        ] {
+            fires.add_fire(Fire::new(first, last, 10.0, 600.0, id, pixels(lat), Satellite::G17, merged_into));
+        }
+
+        db.prepare_to_add_fires().unwrap().add_fires(&fires).unwrap();
+
+        let region = BoundingBox {
+            ll: Coord {lat: 40.0, lon: -125.0},
+            ur: Coord {lat: 50.0, lon: -115.0},
+        };
+
+        let series = db
+            .ignition_extinction_series(region, day1 + hours(3), day3 + hours(3), Duration::days(1))
+            .unwrap();
+
+        assert_eq!(series, vec![(day1, 2, 1), (day2, 2, 0), (day3, 0, 2)]);
+
+        assert!(db.ignition_extinction_series(region, day1, day2, Duration::zero()).is_err());
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_longest_fires() {
+        let db = FiresDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let pixels = |lat: f64| {
+            let mut pixels = PixelList::new();
+            pixels.push(test_cluster(lat, -120.0, 10.0).pixels().pixels()[0]);
+            pixels
+        };
+
+        let mut fires = FireList::new();
+        for (id, hours, sat) in [
+            (1, 2, Satellite::G17),
+            (2, 48, Satellite::G17),
+            (3, 12, Satellite::G16),
+            (4, 24, Satellite::G17),
+            (5, 6, Satellite::G17),
+        ] {
+            let end = start + Duration::hours(hours);
+            fires.add_fire(Fire::new(start, end, 10.0, 600.0, id, pixels(45.0), sat, 0));
+        }
+
+        db.prepare_to_add_fires().unwrap().add_fires(&fires).unwrap();
+
+        let region = BoundingBox {
+            ll: Coord {lat: 40.0, lon: -125.0},
+            ur: Coord {lat: 50.0, lon: -115.0},
+        };
+
+        let ids = |fires: Vec<Fire>| fires.iter().map(|f| f.id()).collect::<Vec<_>>();
+
+        let longest = db.longest_fires(3, None, region, Duration::zero()).unwrap();
+        assert_eq!(ids(longest), vec![2, 4, 3]);
+
+        let longest = db.longest_fires(10, Some(Satellite::G17), region, Duration::zero()).unwrap();
+        assert_eq!(ids(longest), vec![2, 4, 5, 1]);
+
+        let longest = db.longest_fires(10, None, region, Duration::hours(12)).unwrap();
+        assert_eq!(ids(longest), vec![2, 4, 3]);
+
+        let elsewhere = BoundingBox {
+            ll: Coord {lat: 30.0, lon: -125.0},
+            ur: Coord {lat: 40.0, lon: -115.0},
+        };
+        assert!(db.longest_fires(10, None, elsewhere, Duration::zero()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_group_by_scan() {
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let t1 = t0 + Duration::minutes(5);
+        let t2 = t0 + Duration::minutes(10);
+
+        let row = |rowid: u64, start: DateTime<Utc>| ClusterDatabaseClusterRow {
+            rowid,
+            start,
+            end: start + Duration::minutes(4),
+            power: 10.0,
+            max_temperature: 600.0,
+            area: 100.0,
+            scan_angle: 5.0,
+            centroid: Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            sector: Sector::CONUS,
+            sat: Satellite::G17,
+            pixels: PixelList::new(),
+        };
+
+        let rows = vec![
+            row(1, t0),
+            row(2, t0),
+            row(3, t1),
+            row(4, t2),
+            row(5, t2),
+            row(6, t2),
+        ];
+
+        let groups: Vec<_> = group_by_scan(rows.into_iter())
+            .map(|(start, group)| (start, group.iter().map(|r| r.rowid).collect::<Vec<_>>()))
+            .collect();
+
+        assert_eq!(
+            groups,
+            vec![(t0, vec![1, 2]), (t1, vec![3]), (t2, vec![4, 5, 6])]
+        );
+
+        assert_eq!(group_by_scan(std::iter::empty()).count(), 0);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_centroid_drift() {
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let row = |i: i64, lat: f64, lon: f64| {
+            let cluster = test_cluster(lat, lon, 50.0);
+            let start = t0 + Duration::minutes(5 * i);
+            ClusterDatabaseClusterRow {
+                rowid: i as u64 + 1,
+                start,
+                end: start + Duration::minutes(4),
+                power: cluster.total_power(),
+                max_temperature: cluster.max_temperature(),
+                area: cluster.total_area(),
+                scan_angle: cluster.max_scan_angle(),
+                centroid: cluster.pixels().centroid(),
+                sector: Sector::CONUS,
+                sat: Satellite::G17,
+                pixels: cluster.pixels().clone(),
+            }
+        };
+
+        // A flare that never moves, observed for a day.
+        let stationary: Vec<_> = (0..288).map(|i| row(i, 45.0, -120.0)).collect();
+        let drift = CentroidDrift::from_time_series(stationary);
+        assert_eq!(drift.num_scans, 288);
+        assert!(drift.path_length < 1.0);
+        assert!(drift.net_displacement < 1.0);
+
+        // A fire that spreads north by 0.01 degrees (about 1.1 km) every scan, but with two
+        // clusters in every scan whose combined centroid moves a little east and back again.
+        let mut moving = Vec::new();
+        for i in 0..=10 {
+            let lat = 45.0 + 0.01 * i as f64;
+            moving.push(row(i, lat, -120.0));
+            moving.push(row(i, lat, if i % 2 == 0 { -120.0 } else { -119.98 }));
+        }
+        let drift = CentroidDrift::from_time_series(moving);
+        assert_eq!(drift.num_scans, 11);
+
+        let north = Coord {lat: 45.0, lon: -120.0}.distance_meters(Coord {lat: 45.1, lon: -120.0});
+        assert!((drift.net_displacement - north).abs() < 1.0);
+        assert!(drift.path_length > drift.net_displacement + 1_000.0);
+
+        let empty = CentroidDrift::from_time_series(Vec::new());
+        assert_eq!(empty.num_scans, 0);
+        assert_eq!(empty.path_length, 0.0);
+    }
+
+    #[test]
+    fn test_fire_radiative_energy() {
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let row = |i: i64, power: f64| {
+            let cluster = test_cluster(45.0, -120.0, power);
+            let start = t0 + Duration::minutes(5 * i);
+            ClusterDatabaseClusterRow {
+                rowid: i as u64 + 1,
+                start,
+                end: start + Duration::minutes(4),
+                power: cluster.total_power(),
+                max_temperature: cluster.max_temperature(),
+                area: cluster.total_area(),
+                scan_angle: cluster.max_scan_angle(),
+                centroid: cluster.pixels().centroid(),
+                sector: Sector::CONUS,
+                sat: Satellite::G17,
+                pixels: cluster.pixels().clone(),
+            }
+        };
+
+        // Power ramps up linearly from 0 to 100 MW over 10 scans and then back down, so the
+        // trapezoidal rule is exact: a triangle 100 minutes wide and 100 MW tall.
+        let mut rows: Vec<_> = (0..=20)
+            .map(|i| row(i, 10.0 * (10 - (i - 10).abs()) as f64))
+            .collect();
+        let expected = 0.5 * 100.0 * 100.0 * 60.0;
+        assert!((fire_radiative_energy(rows.clone()) - expected).abs() < 1.0e-6);
+
+        // Two clusters in the same scan add their power together.
+        rows.push(row(10, 50.0));
+        rows.sort_by_key(|row| row.start);
+        let expected = expected + 0.5 * 50.0 * 10.0 * 60.0;
+        assert!((fire_radiative_energy(rows) - expected).abs() < 1.0e-6);
+
+        assert_eq!(fire_radiative_energy(vec![row(0, 100.0)]), 0.0);
+        assert_eq!(fire_radiative_energy(Vec::new()), 0.0);
+    }
+
+    #[test]
+    fn test_fire_growth_series() {
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let row = |minutes: i64, lon: f64| {
+            let cluster = test_cluster(45.0, lon, 10.0);
+            let start = t0 + Duration::minutes(minutes);
+            ClusterDatabaseClusterRow {
+                rowid: 0,
+                start,
+                end: start + Duration::minutes(4),
+                power: cluster.total_power(),
+                max_temperature: cluster.max_temperature(),
+                area: cluster.total_area(),
+                scan_angle: cluster.max_scan_angle(),
+                centroid: cluster.pixels().centroid(),
+                sector: Sector::CONUS,
+                sat: Satellite::G17,
+                pixels: cluster.pixels().clone(),
+            }
+        };
+
+        // One pixel, then the same pixel and a new one, then only a third pixel 10 minutes later.
+        let rows = vec![
+            row(0, -120.0),
+            row(5, -120.0),
+            row(5, -119.9),
+            row(15, -119.8),
+        ];
+
+        let series = fire_growth_series(rows);
+        assert_eq!(series.len(), 3);
+
+        let times: Vec<_> = series.iter().map(|&(time, _, _)| time).collect();
+        assert_eq!(
+            times,
+            vec![t0, t0 + Duration::minutes(5), t0 + Duration::minutes(15)]
+        );
+
+        let areas: Vec<_> = series.iter().map(|&(_, area, _)| area).collect();
+        assert_eq!(areas, vec![1_000.0, 2_000.0, 3_000.0]);
+
+        // 1,000 square meters in 5 minutes, then in 10 minutes.
+        let rates: Vec<_> = series.iter().map(|&(_, _, rate)| rate).collect();
+        assert_eq!(rates, vec![0.0, 12_000.0, 6_000.0]);
+
+        assert!(fire_growth_series(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_cached_cluster_database() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        db.prepare_to_add_clusters()
+            .unwrap()
+            .add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                end,
+                vec![
+                    test_cluster(45.0, -120.0, 10.0),
+                    test_cluster(35.0, -110.0, 20.0),
+                ],
+            ))
+            .unwrap();
+
+        let db = CachedClusterDatabase::new(db, 2);
+
+        let everywhere = BoundingBox {
+            ll: Coord {
+                lat: -90.0,
+                lon: -180.0,
+            },
+            ur: Coord {
+                lat: 90.0,
+                lon: 180.0,
+            },
+        };
+        let north = BoundingBox {
+            ll: Coord {
+                lat: 40.0,
+                lon: -180.0,
+            },
+            ..everywhere
+        };
+        let south = BoundingBox {
+            ur: Coord {
+                lat: 40.0,
+                lon: 180.0,
+            },
+            ..everywhere
+        };
+
+        let query = |area: BoundingBox| {
+            db.query_clusters(Some(Satellite::G17), None, start, end, area)
+                .unwrap()
+        };
+
+        let first = query(everywhere);
+        assert_eq!(first.len(), 2);
+        assert_eq!(db.num_database_queries(), 1);
+
+        // The same query again is answered from the cache.
+        let second = query(everywhere);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(db.num_database_queries(), 1);
+
+        assert_eq!(query(north).len(), 1);
+        assert_eq!(db.num_database_queries(), 2);
+
+        // This evicts the least recently used query, everywhere was used more recently than north.
+        query(everywhere);
+        assert_eq!(query(south).len(), 1);
+        assert_eq!(db.num_database_queries(), 3);
+
+        query(everywhere);
+        assert_eq!(db.num_database_queries(), 3);
+        query(north);
+        assert_eq!(db.num_database_queries(), 4);
+
+        db.clear();
+        query(north);
+        assert_eq!(db.num_database_queries(), 5);
+    }
+
+    #[test]
+    fn test_clusters_in_scan() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+        let next_start = end;
+        let next_end = next_start + Duration::minutes(10);
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+
+            let sat = Satellite::G17;
+            let sector = Sector::FULL;
+
+            let first = vec![
+                test_cluster(45.0, -120.0, 10.0),
+                test_cluster(46.0, -121.0, 20.0),
+            ];
+            add.add(ClusterList::new(sat, sector, start, end, first))
+                .unwrap();
+
+            let second = vec![test_cluster(45.0, -120.0, 30.0)];
+            add.add(ClusterList::new(sat, sector, next_start, next_end, second))
+                .unwrap();
+        }
+
+        let rows = db
+            .clusters_in_scan(Satellite::G17, Sector::FULL, start, end)
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|r| r.start == start && r.end == end));
+        assert!((rows[0].power - 10.0).abs() < 1.0e-12);
+        assert!((rows[1].power - 20.0).abs() < 1.0e-12);
+
+        let rows = db
+            .clusters_in_scan(Satellite::G17, Sector::FULL, next_start, next_end)
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!((rows[0].power - 30.0).abs() < 1.0e-12);
+
+        let rows = db
+            .clusters_in_scan(Satellite::G17, Sector::CONUS, start, end)
+            .unwrap();
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_wal_allows_reading_while_writing() {
+        let path =
+            std::env::temp_dir().join(format!("satfire_test_wal_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+        let world = BoundingBox {
+            ll: Coord {
+                lat: -90.0,
+                lon: -180.0,
+            },
+            ur: Coord {
+                lat: 90.0,
+                lon: 180.0,
+            },
+        };
+
+        let writer = ClusterDatabase::connect_wal(&path).unwrap();
+        let mode: String = writer
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        {
+            let mut add = writer.prepare_to_add_clusters().unwrap();
+            let first = vec![test_cluster(45.0, -120.0, 10.0)];
+            add.add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                end,
+                first,
+            ))
+            .unwrap();
+        }
+
+        // Later connections stay in WAL mode.
+        let other = ClusterDatabase::connect(&path).unwrap();
+        let sync: i64 = other
+            .conn
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sync, 1); // NORMAL
+        drop(other);
+
+        let reader = ClusterDatabase::connect_read_only(&path).unwrap();
+        let count = |db: &ClusterDatabase| {
+            let mut query = db.query_clusters(None, None, start, end, world).unwrap();
+            let rows = query.rows().unwrap();
+            rows.count()
+        };
+
+        // An exclusive lock would block readers with a rollback journal.
+        writer.conn.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        writer.conn.execute("DELETE FROM clusters", []).unwrap();
+        assert_eq!(count(&reader), 1);
+        writer.conn.execute_batch("COMMIT").unwrap();
+        assert_eq!(count(&reader), 0);
+
+        drop(reader);
+        drop(writer);
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    #[test]
+    fn test_optimize_reclaims_space() {
+        let path = std::env::temp_dir().join(format!(
+            "satfire_test_optimize_{}.sqlite",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        let db = ClusterDatabase::connect(&path).unwrap();
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+            let clusters = (0..500)
+                .map(|i| test_cluster(45.0, -120.0 + 0.01 * i as f64, 10.0))
+                .collect();
+            add.add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                end,
+                clusters,
+            ))
+            .unwrap();
+        }
+        db.conn.execute("DELETE FROM clusters", []).unwrap();
+
+        let free_pages = |db: &ClusterDatabase| -> i64 {
+            db.conn
+                .query_row("PRAGMA freelist_count", [], |row| row.get(0))
+                .unwrap()
+        };
+        let size_before = std::fs::metadata(&path).unwrap().len();
+        assert!(free_pages(&db) > 0);
+
+        db.optimize().unwrap();
+        assert_eq!(free_pages(&db), 0);
+        assert!(std::fs::metadata(&path).unwrap().len() < size_before);
+
+        let fires = FiresDatabase::connect(":memory:").unwrap();
+        fires.optimize().unwrap();
+
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_query_clusters_page() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+            for i in 0..5 {
+                let scan_start = start + Duration::minutes(10 * i);
+                let scan_end = scan_start + Duration::minutes(10);
+                let clusters = vec![test_cluster(45.0, -120.0, i as f64)];
+                add.add(ClusterList::new(
+                    Satellite::G17,
+                    Sector::FULL,
+                    scan_start,
+                    scan_end,
+                    clusters,
+                ))
+                .unwrap();
+            }
+        }
+
+        let end = start + Duration::hours(1);
+        let area = BoundingBox {
+            ll: Coord {
+                lat: 44.0,
+                lon: -121.0,
+            },
+            ur: Coord {
+                lat: 46.0,
+                lon: -119.0,
+            },
+        };
+        let powers = |limit: Option<usize>, offset: Option<usize>| -> Vec<f64> {
+            let mut query = db.query_clusters(None, None, start, end, area).unwrap();
+            if let Some(limit) = limit {
+                query = query.limit(limit);
+            }
+            if let Some(offset) = offset {
+                query = query.offset(offset);
+            }
+            let rows = query.rows().unwrap().map(|row| row.unwrap().power);
+            rows.collect()
+        };
+
+        assert_eq!(powers(None, None), vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(powers(Some(2), None), vec![0.0, 1.0]);
+        assert_eq!(powers(Some(2), Some(2)), vec![2.0, 3.0]);
+        assert_eq!(powers(Some(2), Some(4)), vec![4.0]);
+        assert_eq!(powers(None, Some(3)), vec![3.0, 4.0]);
+        assert!(powers(Some(2), Some(5)).is_empty());
+        assert!(powers(Some(0), None).is_empty());
+    }
+
+    #[test]
+    fn test_compressed_pixels() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+            let sat = Satellite::G17;
+
+            let first = vec![test_cluster(45.0, -120.0, 10.0)];
+            add.add(ClusterList::new(sat, Sector::FULL, start, end, first))
+                .unwrap();
+
+            add.set_compress_pixels(true);
+            let second = vec![test_cluster(46.0, -121.0, 20.0)];
+            add.add(ClusterList::new(sat, Sector::CONUS, start, end, second))
+                .unwrap();
+        }
+
+        let blobs: Vec<Vec<u8>> = db
+            .conn
+            .prepare("SELECT pixels FROM clusters ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(&blobs[0][..4], b"SFPL");
+        assert_eq!(&blobs[1][..4], b"SFPZ");
+
+        let expected = [
+            (Sector::FULL, 45.0, -120.0, 10.0),
+            (Sector::CONUS, 46.0, -121.0, 20.0),
+        ];
+        for (sector, lat, lon, power) in expected {
+            let rows = db
+                .clusters_in_scan(Satellite::G17, sector, start, end)
+                .unwrap();
+            assert_eq!(rows.len(), 1);
+
+            let expected = test_cluster(lat, lon, power);
+            assert_eq!(rows[0].pixels.len(), 1);
+            assert!(rows[0].pixels.pixels()[0].approx_equal(&expected.pixels().pixels()[0], 0.0));
+        }
+    }
+
+    #[test]
+    fn test_acquisition_offset_is_stored() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(5);
+
+        let mut pixel = test_cluster(45.0, -120.0, 10.0).pixels().pixels()[0];
+        pixel.acquisition_offset = 150.5;
+        let mut pixels = PixelList::new();
+        pixels.push(pixel);
+        let cluster = Cluster::new(
+            10.0,
+            pixel.area,
+            pixel.temperature,
+            pixel.scan_angle,
+            pixels,
+        );
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+            add.add(ClusterList::new(
+                Satellite::G17,
+                Sector::CONUS,
+                start,
+                end,
+                vec![cluster.clone()],
+            ))
+            .unwrap();
+
+            add.set_compress_pixels(true);
+            add.add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                end,
+                vec![cluster],
+            ))
+            .unwrap();
+        }
+
+        for sector in [Sector::CONUS, Sector::FULL] {
+            let rows = db
+                .clusters_in_scan(Satellite::G17, sector, start, end)
+                .unwrap();
+            assert_eq!(rows.len(), 1);
+
+            let stored = rows[0].pixels.pixels()[0];
+            assert_eq!(stored.acquisition_offset, 150.5);
+            assert_eq!(
+                stored.acquisition_time(start),
+                start + Duration::milliseconds(150_500)
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_scans() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let cadence = Sector::CONUS.nominal_cadence();
+        assert_eq!(cadence, Duration::minutes(5));
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+
+            // The scans start a little after the scheduled times, and the ones at 15 and 20
+            // minutes are missing.
+            for minutes in [0, 5, 10, 25] {
+                let scan_start = start + Duration::minutes(minutes) + Duration::seconds(77);
+                let scan_end = scan_start + Duration::seconds(170);
+                let clusters = if minutes == 5 {
+                    vec![]
+                } else {
+                    vec![test_cluster(45.0, -120.0, 10.0)]
+                };
+                add.add(ClusterList::new(
+                    Satellite::G17,
+                    Sector::CONUS,
+                    scan_start,
+                    scan_end,
+                    clusters,
+                ))
+                .unwrap();
+            }
+        }
+
+        let end = start + Duration::minutes(30);
+        let missing = db
+            .missing_scans(Satellite::G17, Sector::CONUS, start, end, cadence)
+            .unwrap();
+        assert_eq!(
+            missing,
+            vec![start + Duration::minutes(15), start + Duration::minutes(20)]
+        );
+
+        // Nothing for other satellites or sectors.
+        let missing = db
+            .missing_scans(Satellite::G16, Sector::CONUS, start, end, cadence)
+            .unwrap();
+        assert_eq!(missing.len(), 6);
+
+        assert!(db
+            .missing_scans(Satellite::G17, Sector::CONUS, start, end, Duration::zero())
+            .is_err());
+    }
+
+    #[test]
+    fn test_scan_gaps() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+
+            // Scans that drift a little are fine, but the ones at 15 and 20 minutes are missing.
+            for (minutes, seconds) in [(0, 77), (5, 80), (10, 71), (25, 77), (30, 77)] {
+                let scan_start = start + Duration::minutes(minutes) + Duration::seconds(seconds);
+                let clusters = if minutes == 5 {
+                    vec![]
+                } else {
+                    vec![test_cluster(45.0, -120.0, 10.0)]
+                };
+                add.add(ClusterList::new(
+                    Satellite::G17,
+                    Sector::CONUS,
+                    scan_start,
+                    scan_start + Duration::seconds(170),
+                    clusters,
+                ))
+                .unwrap();
+            }
+        }
+
+        let end = start + Duration::hours(1);
+        let gaps = db
+            .scan_gaps(Satellite::G17, Sector::CONUS, start, end)
+            .unwrap();
+        assert_eq!(
+            gaps,
+            vec![(
+                start + Duration::minutes(10) + Duration::seconds(71),
+                start + Duration::minutes(25) + Duration::seconds(77)
+            )]
+        );
+
+        // No scans means no gaps.
+        assert!(db
+            .scan_gaps(Satellite::G17, Sector::FULL, start, end)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_diurnal_profile() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let day: DateTime<Utc> = "2021-08-01T00:00:00Z".parse().unwrap();
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+            for (hour, minute, powers) in [
+                (12, 0, vec![10.0]),
+                (20, 30, vec![20.0, 30.0]),
+                (21, 10, vec![40.0]),
+                // Next day at 03 UTC, evening of the same day in local time.
+                (27, 0, vec![50.0]),
+            ] {
+                let scan_start = day + Duration::hours(hour) + Duration::minutes(minute);
+                let clusters = powers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &power)| test_cluster(45.0 + i as f64, -120.0, power))
+                    .collect();
+                add.add(ClusterList::new(
+                    Satellite::G17,
+                    Sector::CONUS,
+                    scan_start,
+                    scan_start + Duration::minutes(5),
+                    clusters,
+                ))
+                .unwrap();
+            }
+        }
+
+        let area = BoundingBox {
+            ll: Coord {
+                lat: 40.0,
+                lon: -125.0,
+            },
+            ur: Coord {
+                lat: 50.0,
+                lon: -115.0,
+            },
+        };
+        let end = day + Duration::days(2);
+
+        // Mountain daylight time.
+        let mdt = crate::parse_time_zone("-06").unwrap();
+        let counts = db.diurnal_profile(area, day, end, mdt).unwrap();
+        let mut expected = [0.0; 24];
+        expected[6] = 1.0;
+        expected[14] = 2.0;
+        expected[15] = 1.0;
+        expected[21] = 1.0;
+        assert_eq!(counts, expected);
+
+        let power = db.diurnal_power_profile(area, day, end, mdt).unwrap();
+        assert!((power[14] - 50.0).abs() < 1.0e-12);
+        assert!((power[21] - 50.0).abs() < 1.0e-12);
+        assert_eq!(power[0], 0.0);
+
+        let utc = OutputTimeZone::default();
+        let counts = db.diurnal_profile(area, day, end, utc).unwrap();
+        assert_eq!(counts[12], 1.0);
+        assert_eq!(counts[20], 2.0);
+        assert_eq!(counts[3], 1.0);
+        assert_eq!(counts.iter().sum::<f64>(), 5.0);
+    }
+
+    #[test]
+    fn test_diurnal_profile_across_dst() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        // Noon local time in Denver the day before and the day after daylight saving time ends.
+        let mdt_noon: DateTime<Utc> = "2021-11-06T18:00:00Z".parse().unwrap();
+        let mst_noon: DateTime<Utc> = "2021-11-08T19:00:00Z".parse().unwrap();
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+            for scan_start in [mdt_noon, mst_noon] {
+                add.add(ClusterList::new(
+                    Satellite::G17,
+                    Sector::CONUS,
+                    scan_start,
+                    scan_start + Duration::minutes(5),
+                    vec![test_cluster(45.0, -120.0, 10.0)],
+                ))
+                .unwrap();
+            }
+        }
+
+        let area = BoundingBox {
+            ll: Coord {
+                lat: 40.0,
+                lon: -125.0,
+            },
+            ur: Coord {
+                lat: 50.0,
+                lon: -115.0,
+            },
+        };
+        let start = mdt_noon - Duration::days(1);
+        let end = mst_noon + Duration::days(1);
+
+        let denver = crate::parse_time_zone("America/Denver").unwrap();
+        let counts = db.diurnal_profile(area, start, end, denver).unwrap();
+        assert_eq!(counts[12], 2.0);
+        assert_eq!(counts.iter().sum::<f64>(), 2.0);
+
+        // A fixed offset doesn't follow the change.
+        let mdt = crate::parse_time_zone("-06").unwrap();
+        let counts = db.diurnal_profile(area, start, end, mdt).unwrap();
+        assert_eq!(counts[12], 1.0);
+        assert_eq!(counts[13], 1.0);
+    }
+
+    #[test]
+    fn test_mask_category_histogram() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        let cluster_with_masks = |lon: f64, masks: &[i16]| {
+            let mut pixels = PixelList::new();
+            for (i, &mask) in masks.iter().enumerate() {
+                let mut pixel = test_cluster(45.0, lon + 0.02 * i as f64, 10.0)
+                    .pixels()
+                    .pixels()[0];
+                pixel.mask_flag = MaskCode(mask);
+                pixels.push(pixel);
+            }
+            Cluster::new(10.0, 1_000.0, 600.0, 5.0, pixels)
+        };
+
+        let cluster_db = ClusterDatabase::connect(":memory:").unwrap();
+        let mut add = cluster_db.prepare_to_add_clusters().unwrap();
+        add.add(ClusterList::new(
+            Satellite::G17,
+            Sector::FULL,
+            start,
+            end,
+            vec![
+                cluster_with_masks(-120.0, &[10, 30, 32]),
+                cluster_with_masks(-119.0, &[11, 15, 99]),
+                // Outside of the query area.
+                cluster_with_masks(-100.0, &[10, 10]),
+            ],
+        ))
+        .unwrap();
+        add.add(ClusterList::new(
+            Satellite::G16,
+            Sector::FULL,
+            start,
+            end,
+            vec![cluster_with_masks(-120.0, &[13])],
+        ))
+        .unwrap();
+        drop(add);
+
+        // A cluster added before the counts were stored only has its pixels.
+        cluster_db
+            .conn
+            .execute(
+                include_str!("database/add_cluster.sql"),
+                rusqlite::params![
+                    "G17",
+                    "FDCF",
+                    start.timestamp(),
+                    end.timestamp(),
+                    45.0,
+                    -118.0,
+                    10.0,
+                    600.0,
+                    1_000.0,
+                    5.0,
+                    cluster_with_masks(-118.0, &[14, 12])
+                        .pixels()
+                        .binary_serialize(),
+                ],
+            )
+            .unwrap();
+
+        let area = BoundingBox {
+            ll: Coord {
+                lat: 44.0,
+                lon: -121.0,
+            },
+            ur: Coord {
+                lat: 46.0,
+                lon: -117.0,
+            },
+        };
+
+        let counts = cluster_db
+            .mask_category_histogram(Some(Satellite::G17), None, start, end, area)
+            .unwrap();
+        assert_eq!(counts.get(MaskCategory::Good), 2);
+        assert_eq!(counts.get(MaskCategory::Saturated), 1);
+        assert_eq!(counts.get(MaskCategory::CloudContaminated), 2);
+        assert_eq!(counts.get(MaskCategory::HighProbability), 0);
+        assert_eq!(counts.get(MaskCategory::MediumProbability), 1);
+        assert_eq!(counts.get(MaskCategory::LowProbability), 1);
+        assert_eq!(counts.get(MaskCategory::Other), 1);
+        assert_eq!(counts.total(), 8);
+
+        let all_sats = cluster_db
+            .mask_category_histogram(None, None, start, end, area)
+            .unwrap();
+        assert_eq!(all_sats.get(MaskCategory::HighProbability), 1);
+        assert_eq!(all_sats.total(), 9);
+
+        // A cluster without counts and with corrupt pixels is left out instead of failing.
+        cluster_db
+            .conn
+            .execute(
+                include_str!("database/add_cluster.sql"),
+                rusqlite::params![
+                    "G17",
+                    "FDCF",
+                    start.timestamp(),
+                    end.timestamp(),
+                    45.0,
+                    -118.5,
+                    10.0,
+                    600.0,
+                    1_000.0,
+                    5.0,
+                    vec![1_u8, 2, 3],
+                ],
+            )
+            .unwrap();
+        let with_corrupt = cluster_db
+            .mask_category_histogram(Some(Satellite::G17), None, start, end, area)
+            .unwrap();
+        assert_eq!(with_corrupt, counts);
+
+        let none = cluster_db
+            .mask_category_histogram(None, None, end, end, area)
+            .unwrap();
+        assert_eq!(none, MaskCategoryCounts::new());
+    }
+
+    #[test]
+    fn test_corrupt_cluster_pixels_are_skipped() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        let cluster_db = ClusterDatabase::connect(":memory:").unwrap();
+        cluster_db
+            .prepare_to_add_clusters()
+            .unwrap()
+            .add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                end,
+                vec![test_cluster(45.0, -120.0, 10.0)],
+            ))
+            .unwrap();
+
+        // A truncated BLOB, like one left by a partially written database.
+        let mut pixels = test_cluster(45.0, -119.0, 20.0).pixels().binary_serialize();
+        pixels.truncate(pixels.len() - 10);
+        cluster_db
+            .conn
+            .execute(
+                include_str!("database/add_cluster.sql"),
+                rusqlite::params![
+                    "G17",
+                    "FDCF",
+                    start.timestamp(),
+                    end.timestamp(),
+                    45.0,
+                    -119.0,
+                    20.0,
+                    600.0,
+                    1_000.0,
+                    5.0,
+                    pixels,
+                ],
+            )
+            .unwrap();
+
+        let area = BoundingBox {
+            ll: Coord {
+                lat: 44.0,
+                lon: -121.0,
+            },
+            ur: Coord {
+                lat: 46.0,
+                lon: -118.0,
+            },
+        };
+        let mut query = cluster_db
+            .query_clusters(None, None, start, end, area)
+            .unwrap();
+        let rows: Vec<_> = query.rows().unwrap().collect::<SatFireResult<_>>().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!((rows[0].power - 10.0).abs() < 1.0e-12);
+
+        let scan = cluster_db
+            .clusters_in_scan(Satellite::G17, Sector::FULL, start, end)
+            .unwrap();
+        assert_eq!(scan.len(), 1);
+        assert_eq!(cluster_db.clusters_by_ids(&[1, 2]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_query_by_ids() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        // More ids than MAX_IDS_PER_QUERY, so they have to be split across more than one query.
+        let num_ids = 1_200;
+        assert!(num_ids > 2 * MAX_IDS_PER_QUERY);
+
+        let cluster_db = ClusterDatabase::connect(":memory:").unwrap();
+        let clusters = (1..=num_ids)
+            .map(|i| test_cluster(45.0, -120.0 + 0.1 * i as f64, i as f64))
+            .collect();
+        cluster_db
+            .prepare_to_add_clusters()
+            .unwrap()
+            .add(ClusterList::new(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                end,
+                clusters,
+            ))
+            .unwrap();
+
+        let rows = cluster_db
+            .clusters_by_ids(&[1_200, 3, 3, 700, 2, 9_999])
+            .unwrap();
+        let ids: Vec<u64> = rows.iter().map(|row| row.rowid).collect();
+        assert_eq!(ids, vec![2, 3, 700, 1_200]);
+        assert!(rows
+            .iter()
+            .all(|row| (row.power - row.rowid as f64).abs() < 1.0e-12));
+
+        // Ask for every id, plus some duplicates and ids that aren't there, so the ids at both
+        // sides of each chunk boundary come back.
+        let mut wanted: Vec<u64> = (1..=num_ids as u64).rev().collect();
+        wanted.extend([MAX_IDS_PER_QUERY as u64, 9_999, 10_000]);
+        let rows = cluster_db.clusters_by_ids(&wanted).unwrap();
+        let ids: Vec<u64> = rows.iter().map(|row| row.rowid).collect();
+        assert_eq!(ids, (1..=num_ids as u64).collect::<Vec<_>>());
+        for boundary in [MAX_IDS_PER_QUERY as u64, 2 * MAX_IDS_PER_QUERY as u64] {
+            assert!(ids.contains(&boundary) && ids.contains(&(boundary + 1)));
+        }
+
+        assert!(cluster_db.clusters_by_ids(&[]).unwrap().is_empty());
+
+        let fires_db = FiresDatabase::connect(":memory:").unwrap();
+        let mut fires = FireList::new();
+        for id in 1..=num_ids as u64 {
+            let cluster = test_cluster(45.0, -120.0 + 0.1 * id as f64, id as f64);
+            fires.add_fire(Fire::new(
+                start,
+                start + Duration::hours(6),
+                id as f64,
+                600.0,
+                id,
+                cluster.pixels().clone(),
+                Satellite::G17,
+                0,
+            ));
+        }
+        fires_db
+            .prepare_to_add_fires()
+            .unwrap()
+            .add_fires(&fires)
+            .unwrap();
+
+        let fires = fires_db.fires_by_ids(&[5, 1, 4, 9_999]).unwrap();
+        let ids: Vec<u64> = fires.iter().map(|fire| fire.id()).collect();
+        assert_eq!(ids, vec![1, 4, 5]);
+        assert!(fires
+            .iter()
+            .all(|fire| (fire.max_power() - fire.id() as f64).abs() < 1.0e-12));
+
+        let boundary = MAX_IDS_PER_QUERY as u64;
+        let wanted: Vec<u64> = (1..=num_ids as u64 + 10).collect();
+        let fires = fires_db.fires_by_ids(&wanted).unwrap();
+        assert_eq!(fires.len(), num_ids);
+        let ids: Vec<u64> = fires.iter().map(|fire| fire.id()).collect();
+        assert!(ids.contains(&boundary) && ids.contains(&(boundary + 1)));
+        assert_eq!(ids.last(), Some(&(num_ids as u64)));
+    }
+
+    #[test]
+    fn test_high_water_mark_advances() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let first: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let second = first + Duration::minutes(10);
+
+        assert_eq!(
+            db.high_water_mark(Satellite::G17, Sector::FULL).unwrap(),
+            None
+        );
+
+        db.update_high_water_mark(Satellite::G17, Sector::FULL, first)
+            .unwrap();
+        assert_eq!(
+            db.high_water_mark(Satellite::G17, Sector::FULL).unwrap(),
+            Some(first)
+        );
+
+        db.update_high_water_mark(Satellite::G17, Sector::FULL, second)
+            .unwrap();
+        assert_eq!(
+            db.high_water_mark(Satellite::G17, Sector::FULL).unwrap(),
+            Some(second)
+        );
+
+        // Processing an older file out of order doesn't move it backwards.
+        db.update_high_water_mark(Satellite::G17, Sector::FULL, first)
+            .unwrap();
+        assert_eq!(
+            db.high_water_mark(Satellite::G17, Sector::FULL).unwrap(),
+            Some(second)
+        );
+
+        // Other satellites and sectors are independent.
+        assert_eq!(
+            db.high_water_mark(Satellite::G17, Sector::CONUS).unwrap(),
+            None
+        );
+        assert_eq!(
+            db.high_water_mark(Satellite::G16, Sector::FULL).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+        assert_eq!(db.summary().unwrap(), DatabaseSummary::default());
+
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+
+            let scans = [
+                (Satellite::G17, Sector::FULL, 0, 2),
+                (Satellite::G17, Sector::FULL, 10, 1),
+                (Satellite::G17, Sector::CONUS, 5, 3),
+                (Satellite::G16, Sector::MESO1, 60, 1),
+                // A scan without any clusters doesn't count.
+                (Satellite::G16, Sector::CONUS, 90, 0),
+            ];
+
+            for (sat, sector, minutes, num_clusters) in scans {
+                let start = t0 + Duration::minutes(minutes);
+                let end = start + Duration::minutes(1);
+
+                let clusters = (0..num_clusters)
+                    .map(|i| test_cluster(45.0 + f64::from(i), -120.0, 10.0))
+                    .collect();
+
+                add.add(ClusterList::new(sat, sector, start, end, clusters))
+                    .unwrap();
+            }
+        }
+
+        let summary = db.summary().unwrap();
+        assert_eq!(
+            summary.counts,
+            vec![
+                (Satellite::G16, Sector::MESO1, 1),
+                (Satellite::G17, Sector::CONUS, 3),
+                (Satellite::G17, Sector::FULL, 3),
+            ]
+        );
+        assert_eq!(summary.total(), 7);
+        assert_eq!(summary.first_scan_start, Some(t0));
+        assert_eq!(summary.last_scan_start, Some(t0 + Duration::minutes(60)));
+    }
+
+    #[test]
+    fn test_latency() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let now: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+
+            let scans = [
+                (Satellite::G17, Sector::FULL, 60, true),
+                (Satellite::G17, Sector::FULL, 30, true),
+                (Satellite::G17, Sector::CONUS, 10, false),
+                (Satellite::G16, Sector::MESO1, 90, true),
+            ];
+
+            for (sat, sector, minutes_ago, has_clusters) in scans {
+                let start = now - Duration::minutes(minutes_ago);
+                let end = start + Duration::minutes(1);
+
+                let clusters = if has_clusters {
+                    vec![test_cluster(45.0, -120.0, 10.0)]
+                } else {
+                    vec![]
+                };
+
+                add.add(ClusterList::new(sat, sector, start, end, clusters))
+                    .unwrap();
+            }
+        }
+
+        let latencies = db.latency(now).unwrap();
+        assert_eq!(latencies.len(), 3);
+
+        let find = |sat, sector| {
+            latencies
+                .iter()
+                .find(|(s, sct, _)| *s == sat && *sct == sector)
+                .map(|(_, _, latency)| *latency)
+        };
+
+        assert_eq!(
+            find(Satellite::G17, Sector::FULL),
+            Some(Duration::minutes(30))
+        );
+        assert_eq!(
+            find(Satellite::G17, Sector::CONUS),
+            Some(Duration::minutes(10))
+        );
+        assert_eq!(
+            find(Satellite::G16, Sector::MESO1),
+            Some(Duration::minutes(90))
+        );
+        assert_eq!(find(Satellite::G16, Sector::FULL), None);
+    }
+
+    #[test]
+    fn test_failed_files_are_remembered() {
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+
+        let bad_file = "G17/ABI-L2-FDCF/2021/213/01/bad_file.nc";
+        let good_file = "G17/ABI-L2-FDCF/2021/213/01/good_file.nc";
+
+        {
+            let mut is_present = db.prepare_to_query_clusters_present().unwrap();
+            assert!(!is_present.previously_failed(bad_file).unwrap());
+            assert!(!is_present.previously_failed(good_file).unwrap());
+        }
+
+        {
+            let mut add = db.prepare_to_add_clusters().unwrap();
+            add.add_failed(bad_file, "corrupt file").unwrap();
+
+            // Recording it twice should not be an error.
+            add.add_failed(bad_file, "still a corrupt file").unwrap();
+        }
+
+        {
+            let mut is_present = db.prepare_to_query_clusters_present().unwrap();
+            assert!(is_present.previously_failed(bad_file).unwrap());
+            assert!(!is_present.previously_failed(good_file).unwrap());
+        }
+
+        // Loading it on a retry clears the failure.
+        let start: DateTime<Utc> = "2021-08-01T01:00:00Z".parse().unwrap();
+        let clist = ClusterList::new(
+            Satellite::G17,
+            Sector::FULL,
+            start,
+            start + Duration::minutes(10),
+            vec![test_cluster(45.0, -120.0, 10.0)],
+        );
+        db.prepare_to_add_clusters()
+            .unwrap()
+            .add_from_file(bad_file, clist)
+            .unwrap();
+
+        let mut is_present = db.prepare_to_query_clusters_present().unwrap();
+        assert!(!is_present.previously_failed(bad_file).unwrap());
+        assert!(is_present
+            .present(
+                Satellite::G17,
+                Sector::FULL,
+                start,
+                start + Duration::minutes(10)
+            )
+            .unwrap());
+    }
+
+    #[test]
+    fn test_initialize_adds_indexes() {
+        let path = std::env::temp_dir().join(format!(
+            "satfire_test_indexes_{}.sqlite",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(OLD_SCHEMA).unwrap();
+
+        let index_exists = |conn: &Connection, name: &str| -> bool {
+            conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?",
+                [name],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+                > 0
+        };
+        let indexes = [
+            "file_processed",
+            "cluster_times",
+            "fire_first_observed",
+            "fire_last_observed",
+        ];
+        assert!(indexes.iter().all(|name| !index_exists(&conn, name)));
+
+        ClusterDatabase::initialize(&path).unwrap();
+        FiresDatabase::initialize(&path).unwrap();
+        assert!(indexes.iter().all(|name| index_exists(&conn, name)));
+
+        // The time bounded cluster query uses an index instead of scanning the whole table.
+        let plan: Vec<String> = conn
+            .prepare(&format!(
+                "EXPLAIN QUERY PLAN SELECT rowid FROM clusters WHERE {}",
+                cluster_query_filter(
+                    None,
+                    None,
+                    "2021-08-01T12:00:00Z".parse().unwrap(),
+                    "2021-08-01T18:00:00Z".parse().unwrap(),
+                    BoundingBox {
+                        ll: Coord {
+                            lat: 25.0,
+                            lon: -125.0,
+                        },
+                        ur: Coord {
+                            lat: 50.0,
+                            lon: -65.0,
+                        },
+                    },
+                )
+            ))
+            .unwrap()
+            .query_map([], |row| row.get(3))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(plan.iter().any(|step| step.contains("cluster_times")));
+
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// The schema from before the failed_files and high_water_marks tables and the fires region
+    /// column were added.
+    const OLD_SCHEMA: &str = "
+        CREATE TABLE clusters (
+          cluster_id      INTEGER PRIMARY KEY AUTOINCREMENT,
+          satellite       TEXT    NOT NULL,
+          sector          TEXT    NOT NULL,
+          start_time      INTEGER NOT NULL,
+          end_time        INTEGER NOT NULL,
+          lat             REAL    NOT NULL,
+          lon             REAL    NOT NULL,
+          power           REAL    NOT NULL,
+          max_temperature REAL    NOT NULL,
+          area            REAL    NOT NULL,
+          max_scan_angle  REAL    NOT NULL,
+          pixels          BLOB    NOT NULL);
+
+        CREATE TABLE no_clusters (
+          satellite  TEXT    NOT NULL,
+          sector     TEXT    NOT NULL,
+          start_time INTEGER NOT NULL,
+          end_time   INTEGER NOT NULL);
+
+        CREATE TABLE fires (
+          fire_id         INTEGER PRIMARY KEY AUTOINCREMENT,
+          merged_into     INTEGER NOT NULL,
+          satellite       TEXT    NOT NULL,
+          first_observed  INTEGER NOT NULL,
+          last_observed   INTEGER NOT NULL,
+          lat             REAL    NOT NULL,
+          lon             REAL    NOT NULL,
+          max_power       REAL    NOT NULL,
+          max_temperature REAL    NOT NULL,
+          num_pixels      INTEGER NOT NULL,
+          pixels          BLOB    NOT NULL);
+
+        CREATE TABLE associations (
+          fire_id    INTEGER NOT NULL,
+          cluster_id INTEGER NOT NULL,
+          UNIQUE(fire_id, cluster_id));
+    ";
+
+    #[test]
+    fn test_read_only_old_schema() {
+        let path = std::env::temp_dir().join(format!(
+            "satfire_test_old_schema_{}.sqlite",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(4);
+        let cluster = test_cluster(45.0, -120.0, 10.0);
+        let pixels = cluster.pixels().binary_serialize();
+
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(OLD_SCHEMA).unwrap();
+        conn.execute(
+            include_str!("database/add_cluster.sql"),
+            rusqlite::params![
+                "G17",
+                "FDCC",
+                start.timestamp(),
+                end.timestamp(),
+                45.0,
+                -120.0,
+                10.0,
+                600.0,
+                1_000.0,
+                5.0,
+                pixels,
+            ],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO fires VALUES (1, 0, 'G17', ?, ?, 45.0, -120.0, 10.0, 600.0, 1, ?)",
+            rusqlite::params![start.timestamp(), end.timestamp(), pixels],
+        )
+        .unwrap();
+        drop(conn);
+
+        let world = BoundingBox {
+            ll: Coord {
+                lat: -90.0,
+                lon: -180.0,
+            },
+            ur: Coord {
+                lat: 90.0,
+                lon: 180.0,
+            },
+        };
+
+        let clusters_db = ClusterDatabase::connect_read_only(&path).unwrap();
+        {
+            let mut query = clusters_db
+                .query_clusters(None, None, start, end, world)
+                .unwrap();
+            let rows: Vec<_> = query.rows().unwrap().map(Result::unwrap).collect();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].pixels.len(), 1);
+
+            let mut is_present = clusters_db.prepare_to_query_clusters_present().unwrap();
+            assert!(is_present
+                .present(Satellite::G17, Sector::CONUS, start, end)
+                .unwrap());
+            assert!(!is_present.previously_failed("some_file.nc").unwrap());
+
+            assert!(clusters_db
+                .high_water_mark(Satellite::G17, Sector::CONUS)
+                .unwrap()
+                .is_none());
+
+            // Read only really means read only.
+            assert!(clusters_db
+                .update_high_water_mark(Satellite::G17, Sector::CONUS, start)
+                .is_err());
+        }
+        drop(clusters_db);
+
+        let fires_db = FiresDatabase::connect_read_only(&path).unwrap();
+        let fires: Vec<_> = fires_db
+            .query_fires(None, start, end, world)
+            .unwrap()
+            .rows()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(fires.len(), 1);
+        assert_eq!(fires[0].id(), 1);
+        assert_eq!(fires_db.fire_region(1).unwrap(), None);
+        assert_eq!(fires_db.next_wildfire_id().unwrap(), 2);
+
+        let mut query = fires_db.iter_fires(Satellite::G17).unwrap();
+        let ongoing: Vec<_> = query.rows().unwrap().map(Result::unwrap).collect();
+        assert_eq!(ongoing.len(), 1);
+        assert_eq!(ongoing[0].max_simultaneous_clusters(), 1);
+        drop(query);
+        drop(fires_db);
+
+        // Nothing was migrated.
+        let conn = Connection::open(&path).unwrap();
+        assert!(!table_exists(&conn, "high_water_marks").unwrap());
+        assert!(!column_exists(&conn, "fires", "region").unwrap());
+        drop(conn);
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,20 @@
+INSERT OR REPLACE INTO clusters (
+  satellite,
+  sector,
+  start_time,
+  end_time,
+  lat,
+  lon,
+  power,
+  max_temperature,
+  area,
+  max_scan_angle,
+  pixels,
+  num_good_pixels,
+  num_saturated_pixels,
+  num_cloud_pixels,
+  num_high_probability_pixels,
+  num_medium_probability_pixels,
+  num_low_probability_pixels,
+  num_other_pixels)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
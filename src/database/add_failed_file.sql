@@ -0,0 +1 @@
+INSERT OR REPLACE INTO failed_files (path, error, timestamp) VALUES (?, ?, ?)
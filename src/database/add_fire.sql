@@ -9,6 +9,8 @@ INSERT OR REPLACE INTO fires (
     max_power,
     max_temperature,
     num_pixels,
-    pixels)
-VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    pixels,
+    region,
+    max_simultaneous_clusters)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
 
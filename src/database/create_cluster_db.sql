@@ -10,7 +10,16 @@ CREATE TABLE IF NOT EXISTS clusters (
   max_temperature REAL    NOT NULL,  -- Kelvin
   area            REAL    NOT NULL,  -- square meters
   max_scan_angle  REAL    NOT NULL,  -- degrees
-  pixels          BLOB    NOT NULL);
+  pixels          BLOB    NOT NULL,
+  -- The number of pixels in each mask category, NULL if the
+  -- cluster was added before these were stored.
+  num_good_pixels               INTEGER,
+  num_saturated_pixels          INTEGER,
+  num_cloud_pixels              INTEGER,
+  num_high_probability_pixels   INTEGER,
+  num_medium_probability_pixels INTEGER,
+  num_low_probability_pixels    INTEGER,
+  num_other_pixels              INTEGER);
 
 CREATE UNIQUE INDEX IF NOT EXISTS no_cluster_dups
   ON clusters (satellite, sector, start_time,
@@ -20,6 +29,11 @@ CREATE INDEX IF NOT EXISTS file_processed
   ON clusters (satellite, sector, start_time,
                end_time);
 
+-- Supporting indexes for queries that don't specify a
+-- satellite and sector.
+CREATE INDEX IF NOT EXISTS cluster_times
+  ON clusters (start_time, end_time);
+
 -- This table records files that have been processed, but 
 -- did not contain any clusters.
 CREATE TABLE IF NOT EXISTS no_clusters (
@@ -28,3 +42,18 @@ CREATE TABLE IF NOT EXISTS no_clusters (
   start_time INTEGER NOT NULL,
   end_time   INTEGER NOT NULL);
 
+
+-- This table records files that could not be loaded, so they
+-- can be skipped on later runs.
+CREATE TABLE IF NOT EXISTS failed_files (
+  path      TEXT    NOT NULL UNIQUE,
+  error     TEXT    NOT NULL,
+  timestamp INTEGER NOT NULL); -- unix timestamp
+
+-- This table records the start time of the newest scan processed
+-- for each satellite and sector at the end of each run.
+CREATE TABLE IF NOT EXISTS high_water_marks (
+  satellite  TEXT    NOT NULL,
+  sector     TEXT    NOT NULL,
+  scan_start INTEGER NOT NULL,  -- unix timestamp
+  UNIQUE(satellite, sector));
@@ -9,7 +9,15 @@ CREATE TABLE IF NOT EXISTS fires (
   max_power       REAL    NOT NULL,
   max_temperature REAL    NOT NULL,
   num_pixels      INTEGER NOT NULL,  -- number of pixels in the pixels object.
-  pixels          BLOB    NOT NULL);
+  pixels          BLOB    NOT NULL,
+  region          TEXT,              -- optional region or jurisdiction name.
+  max_simultaneous_clusters INTEGER); -- most clusters associated in a single scan.
+
+CREATE INDEX IF NOT EXISTS fire_first_observed
+  ON fires (first_observed);
+
+CREATE INDEX IF NOT EXISTS fire_last_observed
+  ON fires (last_observed);
 
 -- These are associations between fires and clusters.
 CREATE TABLE IF NOT EXISTS associations (
@@ -17,3 +25,17 @@ CREATE TABLE IF NOT EXISTS associations (
   cluster_id INTEGER NOT NULL,
   UNIQUE(fire_id, cluster_id));
 
+
+-- Arbitrary key/value annotations of fires, like a named incident.
+CREATE TABLE IF NOT EXISTS fire_metadata (
+  fire_id INTEGER NOT NULL,
+  key     TEXT    NOT NULL,
+  value   TEXT    NOT NULL,
+  UNIQUE(fire_id, key));
+
+-- This table records the start time of the last scan that connectfire
+-- finished processing for each satellite, so a restart can resume after it.
+CREATE TABLE IF NOT EXISTS checkpoints (
+  satellite  TEXT    NOT NULL,
+  scan_start INTEGER NOT NULL,  -- unix timestamp
+  UNIQUE(satellite));
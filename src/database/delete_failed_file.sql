@@ -0,0 +1 @@
+DELETE FROM failed_files WHERE path = ?
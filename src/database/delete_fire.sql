@@ -0,0 +1 @@
+DELETE FROM fires WHERE fire_id = ?
@@ -0,0 +1 @@
+DELETE FROM associations WHERE fire_id = ?
@@ -0,0 +1,16 @@
+SELECT
+  rowid,
+  satellite,
+  sector,
+  start_time,
+  end_time,
+  power,
+  max_temperature,
+  area,
+  max_scan_angle,
+  lat,
+  lon,
+  pixels
+FROM clusters
+WHERE satellite = ? AND sector = ? AND start_time = ? AND end_time = ?
+ORDER BY rowid ASC
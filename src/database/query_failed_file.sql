@@ -0,0 +1 @@
+SELECT COUNT(*) FROM failed_files WHERE path = ?
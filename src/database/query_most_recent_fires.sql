@@ -1,10 +1,12 @@
 SELECT 
   fire_id, 
+  merged_into,
   satellite,
   first_observed, 
   last_observed, 
   max_power, 
   max_temperature, 
-  pixels 
+  pixels,
+  max_simultaneous_clusters
 FROM fires 
 WHERE last_observed > ? AND satellite = ? AND merged_into = 0
@@ -0,0 +1,7 @@
+SELECT satellite, sector, MAX(start_time) as latest FROM (
+  SELECT satellite, sector, start_time FROM clusters
+  UNION ALL
+  SELECT satellite, sector, start_time FROM no_clusters
+)
+GROUP BY satellite, sector
+ORDER BY satellite, sector
@@ -0,0 +1,6 @@
+SELECT start_time FROM clusters
+WHERE satellite = ?1 AND sector = ?2 AND start_time >= ?3 AND start_time < ?4
+UNION
+SELECT start_time FROM no_clusters
+WHERE satellite = ?1 AND sector = ?2 AND start_time >= ?3 AND start_time < ?4
+ORDER BY start_time ASC
@@ -0,0 +1,3 @@
+INSERT OR REPLACE INTO checkpoints (satellite, scan_start)
+VALUES (?1, MAX(?2, IFNULL(
+  (SELECT scan_start FROM checkpoints WHERE satellite = ?1), ?2)))
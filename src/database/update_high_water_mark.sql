@@ -0,0 +1,3 @@
+INSERT OR REPLACE INTO high_water_marks (satellite, sector, scan_start)
+VALUES (?1, ?2, MAX(?3, IFNULL(
+  (SELECT scan_start FROM high_water_marks WHERE satellite = ?1 AND sector = ?2), ?3)))
@@ -0,0 +1,221 @@
+//! Export the time series of a single fire to a CSV file.
+//!
+//! Every cluster that contributed to a fire is grouped by scan start time, and each scan becomes
+//! one row of the output. This is meant for plotting how a fire grew and shrank in a spreadsheet
+//! or with pandas, so only the scalar values are written.
+
+use crate::{group_by_scan, ClusterDatabaseClusterRow, Coord, SatFireResult};
+use chrono::SecondsFormat;
+use std::{borrow::Cow, io::Write};
+
+/// The header row of a fire time series CSV file.
+const TIME_SERIES_HEADER: &[&str] = &[
+    "start_time",
+    "end_time",
+    "satellite",
+    "sector",
+    "num_clusters",
+    "power",
+    "area",
+    "max_temperature",
+    "max_scan_angle",
+    "lat",
+    "lon",
+];
+
+/// Write the time series of a fire as CSV, one row per scan.
+///
+/// The rows are expected to be sorted by scan start time, as they are when returned from
+/// [crate::JointQuerySingleFire::run]. Clusters with the same scan start time are combined into a
+/// single row, and the number of rows written, not counting the header, is returned.
+///
+/// # Columns
+///
+/// | column          | description                                                         |
+/// |-----------------|---------------------------------------------------------------------|
+/// | start_time      | The scan start time in RFC 3339 format, always in UTC.              |
+/// | end_time        | The latest scan end time of the clusters in this scan.              |
+/// | satellite       | The satellite name, e.g. "G17".                                     |
+/// | sector          | The sector name, e.g. "FDCC".                                       |
+/// | num_clusters    | The number of clusters combined into this row.                      |
+/// | power           | The total fire power in MW.                                         |
+/// | area            | The total fire area in square meters.                               |
+/// | max_temperature | The maximum pixel temperature in Kelvin.                            |
+/// | max_scan_angle  | The maximum scan angle of the clusters.                             |
+/// | lat             | The latitude of the area weighted centroid of the clusters.         |
+/// | lon             | The longitude of the area weighted centroid of the clusters.        |
+///
+/// If the clusters in a scan came from more than one satellite or sector, all of the names are
+/// listed in that field separated by commas.
+pub fn write_fire_time_series_csv<W, I>(mut writer: W, rows: I) -> SatFireResult<usize>
+where
+    W: Write,
+    I: IntoIterator<Item = ClusterDatabaseClusterRow>,
+{
+    write_csv_record(
+        &mut writer,
+        TIME_SERIES_HEADER.iter().map(|&h| h.to_owned()),
+    )?;
+
+    let mut num_rows = 0;
+    for (start, group) in group_by_scan(rows.into_iter()) {
+        let end = group.iter().map(|c| c.end).max().unwrap_or(start);
+
+        let mut satellites: Vec<&'static str> = Vec::new();
+        let mut sectors: Vec<&'static str> = Vec::new();
+        let mut power = 0.0;
+        let mut area = 0.0;
+        let mut max_temperature = -f64::INFINITY;
+        let mut max_scan_angle = -f64::INFINITY;
+        let mut lat = 0.0;
+        let mut lon = 0.0;
+
+        for cluster in &group {
+            if !satellites.contains(&cluster.sat.name()) {
+                satellites.push(cluster.sat.name());
+            }
+            if !sectors.contains(&cluster.sector.name()) {
+                sectors.push(cluster.sector.name());
+            }
+
+            power += cluster.power;
+            area += cluster.area;
+            max_temperature = max_temperature.max(cluster.max_temperature);
+            max_scan_angle = max_scan_angle.max(cluster.scan_angle);
+
+            let Coord {
+                lat: c_lat,
+                lon: c_lon,
+            } = cluster.centroid;
+            lat += c_lat * cluster.area;
+            lon += c_lon * cluster.area;
+        }
+
+        if area > 0.0 {
+            lat /= area;
+            lon /= area;
+        } else {
+            // Without an area to weight by, fall back to the plain average.
+            let n = group.len() as f64;
+            lat = group.iter().map(|c| c.centroid.lat).sum::<f64>() / n;
+            lon = group.iter().map(|c| c.centroid.lon).sum::<f64>() / n;
+        }
+
+        write_csv_record(
+            &mut writer,
+            [
+                start.to_rfc3339_opts(SecondsFormat::Secs, true),
+                end.to_rfc3339_opts(SecondsFormat::Secs, true),
+                satellites.join(","),
+                sectors.join(","),
+                group.len().to_string(),
+                power.to_string(),
+                area.to_string(),
+                max_temperature.to_string(),
+                max_scan_angle.to_string(),
+                lat.to_string(),
+                lon.to_string(),
+            ],
+        )?;
+
+        num_rows += 1;
+    }
+
+    writer.flush()?;
+
+    Ok(num_rows)
+}
+
+/// Write one line of a CSV file, escaping each field as needed.
+fn write_csv_record<W, I>(writer: &mut W, fields: I) -> SatFireResult<()>
+where
+    W: Write,
+    I: IntoIterator<Item = String>,
+{
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        writer.write_all(csv_escape(&field).as_bytes())?;
+    }
+    writer.write_all(b"\n")?;
+
+    Ok(())
+}
+
+/// Quote a CSV field if it contains a comma, a quote, or a line break.
+///
+/// Quotes inside a quoted field are doubled, as described in RFC 4180.
+fn csv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PixelList, Satellite, Sector};
+    use chrono::{DateTime, Duration, Utc};
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("FDCC"), "FDCC");
+        assert_eq!(csv_escape("FDCC,FDCF"), "\"FDCC,FDCF\"");
+        assert_eq!(csv_escape("a \"quote\""), "\"a \"\"quote\"\"\"");
+        assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_write_fire_time_series_csv() {
+        let t0: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let t1 = t0 + Duration::minutes(5);
+
+        let row = |start: DateTime<Utc>, sector, power, area, lat| ClusterDatabaseClusterRow {
+            rowid: 0,
+            start,
+            end: start + Duration::minutes(4),
+            power,
+            max_temperature: power * 10.0,
+            area,
+            scan_angle: power / 10.0,
+            centroid: Coord { lat, lon: -120.0 },
+            sector,
+            sat: Satellite::G17,
+            pixels: PixelList::new(),
+        };
+
+        let rows = vec![
+            row(t0, Sector::CONUS, 10.0, 100.0, 45.0),
+            row(t0, Sector::FULL, 30.0, 300.0, 46.0),
+            row(t1, Sector::CONUS, 20.0, 200.0, 45.0),
+        ];
+
+        let mut buf = Vec::new();
+        let num_rows = write_fire_time_series_csv(&mut buf, rows).unwrap();
+        assert_eq!(num_rows, 2);
+
+        let csv = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], TIME_SERIES_HEADER.join(","));
+        assert_eq!(
+            lines[1],
+            concat!(
+                "2021-08-01T12:00:00Z,2021-08-01T12:04:00Z,G17,\"FDCC,FDCF\",2,",
+                "40,400,300,3,45.75,-120"
+            )
+        );
+        assert_eq!(
+            lines[2],
+            "2021-08-01T12:05:00Z,2021-08-01T12:09:00Z,G17,FDCC,1,20,200,200,2,45,-120"
+        );
+
+        // An empty fire still gets a header.
+        let mut buf = Vec::new();
+        assert_eq!(write_fire_time_series_csv(&mut buf, vec![]).unwrap(), 0);
+        assert_eq!(String::from_utf8(buf).unwrap().lines().count(), 1);
+    }
+}
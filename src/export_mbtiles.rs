@@ -0,0 +1,366 @@
+//! Export a fire power density heat map to an [MBTiles](https://github.com/mapbox/mbtiles-spec)
+//! file.
+//!
+//! This module is only available with the `export_mbtiles` feature enabled. An MBTiles file is a
+//! SQLite database of PNG map tiles in the Web Mercator projection, which most web map libraries
+//! and tile servers can display directly.
+//!
+//! The fire power of each pixel is added to the map pixel containing its centroid, then divided
+//! by the area on the ground of that map pixel to get a density in megawatts per square
+//! kilometer. The color of each map pixel goes from orange to red and becomes more opaque as the
+//! density increases, scaled logarithmically up to the highest density at that zoom level.
+
+use crate::{BoundingBox, ClusterDatabaseClusterRow, Coord, Geo, SatFireResult};
+use flate2::{write::ZlibEncoder, Compression};
+use rusqlite::{params, Connection};
+use rustc_hash::FxHashMap as HashMap;
+use std::{io::Write, ops::RangeInclusive, path::Path};
+
+/// The width and height of a tile in pixels.
+const TILE_SIZE: usize = 256;
+
+/// The highest zoom level that can be exported, about 2 meters per map pixel at the equator.
+const MAX_ZOOM: u8 = 16;
+
+/// Half the circumference of the Web Mercator sphere, the largest x or y in meters.
+const WEB_MERCATOR_HALF_WIDTH: f64 = std::f64::consts::PI * 6_378_137.0;
+
+/// Map pixels of fire power, megawatts, for each tile keyed by (column, row) from the north west.
+type PowerTiles = HashMap<(u32, u32), Vec<f64>>;
+
+/// Write a tile pyramid of the fire power density of clusters to an MBTiles file.
+///
+/// The clusters usually come from [crate::ClusterDatabase::query_clusters], which selects them by
+/// region and time. Tiles are only written where there is fire. The `area` is recorded as the
+/// bounds in the metadata, and `zooms` is the inclusive range of zoom levels to render, at most
+/// 16. Any existing file at `path` is replaced.
+pub fn export_mbtiles<P: AsRef<Path>>(
+    path: P,
+    clusters: &[ClusterDatabaseClusterRow],
+    area: BoundingBox,
+    zooms: RangeInclusive<u8>,
+) -> SatFireResult<()> {
+    if zooms.is_empty() || *zooms.end() > MAX_ZOOM {
+        return Err(format!(
+            "Invalid zoom range {}-{}, zoom levels must be increasing and at most {}",
+            zooms.start(),
+            zooms.end(),
+            MAX_ZOOM
+        )
+        .into());
+    }
+
+    let path = path.as_ref();
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(
+        r#"CREATE TABLE metadata (name TEXT, value TEXT);
+           CREATE TABLE tiles (
+             zoom_level  INTEGER,
+             tile_column INTEGER,
+             tile_row    INTEGER,
+             tile_data   BLOB);
+           CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);"#,
+    )?;
+
+    let trans = conn.transaction()?;
+    {
+        let mut add_metadata = trans.prepare("INSERT INTO metadata (name, value) VALUES (?, ?)")?;
+        let bounds = format!(
+            "{},{},{},{}",
+            area.ll.lon, area.ll.lat, area.ur.lon, area.ur.lat
+        );
+        let center = format!(
+            "{},{},{}",
+            (area.ll.lon + area.ur.lon) / 2.0,
+            (area.ll.lat + area.ur.lat) / 2.0,
+            zooms.start()
+        );
+        for (name, value) in [
+            ("name", "satfire fire power density"),
+            ("format", "png"),
+            ("type", "overlay"),
+            ("version", "1"),
+            (
+                "description",
+                "Fire power density from GOES fire detections, MW per square kilometer.",
+            ),
+            ("bounds", &bounds),
+            ("center", &center),
+            ("minzoom", &zooms.start().to_string()),
+            ("maxzoom", &zooms.end().to_string()),
+        ] {
+            add_metadata.execute([name, value])?;
+        }
+
+        let mut add_tile = trans.prepare(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?, ?, ?, ?)",
+        )?;
+        for zoom in zooms {
+            let tiles = accumulate_power(clusters, zoom);
+            let densities = power_to_density(tiles, zoom);
+
+            let max_density = densities
+                .values()
+                .flat_map(|tile| tile.iter())
+                .cloned()
+                .fold(0.0, f64::max);
+
+            let num_tiles = 1u32 << zoom;
+            for ((column, row), tile) in densities {
+                let png = encode_png(&colorize(&tile, max_density))?;
+
+                // MBTiles numbers the rows from the south like TMS.
+                let tms_row = num_tiles - 1 - row;
+                add_tile.execute(params![zoom, column, tms_row, png])?;
+            }
+        }
+    }
+    trans.commit()?;
+
+    Ok(())
+}
+
+/// Add up the fire power into map pixels at a zoom level.
+fn accumulate_power(clusters: &[ClusterDatabaseClusterRow], zoom: u8) -> PowerTiles {
+    let mut tiles = PowerTiles::default();
+    let world_size = (TILE_SIZE as u64) << zoom;
+
+    let mut add = |coord: Coord, power: f64| {
+        if !power.is_finite() || power <= 0.0 {
+            return;
+        }
+
+        let (x, y) = coord.to_web_mercator();
+        let map_x = (x + WEB_MERCATOR_HALF_WIDTH) / (2.0 * WEB_MERCATOR_HALF_WIDTH);
+        let map_y = (WEB_MERCATOR_HALF_WIDTH - y) / (2.0 * WEB_MERCATOR_HALF_WIDTH);
+        if !(0.0..=1.0).contains(&map_x) || !(0.0..=1.0).contains(&map_y) {
+            return;
+        }
+
+        let px = ((map_x * world_size as f64) as u64).min(world_size - 1);
+        let py = ((map_y * world_size as f64) as u64).min(world_size - 1);
+        let tile = (
+            (px / TILE_SIZE as u64) as u32,
+            (py / TILE_SIZE as u64) as u32,
+        );
+        let offset = (py as usize % TILE_SIZE) * TILE_SIZE + px as usize % TILE_SIZE;
+
+        tiles
+            .entry(tile)
+            .or_insert_with(|| vec![0.0; TILE_SIZE * TILE_SIZE])[offset] += power;
+    };
+
+    for cluster in clusters {
+        if cluster.pixels.is_empty() {
+            add(cluster.centroid, cluster.power);
+        } else {
+            for pixel in cluster.pixels.pixels() {
+                add(pixel.centroid(), pixel.power);
+            }
+        }
+    }
+
+    tiles
+}
+
+/// Divide the power in each map pixel by its area on the ground, in square kilometers.
+fn power_to_density(mut tiles: PowerTiles, zoom: u8) -> PowerTiles {
+    let world_size = ((TILE_SIZE as u64) << zoom) as f64;
+    let meters_per_pixel_at_equator = 2.0 * WEB_MERCATOR_HALF_WIDTH / world_size;
+
+    for (&(_, row), tile) in tiles.iter_mut() {
+        for (py, line) in tile.chunks_mut(TILE_SIZE).enumerate() {
+            // The latitude at the middle of this line of map pixels.
+            let map_y = (row as f64 * TILE_SIZE as f64 + py as f64 + 0.5) / world_size;
+            let y = WEB_MERCATOR_HALF_WIDTH * (1.0 - 2.0 * map_y);
+            let lat = Coord::from_web_mercator(0.0, y).lat.to_radians();
+
+            let side_km = meters_per_pixel_at_equator * lat.cos() / 1_000.0;
+            let area_km2 = side_km * side_km;
+
+            for val in line.iter_mut() {
+                *val /= area_km2;
+            }
+        }
+    }
+
+    tiles
+}
+
+/// Convert a tile of densities into RGBA pixels.
+fn colorize(tile: &[f64], max_density: f64) -> Vec<u8> {
+    let scale = (1.0 + max_density).ln();
+
+    let mut rgba = Vec::with_capacity(tile.len() * 4);
+    for &density in tile {
+        if density <= 0.0 || scale <= 0.0 {
+            rgba.extend_from_slice(&[0, 0, 0, 0]);
+            continue;
+        }
+
+        let t = ((1.0 + density).ln() / scale).clamp(0.0, 1.0);
+        let green = (165.0 * (1.0 - t)).round() as u8;
+        let alpha = (64.0 + 191.0 * t).round() as u8;
+        rgba.extend_from_slice(&[255, green, 0, alpha]);
+    }
+
+    rgba
+}
+
+/// Encode a square tile of 8 bit RGBA pixels as a PNG image.
+fn encode_png(rgba: &[u8]) -> SatFireResult<Vec<u8>> {
+    const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(kind);
+        crc.update(data);
+        png.extend_from_slice(&crc.finalize().to_be_bytes());
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(TILE_SIZE as u32).to_be_bytes());
+    header.extend_from_slice(&(TILE_SIZE as u32).to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), default compression, filtering, and no interlacing.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Each line of the image starts with the filter type, 0 is none.
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for line in rgba.chunks(TILE_SIZE * 4) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(line)?;
+    }
+    let data = encoder.finish()?;
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PixelList, Satellite, Sector};
+    use chrono::{DateTime, Duration, Utc};
+
+    #[test]
+    fn test_export_mbtiles() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let rows: Vec<_> = (0..3)
+            .map(|i| ClusterDatabaseClusterRow {
+                rowid: i + 1,
+                start: start + Duration::minutes(5 * i as i64),
+                end: start + Duration::minutes(5 * i as i64 + 4),
+                power: 10.0 * (i + 1) as f64,
+                max_temperature: 500.0,
+                area: 100.0,
+                scan_angle: 4.5,
+                centroid: Coord {
+                    lat: 45.0 + 0.1 * i as f64,
+                    lon: -120.0,
+                },
+                sector: Sector::CONUS,
+                sat: Satellite::G17,
+                pixels: PixelList::new(),
+            })
+            .collect();
+
+        let area = BoundingBox {
+            ll: Coord {
+                lat: 44.0,
+                lon: -121.0,
+            },
+            ur: Coord {
+                lat: 46.0,
+                lon: -119.0,
+            },
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "satfire_test_export_{}.mbtiles",
+            std::process::id()
+        ));
+        let (lo, hi) = (3, 2);
+        assert!(export_mbtiles(&path, &rows, area, lo..=hi).is_err());
+        assert!(export_mbtiles(&path, &rows, area, 0..=17).is_err());
+        export_mbtiles(&path, &rows, area, 0..=4).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let metadata = |name: &str| -> String {
+            conn.query_row("SELECT value FROM metadata WHERE name = ?", [name], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(metadata("format"), "png");
+        assert_eq!(metadata("minzoom"), "0");
+        assert_eq!(metadata("maxzoom"), "4");
+        assert_eq!(metadata("bounds"), "-121,44,-119,46");
+
+        // All the clusters are close enough together to be in one tile at each zoom.
+        let num_tiles: i64 = conn
+            .query_row("SELECT COUNT(*) FROM tiles", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(num_tiles, 5);
+
+        // At zoom 1 the north western quarter of the world is column 0, TMS row 1.
+        let tile: Vec<u8> = conn
+            .query_row(
+                "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 0 AND tile_row = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            &tile[..8],
+            &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]
+        );
+        assert_eq!(&tile[12..16], b"IHDR");
+        assert_eq!(&tile[tile.len() - 8..tile.len() - 4], b"IEND");
+
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_density_accounts_for_latitude() {
+        let row = |lat: f64| ClusterDatabaseClusterRow {
+            rowid: 1,
+            start: "2021-08-01T12:00:00Z".parse().unwrap(),
+            end: "2021-08-01T12:04:00Z".parse().unwrap(),
+            power: 100.0,
+            max_temperature: 500.0,
+            area: 100.0,
+            scan_angle: 4.5,
+            centroid: Coord { lat, lon: 10.0 },
+            sector: Sector::FULL,
+            sat: Satellite::G16,
+            pixels: PixelList::new(),
+        };
+
+        let density = |lat: f64| -> f64 {
+            let tiles = power_to_density(accumulate_power(&[row(lat)], 8), 8);
+            tiles
+                .values()
+                .flat_map(|tile| tile.iter())
+                .cloned()
+                .fold(0.0, f64::max)
+        };
+
+        // Map pixels at 60 degrees cover a quarter of the ground of those at the equator.
+        let ratio = density(60.0) / density(0.0);
+        assert!((ratio - 4.0).abs() < 0.1, "{}", ratio);
+    }
+}
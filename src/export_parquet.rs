@@ -0,0 +1,280 @@
+//! Export cluster and fire query results to [Apache Parquet](https://parquet.apache.org) files.
+//!
+//! This module is only available with the `export_parquet` feature enabled. Parquet files load
+//! much faster into analysis tools like DuckDB, Spark, or pandas than CSV or SQLite do.
+//!
+//! Only the scalar columns are written. The pixels are NOT written as a BLOB, instead the number
+//! of pixels is written.
+
+use crate::{ClusterDatabaseClusterRow, Fire, Geo, SatFireResult};
+use arrow_array::{
+    ArrayRef, Float64Array, RecordBatch, StringArray, TimestampSecondArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use std::{fs::File, path::Path, sync::Arc};
+
+/// Write clusters, as returned from a database query, to a Parquet file.
+///
+/// # Schema
+///
+/// | column          | type                  | description                                   |
+/// |-----------------|-----------------------|-----------------------------------------------|
+/// | rowid           | UInt64                | The row id in the clusters database.          |
+/// | satellite       | Utf8                  | The satellite name, e.g. "G17".               |
+/// | sector          | Utf8                  | The sector name, e.g. "FDCC".                 |
+/// | start_time      | Timestamp(Second, UTC)| The scan start time.                          |
+/// | end_time        | Timestamp(Second, UTC)| The scan end time.                            |
+/// | lat             | Float64               | The latitude of the cluster centroid.         |
+/// | lon             | Float64               | The longitude of the cluster centroid.        |
+/// | power           | Float64               | The total fire power of the cluster in MW.    |
+/// | max_temperature | Float64               | The maximum pixel temperature in Kelvin.      |
+/// | area            | Float64               | The total fire area in square meters.         |
+/// | scan_angle      | Float64               | The maximum scan angle of the cluster.        |
+/// | num_pixels      | UInt64                | The number of pixels in the cluster.          |
+pub fn export_clusters_parquet<P: AsRef<Path>>(
+    path: P,
+    clusters: &[ClusterDatabaseClusterRow],
+) -> SatFireResult<()> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("rowid", DataType::UInt64, false),
+        Field::new("satellite", DataType::Utf8, false),
+        Field::new("sector", DataType::Utf8, false),
+        Field::new("start_time", timestamp_type(), false),
+        Field::new("end_time", timestamp_type(), false),
+        Field::new("lat", DataType::Float64, false),
+        Field::new("lon", DataType::Float64, false),
+        Field::new("power", DataType::Float64, false),
+        Field::new("max_temperature", DataType::Float64, false),
+        Field::new("area", DataType::Float64, false),
+        Field::new("scan_angle", DataType::Float64, false),
+        Field::new("num_pixels", DataType::UInt64, false),
+    ]));
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(
+            clusters.iter().map(|c| c.rowid),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            clusters.iter().map(|c| c.sat.name()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            clusters.iter().map(|c| c.sector.name()),
+        )),
+        timestamps(clusters.iter().map(|c| c.start.timestamp())),
+        timestamps(clusters.iter().map(|c| c.end.timestamp())),
+        floats(clusters.iter().map(|c| c.centroid.lat)),
+        floats(clusters.iter().map(|c| c.centroid.lon)),
+        floats(clusters.iter().map(|c| c.power)),
+        floats(clusters.iter().map(|c| c.max_temperature)),
+        floats(clusters.iter().map(|c| c.area)),
+        floats(clusters.iter().map(|c| c.scan_angle)),
+        Arc::new(UInt64Array::from_iter_values(
+            clusters.iter().map(|c| c.pixels.len() as u64),
+        )),
+    ];
+
+    write_batch(path, schema, columns)
+}
+
+/// Write fires, as returned from a database query, to a Parquet file.
+///
+/// # Schema
+///
+/// | column          | type                  | description                                   |
+/// |-----------------|-----------------------|-----------------------------------------------|
+/// | fire_id         | UInt64                | The id of the fire in the fires database.     |
+/// | merged_into     | UInt64                | The fire this was merged into, 0 if none.     |
+/// | satellite       | Utf8                  | The satellite name, e.g. "G17".               |
+/// | first_observed  | Timestamp(Second, UTC)| The scan start time of the first observation. |
+/// | last_observed   | Timestamp(Second, UTC)| The scan end time of the last observation.    |
+/// | lat             | Float64               | The latitude of the fire centroid.            |
+/// | lon             | Float64               | The longitude of the fire centroid.           |
+/// | max_power       | Float64               | The maximum fire power in MW.                 |
+/// | max_temperature | Float64               | The maximum pixel temperature in Kelvin.      |
+/// | num_pixels      | UInt64                | The number of pixels in the fire.             |
+pub fn export_fires_parquet<P: AsRef<Path>>(path: P, fires: &[Fire]) -> SatFireResult<()> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("fire_id", DataType::UInt64, false),
+        Field::new("merged_into", DataType::UInt64, false),
+        Field::new("satellite", DataType::Utf8, false),
+        Field::new("first_observed", timestamp_type(), false),
+        Field::new("last_observed", timestamp_type(), false),
+        Field::new("lat", DataType::Float64, false),
+        Field::new("lon", DataType::Float64, false),
+        Field::new("max_power", DataType::Float64, false),
+        Field::new("max_temperature", DataType::Float64, false),
+        Field::new("num_pixels", DataType::UInt64, false),
+    ]));
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(fires.iter().map(|f| f.id()))),
+        Arc::new(UInt64Array::from_iter_values(
+            fires.iter().map(|f| f.merged_into()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            fires.iter().map(|f| f.satellite().name()),
+        )),
+        timestamps(fires.iter().map(|f| f.first_observed().timestamp())),
+        timestamps(fires.iter().map(|f| f.last_observed().timestamp())),
+        floats(fires.iter().map(|f| f.centroid().lat)),
+        floats(fires.iter().map(|f| f.centroid().lon)),
+        floats(fires.iter().map(|f| f.max_power())),
+        floats(fires.iter().map(|f| f.max_temperature())),
+        Arc::new(UInt64Array::from_iter_values(
+            fires.iter().map(|f| f.pixels().len() as u64),
+        )),
+    ];
+
+    write_batch(path, schema, columns)
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Second, Some("UTC".into()))
+}
+
+fn timestamps<I: Iterator<Item = i64>>(vals: I) -> ArrayRef {
+    Arc::new(TimestampSecondArray::from_iter_values(vals).with_timezone("UTC"))
+}
+
+fn floats<I: Iterator<Item = f64>>(vals: I) -> ArrayRef {
+    Arc::new(Float64Array::from_iter_values(vals))
+}
+
+fn write_batch<P: AsRef<Path>>(
+    path: P,
+    schema: Arc<Schema>,
+    columns: Vec<ArrayRef>,
+) -> SatFireResult<()> {
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+
+    let f = File::create(path.as_ref())?;
+    let mut writer = ArrowWriter::try_new(f, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Coord, FireList, PixelList, Satellite, Sector};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, TimestampSecondType, UInt64Type};
+    use chrono::{DateTime, Duration, Utc};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn read_back<P: AsRef<Path>>(path: P) -> RecordBatch {
+        let f = File::open(path.as_ref()).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(f)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert!(reader.next().is_none());
+        batch
+    }
+
+    #[test]
+    fn test_export_clusters_parquet() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let rows: Vec<_> = (0..3)
+            .map(|i| ClusterDatabaseClusterRow {
+                rowid: i + 1,
+                start: start + Duration::minutes(5 * i as i64),
+                end: start + Duration::minutes(5 * i as i64 + 4),
+                power: 10.0 * (i + 1) as f64,
+                max_temperature: 500.0 + i as f64,
+                area: 100.0,
+                scan_angle: 4.5,
+                centroid: Coord {
+                    lat: 45.0 + i as f64,
+                    lon: -120.0,
+                },
+                sector: Sector::CONUS,
+                sat: Satellite::G17,
+                pixels: PixelList::new(),
+            })
+            .collect();
+
+        let path = std::env::temp_dir().join("satfire_test_export_clusters.parquet");
+        export_clusters_parquet(&path, &rows).unwrap();
+
+        let batch = read_back(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.num_columns(), 12);
+
+        let rowid = batch.column_by_name("rowid").unwrap();
+        let rowid = rowid.as_primitive::<UInt64Type>();
+        let start_time = batch.column_by_name("start_time").unwrap();
+        let start_time = start_time.as_primitive::<TimestampSecondType>();
+        let power = batch.column_by_name("power").unwrap();
+        let power = power.as_primitive::<Float64Type>();
+        let lat = batch.column_by_name("lat").unwrap();
+        let lat = lat.as_primitive::<Float64Type>();
+        let sector = batch.column_by_name("sector").unwrap().as_string::<i32>();
+        let sat = batch
+            .column_by_name("satellite")
+            .unwrap()
+            .as_string::<i32>();
+        let num_pixels = batch.column_by_name("num_pixels").unwrap();
+        let num_pixels = num_pixels.as_primitive::<UInt64Type>();
+
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(rowid.value(i), row.rowid);
+            assert_eq!(start_time.value(i), row.start.timestamp());
+            assert_eq!(power.value(i), row.power);
+            assert_eq!(lat.value(i), row.centroid.lat);
+            assert_eq!(sector.value(i), "FDCC");
+            assert_eq!(sat.value(i), "G17");
+            assert_eq!(num_pixels.value(i), 0);
+        }
+    }
+
+    #[test]
+    fn test_export_fires_parquet() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(6);
+
+        let mut fires = FireList::new();
+        fires.add_fire(Fire::new(
+            start,
+            end,
+            25.0,
+            650.0,
+            7,
+            PixelList::new(),
+            Satellite::G16,
+            0,
+        ));
+        let fires = fires.into_vec();
+
+        let path = std::env::temp_dir().join("satfire_test_export_fires.parquet");
+        export_fires_parquet(&path, &fires).unwrap();
+
+        let batch = read_back(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.num_columns(), 10);
+
+        let id = batch.column_by_name("fire_id").unwrap();
+        assert_eq!(id.as_primitive::<UInt64Type>().value(0), 7);
+
+        let last = batch.column_by_name("last_observed").unwrap();
+        assert_eq!(
+            last.as_primitive::<TimestampSecondType>().value(0),
+            end.timestamp()
+        );
+
+        let max_power = batch.column_by_name("max_power").unwrap();
+        assert_eq!(max_power.as_primitive::<Float64Type>().value(0), 25.0);
+
+        let sat = batch.column_by_name("satellite").unwrap();
+        assert_eq!(sat.as_string::<i32>().value(0), "G16");
+    }
+}
@@ -1,13 +1,14 @@
 use crate::{
     database::ClusterDatabaseClusterRow,
-    geo::{BoundingBox, Coord, Geo, Hilbert2DRTreeView},
+    geo::{lat_lon_cell_area, BoundingBox, Coord, Ellipsoid, Geo, Hilbert2DRTreeView},
     pixel::PixelList,
     satellite::Satellite,
-    KmlWriter, KmzFile, SatFireResult,
+    KmlWriter, KmzFile, OutputTime, OutputTimeZone, SatFireResult,
 };
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use std::{
     cell::Cell,
+    collections::BTreeMap,
     fmt::{self, Display, Write},
     ops::ControlFlow,
     path::Path,
@@ -20,7 +21,7 @@ const OVERLAP_FUDGE_FACTOR: f64 = 1.0e-2;
  *
  * While the Clusters that make up a fire may come from any [Sector](crate::Sector) of a satellite
  * scan, they must come from the same [Satellite](crate::Satellite) because of the difficulty
- * associated with the different map projections and parallax. Currently the geo-location of an
+ * associated with the different map projections and parallax. By default the geo-location of an
  * observed [Pixel](crate::Pixel) does not take parallax into account. While this is a neglibible
  * issue for low elevation locations considering the resolution of the satellites, for higher
  * elevations it can cause a significant error. Also, for each satellite, the data was reprojected
@@ -29,8 +30,12 @@ const OVERLAP_FUDGE_FACTOR: f64 = 1.0e-2;
  * area, or temperature is straight forward. If we had to deal with Pixels from different satellites
  * that don't totally overlap, or only partially overlap, it's not straightforward at all how to
  * combine the properties of those Pixels into a common projection.
+ *
+ * The one exception is [FireList::merge_fires_cross_satellite], which accepts those errors for
+ * coarse analysis and records every satellite that contributed in [Fire::satellites].
  */
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Fire {
     /// The scan start time of the first Cluster where this fire was detected.
     first_observed: DateTime<Utc>,
@@ -51,24 +56,64 @@ pub struct Fire {
     /// area during the fire. Since all the data for each satellite is projected to a common grid
     /// before being published online, throughout the life of the fire the Pixels will perfectly
     /// overlap. This is kind of a composite of the properties of the fire over it's lifetime.
+    #[cfg_attr(feature = "serde", serde(rename = "pixels"))]
     area: PixelList,
     /// The satellite the Clusters that were a part of this fire were observed with.
     sat: Satellite,
+    /// All the satellites that contributed to this fire, starting with `sat`. This only has more
+    /// than one entry for fires merged with [FireList::merge_fires_cross_satellite], and it is
+    /// not stored in the fires database.
+    satellites: Vec<Satellite>,
     /// If this fire was merged into another, what was the identity of that fire. The value 0
     /// implies it has not yet been merged into another fire.
     merged_into: u64,
+    /// The number of Clusters associated with this fire in the most recent scan.
+    scan_clusters: u32,
+    /// The largest number of Clusters associated with this fire in a single scan. A fire may be
+    /// split into several spatially separated parts at any given time, so this indicates how
+    /// fragmented it has been.
+    max_simultaneous_clusters: u32,
 
     /// Make a cache for items expensive to calculate.
+    #[cfg_attr(feature = "serde", serde(skip))]
     cache_up_to_date: Cell<bool>,
     /// Cache the centroid
+    #[cfg_attr(feature = "serde", serde(skip, default = "empty_centroid_cache"))]
     centroid: Cell<Coord>,
     /// Cache for Bounding Box
+    #[cfg_attr(feature = "serde", serde(skip))]
     bbox: Cell<BoundingBox>,
 }
 
+/// The cached centroid of a deserialized Fire, it is recalculated on first use.
+#[cfg(feature = "serde")]
+fn empty_centroid_cache() -> Cell<Coord> {
+    Cell::new(Coord { lat: 0.0, lon: 0.0 })
+}
+
 impl Display for Fire {
-    #[rustfmt::skip]
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.fmt_at(f, OutputTimeZone::default())
+    }
+}
+
+/// A [Fire] displayed with its times in a time zone, see [Fire::display_in].
+struct FireInTimeZone<'a>(&'a Fire, OutputTimeZone);
+
+impl Display for FireInTimeZone<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.0.fmt_at(f, self.1)
+    }
+}
+
+impl Fire {
+    /// Get the [Display] output with times shown in `zone` instead of UTC.
+    pub fn display_in(&self, zone: OutputTimeZone) -> impl Display + '_ {
+        FireInTimeZone(self, zone)
+    }
+
+    #[rustfmt::skip]
+    fn fmt_at(&self, f: &mut fmt::Formatter, zone: OutputTimeZone) -> Result<(), fmt::Error> {
         let duration = self.duration();
         let mut duration_buf = String::with_capacity(64);
         let weeks = duration.num_weeks();
@@ -87,12 +132,20 @@ impl Display for Fire {
         let centroid = self.centroid();
 
         writeln!(f, "               ID: {:9}", self.id)?;
-        writeln!(f, "        Satellite: {}", self.sat)?;
-        writeln!(f, "   First Observed: {}", self.first_observed)?;
-        writeln!(f, "    Last Observed: {}", self.last_observed)?;
+        if self.satellites.len() > 1 {
+            let satellites: Vec<_> = self.satellites.iter().map(|sat| sat.name()).collect();
+            let satellites = satellites.join(", ");
+            writeln!(f, "        Satellite: {} (only {} is stored)", satellites, self.sat.name())?;
+        } else {
+            writeln!(f, "        Satellite: {}", self.sat.name())?;
+        }
+        writeln!(f, "   First Observed: {}", OutputTime(self.first_observed, zone))?;
+        writeln!(f, "    Last Observed: {}", OutputTime(self.last_observed, zone))?;
         writeln!(f, "         Duration: {}", duration_buf)?;
         writeln!(f, "         Centroid: {:.6},{:.6}", centroid.lat, centroid.lon)?;
         writeln!(f, "Pixel List Length: {}", self.area.len())?;
+        writeln!(f, "         Max Area: {:.0} m^2", self.max_area_m2())?;
+        writeln!(f, "Bounding Box Area: {:.1} km^2", self.bounding_box_area_km2())?;
         writeln!(f, "        Max Power: {:.0} MW", self.max_power)?;
         writeln!(f, "  Max Temperature: {:.0}K", self.max_temperature)
     }
@@ -118,7 +171,10 @@ impl Fire {
             id,
             area,
             sat,
+            satellites: vec![sat],
             merged_into,
+            scan_clusters: 0,
+            max_simultaneous_clusters: 1,
             cache_up_to_date: Cell::new(false),
             centroid: Cell::new(Coord { lat: 0.0, lon: 0.0 }),
             bbox: Cell::new(BoundingBox::default()),
@@ -127,7 +183,7 @@ impl Fire {
 
     /// Create a new fire from a cluster.
     pub fn create_from_cluster(id: u64, initial: ClusterDatabaseClusterRow) -> Self {
-        Self::new(
+        let mut fire = Self::new(
             initial.start,
             initial.end,
             initial.power,
@@ -136,7 +192,20 @@ impl Fire {
             initial.pixels,
             initial.sat,
             0,
-        )
+        );
+        fire.scan_clusters = 1;
+        fire
+    }
+
+    /// Get the largest number of clusters associated with this fire in a single scan.
+    pub fn max_simultaneous_clusters(&self) -> u32 {
+        self.max_simultaneous_clusters
+    }
+
+    /// Set the largest number of clusters associated with this fire in a single scan, for fires
+    /// loaded from a database.
+    pub(crate) fn set_max_simultaneous_clusters(&mut self, count: u32) {
+        self.max_simultaneous_clusters = count.max(1);
     }
 
     /// Get the id number of the fire.
@@ -164,6 +233,26 @@ impl Fire {
         self.last_observed - self.first_observed
     }
 
+    /// Get the age of this fire at the given time.
+    ///
+    /// The age is the time since the fire was first observed. A fire is only considered to exist
+    /// from the time it was first observed until the time it was last observed (inclusive), so if
+    /// `when` is outside of that range this returns `None`. That includes times after the last
+    /// observation, even though the fire may still have been burning undetected.
+    pub fn age_at(&self, when: DateTime<Utc>) -> Option<Duration> {
+        if when < self.first_observed || when > self.last_observed {
+            None
+        } else {
+            Some(when - self.first_observed)
+        }
+    }
+
+    /// Check if this fire was observed at any time during the period from `start` to `end`
+    /// (inclusive).
+    pub fn was_active_during(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.first_observed <= end && self.last_observed >= start
+    }
+
     /// Get the maximum power observed for this fire, megawatts.
     pub fn max_power(&self) -> f64 {
         self.max_power
@@ -174,20 +263,65 @@ impl Fire {
         self.max_temperature
     }
 
+    /// Get the total fire area of the pixels in the wildfire, square meters.
+    ///
+    /// Each pixel holds the largest area observed at that location, so this is the largest area
+    /// the fire could have covered at once.
+    pub fn max_area_m2(&self) -> f64 {
+        self.area.total_area()
+    }
+
+    /// Get the area of the latitude-longitude bounding box of the wildfire, square kilometers.
+    pub fn bounding_box_area_km2(&self) -> f64 {
+        if self.area.is_empty() {
+            return 0.0;
+        }
+
+        let BoundingBox { ll, ur } = self.bounding_box();
+        lat_lon_cell_area(ll.lat, ur.lat, ur.lon - ll.lon, &Ellipsoid::GRS80) / 1.0e6
+    }
+
     /// Get access to the pixels in the wildfire.
     pub fn pixels(&self) -> &PixelList {
         &self.area
     }
 
+    /// Get the centroid of this fire calculated with the given method.
+    pub fn centroid_by(&self, method: CentroidMethod) -> Coord {
+        match method {
+            CentroidMethod::Uniform => self.centroid(),
+            CentroidMethod::PowerWeighted => self.area.power_weighted_centroid(),
+        }
+    }
+
     /// Get the satellite this fire was observed from.
+    ///
+    /// For a fire merged across satellites this is the satellite of the fire that was created
+    /// first, see [Fire::satellites] for all of them.
     pub fn satellite(&self) -> Satellite {
         self.sat
     }
 
+    /// Get all the satellites that contributed to this fire, starting with [Fire::satellite].
+    ///
+    /// There is only one unless the fire came from [FireList::merge_fires_cross_satellite]. Only
+    /// [Fire::satellite] is stored in the fires database, so a fire loaded from it always has just
+    /// the one.
+    pub fn satellites(&self) -> &[Satellite] {
+        &self.satellites
+    }
+
     /// Update a wildfire by adding the information in this ClusterDatabaseClusterRow to it.
     pub fn update(&mut self, row: &ClusterDatabaseClusterRow) {
         debug_assert!(row.sat == self.sat);
 
+        if row.end == self.last_observed {
+            self.scan_clusters += 1;
+        } else {
+            self.scan_clusters = 1;
+        }
+        self.max_simultaneous_clusters = self.max_simultaneous_clusters.max(self.scan_clusters);
+
         self.last_observed = row.end;
         self.max_power = self.max_power.max(row.power);
         self.max_temperature = self.max_temperature.max(row.max_temperature);
@@ -200,6 +334,11 @@ impl Fire {
     fn merge_with(&mut self, right: &mut Self) {
         debug_assert_eq!(self.sat, right.sat);
 
+        self.merge_with_any_satellite(right)
+    }
+
+    /// Merge two wildfires that may have been observed by different satellites.
+    fn merge_with_any_satellite(&mut self, right: &mut Self) {
         // The fire with the lower value for the id was created first, so prefer to keep it
         // around.
         if self.id > right.id {
@@ -210,6 +349,18 @@ impl Fire {
             self.first_observed = right.first_observed;
         }
 
+        // If both were observed in the same latest scan, their clusters are now simultaneous parts
+        // of the same fire.
+        if right.last_observed == self.last_observed {
+            self.scan_clusters += right.scan_clusters;
+        } else if right.last_observed > self.last_observed {
+            self.scan_clusters = right.scan_clusters;
+        }
+        self.max_simultaneous_clusters = self
+            .max_simultaneous_clusters
+            .max(right.max_simultaneous_clusters)
+            .max(self.scan_clusters);
+
         if right.last_observed > self.last_observed {
             self.last_observed = right.last_observed;
         }
@@ -220,6 +371,12 @@ impl Fire {
         self.max_power = self.max_power.max(right.max_power);
         self.max_temperature = self.max_temperature.max(right.max_temperature);
 
+        for &sat in &right.satellites {
+            if !self.satellites.contains(&sat) {
+                self.satellites.push(sat);
+            }
+        }
+
         right.merged_into = self.id;
     }
 
@@ -266,6 +423,20 @@ impl Geo for Fire {
     }
 }
 
+/** How the centroid of a [Fire] is calculated from its pixels.
+ *
+ * The default is [CentroidMethod::Uniform], which is what the [Geo] implementation for [Fire]
+ * uses.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CentroidMethod {
+    /// Every pixel counts the same, see [PixelList] [centroid](Geo::centroid).
+    #[default]
+    Uniform,
+    /// Pixels are weighted by their power, see [PixelList::power_weighted_centroid].
+    PowerWeighted,
+}
+
 /// A list of [Fire] objects.
 pub struct FireList(Vec<Fire>);
 
@@ -343,12 +514,44 @@ impl FireList {
 
     /// Detect overlaps in the fires in the list and merge them together into a single fire.
     ///
+    /// Only fires from the same satellite are merged.
+    ///
     /// # Arguments
     /// merged_away - is a list to move the smaller of two merged fires into.
     ///
     /// # Returns
     /// The number of mergers that occurred.
     pub fn merge_fires(&mut self, merged_away: &mut Self) -> usize {
+        self.merge_overlapping_fires(merged_away, false)
+    }
+
+    /// Detect overlaps in the fires in the list and merge them together even if they were
+    /// observed by different satellites.
+    ///
+    /// # Accuracy
+    ///
+    /// **This is only suitable for coarse, continental scale analysis.** Each satellite has its
+    /// own projection, and pixels are geolocated without correcting for parallax, so the same fire
+    /// seen from two satellites lands in different, partially overlapping pixels that can be
+    /// kilometers apart over high terrain. The merged fire keeps the pixels from both, so its area
+    /// and pixel count are overestimated, and unrelated fires close to each other may be merged.
+    /// See [Fire] for more.
+    ///
+    /// The merged fires keep the id and [satellite](Fire::satellite) of the fire created first,
+    /// and every contributing satellite is in [Fire::satellites]. The list of satellites only
+    /// exists in memory, storing a merged fire in the fires database keeps only the first
+    /// satellite, so reload the fires and merge them again instead of storing the result.
+    ///
+    /// # Arguments
+    /// merged_away - is a list to move the smaller of two merged fires into.
+    ///
+    /// # Returns
+    /// The number of mergers that occurred.
+    pub fn merge_fires_cross_satellite(&mut self, merged_away: &mut Self) -> usize {
+        self.merge_overlapping_fires(merged_away, true)
+    }
+
+    fn merge_overlapping_fires(&mut self, merged_away: &mut Self, cross_satellite: bool) -> usize {
         const FULL_DOMAIN: BoundingBox = BoundingBox {
             ll: Coord {
                 lat: -90.0,
@@ -393,6 +596,7 @@ impl FireList {
                                 if fire.id() == candidate_fire.id()
                                     || to_del_set.contains(&index)
                                     || to_del_set.contains(&candidate_index)
+                                    || (!cross_satellite && fire.sat != candidate_fire.sat)
                                     || !fire.area.adjacent_to_or_overlaps(
                                         &candidate_fire.area,
                                         OVERLAP_FUDGE_FACTOR,
@@ -400,7 +604,7 @@ impl FireList {
                                 {
                                     (false, ControlFlow::Continue((to_del_set, fire)))
                                 } else {
-                                    fire.merge_with(candidate_fire);
+                                    fire.merge_with_any_satellite(candidate_fire);
                                     to_del_set.insert(candidate_index);
                                     (true, ControlFlow::Continue((to_del_set, fire)))
                                 }
@@ -423,6 +627,59 @@ impl FireList {
         starting_size - self.0.len()
     }
 
+    /// Merge groups of fires that a cluster bridged, see [FireListView::matching_fires].
+    ///
+    /// All the fires in a group are merged into the one with the lowest id. Groups may share fires,
+    /// in which case they all end up merged together. Ids that aren't in the list are ignored.
+    ///
+    /// # Arguments
+    /// bridged - the groups of fire ids to merge.
+    /// merged_away - is a list to move the fires that were merged into another into.
+    ///
+    /// # Returns
+    /// The number of mergers that occurred.
+    pub fn merge_bridged_fires(&mut self, bridged: &[Vec<u64>], merged_away: &mut Self) -> usize {
+        let mut merged_into = std::collections::HashMap::<u64, u64>::new();
+        let resolve = |merged_into: &std::collections::HashMap<u64, u64>, mut id: u64| {
+            while let Some(&next) = merged_into.get(&id) {
+                id = next;
+            }
+            id
+        };
+
+        let mut num_merged = 0;
+        for group in bridged {
+            let mut ids: Vec<u64> = group.iter().map(|&id| resolve(&merged_into, id)).collect();
+            ids.sort_unstable();
+            ids.dedup();
+
+            let survivor_id = match ids.first() {
+                Some(&id) if self.0.iter().any(|fire| fire.id == id) => id,
+                _ => continue,
+            };
+
+            for &id in &ids[1..] {
+                let mut other = match self.0.iter().position(|fire| fire.id == id) {
+                    Some(idx) => self.0.swap_remove(idx),
+                    None => continue,
+                };
+
+                let survivor = self
+                    .0
+                    .iter_mut()
+                    .find(|fire| fire.id == survivor_id)
+                    .expect("survivor is still in the list");
+                survivor.merge_with(&mut other);
+
+                merged_into.insert(id, survivor_id);
+                merged_away.0.push(other);
+                num_merged += 1;
+            }
+        }
+
+        num_merged
+    }
+
     /// Get the number of fires in the list.
     pub fn len(&self) -> usize {
         self.0.len()
@@ -438,16 +695,22 @@ impl FireList {
     /// # Arguments
     /// removed - is the list to add the drained elements into.
     /// current_time - is the current time of the clusters that are being processed.
+    /// config - is the set of thresholds used to decide if a fire is stale.
     ///
     /// # Returns
     /// The number of items moved to the `removed` list.
-    pub fn drain_stale_fires(&mut self, removed: &mut Self, current_time: DateTime<Utc>) -> usize {
+    pub fn drain_stale_fires(
+        &mut self,
+        removed: &mut Self,
+        current_time: DateTime<Utc>,
+        config: &StalenessConfig,
+    ) -> usize {
         let mut i = 0;
         let mut len = self.0.len();
         let starting_size = self.0.len();
         while i < len {
             let f = unsafe { self.0.get_unchecked(i) };
-            if wildfire_is_stale(f, current_time) {
+            if config.is_stale(f, current_time) {
                 let temp = self.0.swap_remove(i);
                 len -= 1;
                 removed.0.push(temp);
@@ -464,64 +727,136 @@ impl FireList {
         self.0.iter()
     }
 
-    /// Save this list in a KML file.
+    /// Save this list in a KML file, with the times in the descriptions shown in `zone`.
     pub fn save_kmz<P: AsRef<Path>>(
         &self,
         minimum_duration: Duration,
         kmz_path: P,
+        zone: OutputTimeZone,
     ) -> SatFireResult<()> {
         let mut kmz = KmzFile::new(kmz_path)?;
 
-        kmz.start_style(Some("fire"))?;
-        kmz.create_icon_style(
-            Some("http://maps.google.com/mapfiles/kml/shapes/firedept.png"),
-            1.0,
-        )?;
-        kmz.finish_style()?;
+        kml_write_fire_style(&mut kmz)?;
+        for fire in self.iter().filter(|f| f.duration() >= minimum_duration) {
+            fire.kml_write(&mut kmz, false, zone)?;
+        }
 
-        let mut name = String::with_capacity(32);
-        let mut description = String::with_capacity(256);
-        let mut duration_buf = String::with_capacity(64);
+        Ok(())
+    }
+
+    /// Save this list in a KMZ file with a separate KML document for each day.
+    ///
+    /// A season of fires in a single document is too large for Google Earth to open. Here the
+    /// fires are grouped by the UTC day they were first observed, and each day is written to its
+    /// own document in the archive. The main document only has a NetworkLink to each day with a
+    /// TimeSpan covering the fires in it. Each fire also gets a TimeSpan for the time slider. The
+    /// times in the descriptions are shown in `zone`.
+    pub fn save_kmz_timesliced<P: AsRef<Path>>(
+        &self,
+        minimum_duration: Duration,
+        kmz_path: P,
+        zone: OutputTimeZone,
+    ) -> SatFireResult<()> {
+        let mut days: BTreeMap<NaiveDate, Vec<&Fire>> = BTreeMap::new();
         for fire in self.iter().filter(|f| f.duration() >= minimum_duration) {
-            name.clear();
-            let _ = write!(&mut name, "{}", fire.id());
-
-            kmz.start_folder(Some(&name), None, false)?;
-
-            fire.format_duration(&mut duration_buf);
-
-            description.clear();
-            let _ = write!(
-                &mut description,
-                concat!(
-                    "ID: {}<br/>",
-                    "Start: {}<br/>",
-                    "End: {}<br/>",
-                    "Duration: {}<br/>",
-                    "Max Power: {:.0} MW<br/>",
-                    "Max Temperature: {:.0} Kelvin<br/>",
-                ),
-                fire.id(),
-                fire.first_observed(),
-                fire.last_observed(),
-                duration_buf,
-                fire.max_power(),
-                fire.max_temperature()
-            );
-
-            kmz.start_placemark(Some(&name), Some(&description), Some("#fire"))?;
-            let centroid = fire.centroid();
-            kmz.create_point(centroid.lat, centroid.lon, 0.0)?;
-            kmz.finish_placemark()?;
-
-            fire.pixels().kml_write(&mut kmz);
-            kmz.finish_folder()?;
+            days.entry(fire.first_observed().date_naive())
+                .or_default()
+                .push(fire);
+        }
+
+        let day_file_name = |day: &NaiveDate| format!("days/{}.kml", day.format("%Y-%m-%d"));
+
+        let mut kmz = KmzFile::new(kmz_path)?;
+
+        for (day, fires) in &days {
+            let name = day.format("%Y-%m-%d").to_string();
+            // There is at least one fire for every day, so these are never None.
+            let start = fires.iter().map(|f| f.first_observed()).min().unwrap();
+            let end = fires.iter().map(|f| f.last_observed()).max().unwrap();
+
+            kmz.start_network_link(Some(&name))?;
+            kmz.timespan(start, end)?;
+            kmz.create_link(&day_file_name(day))?;
+            kmz.finish_network_link()?;
+        }
+
+        for (day, fires) in &days {
+            kmz.start_kml_file(&day_file_name(day))?;
+
+            kml_write_fire_style(&mut kmz)?;
+            for fire in fires {
+                fire.kml_write(&mut kmz, true, zone)?;
+            }
         }
 
         Ok(())
     }
 }
 
+/// Write the style used for the fire placemarks.
+fn kml_write_fire_style<K: KmlWriter>(kml: &mut K) -> SatFireResult<()> {
+    kml.start_style(Some("fire"))?;
+    kml.create_icon_style(
+        Some("http://maps.google.com/mapfiles/kml/shapes/firedept.png"),
+        1.0,
+    )?;
+    kml.finish_style()
+}
+
+impl Fire {
+    /// Write this fire as a KML folder with a placemark at the centroid and the pixels.
+    ///
+    /// If `timespan` is true the folder gets a TimeSpan from the first to last observation. The
+    /// times in the description are shown in `zone`.
+    fn kml_write<K: KmlWriter>(
+        &self,
+        kml: &mut K,
+        timespan: bool,
+        zone: OutputTimeZone,
+    ) -> SatFireResult<()> {
+        let name = self.id().to_string();
+
+        kml.start_folder(Some(&name), None, false)?;
+        if timespan {
+            kml.timespan(self.first_observed(), self.last_observed())?;
+        }
+
+        let mut duration_buf = String::with_capacity(64);
+        self.format_duration(&mut duration_buf);
+
+        let mut description = String::with_capacity(256);
+        let _ = write!(
+            &mut description,
+            concat!(
+                "ID: {}<br/>",
+                "Start: {}<br/>",
+                "End: {}<br/>",
+                "Duration: {}<br/>",
+                "Max Power: {:.0} MW<br/>",
+                "Max Temperature: {:.0} Kelvin<br/>",
+                "Max Area: {:.0} m^2<br/>",
+                "Bounding Box Area: {:.1} km^2<br/>",
+            ),
+            self.id(),
+            OutputTime(self.first_observed(), zone),
+            OutputTime(self.last_observed(), zone),
+            duration_buf,
+            self.max_power(),
+            self.max_temperature(),
+            self.max_area_m2(),
+            self.bounding_box_area_km2()
+        );
+
+        kml.start_placemark(Some(&name), Some(&description), Some("#fire"))?;
+        let centroid = self.centroid();
+        kml.create_point(centroid.lat, centroid.lon, 0.0)?;
+        kml.finish_placemark()?;
+
+        self.pixels().kml_write(kml);
+        kml.finish_folder()
+    }
+}
+
 pub struct FireListView<'a> {
     view: Hilbert2DRTreeView<'a, Fire>,
 }
@@ -581,27 +916,592 @@ impl<'a> FireListView<'a> {
             },
         )
     }
+
+    /// Find all the wildfires in the list that the cluster matches, without updating any of them.
+    ///
+    /// [FireListView::update] stops at the first match, but a cluster may bridge the gap between
+    /// two or more fires that really should be merged. This finds all of them so the caller can
+    /// merge them explicitly.
+    ///
+    /// # Returns
+    ///
+    /// The ids of the matching fires sorted in ascending order, which is empty if there were none.
+    pub fn matching_fires(&mut self, row: &ClusterDatabaseClusterRow) -> Vec<u64> {
+        let bbox = row.pixels.bounding_box();
+
+        let mut ids = self
+            .view
+            .foreach(bbox, Vec::new(), |fire, _fire_idx, mut ids| {
+                if row
+                    .pixels
+                    .adjacent_to_or_overlaps(&fire.area, OVERLAP_FUDGE_FACTOR)
+                {
+                    ids.push(fire.id());
+                }
+                (false, ControlFlow::Continue(ids))
+            });
+
+        ids.sort_unstable();
+        ids
+    }
+}
+
+/// The thresholds used by [FireList::drain_stale_fires] to decide when a fire is no longer
+/// burning.
+///
+/// The defaults are tuned for the GOES CONUS sector. A fire that has not been observed for a while
+/// is only kept if it burned for longer than it has been quiet, scaled by the quiet burn factor.
+/// Slow burning fires, like peat fires, that smolder undetected for long periods may need a
+/// larger maximum quiet time or factor.
+///
+/// ```
+/// use chrono::Duration;
+/// use satfire::StalenessConfig;
+///
+/// let config = StalenessConfig::default()
+///     .max_quiet(Duration::days(60))
+///     .quiet_burn_factor(2.0);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct StalenessConfig {
+    noise_pixel_cap: usize,
+    min_quiet: Duration,
+    max_quiet: Duration,
+    quiet_burn_factor: f64,
+    max_lifetime: Option<Duration>,
+}
+
+impl Default for StalenessConfig {
+    fn default() -> Self {
+        StalenessConfig {
+            noise_pixel_cap: 1_000,
+            min_quiet: Duration::hours(24),
+            max_quiet: Duration::days(30),
+            quiet_burn_factor: 1.0,
+            max_lifetime: None,
+        }
+    }
+}
+
+impl StalenessConfig {
+    /// Fires with at least this many pixels are considered noise and are always stale.
+    pub fn noise_pixel_cap(mut self, num_pixels: usize) -> Self {
+        self.noise_pixel_cap = num_pixels;
+        self
+    }
+
+    /// Fires last observed more recently than this are never stale, unless they are noise or
+    /// too old.
+    pub fn min_quiet(mut self, min_quiet: Duration) -> Self {
+        self.min_quiet = min_quiet;
+        self
+    }
+
+    /// Fires last observed longer ago than this are always stale.
+    pub fn max_quiet(mut self, max_quiet: Duration) -> Self {
+        self.max_quiet = max_quiet;
+        self
+    }
+
+    /// Between the minimum and maximum quiet times, a fire is stale if it has been quiet for
+    /// longer than its duration multiplied by this factor.
+    pub fn quiet_burn_factor(mut self, factor: f64) -> Self {
+        self.quiet_burn_factor = factor;
+        self
+    }
+
+    /// Fires first observed at least this long ago are stale even if they are still burning.
+    ///
+    /// A fixed heat source, like a gas flare, could otherwise look like a single fire burning for
+    /// months. Any clusters at the same location after a fire is removed start a new fire, so a
+    /// long lived detection is split into several fires no longer than about `max_lifetime` each.
+    pub fn max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Drop the fires that are stale at `current_time` from a stream of fires.
+    ///
+    /// This is [FireList::drain_stale_fires] for fires that aren't collected into a list, like
+    /// those from [FiresDatabase::iter_fires](crate::FiresDatabase::iter_fires). Errors are passed
+    /// through.
+    pub fn without_stale<I>(
+        self,
+        fires: I,
+        current_time: DateTime<Utc>,
+    ) -> impl Iterator<Item = SatFireResult<Fire>>
+    where
+        I: IntoIterator<Item = SatFireResult<Fire>>,
+    {
+        fires
+            .into_iter()
+            .filter(move |fire| !matches!(fire, Ok(fire) if self.is_stale(fire, current_time)))
+    }
+
+    /// Is this fire likely no longer burning at `current_time`?
+    pub fn is_stale(&self, fire: &Fire, current_time: DateTime<Utc>) -> bool {
+        let duration_since_last_observed = current_time - fire.last_observed;
+
+        // If it got this big, it can't be real. It must be a "noise fire"
+        if fire.pixels().len() >= self.noise_pixel_cap {
+            return true;
+        }
+
+        // Burning for too long, probably a fixed heat source.
+        if matches!(self.max_lifetime, Some(max) if current_time - fire.first_observed >= max) {
+            return true;
+        }
+
+        // Minimum time to stick around.
+        if duration_since_last_observed < self.min_quiet {
+            return false;
+        }
+
+        // Maximum time to stick around after being last observed.
+        if duration_since_last_observed > self.max_quiet {
+            return true;
+        }
+
+        // If it's been out longer than it burned, let it go.
+        let wildfire_duration = fire.duration().num_seconds() as f64 * self.quiet_burn_factor;
+        wildfire_duration < duration_since_last_observed.num_seconds() as f64
+    }
 }
 
-fn wildfire_is_stale(fire: &Fire, current_time: DateTime<Utc>) -> bool {
-    let duration_since_last_observed = current_time - fire.last_observed;
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        satellite::Sector,
+        testutil::{fire_from_pixels, PixelGrid},
+    };
+
+    fn test_fire() -> Fire {
+        let first_observed: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let last_observed = first_observed + Duration::hours(6);
+
+        Fire::new(
+            first_observed,
+            last_observed,
+            100.0,
+            600.0,
+            1,
+            PixelList::new(),
+            Satellite::G17,
+            0,
+        )
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_fire_serde_round_trip() {
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            0.1,
+        );
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let fire = fire_from_pixels(
+            7,
+            grid.block(0, 0, 2, 3, 10.0),
+            Satellite::G17,
+            start,
+            start + Duration::hours(2),
+        );
+
+        // Fill the cache, it shouldn't be written.
+        let centroid = fire.centroid();
+
+        let json = serde_json::to_string(&fire).unwrap();
+        assert!(json.contains(r#""pixels":["#));
+        assert!(!json.contains("cache_up_to_date"));
+        assert!(!json.contains("bbox"));
+
+        let back: Fire = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.id(), fire.id());
+        assert_eq!(back.satellite(), fire.satellite());
+        assert_eq!(back.first_observed(), fire.first_observed());
+        assert_eq!(back.last_observed(), fire.last_observed());
+        assert_eq!(back.max_power(), fire.max_power());
+        assert_eq!(back.pixels().len(), fire.pixels().len());
+        assert!(back.centroid().is_close(centroid, 1.0e-12));
+    }
+
+    #[test]
+    fn test_merge_fires_cross_satellite() {
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -110.0,
+            },
+            0.02,
+        );
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::hours(1);
+
+        let make_list = || {
+            let mut fires = FireList::new();
+            fires.add_fire(fire_from_pixels(
+                2,
+                grid.block(0, 0, 3, 3, 10.0),
+                Satellite::G18,
+                start,
+                end,
+            ));
+            fires.add_fire(fire_from_pixels(
+                1,
+                grid.block(1, 1, 3, 3, 20.0),
+                Satellite::G16,
+                start,
+                end,
+            ));
+            fires
+        };
+
+        // The default merge keeps the satellites apart.
+        let mut fires = make_list();
+        let mut merged_away = FireList::new();
+        assert_eq!(fires.merge_fires(&mut merged_away), 0);
+        assert_eq!(fires.len(), 2);
+        assert!(merged_away.is_empty());
+
+        let mut fires = make_list();
+        assert_eq!(fires.merge_fires_cross_satellite(&mut merged_away), 1);
+        assert_eq!(fires.len(), 1);
+        assert_eq!(merged_away.len(), 1);
+
+        let merged = &fires.0[0];
+        assert_eq!(merged.id(), 1);
+        assert_eq!(merged.satellite(), Satellite::G16);
+        assert_eq!(merged.satellites(), &[Satellite::G16, Satellite::G18]);
+        assert_eq!(merged.max_power(), 20.0);
+        assert!(merged
+            .to_string()
+            .contains("Satellite: G16, G18 (only G16 is stored)"));
+        assert!(merged_away.0[0].to_string().contains("Satellite: G18\n"));
+
+        assert_eq!(merged_away.0[0].merged_into, 1);
+        assert_eq!(merged_away.0[0].satellites(), &[Satellite::G18]);
+    }
+
+    #[test]
+    fn test_fire_display_in_time_zone() {
+        let fire = test_fire();
+
+        let local = fire
+            .display_in(crate::parse_time_zone("America/Denver").unwrap())
+            .to_string();
+        let utc = fire
+            .display_in(crate::parse_time_zone("UTC").unwrap())
+            .to_string();
+        assert_eq!(utc, fire.to_string());
+
+        assert!(local.contains("First Observed: 2021-08-01 06:00:00 -06:00"));
+        assert!(local.contains("Last Observed: 2021-08-01 12:00:00 -06:00"));
+        assert!(utc.contains("First Observed: 2021-08-01 12:00:00 UTC"));
+    }
+
+    #[test]
+    fn test_fire_areas() {
+        let fire = test_fire();
+        assert_eq!(fire.max_area_m2(), 0.0);
+        assert_eq!(fire.bounding_box_area_km2(), 0.0);
+
+        // A 2 by 3 block of 0.1 degree pixels.
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            0.1,
+        );
+        let start = fire.first_observed();
+        let fire = fire_from_pixels(
+            1,
+            grid.block(0, 0, 2, 3, 10.0),
+            Satellite::G17,
+            start,
+            start,
+        );
+
+        assert_eq!(fire.max_area_m2(), 6_000.0);
+
+        // About 15.7 km wide by 33.4 km tall.
+        let expected = lat_lon_cell_area(44.7, 45.0, 0.2, &Ellipsoid::GRS80) / 1.0e6;
+        assert!((fire.bounding_box_area_km2() - expected).abs() < 1.0e-9);
+        assert!((fire.bounding_box_area_km2() - 525.0).abs() < 5.0);
+
+        let text = fire.to_string();
+        assert!(text.contains("Max Area: 6000 m^2"));
+        assert!(text.contains("Bounding Box Area: "));
+    }
 
-    // If it got this big, it can't be real. It must be a "noise fire"
-    if fire.pixels().len() >= 1_000 {
-        return true;
+    #[test]
+    fn test_fire_age_at() {
+        let fire = test_fire();
+        let first = fire.first_observed();
+
+        assert_eq!(fire.age_at(first - Duration::minutes(1)), None);
+        assert_eq!(fire.age_at(first), Some(Duration::zero()));
+        assert_eq!(
+            fire.age_at(first + Duration::hours(2)),
+            Some(Duration::hours(2))
+        );
+        assert_eq!(fire.age_at(fire.last_observed()), Some(Duration::hours(6)));
+        assert_eq!(
+            fire.age_at(fire.last_observed() + Duration::minutes(1)),
+            None
+        );
     }
 
-    // Minimum time to stick around.
-    if duration_since_last_observed < Duration::hours(24) {
-        return false;
+    #[test]
+    fn test_fire_was_active_during() {
+        let fire = test_fire();
+        let first = fire.first_observed();
+        let last = fire.last_observed();
+
+        assert!(!fire.was_active_during(first - Duration::hours(2), first - Duration::hours(1)));
+        assert!(fire.was_active_during(first - Duration::hours(1), first));
+        assert!(fire.was_active_during(first + Duration::hours(1), first + Duration::hours(2)));
+        assert!(fire.was_active_during(first - Duration::hours(1), last + Duration::hours(1)));
+        assert!(fire.was_active_during(last, last + Duration::hours(1)));
+        assert!(!fire.was_active_during(last + Duration::hours(1), last + Duration::hours(2)));
     }
 
-    // Maximum time to stick around after being last observed.
-    if duration_since_last_observed > Duration::days(30) {
-        return true;
+    #[test]
+    fn test_max_lifetime_splits_fire() {
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            0.02,
+        );
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::days(10);
+        let now = end + Duration::minutes(10);
+
+        let make_fires = || {
+            let mut fires = FireList::new();
+            let pixels = grid.block(0, 0, 2, 2, 10.0);
+            fires.add_fire(fire_from_pixels(1, pixels, Satellite::G17, start, end));
+            fires
+        };
+
+        // Still burning, so it isn't stale.
+        let mut fires = make_fires();
+        let mut removed = FireList::new();
+        assert_eq!(
+            fires.drain_stale_fires(&mut removed, now, &StalenessConfig::default()),
+            0
+        );
+        let month = StalenessConfig::default().max_lifetime(Duration::days(30));
+        assert_eq!(fires.drain_stale_fires(&mut removed, now, &month), 0);
+
+        // But it has been burning for too long.
+        let week = StalenessConfig::default().max_lifetime(Duration::days(7));
+        assert_eq!(fires.drain_stale_fires(&mut removed, now, &week), 1);
+        assert!(fires.is_empty());
+
+        // The next cluster at the same location starts a new fire.
+        let row = ClusterDatabaseClusterRow {
+            rowid: 1,
+            start: now,
+            end: now + Duration::minutes(10),
+            power: 10.0,
+            max_temperature: 600.0,
+            area: 1_000.0,
+            scan_angle: 5.0,
+            centroid: Coord {
+                lat: 44.99,
+                lon: -119.99,
+            },
+            sector: Sector::FULL,
+            sat: Satellite::G17,
+            pixels: grid.block(0, 0, 1, 1, 10.0),
+        };
+        match fires.update(row) {
+            FireListUpdateResult::NoMatch(row) => fires.create_add_fire(2, row),
+            FireListUpdateResult::Match(_) => panic!("matched a closed fire"),
+        }
+
+        let records: Vec<(u64, DateTime<Utc>)> = removed
+            .iter()
+            .chain(fires.iter())
+            .map(|fire| (fire.id(), fire.first_observed()))
+            .collect();
+        assert_eq!(records, vec![(1, start), (2, now)]);
     }
 
-    // If it's been out longer than it burned, let it go.
-    let wildfire_duration = fire.duration();
-    wildfire_duration < duration_since_last_observed
+    #[test]
+    fn test_staleness_config_thresholds() {
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            0.02,
+        );
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::days(2);
+        let fire = fire_from_pixels(1, grid.block(0, 0, 2, 2, 10.0), Satellite::G17, start, end);
+
+        let default = StalenessConfig::default();
+        assert!(!default.is_stale(&fire, end + Duration::hours(12)));
+        assert!(!default.is_stale(&fire, end + Duration::days(1)));
+        assert!(default.is_stale(&fire, end + Duration::days(3)));
+
+        // Quiet for longer than it burned, but a larger factor keeps it around.
+        let peat = default.quiet_burn_factor(5.0);
+        assert!(!peat.is_stale(&fire, end + Duration::days(3)));
+        assert!(peat.is_stale(&fire, end + Duration::days(11)));
+
+        // But never past the maximum quiet time.
+        let peat = peat.max_quiet(Duration::days(7));
+        assert!(peat.is_stale(&fire, end + Duration::days(8)));
+
+        // A shorter minimum quiet time lets it go sooner.
+        let quick = default.min_quiet(Duration::hours(6)).quiet_burn_factor(0.1);
+        assert!(!quick.is_stale(&fire, end + Duration::hours(5)));
+        assert!(quick.is_stale(&fire, end + Duration::hours(7)));
+
+        // Too many pixels is noise.
+        assert!(default.noise_pixel_cap(4).is_stale(&fire, end));
+        assert!(!default.noise_pixel_cap(5).is_stale(&fire, end));
+
+        let mut fires = FireList::new();
+        fires.add_fire(fire);
+        let mut removed = FireList::new();
+        assert_eq!(
+            fires.drain_stale_fires(&mut removed, end + Duration::days(3), &peat),
+            0
+        );
+        assert_eq!(
+            fires.drain_stale_fires(&mut removed, end + Duration::days(3), &default),
+            1
+        );
+        assert_eq!(removed.len(), 1);
+    }
+
+    #[test]
+    fn test_matching_fires_finds_bridged_fires() {
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            0.02,
+        );
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        let mut fires = FireList::new();
+        for (id, col) in [(1, 0), (2, 5), (3, 20)] {
+            let pixels = grid.block(col, 0, 2, 1, 10.0);
+            fires.add_fire(fire_from_pixels(id, pixels, Satellite::G17, start, end));
+        }
+
+        // Fills the gap between fires 1 and 2, but nowhere near fire 3.
+        let row = ClusterDatabaseClusterRow {
+            rowid: 1,
+            start: end,
+            end: end + Duration::minutes(10),
+            power: 30.0,
+            max_temperature: 600.0,
+            area: 3_000.0,
+            scan_angle: 5.0,
+            centroid: Coord {
+                lat: 44.99,
+                lon: -119.93,
+            },
+            sector: Sector::FULL,
+            sat: Satellite::G17,
+            pixels: grid.block(2, 0, 3, 1, 10.0),
+        };
+
+        let mut view = FireListView::new(&mut fires).unwrap();
+        assert_eq!(view.matching_fires(&row), vec![1, 2]);
+
+        // Finding the matches doesn't update anything, but update only matches one of them.
+        match view.update(row) {
+            FireListUpdateResult::Match(id) => assert!(id == 1 || id == 2),
+            FireListUpdateResult::NoMatch(_) => panic!("no match"),
+        }
+        drop(view);
+
+        let updated: Vec<u64> = fires
+            .iter()
+            .filter(|fire| fire.last_observed() > end)
+            .map(|fire| fire.id())
+            .collect();
+        assert_eq!(updated.len(), 1);
+    }
+
+    #[test]
+    fn test_bridging_cluster_merges_fires() {
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            0.02,
+        );
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(10);
+
+        let mut fires = FireList::new();
+        for (id, col) in [(1, 0), (2, 5), (3, 10), (4, 20)] {
+            let pixels = grid.block(col, 0, 2, 1, 10.0);
+            fires.add_fire(fire_from_pixels(id, pixels, Satellite::G17, start, end));
+        }
+
+        // One cluster bridges fires 1 and 2, another bridges 2 and 3 in the same scan.
+        let bridge = |rowid: u64, col: isize| ClusterDatabaseClusterRow {
+            rowid,
+            start: end,
+            end: end + Duration::minutes(10),
+            power: 30.0,
+            max_temperature: 600.0,
+            area: 3_000.0,
+            scan_angle: 5.0,
+            centroid: Coord {
+                lat: 44.99,
+                lon: -120.0 + (col as f64 + 1.5) * 0.02,
+            },
+            sector: Sector::FULL,
+            sat: Satellite::G17,
+            pixels: grid.block(col, 0, 3, 1, 10.0),
+        };
+
+        let mut bridged = vec![];
+        {
+            let mut view = FireListView::new(&mut fires).unwrap();
+            for row in [bridge(1, 2), bridge(2, 7)] {
+                let matches = view.matching_fires(&row);
+                assert!(matches!(view.update(row), FireListUpdateResult::Match(_)));
+                if matches.len() > 1 {
+                    bridged.push(matches);
+                }
+            }
+        }
+        assert_eq!(bridged, vec![vec![1, 2], vec![2, 3]]);
+
+        let mut merged_away = FireList::new();
+        assert_eq!(fires.merge_bridged_fires(&bridged, &mut merged_away), 2);
+
+        let mut ids: Vec<u64> = fires.iter().map(|fire| fire.id()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 4]);
+        assert!(merged_away.iter().all(|fire| fire.merged_into() == 1));
+
+        // The merged fire covers all three fires and both bridges.
+        let merged = fires.iter().find(|fire| fire.id() == 1).unwrap();
+        assert_eq!(merged.pixels().len(), 12);
+        assert_eq!(merged.last_observed(), end + Duration::minutes(10));
+
+        // Nothing is left to merge in the periodic pass.
+        assert_eq!(fires.merge_fires(&mut merged_away), 0);
+    }
 }
@@ -1,16 +1,23 @@
 use crate::{
+    cluster::Cluster,
     geo::Coord,
     pixel::Pixel,
-    satellite::{DataQualityFlagCode, MaskCode},
+    qc::ClusterQc,
+    satellite::{
+        DataQualityFlagCode, MaskCode, Satellite, GOES_PERSPECTIVE_POINT_HEIGHT,
+        GOES_SEMI_MAJOR_AXIS, GOES_SEMI_MINOR_AXIS,
+    },
     SatFireResult,
 };
+use chrono::Duration;
 use libc::{c_char, c_double, c_int, c_short, c_void, size_t};
 use once_cell::sync::OnceCell;
 use std::{
     ffi::{CStr, CString},
-    io::Read,
-    path::Path,
-    sync::Mutex,
+    fmt,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
 static_assertions::assert_eq_size!(c_short, i16);
@@ -27,6 +34,8 @@ pub(crate) struct SatFireImage {
     ylen: usize,
     /// All the information needed for transforming from row and column numbers to coordinates.
     tran: CoordTransform,
+    /// Elevation of the terrain used to correct the pixel locations for parallax.
+    terrain: Option<TerrainHeight>,
     /// In memory buffer if this is from a zip file
     buffer: Option<Vec<u8>>,
     /// Handle to the NetCDF file
@@ -44,9 +53,180 @@ macro_rules! check_error {
     };
 }
 
+/// The elevation of the Earth's surface above the ellipsoid in meters.
+///
+/// The GOES fixed grid projection locates every pixel on the surface of the ellipsoid. An
+/// elevated fire is seen along the same line of sight, but it is closer to the satellite, so
+/// supplying the terrain height moves the pixel toward the sub-satellite point.
+///
+/// Use it with [LoadOptions::correct_parallax].
+#[derive(Clone)]
+pub enum TerrainHeight {
+    /// The same elevation everywhere.
+    Constant(f64),
+    /// Look up the elevation at a location, typically from a digital elevation model (DEM).
+    Lookup(Arc<dyn Fn(Coord) -> f64 + Send + Sync>),
+}
+
+impl TerrainHeight {
+    /// Create a terrain height from a DEM lookup function.
+    pub fn from_lookup<F>(lookup: F) -> Self
+    where
+        F: Fn(Coord) -> f64 + Send + Sync + 'static,
+    {
+        TerrainHeight::Lookup(Arc::new(lookup))
+    }
+
+    /// The elevation at a location on the ellipsoid in meters.
+    pub fn height_at(&self, coord: Coord) -> f64 {
+        match self {
+            TerrainHeight::Constant(height) => *height,
+            TerrainHeight::Lookup(lookup) => lookup(coord),
+        }
+    }
+}
+
+impl fmt::Debug for TerrainHeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            TerrainHeight::Constant(height) => f.debug_tuple("Constant").field(height).finish(),
+            TerrainHeight::Lookup(_) => f.write_str("Lookup(..)"),
+        }
+    }
+}
+
+/// Options for reading a file when loading clusters from it.
+///
+/// These change where the pixels are located and what is logged about them, while [ClusterQc]
+/// picks which pixels and clusters are kept. Use them with
+/// [ClusterList::from_file_with_options](crate::ClusterList::from_file_with_options).
+///
+/// ```
+/// use satfire::{LoadOptions, TerrainHeight};
+///
+/// let options = LoadOptions::default()
+///     .use_default_projection(true)
+///     .correct_parallax(TerrainHeight::Constant(1_500.0));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    default_projection: bool,
+    terrain: Option<TerrainHeight>,
+    max_area_ratio: Option<f64>,
+}
+
+impl LoadOptions {
+    /// When a file is missing some or all of the projection attributes, use the published values
+    /// for the satellite instead of failing to load it.
+    ///
+    /// The projection is fixed for each GOES satellite position, so this is safe for older or
+    /// non-standard files that left it out.
+    pub fn use_default_projection(mut self, use_default: bool) -> Self {
+        self.default_projection = use_default;
+        self
+    }
+
+    /// Is the satellite default projection used for files missing the projection attributes?
+    pub fn uses_default_projection(&self) -> bool {
+        self.default_projection
+    }
+
+    /// Correct the pixel locations for parallax when a file is loaded, assuming the fires are at
+    /// this height above the ellipsoid.
+    ///
+    /// By default pixels are located on the surface of the ellipsoid, which puts fires in high
+    /// terrain too far from the sub-satellite point.
+    pub fn correct_parallax(mut self, terrain: TerrainHeight) -> Self {
+        self.terrain = Some(terrain);
+        self
+    }
+
+    /// The terrain height used to correct pixel locations for parallax, if any.
+    pub fn parallax_terrain(&self) -> Option<&TerrainHeight> {
+        self.terrain.as_ref()
+    }
+
+    /// Warn when a cluster's reported fire area and geographic area differ by more than a factor of
+    /// `ratio` when loading a file.
+    ///
+    /// Near the limb of the disk the fire area reported in the file can be wildly inconsistent
+    /// with the size of the pixels. These suspect clusters are only logged, they are still kept.
+    /// The reported area is only the part of the pixels that is burning, so it is normally smaller
+    /// than the geographic area and `ratio` should be fairly large.
+    pub fn warn_area_ratio(mut self, ratio: f64) -> Self {
+        self.max_area_ratio = Some(ratio);
+        self
+    }
+
+    /// Do the reported and geographic areas of this cluster differ by more than the ratio set with
+    /// [LoadOptions::warn_area_ratio]?
+    ///
+    /// This is always false if no ratio was set or the cluster doesn't have a reported area.
+    pub fn area_disagrees(&self, cluster: &Cluster) -> bool {
+        let ratio = match self.max_area_ratio {
+            Some(ratio) => ratio,
+            None => return false,
+        };
+
+        let area = cluster.total_area();
+        let geographic_area = cluster.total_geographic_area_m2();
+        if area <= 0.0 || geographic_area <= 0.0 {
+            return false;
+        }
+
+        area.max(geographic_area) / area.min(geographic_area) > ratio
+    }
+}
+
+/// Options for opening a [SatFireImage], created with [SatFireImage::builder].
+#[derive(Debug, Clone)]
+pub(crate) struct SatFireImageBuilder {
+    path: PathBuf,
+    default_projection: Option<Satellite>,
+    terrain: Option<TerrainHeight>,
+}
+
+impl SatFireImageBuilder {
+    /// Fill in any projection attributes missing from the file with the published values for
+    /// this satellite instead of failing.
+    pub(crate) fn default_projection(mut self, sat: Option<Satellite>) -> Self {
+        self.default_projection = sat;
+        self
+    }
+
+    /// Correct the pixel locations for parallax using this terrain height, if any.
+    pub(crate) fn terrain_height(mut self, terrain: Option<TerrainHeight>) -> Self {
+        self.terrain = terrain;
+        self
+    }
+
+    /// Open the file.
+    pub(crate) fn open(self) -> SatFireResult<SatFireImage> {
+        let mut image = SatFireImage::open(&self.path, self.default_projection)?;
+        image.terrain = self.terrain;
+        Ok(image)
+    }
+}
+
 impl SatFireImage {
+    /// Start building the options for opening a file, see [SatFireImageBuilder].
+    pub(crate) fn builder<P: AsRef<Path>>(path: P) -> SatFireImageBuilder {
+        SatFireImageBuilder {
+            path: path.as_ref().to_path_buf(),
+            default_projection: None,
+            terrain: None,
+        }
+    }
+
     /// Open a file containing GOES-R/S Fire Detection Characteristics.
-    pub(crate) fn open<P: AsRef<Path>>(path: P) -> SatFireResult<Self> {
+    ///
+    /// If `default_projection` is given, any projection attributes missing from the file are
+    /// filled in with the published values for that satellite instead of failing. The pixel
+    /// locations are not corrected for parallax, use [SatFireImage::builder] for that.
+    pub(crate) fn open<P: AsRef<Path>>(
+        path: P,
+        default_projection: Option<Satellite>,
+    ) -> SatFireResult<Self> {
         let p: &Path = path.as_ref();
         // FIXME change option into error
         let fname: String = p
@@ -57,9 +237,9 @@ impl SatFireImage {
 
         if let Some(ext) = p.extension() {
             if ext == "zip" {
-                Self::open_zip(p, fname)
+                Self::open_zip(p, fname, default_projection)
             } else if ext == "nc" {
-                Self::open_nc(p, fname)
+                Self::open_nc(p, fname, default_projection)
             } else {
                 Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into())
             }
@@ -68,16 +248,16 @@ impl SatFireImage {
         }
     }
 
-    fn open_zip(p: &Path, fname: String) -> SatFireResult<Self> {
+    fn open_zip(
+        p: &Path,
+        fname: String,
+        default_projection: Option<Satellite>,
+    ) -> SatFireResult<Self> {
         let path_str = CString::new(p.to_string_lossy().as_bytes())?;
 
         let file = std::fs::File::open(p)?;
         let mut zip = zip::ZipArchive::new(file)?;
-        assert_eq!(zip.len(), 1);
-
-        let mut nc_file = zip.by_index(0)?;
-        let mut buf: Vec<u8> = Vec::with_capacity(nc_file.size() as usize + 10);
-        let _size_read = nc_file.read_to_end(&mut buf)?;
+        let mut buf = Self::read_nc_from_zip(&mut zip)?;
 
         let lock = get_netcdf_lock()
             .lock()
@@ -100,14 +280,54 @@ impl SatFireImage {
             }
         }
 
-        let res = Self::initialize_with_nc_file_handle(fname, file_id, Some(buf))?;
+        let res =
+            Self::initialize_with_nc_file_handle(fname, file_id, Some(buf), default_projection)?;
 
         drop(lock);
 
         Ok(res)
     }
 
-    fn open_nc(p: &Path, fname: String) -> SatFireResult<Self> {
+    /// Read the NetCDF member of a zip archive into memory.
+    ///
+    /// Archives sometimes have auxiliary files bundled with the data, so the member is found by
+    /// its extension. It is an error if there isn't exactly one ".nc" file in the archive.
+    fn read_nc_from_zip<R: Read + Seek>(zip: &mut zip::ZipArchive<R>) -> SatFireResult<Vec<u8>> {
+        let nc_names: Vec<&str> = zip
+            .file_names()
+            .filter(|name| {
+                Path::new(name)
+                    .extension()
+                    .map(|ext| ext.eq_ignore_ascii_case("nc"))
+                    .unwrap_or(false)
+            })
+            .collect();
+
+        let nc_name = match nc_names[..] {
+            [nc_name] => nc_name.to_owned(),
+            [] => return Err("No NetCDF file in zip archive".into()),
+            _ => {
+                return Err(format!(
+                    "Expected one NetCDF file in zip archive, found {}: {}",
+                    nc_names.len(),
+                    nc_names.join(", ")
+                )
+                .into())
+            }
+        };
+
+        let mut nc_file = zip.by_name(&nc_name)?;
+        let mut buf: Vec<u8> = Vec::with_capacity(nc_file.size() as usize + 10);
+        let _size_read = nc_file.read_to_end(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    fn open_nc(
+        p: &Path,
+        fname: String,
+        default_projection: Option<Satellite>,
+    ) -> SatFireResult<Self> {
         let path_str = CString::new(p.to_string_lossy().as_bytes())?;
 
         let lock = get_netcdf_lock()
@@ -119,7 +339,7 @@ impl SatFireImage {
             check_error!(status)?;
         }
 
-        let res = Self::initialize_with_nc_file_handle(fname, file_id, None)?;
+        let res = Self::initialize_with_nc_file_handle(fname, file_id, None, default_projection)?;
 
         drop(lock);
 
@@ -131,6 +351,7 @@ impl SatFireImage {
         fname: String,
         handle: c_int,
         in_memory_buffer: Option<Vec<u8>>,
+        default_projection: Option<Satellite>,
     ) -> SatFireResult<Self> {
         let mut xlen: usize = 0;
         let mut ylen: usize = 0;
@@ -142,10 +363,7 @@ impl SatFireImage {
         let mut xoffset: f64 = f64::NAN;
         let mut yscale: f64 = f64::NAN;
         let mut yoffset: f64 = f64::NAN;
-        let mut req: f64 = f64::NAN;
-        let mut rpol: f64 = f64::NAN;
-        let mut H: f64 = f64::NAN;
-        let mut lon0: f64 = f64::NAN;
+        let mut proj = ProjectionParameters::missing();
 
         unsafe {
             let mut xdimid: c_int = -1;
@@ -193,20 +411,33 @@ impl SatFireImage {
                 b"goes_imager_projection\0".as_ptr() as *const c_char,
                 &mut proj_id as *mut c_int,
             );
-            check_error!(status)?;
 
-            let semi_major_axis = b"semi_major_axis\0".as_ptr() as *const c_char;
-            let semi_minor_axis = b"semi_minor_axis\0".as_ptr() as *const c_char;
-            let perp_point_h = b"perspective_point_height\0".as_ptr() as *const c_char;
-            let lon_origin = b"longitude_of_projection_origin\0".as_ptr() as *const c_char;
-            status = nc_get_att_double(h, proj_id, semi_major_axis, &mut req as *mut c_double);
-            check_error!(status)?;
-            status = nc_get_att_double(h, proj_id, semi_minor_axis, &mut rpol as *mut c_double);
-            check_error!(status)?;
-            status = nc_get_att_double(h, proj_id, perp_point_h, &mut H as *mut c_double);
-            check_error!(status)?;
-            status = nc_get_att_double(h, proj_id, lon_origin, &mut lon0 as *mut c_double);
-            check_error!(status)?;
+            // Without a fallback a missing projection is still an error, otherwise the attributes
+            // are left missing and filled in below.
+            if default_projection.is_none() || status == NC_NOERR {
+                check_error!(status)?;
+
+                let semi_major_axis = b"semi_major_axis\0".as_ptr() as *const c_char;
+                let semi_minor_axis = b"semi_minor_axis\0".as_ptr() as *const c_char;
+                let perp_point_h = b"perspective_point_height\0".as_ptr() as *const c_char;
+                let lon_origin = b"longitude_of_projection_origin\0".as_ptr() as *const c_char;
+                status = nc_get_att_double(h, proj_id, semi_major_axis, &mut proj.req);
+                check_error!(status, "attr")?;
+                status = nc_get_att_double(h, proj_id, semi_minor_axis, &mut proj.rpol);
+                check_error!(status, "attr")?;
+                status = nc_get_att_double(h, proj_id, perp_point_h, &mut proj.H);
+                check_error!(status, "attr")?;
+                status = nc_get_att_double(h, proj_id, lon_origin, &mut proj.lon0);
+                check_error!(status, "attr")?;
+            }
+        }
+
+        if let Some(sat) = default_projection {
+            proj = proj.or_satellite_default(sat);
+        }
+
+        if !proj.is_complete() {
+            return Err(format!("Missing projection attributes in {}", fname).into());
         }
 
         Ok(SatFireImage {
@@ -217,81 +448,44 @@ impl SatFireImage {
                 xoffset,
                 yscale,
                 yoffset,
-                req,
-                rpol,
-                H: H + req,
-                lon0,
+                req: proj.req,
+                rpol: proj.rpol,
+                H: proj.H + proj.req,
+                lon0: proj.lon0,
             },
+            terrain: None,
             buffer: in_memory_buffer,
             nc_file_id: handle,
             fname,
         })
     }
 
-    pub(crate) fn extract_fire_points(&self) -> SatFireResult<Vec<FirePoint>> {
-        let mut points: Vec<FirePoint> = Vec::new();
-
+    /// Extract all the fire points with a DQF accepted by the quality control from the image.
+    ///
+    /// The `scan_duration` is the time from the scan start to the scan end, and it is used to
+    /// estimate the [acquisition_offset](Pixel::acquisition_offset) of each pixel.
+    pub(crate) fn extract_fire_points(
+        &self,
+        scan_duration: Duration,
+        qc: &ClusterQc,
+    ) -> SatFireResult<Vec<FirePoint>> {
         let lock = get_netcdf_lock()
             .lock()
             .expect("Error locking global mutex for netCDF");
 
-        let powers = self.extract_variable_double(b"Power\0".as_ptr() as *const c_char)?;
-        let areas = self.extract_variable_double(b"Area\0".as_ptr() as *const c_char)?;
-        let temperatures = self.extract_variable_double(b"Temp\0".as_ptr() as *const c_char)?;
-        let masks = self.extract_variable_short(b"Mask\0".as_ptr() as *const c_char)?;
-        let dqfs = self.extract_variable_short(b"DQF\0".as_ptr() as *const c_char)?;
+        let vars = FireVariables {
+            xlen: self.xlen,
+            ylen: self.ylen,
+            powers: self.extract_variable_double(b"Power\0".as_ptr() as *const c_char)?,
+            areas: self.extract_variable_double(b"Area\0".as_ptr() as *const c_char)?,
+            temperatures: self.extract_variable_double(b"Temp\0".as_ptr() as *const c_char)?,
+            masks: self.extract_variable_short(b"Mask\0".as_ptr() as *const c_char)?,
+            dqfs: self.extract_variable_short(b"DQF\0".as_ptr() as *const c_char)?,
+        };
 
         drop(lock);
 
-        for j in 0..self.ylen {
-            for i in 0..self.xlen {
-                let index = i + j * self.xlen;
-
-                let power_mw;
-                let area;
-                let temperature;
-                let mask;
-                let dqf;
-
-                unsafe {
-                    power_mw = *powers.get_unchecked(index);
-                    area = *areas.get_unchecked(index);
-                    temperature = *temperatures.get_unchecked(index);
-                    mask = *masks.get_unchecked(index);
-                    dqf = *dqfs.get_unchecked(index);
-                }
-
-                // 0 for a data quality flag indicates a good quality fire detection
-                if dqf == 0 {
-                    let ii = i as f64;
-                    let jj = j as f64;
-
-                    let ips: [f64; 5] = [ii - 0.5, ii - 0.5, ii + 0.5, ii + 0.5, ii];
-                    let jps: [f64; 5] = [jj - 0.5, jj + 0.5, jj + 0.5, jj - 0.5, jj];
-
-                    let (scan_angle, coords) = self.tran.convert_row_cols_to_latlon(&jps, &ips);
-
-                    points.push(FirePoint {
-                        x: i as isize,
-                        y: j as isize,
-                        pixel: Pixel {
-                            ul: coords[0],
-                            ll: coords[1],
-                            lr: coords[2],
-                            ur: coords[3],
-                            power: power_mw,
-                            area,
-                            temperature,
-                            mask_flag: MaskCode(mask),
-                            data_quality_flag: DataQualityFlagCode(dqf),
-                            scan_angle,
-                        },
-                    });
-                }
-            }
-        }
-
-        Ok(points)
+        Ok(vars.fire_points(&self.tran, self.terrain.as_ref(), scan_duration, qc))
     }
 
     fn extract_variable_double(&self, vname: *const c_char) -> SatFireResult<Vec<f64>> {
@@ -389,6 +583,102 @@ impl Drop for SatFireImage {
     }
 }
 
+/// The fire variables of an image, each in row major order.
+struct FireVariables {
+    /// Image width in pixels
+    xlen: usize,
+    /// Image height in pixels
+    ylen: usize,
+    powers: Vec<f64>,
+    areas: Vec<f64>,
+    temperatures: Vec<f64>,
+    masks: Vec<i16>,
+    dqfs: Vec<i16>,
+}
+
+impl FireVariables {
+    /// Create the fire points for the pixels with a DQF accepted by the quality control.
+    fn fire_points(
+        &self,
+        tran: &CoordTransform,
+        terrain: Option<&TerrainHeight>,
+        scan_duration: Duration,
+        qc: &ClusterQc,
+    ) -> Vec<FirePoint> {
+        // The unchecked indexing below relies on this.
+        let size = self.xlen * self.ylen;
+        assert!(
+            self.powers.len() == size
+                && self.areas.len() == size
+                && self.temperatures.len() == size
+                && self.masks.len() == size
+                && self.dqfs.len() == size
+        );
+
+        let mut points: Vec<FirePoint> = Vec::new();
+
+        for j in 0..self.ylen {
+            for i in 0..self.xlen {
+                let index = i + j * self.xlen;
+
+                let power_mw;
+                let area;
+                let temperature;
+                let mask;
+                let dqf;
+
+                unsafe {
+                    power_mw = *self.powers.get_unchecked(index);
+                    area = *self.areas.get_unchecked(index);
+                    temperature = *self.temperatures.get_unchecked(index);
+                    mask = *self.masks.get_unchecked(index);
+                    dqf = *self.dqfs.get_unchecked(index);
+                }
+
+                // By default only 0, a good quality fire detection, is accepted.
+                if qc.accepts_dqf(DataQualityFlagCode(dqf)) {
+                    let ii = i as f64;
+                    let jj = j as f64;
+
+                    let ips: [f64; 5] = [ii - 0.5, ii - 0.5, ii + 0.5, ii + 0.5, ii];
+                    let jps: [f64; 5] = [jj - 0.5, jj + 0.5, jj + 0.5, jj - 0.5, jj];
+
+                    let (scan_angle, coords) = tran.convert_row_cols_to_latlon(&jps, &ips, terrain);
+
+                    points.push(FirePoint {
+                        x: i as isize,
+                        y: j as isize,
+                        pixel: Pixel {
+                            ul: coords[0],
+                            ll: coords[1],
+                            lr: coords[2],
+                            ur: coords[3],
+                            power: power_mw,
+                            area,
+                            temperature,
+                            mask_flag: MaskCode(mask),
+                            data_quality_flag: DataQualityFlagCode(dqf),
+                            scan_angle,
+                            acquisition_offset: acquisition_offset(j, self.ylen, scan_duration),
+                        },
+                    });
+                }
+            }
+        }
+
+        points
+    }
+}
+
+/// Estimate how long after the scan start a row of the image was acquired, in seconds.
+///
+/// This assumes the rows are scanned from north to south (top to bottom) at a constant rate
+/// during the scan, and uses the time at the middle of the row.
+fn acquisition_offset(row: usize, num_rows: usize, scan_duration: Duration) -> f32 {
+    let scan_seconds = scan_duration.num_milliseconds() as f64 / 1000.0;
+    ((row as f64 + 0.5) / num_rows as f64 * scan_seconds) as f32
+}
+
 /**
  * Represents all the data associated with a single pixel in which the satellite has detected
  * a fire.
@@ -403,6 +693,51 @@ pub(crate) struct FirePoint {
     pub y: isize,
 }
 
+/// The attributes of the GOES fixed grid projection as stored in the files.
+///
+/// Missing attributes are NaN.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy)]
+struct ProjectionParameters {
+    /// The semi_major_axis, radius of the Earth at the equator in meters.
+    req: f64,
+    /// The semi_minor_axis, radius of the Earth at the poles in meters.
+    rpol: f64,
+    /// The perspective_point_height, height of the satellite above the equator in meters.
+    H: f64,
+    /// The longitude_of_projection_origin, longitude of the nadir point in degrees.
+    lon0: f64,
+}
+
+impl ProjectionParameters {
+    fn missing() -> Self {
+        ProjectionParameters {
+            req: f64::NAN,
+            rpol: f64::NAN,
+            H: f64::NAN,
+            lon0: f64::NAN,
+        }
+    }
+
+    /// Fill in any missing attributes with the published values for the satellite.
+    fn or_satellite_default(self, sat: Satellite) -> Self {
+        let or = |val: f64, default: f64| if val.is_nan() { default } else { val };
+
+        ProjectionParameters {
+            req: or(self.req, GOES_SEMI_MAJOR_AXIS),
+            rpol: or(self.rpol, GOES_SEMI_MINOR_AXIS),
+            H: or(self.H, GOES_PERSPECTIVE_POINT_HEIGHT),
+            lon0: or(self.lon0, sat.longitude_of_projection_origin()),
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        [self.req, self.rpol, self.H, self.lon0]
+            .iter()
+            .all(|val| !val.is_nan())
+    }
+}
+
 /// Projection information required to convert from row/column number to scan angles and lat-lon.
 #[allow(non_snake_case)]
 #[derive(Debug, Clone, Copy)]
@@ -426,8 +761,19 @@ struct CoordTransform {
 }
 
 impl CoordTransform {
-    #[allow(non_snake_case)]
-    fn convert_row_cols_to_latlon(&self, rows: &[f64; 5], cols: &[f64; 5]) -> (f64, [Coord; 5]) {
+    /// Convert the row and column numbers of the 4 corners and the center of a pixel to a scan
+    /// angle and coordinates.
+    ///
+    /// Without a `terrain` height the coordinates are where the line of sight intersects the
+    /// ellipsoid. With it, the line of sight is intersected with the ellipsoid raised by the
+    /// elevation found at the uncorrected location, which shifts the coordinates toward the
+    /// sub-satellite point.
+    fn convert_row_cols_to_latlon(
+        &self,
+        rows: &[f64; 5],
+        cols: &[f64; 5],
+        terrain: Option<&TerrainHeight>,
+    ) -> (f64, [Coord; 5]) {
         let mut coords = [Coord { lat: 0.0, lon: 0.0 }; 5];
 
         let x = self.xscale * cols[4] + self.xoffset;
@@ -438,36 +784,50 @@ impl CoordTransform {
             let x = self.xscale * cols[i] + self.xoffset;
             let y = self.yscale * rows[i] + self.yoffset;
 
-            let sinx = x.sin();
-            let cosx = x.cos();
-            let siny = y.sin();
-            let cosy = y.cos();
-            let req = self.req;
-            let rpol = self.rpol;
-            let H = self.H;
-            let lon0 = self.lon0;
-
-            let a =
-                sinx * sinx + cosx * cosx * (cosy * cosy + req * req / (rpol * rpol) * siny * siny);
-            let b = -2.0 * H * cosx * cosy;
-            let c = H * H - req * req;
-
-            let rs = (-b - (b * b - 4.0 * a * c).sqrt()) / (2.0 * a);
-
-            let sx = rs * cosx * cosy;
-            let sy = -rs * sinx;
-            let sz = rs * cosx * siny;
-
-            let lat = (req * req * sz)
-                .atan2(rpol * rpol * ((H - sx) * (H - sx) + sy * sy).sqrt())
-                .to_degrees();
-            let lon = lon0 - (sy.atan2(H - sx)).to_degrees();
+            let mut coord = self.scan_angles_to_latlon(x, y, 0.0);
+            if let Some(terrain) = terrain {
+                let height = terrain.height_at(coord);
+                if height != 0.0 && !height.is_nan() {
+                    coord = self.scan_angles_to_latlon(x, y, height);
+                }
+            }
 
-            coords[i] = Coord { lat, lon };
+            coords[i] = coord;
         }
 
         (scan_angle, coords)
     }
+
+    /// Intersect the line of sight at scan angles `x` and `y` (radians) with the ellipsoid raised
+    /// by `height` meters.
+    #[allow(non_snake_case)]
+    fn scan_angles_to_latlon(&self, x: f64, y: f64, height: f64) -> Coord {
+        let sinx = x.sin();
+        let cosx = x.cos();
+        let siny = y.sin();
+        let cosy = y.cos();
+        let req = self.req + height;
+        let rpol = self.rpol + height;
+        let H = self.H;
+        let lon0 = self.lon0;
+
+        let a = sinx * sinx + cosx * cosx * (cosy * cosy + req * req / (rpol * rpol) * siny * siny);
+        let b = -2.0 * H * cosx * cosy;
+        let c = H * H - req * req;
+
+        let rs = (-b - (b * b - 4.0 * a * c).sqrt()) / (2.0 * a);
+
+        let sx = rs * cosx * cosy;
+        let sy = -rs * sinx;
+        let sz = rs * cosx * siny;
+
+        let lat = (req * req * sz)
+            .atan2(rpol * rpol * ((H - sx) * (H - sx) + sy * sy).sqrt())
+            .to_degrees();
+        let lon = lon0 - (sy.atan2(H - sx)).to_degrees();
+
+        Coord { lat, lon }
+    }
 }
 
 static NETCDF_GLOBAL_LOCK: OnceCell<Mutex<()>> = OnceCell::new();
@@ -554,3 +914,246 @@ extern "C" {
         vals: *mut c_double,
     ) -> c_int;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        pixel::PixelList,
+        testutil::{cluster_from_pixels, PixelGrid},
+    };
+    use chrono::{DateTime, Utc};
+
+    #[test]
+    fn test_read_nc_from_zip() {
+        use std::io::{Cursor, Write};
+
+        let zip_of = |members: &[(&str, &[u8])]| {
+            let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            for (name, data) in members {
+                zip.start_file(*name, zip::write::FileOptions::default())
+                    .unwrap();
+                zip.write_all(data).unwrap();
+            }
+            let buf = zip.finish().unwrap().into_inner();
+            zip::ZipArchive::new(Cursor::new(buf)).unwrap()
+        };
+
+        let mut zip = zip_of(&[
+            ("README.txt", b"Not data."),
+            ("OR_ABI-L2-FDCC-M6_G17_s20212130101177.nc", b"netcdf bytes"),
+        ]);
+        assert_eq!(
+            SatFireImage::read_nc_from_zip(&mut zip).unwrap(),
+            b"netcdf bytes"
+        );
+
+        let mut zip = zip_of(&[("README.txt", b"Not data.")]);
+        assert!(SatFireImage::read_nc_from_zip(&mut zip).is_err());
+
+        let mut zip = zip_of(&[("a.nc", b"one"), ("b.nc", b"two")]);
+        assert!(SatFireImage::read_nc_from_zip(&mut zip).is_err());
+    }
+
+    #[test]
+    fn test_projection_fallback() {
+        // A file without the perspective_point_height.
+        let mut proj = ProjectionParameters::missing();
+        proj.req = 6_378_137.0;
+        proj.rpol = 6_356_752.314_14;
+        proj.lon0 = -137.0;
+        assert!(!proj.is_complete());
+
+        let filled = proj.or_satellite_default(Satellite::G16);
+        assert!(filled.is_complete());
+        assert_eq!(filled.H, 35_786_023.0);
+        // The values that were in the file are kept.
+        assert_eq!(filled.lon0, -137.0);
+
+        // A file without the projection variable at all.
+        let proj = ProjectionParameters::missing().or_satellite_default(Satellite::G16);
+        assert!(proj.is_complete());
+        assert_eq!(proj.lon0, -75.0);
+
+        // The sub-satellite point of the fallback projection is on the equator below the satellite.
+        let tran = CoordTransform {
+            xscale: 1.0,
+            xoffset: 0.0,
+            yscale: 1.0,
+            yoffset: 0.0,
+            req: proj.req,
+            rpol: proj.rpol,
+            H: proj.H + proj.req,
+            lon0: proj.lon0,
+        };
+        let (scan_angle, coords) = tran.convert_row_cols_to_latlon(&[0.0; 5], &[0.0; 5], None);
+        assert_eq!(scan_angle, 0.0);
+        assert!(coords[4].is_close(
+            Coord {
+                lat: 0.0,
+                lon: -75.0
+            },
+            1.0e-9
+        ));
+    }
+
+    #[test]
+    fn test_parallax_correction() {
+        let proj = ProjectionParameters::missing().or_satellite_default(Satellite::G17);
+        let tran = CoordTransform {
+            xscale: 1.0,
+            xoffset: 0.0,
+            yscale: 1.0,
+            yoffset: 0.0,
+            req: proj.req,
+            rpol: proj.rpol,
+            H: proj.H + proj.req,
+            lon0: proj.lon0,
+        };
+        let sub_point = Coord {
+            lat: 0.0,
+            lon: proj.lon0,
+        };
+
+        // Scan angles in radians looking north east of the sub-satellite point, into the western
+        // US.
+        let rows = [0.1; 5];
+        let cols = [0.05; 5];
+
+        let (_, surface) = tran.convert_row_cols_to_latlon(&rows, &cols, None);
+        let (_, sea_level) =
+            tran.convert_row_cols_to_latlon(&rows, &cols, Some(&TerrainHeight::Constant(0.0)));
+        assert!(surface[4].is_close(sea_level[4], 1.0e-12));
+
+        let mountain = TerrainHeight::Constant(3000.0);
+        let (_, elevated) = tran.convert_row_cols_to_latlon(&rows, &cols, Some(&mountain));
+        assert!(elevated[4].distance_meters(sub_point) < surface[4].distance_meters(sub_point));
+        // Several kilometers at this viewing angle.
+        let shift = elevated[4].distance_meters(surface[4]);
+        assert!(shift > 1_000.0 && shift < 10_000.0, "shift = {} m", shift);
+
+        // A DEM lookup is evaluated at the uncorrected location.
+        let expected = surface[4];
+        let dem = TerrainHeight::from_lookup(move |coord| {
+            assert!(coord.is_close(expected, 1.0e-9));
+            3000.0
+        });
+        let (_, from_dem) = tran.convert_row_cols_to_latlon(&rows, &cols, Some(&dem));
+        assert!(from_dem[4].is_close(elevated[4], 1.0e-12));
+    }
+
+    #[test]
+    fn test_extract_fire_points() {
+        use crate::geo::Geo;
+
+        let proj = ProjectionParameters::missing().or_satellite_default(Satellite::G17);
+        // About 2 km pixels in the western US, rows go from north to south.
+        let tran = CoordTransform {
+            xscale: 5.6e-5,
+            xoffset: 0.05,
+            yscale: -5.6e-5,
+            yoffset: 0.1,
+            req: proj.req,
+            rpol: proj.rpol,
+            H: proj.H + proj.req,
+            lon0: proj.lon0,
+        };
+
+        let vars = FireVariables {
+            xlen: 3,
+            ylen: 2,
+            powers: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+            areas: vec![10.0; 6],
+            temperatures: vec![600.0; 6],
+            masks: vec![10, 11, 12, 13, 14, 15],
+            dqfs: vec![0, 1, 0, 0, 0, 2],
+        };
+
+        let points = vars.fire_points(&tran, None, Duration::seconds(300), &ClusterQc::default());
+
+        let cells: Vec<_> = points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(cells, vec![(0, 0), (2, 0), (0, 1), (1, 1)]);
+
+        let powers: Vec<_> = points.iter().map(|p| p.pixel.power).collect();
+        assert_eq!(powers, vec![1.0, 3.0, 4.0, 5.0]);
+        assert_eq!(points[1].pixel.mask_flag.0, 12);
+
+        // The top row is acquired in the first half of the scan and the bottom row in the second.
+        let offsets: Vec<_> = points.iter().map(|p| p.pixel.acquisition_offset).collect();
+        assert_eq!(offsets, vec![75.0, 75.0, 225.0, 225.0]);
+
+        // The second row is south of the first and the pixels share an edge.
+        let (upper, lower) = (&points[0].pixel, &points[2].pixel);
+        assert!(lower.centroid().lat < upper.centroid().lat);
+        assert!(upper.ll.is_close(lower.ul, 1.0e-9));
+        assert!(upper.lr.is_close(lower.ur, 1.0e-9));
+    }
+
+    #[test]
+    fn test_acquisition_offset_within_scan() {
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::seconds(299);
+
+        const NUM_ROWS: usize = 1500;
+        const GRID: PixelGrid = PixelGrid {
+            origin: Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            cell_size: 1.0,
+        };
+
+        let mut last = start;
+        for row in 0..NUM_ROWS {
+            let mut pixel = GRID.pixel(0, 0, 10.0);
+            pixel.acquisition_offset = acquisition_offset(row, NUM_ROWS, end - start);
+            let when = pixel.acquisition_time(start);
+
+            assert!(when >= start && when <= end);
+            assert!(when >= last);
+            last = when;
+        }
+
+        // The middle of the image is about the middle of the scan.
+        let middle = acquisition_offset(NUM_ROWS / 2, NUM_ROWS, end - start);
+        assert!((middle - 149.6).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_area_disagrees() {
+        // A 0.02 degree pixel that covers about 3.5 million square meters.
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.01,
+                lon: -120.01,
+            },
+            0.02,
+        );
+        let with_area = |area: f64| {
+            let mut plist = PixelList::new();
+            plist.push(Pixel {
+                area,
+                ..grid.pixel(0, 0, 10.0)
+            });
+            cluster_from_pixels(plist)
+        };
+
+        let geographic_area = with_area(1.0).total_geographic_area_m2();
+        assert!(geographic_area > 3.0e6 && geographic_area < 4.0e6);
+
+        let plausible = with_area(2.0e6);
+        let implausible = with_area(1.0);
+        let missing = with_area(0.0);
+
+        // Off by default.
+        assert!(!LoadOptions::default().area_disagrees(&implausible));
+
+        let options = LoadOptions::default().warn_area_ratio(100.0);
+        assert!(!options.area_disagrees(&plausible));
+        assert!(options.area_disagrees(&implausible));
+        assert!(!options.area_disagrees(&missing));
+
+        // It's only a warning, the cluster still passes quality control.
+        assert!(ClusterQc::default().is_keeper(&implausible));
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Display;
 
 /// A coordinate consisting of a latitude and a longitude.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Coord {
     /// Latitude. Should be -90 to 90, but that's not checked or enforced.
@@ -23,10 +24,89 @@ impl Coord {
 
         distance_squared <= (eps * eps)
     }
+
+    /// Calculate the great circle distance to another point on a spherical Earth, meters.
+    ///
+    /// Unlike [Coord::is_close], this is a physical distance on the surface of the Earth. It uses
+    /// the haversine formula on a sphere with the mean radius of the [WGS84](Ellipsoid::WGS84)
+    /// ellipsoid.
+    pub fn distance_meters(&self, other: Coord) -> f64 {
+        self.distance_meters_with_ellipsoid(other, &Ellipsoid::WGS84)
+    }
+
+    /// Calculate the great circle distance to another point on a sphere with the
+    /// [mean radius](Ellipsoid::mean_radius) of `ellipsoid`, meters.
+    pub fn distance_meters_with_ellipsoid(&self, other: Coord, ellipsoid: &Ellipsoid) -> f64 {
+        self.distance_meters_with_radius(other, ellipsoid.mean_radius())
+    }
+
+    /// Calculate the great circle distance to another point on a sphere with the given radius.
+    ///
+    /// The distance is in the same units as the radius.
+    pub fn distance_meters_with_radius(&self, other: Coord, radius: f64) -> f64 {
+        let lat1 = self.lat.to_radians();
+        let lat2 = other.lat.to_radians();
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+
+        2.0 * radius * a.sqrt().min(1.0).asin()
+    }
+
+    /// Calculate the initial bearing of the great circle route to another point, degrees
+    /// clockwise from north from 0 up to 360.
+    pub fn bearing_to(&self, other: Coord) -> f64 {
+        let lat1 = self.lat.to_radians();
+        let lat2 = other.lat.to_radians();
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Project to Web Mercator (EPSG:3857), returning `(x, y)` in meters.
+    ///
+    /// Latitudes beyond the limits of the projection, about ±85.0511°, are clamped to them.
+    pub fn to_web_mercator(&self) -> (f64, f64) {
+        let lat = self
+            .lat
+            .clamp(-WEB_MERCATOR_MAX_LATITUDE, WEB_MERCATOR_MAX_LATITUDE)
+            .to_radians();
+
+        let x = WEB_MERCATOR_RADIUS * self.lon.to_radians();
+        let y = WEB_MERCATOR_RADIUS * (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln();
+
+        (x, y)
+    }
+
+    /// Create a Coord from Web Mercator (EPSG:3857) coordinates in meters.
+    ///
+    /// This is the inverse of [Coord::to_web_mercator].
+    pub fn from_web_mercator(x: f64, y: f64) -> Coord {
+        let lon = (x / WEB_MERCATOR_RADIUS).to_degrees();
+        let lat = (2.0 * (y / WEB_MERCATOR_RADIUS).exp().atan() - std::f64::consts::FRAC_PI_2)
+            .to_degrees()
+            .clamp(-WEB_MERCATOR_MAX_LATITUDE, WEB_MERCATOR_MAX_LATITUDE);
+
+        Coord { lat, lon }
+    }
 }
 
+/// The radius of the sphere used by Web Mercator, the WGS84 semi-major axis in meters.
+const WEB_MERCATOR_RADIUS: f64 = 6_378_137.0;
+
+/// The largest latitude shown on Web Mercator maps, where the map becomes square.
+const WEB_MERCATOR_MAX_LATITUDE: f64 = 85.051_128_779_806_6;
+
 /// Represents a "square" area in latitude-longitude coordinates.
+///
+/// A box with `ll.lon` greater than `ur.lon` wraps around the antimeridian, so the box from 170
+/// to -170 degrees longitude is 20 degrees wide, not 340.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoundingBox {
     /// The lower left corner of the box.  
     pub ll: Coord,
@@ -74,6 +154,13 @@ impl BoundingBox {
     ///
     /// Returns `true` if the point `coord` is interior to the `box`.
     pub fn contains_coord(&self, coord: Coord, eps: f64) -> bool {
+        let (east, west) = self.split_at_antimeridian();
+
+        east.contains_coord_unwrapped(coord, eps)
+            || west.is_some_and(|west| west.contains_coord_unwrapped(coord, eps))
+    }
+
+    fn contains_coord_unwrapped(&self, coord: Coord, eps: f64) -> bool {
         let lon_in_range = (coord.lon - self.ur.lon) < eps && (coord.lon - self.ll.lon) > -eps;
         let lat_in_range = (coord.lat - self.ur.lat) < eps && (coord.lat - self.ll.lat) > -eps;
 
@@ -87,6 +174,18 @@ impl BoundingBox {
     /// * `eps` - is a fuzzy factor. In any point comparisons, any point within 'eps' close to the
     ///    box `self` will be considered as overlapping.
     pub fn overlap(&self, other: &BoundingBox, eps: f64) -> bool {
+        let (east1, west1) = self.split_at_antimeridian();
+        let (east2, west2) = other.split_at_antimeridian();
+
+        [Some(east1), west1].into_iter().flatten().any(|part1| {
+            [Some(east2), west2]
+                .into_iter()
+                .flatten()
+                .any(|part2| part1.overlap_unwrapped(&part2, eps))
+        })
+    }
+
+    fn overlap_unwrapped(&self, other: &BoundingBox, eps: f64) -> bool {
         let BoundingBox {
             ll: Coord {
                 lat: lly1,
@@ -145,6 +244,60 @@ impl BoundingBox {
     }
 }
 
+impl BoundingBox {
+    /// Does this box wrap around the antimeridian?
+    ///
+    /// A box with an infinite or NaN longitude, such as the bounding box of an empty list, never
+    /// wraps.
+    pub fn wraps_antimeridian(&self) -> bool {
+        self.ll.lon.is_finite() && self.ur.lon.is_finite() && self.ll.lon > self.ur.lon
+    }
+
+    /// Split a box that wraps around the antimeridian into the part east of it, up to 180 degrees
+    /// longitude, and the part west of it, from -180 degrees.
+    ///
+    /// A box that doesn't wrap is returned unchanged.
+    fn split_at_antimeridian(&self) -> (BoundingBox, Option<BoundingBox>) {
+        if !self.wraps_antimeridian() {
+            return (*self, None);
+        }
+
+        let east = BoundingBox {
+            ll: self.ll,
+            ur: Coord {
+                lat: self.ur.lat,
+                lon: 180.0,
+            },
+        };
+        let west = BoundingBox {
+            ll: Coord {
+                lat: self.ll.lat,
+                lon: -180.0,
+            },
+            ur: self.ur,
+        };
+
+        (east, Some(west))
+    }
+
+    /// Grow this box to cover `other` too.
+    ///
+    /// A box that wraps around the antimeridian is treated as covering all longitudes, so the
+    /// result always covers both boxes, though it may be much larger than needed.
+    pub(crate) fn expand_to_include(&mut self, other: &BoundingBox) {
+        let (ll_lon, ur_lon) = if other.wraps_antimeridian() {
+            (-180.0, 180.0)
+        } else {
+            (other.ll.lon, other.ur.lon)
+        };
+
+        self.ll.lat = self.ll.lat.min(other.ll.lat);
+        self.ll.lon = self.ll.lon.min(ll_lon);
+        self.ur.lat = self.ur.lat.max(other.ur.lat);
+        self.ur.lon = self.ur.lon.max(ur_lon);
+    }
+}
+
 /// Some simple geographic operations.
 pub trait Geo {
     /// Get the centroid of the object.
@@ -171,9 +324,12 @@ pub(crate) fn triangle_centroid(v1: Coord, v2: Coord, v3: Coord) -> Coord {
  *                                    Helper types and functions
  *-----------------------------------------------------------------------------------------------*/
 
+/// A line segment between two coordinates.
 #[derive(Debug, Clone, Copy)]
-pub(crate) struct Line {
+pub struct Line {
+    /// One end of the segment.
     pub start: Coord,
+    /// The other end of the segment.
     pub end: Coord,
 }
 
@@ -189,7 +345,7 @@ impl Line {
         (num * num / denom2) <= eps2
     }
 
-    pub fn intersect(&self, other: Line, eps: f64) -> Option<IntersectResult> {
+    pub(crate) fn intersect(&self, other: Line, eps: f64) -> Option<IntersectResult> {
         // Check if they are nearly co-linear
         let mut num_close = 0;
         if self.is_close(other.start, eps) {
@@ -287,6 +443,51 @@ impl Line {
 
         Some(result)
     }
+
+    /// Find where this segment crosses another with both treated as great circle arcs.
+    ///
+    /// The pixel overlap checks in this crate intersect straight lines in the latitude-longitude
+    /// plane, which is fine for pixels but gets worse for long segments and near the poles. This
+    /// intersects the arcs on a sphere instead, so a segment can even pass over a pole. Returns
+    /// `None` if the arcs don't cross, if they are on the same great circle, or if either one has
+    /// its ends at the same or antipodal points, since then the arc isn't defined.
+    pub fn intersect_great_circle(&self, other: Line) -> Option<Coord> {
+        // In radians, a few millimeters on the surface of the Earth.
+        const TOLERANCE: f64 = 1.0e-9;
+
+        let normalize = |v: [f64; 3]| {
+            let norm = dot(v, v).sqrt();
+            if norm > 1.0e-15 {
+                Some([v[0] / norm, v[1] / norm, v[2] / norm])
+            } else {
+                None
+            }
+        };
+
+        let angle = |u: [f64; 3], v: [f64; 3]| {
+            let c = cross(u, v);
+            dot(c, c).sqrt().atan2(dot(u, v))
+        };
+
+        let on_arc = |p: [f64; 3], start: [f64; 3], end: [f64; 3]| {
+            angle(start, p) + angle(p, end) - angle(start, end) <= TOLERANCE
+        };
+
+        let (a1, a2) = (unit_vector(self.start), unit_vector(self.end));
+        let (b1, b2) = (unit_vector(other.start), unit_vector(other.end));
+
+        let n1 = normalize(cross(a1, a2))?;
+        let n2 = normalize(cross(b1, b2))?;
+
+        // The great circles cross at two antipodal points, at most one of them is on both arcs.
+        let p = normalize(cross(n1, n2))?;
+        let antipode = [-p[0], -p[1], -p[2]];
+
+        [p, antipode]
+            .into_iter()
+            .find(|&p| on_arc(p, a1, a2) && on_arc(p, b1, b2))
+            .map(from_unit_vector)
+    }
 }
 
 pub(crate) struct IntersectResult {
@@ -294,8 +495,212 @@ pub(crate) struct IntersectResult {
     pub intersect_is_endpoints: bool,
 }
 
+/// The mean radius of the Earth in meters, used when treating the Earth as a sphere.
+///
+/// This is the [mean radius](Ellipsoid::mean_radius) of [Ellipsoid::WGS84] rounded to a tenth of
+/// a meter.
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// The shape of the Earth as an ellipsoid of revolution, with the axes in meters.
+///
+/// The distance and area calculations in this crate treat the Earth as a sphere, the ellipsoid
+/// picks the radius of that sphere. The default is [Ellipsoid::WGS84]. The GOES projection uses
+/// [Ellipsoid::GRS80], which differs from WGS84 by a fraction of a millimeter at the poles, so the
+/// calculations on pixels, clusters, and fires, which all come from that projection, use GRS80.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ellipsoid {
+    /// The semi-major axis, the radius at the equator.
+    pub req: f64,
+    /// The semi-minor axis, the radius at the poles.
+    pub rpol: f64,
+}
+
+impl Ellipsoid {
+    /// The World Geodetic System 1984 ellipsoid.
+    pub const WGS84: Ellipsoid = Ellipsoid {
+        req: 6_378_137.0,
+        rpol: 6_356_752.314_245,
+    };
+
+    /// The Geodetic Reference System 1980 ellipsoid, as used by the GOES fixed grid projection.
+    pub const GRS80: Ellipsoid = Ellipsoid {
+        req: crate::satellite::GOES_SEMI_MAJOR_AXIS,
+        rpol: crate::satellite::GOES_SEMI_MINOR_AXIS,
+    };
+
+    /// A sphere with the given radius.
+    pub const fn sphere(radius: f64) -> Self {
+        Ellipsoid {
+            req: radius,
+            rpol: radius,
+        }
+    }
+
+    /// The arithmetic mean of the three semi-axes, used for distances.
+    pub fn mean_radius(&self) -> f64 {
+        (2.0 * self.req + self.rpol) / 3.0
+    }
+
+    /// The radius of the sphere with the same surface area as the ellipsoid, used for areas.
+    pub fn authalic_radius(&self) -> f64 {
+        let e = (1.0 - (self.rpol * self.rpol) / (self.req * self.req))
+            .max(0.0)
+            .sqrt();
+
+        if e < 1.0e-9 {
+            return self.req;
+        }
+
+        let factor = 1.0 + (1.0 - e * e) / (2.0 * e) * ((1.0 + e) / (1.0 - e)).ln();
+        self.req * (factor / 2.0).sqrt()
+    }
+}
+
+impl Default for Ellipsoid {
+    fn default() -> Self {
+        Ellipsoid::WGS84
+    }
+}
+
+/// Calculate the area in square meters of a latitude-longitude grid cell on a spherical Earth.
+///
+/// The cell spans from latitude `south` to `north` and is `width` degrees of longitude wide.
+pub(crate) fn lat_lon_cell_area(south: f64, north: f64, width: f64, ellipsoid: &Ellipsoid) -> f64 {
+    let radius = ellipsoid.authalic_radius();
+
+    radius
+        * radius
+        * width.to_radians()
+        * (north.to_radians().sin() - south.to_radians().sin()).abs()
+}
+
+/// Calculate the area in square meters of a polygon with great circle edges on a spherical Earth.
+///
+/// The vertices must be in order around the polygon, in either direction, and the polygon must be
+/// smaller than a hemisphere. The polygon is split into a fan of triangles from the first vertex
+/// and the spherical excess of each is found from the unit vectors of its corners, so skewed
+/// quadrilaterals such as pixels near the limb of the disk need no special handling.
+pub(crate) fn spherical_excess_area(vertices: &[Coord], ellipsoid: &Ellipsoid) -> f64 {
+    if vertices.len() < 3 {
+        return 0.0;
+    }
+
+    let a = unit_vector(vertices[0]);
+
+    // The signed excess of each triangle, so concave polygons still come out right.
+    let excess: f64 = vertices[1..]
+        .windows(2)
+        .map(|pair| {
+            let b = unit_vector(pair[0]);
+            let c = unit_vector(pair[1]);
+
+            let numerator = dot(a, cross(b, c));
+            let denominator = 1.0 + dot(a, b) + dot(b, c) + dot(c, a);
+
+            2.0 * numerator.atan2(denominator)
+        })
+        .sum();
+
+    let radius = ellipsoid.authalic_radius();
+    (excess * radius * radius).abs()
+}
+
+/// The point on the unit sphere at a coordinate, in Earth centered coordinates.
+fn unit_vector(coord: Coord) -> [f64; 3] {
+    let (lat, lon) = (coord.lat.to_radians(), coord.lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// The coordinate of a point on the unit sphere, the inverse of [unit_vector].
+fn from_unit_vector(v: [f64; 3]) -> Coord {
+    Coord {
+        lat: v[2].atan2(v[0].hypot(v[1])).to_degrees(),
+        lon: v[1].atan2(v[0]).to_degrees(),
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Clip a polygon to the inside of a convex polygon with the Sutherland-Hodgman algorithm.
+///
+/// Both polygons are treated as planar in latitude and longitude, and their vertices may be in
+/// either direction. Vertices within `eps` of an edge of the clipping polygon count as inside it.
+/// If the polygons don't overlap, the result has fewer than 3 vertices.
+pub(crate) fn clip_to_convex_polygon(subject: &[Coord], clip: &[Coord], eps: f64) -> Vec<Coord> {
+    // The z component of the cross product of (b - a) and (p - a), positive if p is to the left.
+    fn cross(a: Coord, b: Coord, p: Coord) -> f64 {
+        (b.lon - a.lon) * (p.lat - a.lat) - (b.lat - a.lat) * (p.lon - a.lon)
+    }
+
+    let num_clip = clip.len();
+    if num_clip < 3 {
+        return vec![];
+    }
+
+    // Twice the signed area, positive if the clipping polygon is counter-clockwise.
+    let orientation: f64 = (0..num_clip)
+        .map(|i| {
+            let a = clip[i];
+            let b = clip[(i + 1) % num_clip];
+            a.lon * b.lat - b.lon * a.lat
+        })
+        .sum::<f64>()
+        .signum();
+
+    let mut output: Vec<Coord> = subject.to_vec();
+    for i in 0..num_clip {
+        let a = clip[i];
+        let b = clip[(i + 1) % num_clip];
+        let edge_length = (b.lon - a.lon).hypot(b.lat - a.lat);
+
+        // Signed distance from the edge line, positive on the inside.
+        let distance = |p: Coord| orientation * cross(a, b, p) / edge_length;
+
+        let input = std::mem::take(&mut output);
+        let num_input = input.len();
+        for j in 0..num_input {
+            let current = input[j];
+            let previous = input[(j + num_input - 1) % num_input];
+            let (d_current, d_previous) = (distance(current), distance(previous));
+
+            let intersection = || {
+                let t = d_previous / (d_previous - d_current);
+                Coord {
+                    lat: previous.lat + t * (current.lat - previous.lat),
+                    lon: previous.lon + t * (current.lon - previous.lon),
+                }
+            };
+
+            if d_current >= -eps {
+                if d_previous < -eps {
+                    output.push(intersection());
+                }
+                output.push(current);
+            } else if d_previous >= -eps {
+                output.push(intersection());
+            }
+        }
+
+        if output.is_empty() {
+            break;
+        }
+    }
+
+    output
+}
+
 mod hilbert_rtree;
-pub(crate) use hilbert_rtree::Hilbert2DRTreeView;
+pub use hilbert_rtree::Hilbert2DRTreeView;
 
 #[cfg(test)]
 mod test {
@@ -320,6 +725,306 @@ mod test {
         assert!(!left.is_close(right, 1.0e-8));
     }
 
+    #[test]
+    fn test_line_intersect_great_circle_small_segments() {
+        // Short segments crossing away from the poles nearly agree with the planar version.
+        let l1 = Line {
+            start: Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            end: Coord {
+                lat: 45.1,
+                lon: -119.9,
+            },
+        };
+        let l2 = Line {
+            start: Coord {
+                lat: 45.1,
+                lon: -120.0,
+            },
+            end: Coord {
+                lat: 45.0,
+                lon: -119.9,
+            },
+        };
+
+        let planar = l1.intersect(l2, 1.0e-9).unwrap().intersection;
+        let spherical = l1.intersect_great_circle(l2).unwrap();
+        assert!(spherical.is_close(planar, 1.0e-4));
+        assert!(!spherical.is_close(planar, 1.0e-9));
+
+        // The same segments on the other side of the Earth, so the wrong one of the two crossings
+        // of the great circles isn't picked.
+        let flip = |c: Coord| Coord {
+            lat: -c.lat,
+            lon: c.lon + 180.0,
+        };
+        let l3 = Line {
+            start: flip(l1.start),
+            end: flip(l1.end),
+        };
+        let l4 = Line {
+            start: flip(l2.start),
+            end: flip(l2.end),
+        };
+        let antipodal = l3.intersect_great_circle(l4).unwrap();
+        assert!(antipodal.is_close(flip(spherical), 1.0e-9));
+
+        // Segments that would only cross if they were longer.
+        let short = Line {
+            start: l1.start,
+            end: Coord {
+                lat: 45.02,
+                lon: -119.98,
+            },
+        };
+        assert!(short.intersect_great_circle(l2).is_none());
+        assert!(short.intersect(l2, 1.0e-9).is_none());
+
+        // Degenerate arcs and arcs on the same great circle.
+        let point = Line {
+            start: l1.start,
+            end: l1.start,
+        };
+        assert!(point.intersect_great_circle(l2).is_none());
+        assert!(l1.intersect_great_circle(l1).is_none());
+    }
+
+    #[test]
+    fn test_line_intersect_great_circle_over_the_pole() {
+        // Both of these go over the north pole.
+        let l1 = Line {
+            start: Coord {
+                lat: 80.0,
+                lon: 0.0,
+            },
+            end: Coord {
+                lat: 80.0,
+                lon: 180.0,
+            },
+        };
+        let l2 = Line {
+            start: Coord {
+                lat: 70.0,
+                lon: 90.0,
+            },
+            end: Coord {
+                lat: 85.0,
+                lon: -90.0,
+            },
+        };
+
+        let pole = l1.intersect_great_circle(l2).unwrap();
+        assert!((pole.lat - 90.0).abs() < 1.0e-6);
+
+        // In the latitude-longitude plane these don't come anywhere near the pole.
+        if let Some(planar) = l1.intersect(l2, 1.0e-9) {
+            assert!(planar.intersection.lat < 85.0);
+        }
+
+        // A long arc over the pole crosses a short one on the far side, where the short one bulges
+        // north of the parallel through its ends.
+        let meridian = Line {
+            start: Coord {
+                lat: 60.0,
+                lon: -100.0,
+            },
+            end: Coord {
+                lat: 10.0,
+                lon: 80.0,
+            },
+        };
+        let parallel = Line {
+            start: Coord {
+                lat: 45.0,
+                lon: 70.0,
+            },
+            end: Coord {
+                lat: 45.0,
+                lon: 90.0,
+            },
+        };
+        let crossing = meridian.intersect_great_circle(parallel).unwrap();
+        assert!((crossing.lon - 80.0).abs() < 1.0e-9);
+        assert!(crossing.lat > 45.0 && crossing.lat < 46.0);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_web_mercator() {
+        // Reference values from the EPSG:3857 formulas.
+        let (x, y) = Coord {lat: 45.0, lon: -120.0}.to_web_mercator();
+        assert!((x - -13_358_338.895_192_828).abs() < 1.0e-3);
+        assert!((y - 5_621_521.486_192_066).abs() < 1.0e-3);
+
+        // The corners of the world are at the edges of the square map.
+        let (x, y) = Coord {lat: WEB_MERCATOR_MAX_LATITUDE, lon: 180.0}.to_web_mercator();
+        assert!((x - 20_037_508.342_789_244).abs() < 1.0e-3);
+        assert!((y - 20_037_508.342_789_244).abs() < 1.0e-3);
+
+        // Latitudes past the edge of the map are clamped.
+        let (_, y_pole) = Coord {lat: 90.0, lon: 0.0}.to_web_mercator();
+        assert!((y_pole - y).abs() < 1.0e-6);
+        let (_, y_south) = Coord {lat: -89.0, lon: 0.0}.to_web_mercator();
+        assert!((y_south + y).abs() < 1.0e-6);
+        assert_eq!(Coord::from_web_mercator(0.0, 1.0e9).lat, WEB_MERCATOR_MAX_LATITUDE);
+
+        for lat in [-85.0, -45.5, 0.0, 12.25, 60.0, 85.0] {
+            for lon in [-179.9, -120.0, 0.0, 33.3, 180.0] {
+                let coord = Coord { lat, lon };
+                let (x, y) = coord.to_web_mercator();
+                assert!(Coord::from_web_mercator(x, y).is_close(coord, 1.0e-9));
+            }
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_city_pair_distances() {
+        let new_york = Coord {lat: 40.7128, lon: -74.0060};
+        let london = Coord {lat: 51.5074, lon: -0.1278};
+        let los_angeles = Coord {lat: 34.0522, lon: -118.2437};
+        let paris = Coord {lat: 48.8566, lon: 2.3522};
+        let sydney = Coord {lat: -33.8688, lon: 151.2093};
+        let tokyo = Coord {lat: 35.6762, lon: 139.6503};
+
+        // (from, to, distance in km)
+        let cases = [
+            (new_york, london, 5_570.0),
+            (los_angeles, new_york, 3_944.0),
+            (paris, london, 344.0),
+            (sydney, tokyo, 7_823.0),
+        ];
+
+        for (from, to, km) in cases {
+            let expected = km * 1_000.0;
+            assert!((from.distance_meters(to) - expected).abs() < 0.005 * expected);
+            assert!((to.distance_meters(from) - expected).abs() < 0.005 * expected);
+        }
+
+        // On a unit sphere the distance is the angle in radians.
+        let quarter = Coord {lat: 0.0, lon: 0.0}
+            .distance_meters_with_radius(Coord {lat: 0.0, lon: 90.0}, 1.0);
+        assert!((quarter - std::f64::consts::FRAC_PI_2).abs() < 1.0e-12);
+
+        assert!((new_york.bearing_to(london) - 51.2).abs() < 0.5);
+        assert!((paris.bearing_to(london) - 330.0).abs() < 0.5);
+        assert!((sydney.bearing_to(tokyo) - 350.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_spherical_excess_area() {
+        // One eighth of the sphere.
+        let octant = [
+            Coord { lat: 0.0, lon: 0.0 },
+            Coord {
+                lat: 0.0,
+                lon: 90.0,
+            },
+            Coord {
+                lat: 90.0,
+                lon: 0.0,
+            },
+        ];
+        let radius = Ellipsoid::WGS84.authalic_radius();
+        let expected = std::f64::consts::PI * radius * radius / 2.0;
+        assert!(
+            (spherical_excess_area(&octant, &Ellipsoid::WGS84) - expected).abs()
+                < 1.0e-9 * expected
+        );
+
+        let mut reversed = octant;
+        reversed.reverse();
+        assert!(
+            (spherical_excess_area(&reversed, &Ellipsoid::WGS84) - expected).abs()
+                < 1.0e-9 * expected
+        );
+
+        // Degenerate polygons have no area.
+        assert_eq!(spherical_excess_area(&octant[..2], &Ellipsoid::WGS84), 0.0);
+    }
+
+    #[test]
+    fn test_ellipsoid_radii() {
+        let wgs84 = Ellipsoid::default();
+        assert_eq!(wgs84, Ellipsoid::WGS84);
+
+        assert!((wgs84.mean_radius() - EARTH_RADIUS_METERS).abs() < 0.1);
+        assert!((wgs84.authalic_radius() - 6_371_007.181).abs() < 1.0e-3);
+
+        // GRS80 and WGS84 differ by about a tenth of a millimeter at the poles.
+        assert!((Ellipsoid::GRS80.authalic_radius() - wgs84.authalic_radius()).abs() < 1.0e-3);
+
+        let sphere = Ellipsoid::sphere(EARTH_RADIUS_METERS);
+        assert_eq!(sphere.mean_radius(), EARTH_RADIUS_METERS);
+        assert_eq!(sphere.authalic_radius(), EARTH_RADIUS_METERS);
+
+        let a = Coord {
+            lat: 45.0,
+            lon: -120.0,
+        };
+        let b = Coord {
+            lat: 46.0,
+            lon: -119.0,
+        };
+        assert_eq!(
+            a.distance_meters_with_ellipsoid(b, &sphere),
+            a.distance_meters_with_radius(b, EARTH_RADIUS_METERS)
+        );
+
+        // Areas scale with the square of the radius.
+        let cell = lat_lon_cell_area(45.0, 46.0, 1.0, &sphere);
+        let half = lat_lon_cell_area(
+            45.0,
+            46.0,
+            1.0,
+            &Ellipsoid::sphere(EARTH_RADIUS_METERS / 2.0),
+        );
+        assert!((cell - 4.0 * half).abs() < 1.0e-9 * cell);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_coord_serde_round_trip() {
+        let coord = Coord {
+            lat: 45.5,
+            lon: -120.25,
+        };
+
+        let json = serde_json::to_string(&coord).unwrap();
+        assert_eq!(json, r#"{"lat":45.5,"lon":-120.25}"#);
+
+        let back: Coord = serde_json::from_str(&json).unwrap();
+        assert_eq!((back.lat, back.lon), (coord.lat, coord.lon));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_bounding_box_serde_round_trip() {
+        let bbox = BoundingBox {
+            ll: Coord {
+                lat: 44.0,
+                lon: 170.0,
+            },
+            ur: Coord {
+                lat: 46.0,
+                lon: -170.0,
+            },
+        };
+
+        let json = serde_json::to_string(&bbox).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ll":{"lat":44.0,"lon":170.0},"ur":{"lat":46.0,"lon":-170.0}}"#
+        );
+
+        let back: BoundingBox = serde_json::from_str(&json).unwrap();
+        assert_eq!((back.ll.lat, back.ll.lon), (bbox.ll.lat, bbox.ll.lon));
+        assert_eq!((back.ur.lat, back.ur.lon), (bbox.ur.lat, bbox.ur.lon));
+        assert!(back.wraps_antimeridian());
+    }
+
     #[test]
     fn test_default_bounding_boxes_do_not_overlap() {
         let b1 = BoundingBox::default();
@@ -363,4 +1068,100 @@ mod test {
 
         assert!(!b5.overlap(&b6, 1.0e-9));
     }
+
+    #[test]
+    fn test_bounding_box_across_antimeridian() {
+        let wrapped = BoundingBox {
+            ll: Coord {
+                lat: 50.0,
+                lon: 170.0,
+            },
+            ur: Coord {
+                lat: 60.0,
+                lon: -170.0,
+            },
+        };
+        assert!(wrapped.wraps_antimeridian());
+        assert!(!BoundingBox::default().wraps_antimeridian());
+
+        let eps = 1.0e-9;
+        assert!(wrapped.contains_coord(
+            Coord {
+                lat: 55.0,
+                lon: 175.0
+            },
+            eps
+        ));
+        assert!(wrapped.contains_coord(
+            Coord {
+                lat: 55.0,
+                lon: -175.0
+            },
+            eps
+        ));
+        assert!(!wrapped.contains_coord(
+            Coord {
+                lat: 55.0,
+                lon: 0.0
+            },
+            eps
+        ));
+
+        let east = BoundingBox {
+            ll: Coord {
+                lat: 52.0,
+                lon: 172.0,
+            },
+            ur: Coord {
+                lat: 58.0,
+                lon: 178.0,
+            },
+        };
+        let west = BoundingBox {
+            ll: Coord {
+                lat: 52.0,
+                lon: -178.0,
+            },
+            ur: Coord {
+                lat: 58.0,
+                lon: -172.0,
+            },
+        };
+        let middle = BoundingBox {
+            ll: Coord {
+                lat: 52.0,
+                lon: 0.0,
+            },
+            ur: Coord {
+                lat: 58.0,
+                lon: 10.0,
+            },
+        };
+        assert!(wrapped.overlap(&east, eps));
+        assert!(wrapped.overlap(&west, eps));
+        assert!(east.overlap(&wrapped, eps));
+        assert!(west.overlap(&wrapped, eps));
+        assert!(!wrapped.overlap(&middle, eps));
+        assert!(!middle.overlap(&wrapped, eps));
+        assert!(wrapped.overlap(&wrapped, eps));
+
+        let mut grown = BoundingBox {
+            ll: Coord {
+                lat: f64::INFINITY,
+                lon: f64::INFINITY,
+            },
+            ur: Coord {
+                lat: -f64::INFINITY,
+                lon: -f64::INFINITY,
+            },
+        };
+        grown.expand_to_include(&east);
+        assert_eq!(grown.ll.lon, 172.0);
+        assert_eq!(grown.ur.lon, 178.0);
+        grown.expand_to_include(&wrapped);
+        assert_eq!(grown.ll.lon, -180.0);
+        assert_eq!(grown.ur.lon, 180.0);
+        assert_eq!(grown.ll.lat, 50.0);
+        assert_eq!(grown.ur.lat, 60.0);
+    }
 }
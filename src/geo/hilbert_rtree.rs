@@ -51,10 +51,7 @@ impl RTreeNode {
 
         let mut children_overlap = false;
         for (i, child_box) in children.iter().map(|c| c.bounding_box()).enumerate() {
-            bbox.ll.lat = bbox.ll.lat.min(child_box.ll.lat);
-            bbox.ll.lon = bbox.ll.lon.min(child_box.ll.lon);
-            bbox.ur.lat = bbox.ur.lat.max(child_box.ur.lat);
-            bbox.ur.lon = bbox.ur.lon.max(child_box.ur.lon);
+            bbox.expand_to_include(&child_box);
 
             // Only check if we haven't already found an overlap.
             if !children_overlap {
@@ -150,10 +147,7 @@ impl RTreeNode {
                     if local_updated {
                         let child_box = child.bounding_box();
 
-                        bbox.ll.lat = bbox.ll.lat.min(child_box.ll.lat);
-                        bbox.ll.lon = bbox.ll.lon.min(child_box.ll.lon);
-                        bbox.ur.lat = bbox.ur.lat.max(child_box.ur.lat);
-                        bbox.ur.lon = bbox.ur.lon.max(child_box.ur.lon);
+                        bbox.expand_to_include(&child_box);
 
                         updated = true;
                     }
@@ -200,6 +194,22 @@ impl RTreeNode {
         }
     }
 
+    /// Recursively collect the indexes of the leaves with bounding boxes that overlap `region`.
+    fn query(&self, region: &BoundingBox, buffer: &mut Vec<usize>) {
+        if !self.bounding_box().overlap(region, 0.0) {
+            return;
+        }
+
+        match self {
+            Self::Leaf { index, .. } => buffer.push(*index),
+            Self::Node { children, .. } => {
+                for child in children {
+                    child.query(region, buffer);
+                }
+            }
+        }
+    }
+
     fn get_indexes_of_potential_overlap(&self, buffer: &mut Vec<usize>) {
         match self {
             Self::Leaf { index, .. } => buffer.push(*index),
@@ -218,6 +228,42 @@ impl RTreeNode {
     }
 }
 
+/// A spatial index over a slice of [Geo] items.
+///
+/// The index is bulk loaded: all the items are sorted along a Hilbert curve by their centroids and
+/// the tree is built from the bottom up in one go. That makes building it fast and the tree well
+/// balanced, but items can't be added or removed afterwards, a new view has to be built instead.
+/// The view borrows the slice for as long as it lives, and query results are indexes into it.
+///
+/// ```
+/// use satfire::{BoundingBox, Coord, Geo, Hilbert2DRTreeView};
+///
+/// struct Site(Coord);
+///
+/// impl Geo for Site {
+///     fn centroid(&self) -> Coord {
+///         self.0
+///     }
+///
+///     fn bounding_box(&self) -> BoundingBox {
+///         BoundingBox { ll: self.0, ur: self.0 }
+///     }
+/// }
+///
+/// let mut sites: Vec<Site> = (0..10)
+///     .map(|i| Site(Coord { lat: 45.0, lon: -120.0 + i as f64 }))
+///     .collect();
+///
+/// let view = Hilbert2DRTreeView::build_for(&mut sites, None).unwrap();
+/// let region = BoundingBox {
+///     ll: Coord { lat: 44.0, lon: -117.5 },
+///     ur: Coord { lat: 46.0, lon: -115.5 },
+/// };
+///
+/// let mut hits = view.query(region);
+/// hits.sort_unstable();
+/// assert_eq!(hits, vec![3, 4]);
+/// ```
 #[derive(Debug)]
 pub struct Hilbert2DRTreeView<'a, T> {
     root: RTreeNode,
@@ -227,6 +273,10 @@ pub struct Hilbert2DRTreeView<'a, T> {
 
 impl<'a, T: Geo> Hilbert2DRTreeView<'a, T> {
     /// Build a view into the provided list.
+    ///
+    /// The `precomputed_domain` is the area covered by the Hilbert curve, it should contain the
+    /// centroids of all the items. If it is `None`, it is calculated from the data. Returns `None`
+    /// if `data` is empty.
     pub fn build_for(data: &'a mut [T], precomputed_domain: Option<BoundingBox>) -> Option<Self> {
         if data.is_empty() {
             return None;
@@ -283,13 +333,29 @@ impl<'a, T: Geo> Hilbert2DRTreeView<'a, T> {
         Some(Hilbert2DRTreeView { root, hc, data })
     }
 
+    /// Get the indexes of all the items with bounding boxes that overlap `region`.
+    ///
+    /// Boxes that only touch along an edge or at a corner count as overlapping. The indexes are
+    /// in no particular order.
+    pub fn query(&self, region: BoundingBox) -> Vec<usize> {
+        let mut buffer = vec![];
+        self.root.query(&region, &mut buffer);
+        buffer
+    }
+
+    /// The items in this view, the indexes returned by [Hilbert2DRTreeView::query] index into
+    /// this slice.
+    pub fn data(&self) -> &[T] {
+        self.data
+    }
+
     /// Apply a function to all elements with boundaries that overlap.
     ///
     /// If the closure returns `true`, then an element was updated and we need to update the upper
     /// levels of the bounding boxes.
     ///
     /// Returns `true` if `update` EVER returns `true`, that is if anything was ever updated.
-    pub fn foreach<V, F>(&mut self, region: BoundingBox, user_data: V, update: F) -> V
+    pub(crate) fn foreach<V, F>(&mut self, region: BoundingBox, user_data: V, update: F) -> V
     where
         F: FnMut(&mut T, usize, V) -> (bool, ControlFlow<V, V>) + Copy,
     {
@@ -305,7 +371,7 @@ impl<'a, T: Geo> Hilbert2DRTreeView<'a, T> {
     }
 
     /// Get the indexes of items which potentially overlap other items.
-    pub fn indexes_of_potential_overlap(&self) -> Vec<usize> {
+    pub(crate) fn indexes_of_potential_overlap(&self) -> Vec<usize> {
         let mut buffer = Vec::with_capacity(self.data.len() / 100);
 
         if self.root.num_children() > 0 {
@@ -330,10 +396,7 @@ impl<'a, T: Geo> Hilbert2DRTreeView<'a, T> {
         for item in data {
             let item_rect = item.bounding_box();
 
-            mbr.ll.lat = mbr.ll.lat.min(item_rect.ll.lat);
-            mbr.ll.lon = mbr.ll.lon.min(item_rect.ll.lon);
-            mbr.ur.lat = mbr.ur.lat.max(item_rect.ur.lat);
-            mbr.ur.lon = mbr.ur.lon.max(item_rect.ur.lon);
+            mbr.expand_to_include(&item_rect);
         }
 
         mbr
@@ -795,4 +858,96 @@ mod test {
             test_bb_for_hits(&mut rectangles, bb, num_hit);
         }
     }
+
+    #[test]
+    fn rtree_test_query_matches_brute_force() {
+        let mut rectangles = create_rectangles_for_rtree_view_test();
+        let boxes: Vec<BoundingBox> = rectangles.iter().map(|r| r.rect).collect();
+        let labels: Vec<String> = rectangles.iter().map(|r| r.label.clone()).collect();
+
+        let view = Hilbert2DRTreeView::build_for(&mut rectangles, None).unwrap();
+        assert_eq!(view.data().len(), boxes.len());
+
+        for i in 0..20 {
+            for j in 0..20 {
+                let region = BoundingBox {
+                    ll: Coord {
+                        lat: j as f64 * 0.5,
+                        lon: i as f64 * 0.75,
+                    },
+                    ur: Coord {
+                        lat: j as f64 * 0.5 + 1.25,
+                        lon: i as f64 * 0.75 + 2.0,
+                    },
+                };
+
+                let mut hits = view.query(region);
+                hits.sort_unstable();
+
+                let expected: Vec<usize> = (0..boxes.len())
+                    .filter(|&k| boxes[k].overlap(&region, 0.0))
+                    .collect();
+                assert_eq!(hits, expected, "{}", region);
+
+                for &k in &hits {
+                    assert_eq!(view.data()[k].label, labels[k]);
+                }
+            }
+        }
+
+        let nowhere = BoundingBox {
+            ll: Coord {
+                lat: 50.0,
+                lon: 50.0,
+            },
+            ur: Coord {
+                lat: 60.0,
+                lon: 60.0,
+            },
+        };
+        assert!(view.query(nowhere).is_empty());
+    }
+
+    #[test]
+    fn rtree_test_query_across_antimeridian() {
+        let lon_box = |label: &str, ll_lon: f64, ur_lon: f64| LabeledBB {
+            rect: BoundingBox {
+                ll: Coord {
+                    lat: 50.0,
+                    lon: ll_lon,
+                },
+                ur: Coord {
+                    lat: 51.0,
+                    lon: ur_lon,
+                },
+            },
+            label: label.to_owned(),
+        };
+
+        let mut rectangles = vec![
+            lon_box("east", 175.0, 176.0),
+            lon_box("west", -176.0, -175.0),
+            lon_box("straddle", 179.5, -179.5),
+            lon_box("far", 0.0, 1.0),
+        ];
+
+        let view = Hilbert2DRTreeView::build_for(&mut rectangles, None).unwrap();
+
+        let labels_for = |ll_lon: f64, ur_lon: f64| {
+            let region = lon_box("", ll_lon, ur_lon).rect;
+            let mut labels: Vec<&str> = view
+                .query(region)
+                .into_iter()
+                .map(|i| view.data()[i].label.as_str())
+                .collect();
+            labels.sort_unstable();
+            labels
+        };
+
+        assert_eq!(labels_for(170.0, -170.0), ["east", "straddle", "west"]);
+        assert_eq!(labels_for(174.0, 177.0), ["east"]);
+        assert_eq!(labels_for(-177.0, -174.0), ["west"]);
+        assert_eq!(labels_for(179.0, 180.0), ["straddle"]);
+        assert_eq!(labels_for(-1.0, 2.0), ["far"]);
+    }
 }
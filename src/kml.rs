@@ -6,15 +6,18 @@
 //! crate. However, when generating large KML files, it crashed because it took too much memory. So
 //! for this implementation I'm only implementing the parts I need with a focus on a more streaming
 //! type API. That means the user is responsible for closing all tags.
+//!
+//! There is also a reader for the fires written by [FireList::save_kmz](crate::FireList::save_kmz),
+//! so an exported file can be re-analyzed. It only understands the output of this module.
 
-use crate::SatFireResult;
-use chrono::{DateTime, Utc};
+use crate::{geo::Coord, SatFireResult};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use std::{
     fs::File,
-    io::{BufWriter, Write},
+    io::{BufRead, BufReader, BufWriter, Write},
     path::Path,
 };
-use zip::ZipWriter;
+use zip::{read::ZipFile, ZipArchive, ZipWriter};
 
 pub struct KmzFile(ZipWriter<BufWriter<File>>);
 
@@ -30,6 +33,18 @@ impl KmzFile {
         new.start_document()?;
         Ok(new)
     }
+
+    /// Finish the current KML document and start a new one in the archive at `name`.
+    ///
+    /// The first document is always "doc.kml", and it can refer to the others with a NetworkLink
+    /// using `name` as the href.
+    pub fn start_kml_file(&mut self, name: &str) -> SatFireResult<()> {
+        self.finish_document();
+
+        let kmz_opts = zip::write::FileOptions::default().compression_level(Some(9));
+        self.0.start_file(name, kmz_opts)?;
+        self.start_document()
+    }
 }
 
 impl KmlWriter for KmzFile {
@@ -244,6 +259,31 @@ pub trait KmlWriter {
         Ok(())
     }
 
+    /// Start a NetworkLink element.
+    fn start_network_link(&mut self, name: Option<&str>) -> SatFireResult<()> {
+        writeln!(self.output(), "<NetworkLink>")?;
+
+        if let Some(name) = name {
+            writeln!(self.output(), "<name>{}</name>", name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Close out a NetworkLink element.
+    fn finish_network_link(&mut self) -> SatFireResult<()> {
+        writeln!(self.output(), "</NetworkLink>")?;
+        Ok(())
+    }
+
+    /// Create a Link element.
+    ///
+    /// These should ONLY go inside a NetworkLink element.
+    fn create_link(&mut self, href: &str) -> SatFireResult<()> {
+        writeln!(self.output(), "<Link><href>{}</href></Link>", href)?;
+        Ok(())
+    }
+
     /// Start a MultiGeometry
     fn start_multi_geometry(&mut self) -> SatFireResult<()> {
         self.output().write_all("<MultiGeometry>\n".as_bytes())?;
@@ -350,3 +390,388 @@ pub trait KmlWriter {
         Ok(())
     }
 }
+
+/*-------------------------------------------------------------------------------------------------
+ *                                         KML Import
+ *-----------------------------------------------------------------------------------------------*/
+
+/// The summary of a fire recovered from a KML file written by [FireList::save_kmz].
+///
+/// Values that couldn't be found in the file are `None` or empty.
+///
+/// [FireList::save_kmz]: crate::FireList::save_kmz
+#[derive(Debug, Clone, Default)]
+pub struct KmlFireSummary {
+    /// The name of the fire folder, this is the fire id.
+    pub name: String,
+    /// The description of the fire placemark.
+    pub description: String,
+    /// The id of the fire.
+    pub id: Option<u64>,
+    /// The time the fire was first observed.
+    pub first_observed: Option<DateTime<Utc>>,
+    /// The time the fire was last observed.
+    pub last_observed: Option<DateTime<Utc>>,
+    /// The maximum power in megawatts, rounded to the nearest megawatt.
+    pub max_power: Option<f64>,
+    /// The maximum temperature in Kelvin, rounded to the nearest Kelvin.
+    pub max_temperature: Option<f64>,
+    /// The location of the fire placemark.
+    pub centroid: Option<Coord>,
+    /// The outer rings of the pixel polygons, the first vertex is repeated at the end.
+    pub pixel_rings: Vec<Vec<Coord>>,
+}
+
+/// Read the fires back out of a KMZ file written by [FireList::save_kmz].
+///
+/// [FireList::save_kmz]: crate::FireList::save_kmz
+pub struct KmzReader(ZipArchive<BufReader<File>>);
+
+impl KmzReader {
+    pub fn open<P: AsRef<Path>>(pth: P) -> SatFireResult<Self> {
+        let f = File::open(pth.as_ref())?;
+        Ok(KmzReader(ZipArchive::new(BufReader::new(f))?))
+    }
+
+    /// Stream the fires out of the doc.kml file in the archive.
+    pub fn fires(&mut self) -> SatFireResult<KmlFireReader<BufReader<ZipFile<'_>>>> {
+        let doc = self.0.by_name("doc.kml")?;
+        Ok(KmlFireReader::new(BufReader::new(doc)))
+    }
+}
+
+/// Stream the fires out of a KML document written by [FireList::save_kmz].
+///
+/// This is the inverse of the [KmlWriter] functions as they are used by this crate, one element
+/// per line. It is not a general KML parser, anything it doesn't recognize is skipped.
+///
+/// [FireList::save_kmz]: crate::FireList::save_kmz
+pub struct KmlFireReader<R: BufRead> {
+    input: R,
+    line: String,
+}
+
+impl<R: BufRead> KmlFireReader<R> {
+    pub fn new(input: R) -> Self {
+        KmlFireReader {
+            input,
+            line: String::with_capacity(256),
+        }
+    }
+
+    /// Read the next line into the buffer, returning false at the end of the input.
+    fn next_line(&mut self) -> SatFireResult<bool> {
+        self.line.clear();
+        Ok(self.input.read_line(&mut self.line)? > 0)
+    }
+
+    /// Read the contents of a fire folder after the opening tag up to the closing tag.
+    fn read_folder(&mut self) -> SatFireResult<KmlFireSummary> {
+        let mut fire = KmlFireSummary::default();
+
+        let mut depth = 1;
+        let mut in_placemark = false;
+        let mut in_ring = false;
+        let mut description: Option<String> = None;
+        let mut ring: Vec<Coord> = vec![];
+
+        while self.next_line()? {
+            let line = self.line.trim();
+
+            if in_ring {
+                if line == "</coordinates>" {
+                    in_ring = false;
+                    fire.pixel_rings.push(std::mem::take(&mut ring));
+                } else if !line.is_empty() {
+                    ring.push(parse_kml_coordinates(line)?);
+                }
+            } else if line == "<Folder>" {
+                depth += 1;
+            } else if line == "</Folder>" {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(fire);
+                }
+            } else if line == "<Placemark>" {
+                in_placemark = true;
+                description = None;
+            } else if line == "</Placemark>" {
+                in_placemark = false;
+            } else if line == "<coordinates>" {
+                in_ring = true;
+            } else if let Some(name) = element_text(line, "<name>", "</name>") {
+                if !in_placemark && depth == 1 {
+                    fire.name = name.to_string();
+                    fire.id = name.trim().parse().ok();
+                }
+            } else if let Some(desc) =
+                element_text(line, "<description><![CDATA[", "]]></description>")
+            {
+                description = Some(desc.to_string());
+            } else if let Some(coords) = element_text(line, "<coordinates>", "</coordinates>") {
+                // Only the fire placemark has a Point, the pixels are Polygons.
+                fire.centroid = Some(parse_kml_coordinates(coords)?);
+                if in_placemark {
+                    if let Some(desc) = description.take() {
+                        parse_fire_description(&desc, &mut fire)?;
+                        fire.description = desc;
+                    }
+                }
+            }
+        }
+
+        Err("Unexpected end of KML document inside a Folder".into())
+    }
+}
+
+impl<R: BufRead> Iterator for KmlFireReader<R> {
+    type Item = SatFireResult<KmlFireSummary>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_line() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+
+            if self.line.trim() == "<Folder>" {
+                return Some(self.read_folder());
+            }
+        }
+    }
+}
+
+/// Get the text between the opening and closing tags if the line is that element.
+fn element_text<'a>(line: &'a str, open: &str, close: &str) -> Option<&'a str> {
+    line.strip_prefix(open)?.strip_suffix(close)
+}
+
+/// Parse a KML "lon,lat,z" coordinate.
+fn parse_kml_coordinates(text: &str) -> SatFireResult<Coord> {
+    let mut parts = text.trim().split(',');
+    let lon = parts.next().ok_or("Missing longitude")?.trim().parse()?;
+    let lat = parts
+        .next()
+        .ok_or_else(|| format!("Missing latitude in KML coordinates: {}", text))?
+        .trim()
+        .parse()?;
+    Ok(Coord { lat, lon })
+}
+
+/// Fill in the fields from the description of a fire placemark.
+fn parse_fire_description(description: &str, fire: &mut KmlFireSummary) -> SatFireResult<()> {
+    // The leading number of values like "600 Kelvin".
+    fn leading_number(value: &str) -> SatFireResult<f64> {
+        let number = value.split_whitespace().next().unwrap_or_default();
+        Ok(number.parse()?)
+    }
+
+    for item in description.split("<br/>") {
+        let (key, value) = match item.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+
+        match key {
+            "ID" => fire.id = Some(value.parse()?),
+            "Start" => fire.first_observed = Some(parse_output_time(value)?),
+            "End" => fire.last_observed = Some(parse_output_time(value)?),
+            "Max Power" => fire.max_power = Some(leading_number(value)?),
+            "Max Temperature" => fire.max_temperature = Some(leading_number(value)?),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a time written with [OutputTime](crate::OutputTime), in UTC or with an offset.
+fn parse_output_time(value: &str) -> SatFireResult<DateTime<Utc>> {
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+    if let Some(utc) = value.strip_suffix(" UTC") {
+        let naive = NaiveDateTime::parse_from_str(utc, FORMAT)?;
+        Ok(DateTime::from_naive_utc_and_offset(naive, Utc))
+    } else {
+        let local = DateTime::parse_from_str(value, &format!("{} %:z", FORMAT))?;
+        Ok(local.with_timezone(&Utc))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        fire::{Fire, FireList},
+        geo::Geo,
+        pixel::PixelList,
+        satellite::Satellite,
+        testutil::pixel_with_corners,
+    };
+    use chrono::Duration;
+
+    fn test_pixels(lat: f64, lon: f64) -> PixelList {
+        let pixel = pixel_with_corners(
+            Coord { lat, lon },
+            Coord {
+                lat: lat - 0.02,
+                lon,
+            },
+            Coord {
+                lat: lat - 0.02,
+                lon: lon + 0.021,
+            },
+            Coord {
+                lat,
+                lon: lon + 0.021,
+            },
+            35.2,
+        );
+
+        let mut pixels = PixelList::new();
+        pixels.push(pixel);
+        pixels
+    }
+
+    #[test]
+    fn test_kmz_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "satfire_test_kmz_round_trip_{}.kmz",
+            std::process::id()
+        ));
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let mut fires = FireList::new();
+        fires.add_fire(Fire::new(
+            start,
+            start + Duration::hours(30),
+            1234.4,
+            712.6,
+            7,
+            test_pixels(45.0, -120.0),
+            Satellite::G17,
+            0,
+        ));
+        fires.add_fire(Fire::new(
+            start + Duration::minutes(10),
+            start + Duration::hours(3),
+            42.0,
+            600.0,
+            12,
+            test_pixels(38.5, -105.25),
+            Satellite::G17,
+            0,
+        ));
+
+        // The times are read back the same from any time zone.
+        let zone = crate::parse_time_zone("America/Denver").unwrap();
+        fires.save_kmz(Duration::zero(), &path, zone).unwrap();
+
+        let mut kmz = KmzReader::open(&path).unwrap();
+        let recovered: Vec<KmlFireSummary> = kmz.fires().unwrap().map(Result::unwrap).collect();
+        assert_eq!(recovered.len(), fires.len());
+
+        for (fire, summary) in fires.iter().zip(&recovered) {
+            assert_eq!(summary.name, fire.id().to_string());
+            assert_eq!(summary.id, Some(fire.id()));
+            assert_eq!(summary.first_observed, Some(fire.first_observed()));
+            assert_eq!(summary.last_observed, Some(fire.last_observed()));
+            assert_eq!(summary.max_power, Some(fire.max_power().round()));
+            assert_eq!(
+                summary.max_temperature,
+                Some(fire.max_temperature().round())
+            );
+            assert!(summary.centroid.unwrap().is_close(fire.centroid(), 1.0e-12));
+
+            assert_eq!(summary.pixel_rings.len(), fire.pixels().len());
+            for (ring, pixel) in summary.pixel_rings.iter().zip(fire.pixels().pixels()) {
+                let expected = [pixel.ul, pixel.ll, pixel.lr, pixel.ur, pixel.ul];
+                assert_eq!(ring.len(), expected.len());
+                for (&v, &e) in ring.iter().zip(&expected) {
+                    assert!(v.is_close(e, 1.0e-12));
+                }
+            }
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_kmz_timesliced() {
+        let path = std::env::temp_dir().join(format!(
+            "satfire_test_kmz_timesliced_{}.kmz",
+            std::process::id()
+        ));
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        let mut fires = FireList::new();
+        for (id, first, hours, lat) in [
+            (7, start, 30, 45.0),
+            (12, start + Duration::hours(2), 3, 38.5),
+            (15, start + Duration::days(3), 5, 40.0),
+        ] {
+            fires.add_fire(Fire::new(
+                first,
+                first + Duration::hours(hours),
+                42.0,
+                600.0,
+                id,
+                test_pixels(lat, -110.0),
+                Satellite::G17,
+                0,
+            ));
+        }
+
+        fires
+            .save_kmz_timesliced(Duration::zero(), &path, Default::default())
+            .unwrap();
+
+        let mut zip = ZipArchive::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let mut names: Vec<&str> = zip.file_names().collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            ["days/2021-08-01.kml", "days/2021-08-04.kml", "doc.kml"]
+        );
+
+        let mut doc = String::new();
+        std::io::Read::read_to_string(&mut zip.by_name("doc.kml").unwrap(), &mut doc).unwrap();
+        assert_eq!(doc.matches("<NetworkLink>").count(), 2);
+        assert!(doc.contains("<Link><href>days/2021-08-01.kml</href></Link>"));
+        // The first day covers both fires that started on it.
+        assert!(doc.contains("<begin>2021-08-01T12:00:00.000Z</begin>"));
+        assert!(doc.contains("<end>2021-08-02T18:00:00.000Z</end>"));
+        assert!(!doc.contains("<Placemark>"));
+
+        for (day, ids) in [
+            ("days/2021-08-01.kml", vec![7, 12]),
+            ("days/2021-08-04.kml", vec![15]),
+        ] {
+            let recovered: Vec<Option<u64>> =
+                KmlFireReader::new(BufReader::new(zip.by_name(day).unwrap()))
+                    .map(|summary| summary.unwrap().id)
+                    .collect();
+            let expected: Vec<Option<u64>> = ids.into_iter().map(Some).collect();
+            assert_eq!(recovered, expected);
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_parse_output_time() {
+        let expected: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        assert_eq!(
+            parse_output_time("2021-08-01 12:00:00 UTC").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_output_time("2021-08-01 06:00:00 -06:00").unwrap(),
+            expected
+        );
+        assert!(parse_output_time("yesterday").is_err());
+    }
+}
@@ -2,19 +2,38 @@
 #![allow(dead_code)]
 
 // Public API
-pub use cluster::{Cluster, ClusterList};
+pub use cluster::{Cluster, ClusterList, Connectivity};
+pub use config::SatFireConfig;
 pub use database::{
+    fire_growth_series, fire_radiative_energy, group_by_scan, CachedClusterDatabase, CentroidDrift,
     ClusterDatabase, ClusterDatabaseAddCluster, ClusterDatabaseClusterRow,
-    ClusterDatabaseQueryClusterPresent, ClusterDatabaseQueryClusters, FiresDatabase,
-    FiresDatabaseAddFire, JointFiresClusterDatabases, JointQuerySingleFire,
+    ClusterDatabaseQueryClusterPresent, ClusterDatabaseQueryClusters, ConsistencyIssue,
+    DatabaseSummary, FiresDatabase, FiresDatabaseAddFire, FiresDatabaseIterFires,
+    JointFiresClusterDatabases, JointQuerySingleFire,
 };
-pub use fire::{Fire, FireList, FireListUpdateResult, FireListView};
-pub use geo::{BoundingBox, Coord, Geo};
-pub use kml::{KmlFile, KmlWriter, KmzFile};
-pub use pixel::{Pixel, PixelList};
+pub use export_csv::write_fire_time_series_csv;
+#[cfg(feature = "export_mbtiles")]
+pub use export_mbtiles::export_mbtiles;
+#[cfg(feature = "export_parquet")]
+pub use export_parquet::{export_clusters_parquet, export_fires_parquet};
+pub use fire::{
+    CentroidMethod, Fire, FireList, FireListUpdateResult, FireListView, StalenessConfig,
+};
+pub use firesatimage::{LoadOptions, TerrainHeight};
+pub use geo::{BoundingBox, Coord, Ellipsoid, Geo, Hilbert2DRTreeView, Line, EARTH_RADIUS_METERS};
+pub use kml::{KmlFile, KmlFireReader, KmlFireSummary, KmlWriter, KmzFile, KmzReader};
+pub use pixel::{ColorRamp, Pixel, PixelList};
+pub use qc::{ClusterQc, ExclusionZones};
 pub use satellite::{
-    parse_satellite_description_from_file_name, DataQualityFlagCode, MaskCode, Satellite, Sector,
+    parse_satellite_description_from_file_name,
+    parse_satellite_description_from_file_name_with_offsets, DataQualityFlagCode, MaskCategory,
+    MaskCategoryCounts, MaskCode, Satellite, SatelliteTimeOffsets, Sector,
 };
+pub use time_zone::{parse_time_zone, parse_utc_offset, OutputTime, OutputTimeZone};
+
+/// Synthetic data for tests and benchmarks, only available with the `testutil` feature.
+#[cfg(any(test, feature = "testutil"))]
+pub mod testutil;
 
 /// A generic error type.
 pub type SatFireError = Box<dyn Error + Send + Sync>;
@@ -23,46 +42,77 @@ pub type SatFireError = Box<dyn Error + Send + Sync>;
 pub type SatFireResult<T> = Result<T, SatFireError>;
 
 /// Parse the file name and find the scan start time.
+///
+/// The time is truncated to whole seconds, see [precise_start_time_from_file_name].
 pub fn start_time_from_file_name(fname: &str) -> Option<DateTime<Utc>> {
-    let start_idx = fname.find("_s")? + 2;
-    let slice = &fname[start_idx..];
-    let slice = if slice.len() > 13 {
-        &slice[..13]
-    } else {
-        return None;
-    };
-
-    NaiveDateTime::parse_from_str(slice, "%Y%j%H%M%S")
-        .ok()
-        .map(|naive| DateTime::<Utc>::from_utc(naive, Utc))
+    time_from_file_name(fname, "_s", false)
 }
 
 /// Parse the file name and find the scan end time.
+///
+/// The time is truncated to whole seconds, see [precise_end_time_from_file_name].
 pub fn end_time_from_file_name(fname: &str) -> Option<DateTime<Utc>> {
-    let start_idx = fname.find("_e")? + 2;
-    let slice = &fname[start_idx..];
-    let slice = if slice.len() > 13 {
-        &slice[..13]
-    } else {
-        return None;
-    };
-
-    NaiveDateTime::parse_from_str(slice, "%Y%j%H%M%S")
-        .ok()
-        .map(|naive| DateTime::<Utc>::from_utc(naive, Utc))
+    time_from_file_name(fname, "_e", false)
+}
+
+/// Parse the file name and find the scan start time, including tenths of a second if present.
+pub fn precise_start_time_from_file_name(fname: &str) -> Option<DateTime<Utc>> {
+    time_from_file_name(fname, "_s", true)
+}
+
+/// Parse the file name and find the scan end time, including tenths of a second if present.
+pub fn precise_end_time_from_file_name(fname: &str) -> Option<DateTime<Utc>> {
+    time_from_file_name(fname, "_e", true)
+}
+
+/// Find the time stamp after `tag` in a file name.
+///
+/// The time stamp is in "%Y%j%H%M%S" format, with or without a trailing digit for the tenths of a
+/// second. The first `tag` followed by a time stamp of either width is used.
+fn time_from_file_name(fname: &str, tag: &str, keep_tenths: bool) -> Option<DateTime<Utc>> {
+    const WHOLE_SECONDS_WIDTH: usize = 13;
+
+    fname.match_indices(tag).find_map(|(idx, _)| {
+        let rest = &fname[idx + tag.len()..];
+        let num_digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+
+        let tenths = match num_digits {
+            WHOLE_SECONDS_WIDTH => 0,
+            14 => u32::from(rest.as_bytes()[WHOLE_SECONDS_WIDTH] - b'0'),
+            _ => return None,
+        };
+
+        let naive =
+            NaiveDateTime::parse_from_str(&rest[..WHOLE_SECONDS_WIDTH], "%Y%j%H%M%S").ok()?;
+        let naive = if keep_tenths {
+            naive + chrono::Duration::milliseconds(i64::from(tenths) * 100)
+        } else {
+            naive
+        };
+
+        Some(Utc.from_utc_datetime(&naive))
+    })
 }
 
 // Private API
 mod cluster;
+mod config;
 mod database;
+mod export_csv;
+#[cfg(feature = "export_mbtiles")]
+mod export_mbtiles;
+#[cfg(feature = "export_parquet")]
+mod export_parquet;
 mod fire;
 mod firesatimage;
 mod geo;
 mod kml;
 mod pixel;
+mod qc;
 mod satellite;
+mod time_zone;
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use std::error::Error;
 
 // test
@@ -98,4 +148,86 @@ mod test {
             )
         );
     }
+
+    #[test]
+    fn test_time_from_file_name_widths() {
+        // A full disk name with tenths of a second.
+        const FULL: &str =
+            "OR_ABI-L2-FDCF-M6_G17_s20212130100319_e20212130109386_c20212130109511.nc.zip";
+        // A meso name from a reprocessing without the tenths of a second.
+        const MESO: &str = "OR_ABI-L2-FDCM1-M6_G17_s2021213010053_e2021213010110_c2021213010150.nc";
+
+        let start = start_time_from_file_name(FULL).unwrap();
+        assert_eq!(
+            start,
+            "2021-08-01T01:00:31Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            precise_start_time_from_file_name(FULL).unwrap(),
+            "2021-08-01T01:00:31.9Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            precise_end_time_from_file_name(FULL).unwrap(),
+            "2021-08-01T01:09:38.6Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        let start = start_time_from_file_name(MESO).unwrap();
+        assert_eq!(
+            start,
+            "2021-08-01T01:00:53Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(precise_start_time_from_file_name(MESO).unwrap(), start);
+        assert_eq!(
+            end_time_from_file_name(MESO).unwrap(),
+            "2021-08-01T01:01:10Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        // An earlier "_s" that isn't a time stamp is skipped.
+        assert_eq!(
+            start_time_from_file_name("FDC_sample_s20212130100319_e20212130109386.nc").unwrap(),
+            "2021-08-01T01:00:31Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        assert!(start_time_from_file_name("FDC_s202121301003.nc").is_none());
+        assert!(start_time_from_file_name("FDC_s202121301003191.nc").is_none());
+    }
+
+    #[test]
+    fn test_satellite_time_offsets() {
+        const FNAME: &str =
+            "OR_ABI-L2-FDCF-M6_G17_s20212130100319_e20212130109386_c20212130109511.nc.zip";
+
+        let (_, _, start, end) = parse_satellite_description_from_file_name(FNAME).unwrap();
+
+        // A correction for another satellite doesn't change anything.
+        let mut offsets = SatelliteTimeOffsets::new();
+        offsets.add(Satellite::G16, chrono::Duration::seconds(30));
+        assert_eq!(
+            parse_satellite_description_from_file_name_with_offsets(FNAME, &offsets),
+            Some((Satellite::G17, Sector::FULL, start, end))
+        );
+
+        offsets.add_for_period(
+            Satellite::G17,
+            start - chrono::Duration::days(1),
+            start + chrono::Duration::minutes(5),
+            chrono::Duration::seconds(-90),
+        );
+        let (_, _, shifted_start, shifted_end) =
+            parse_satellite_description_from_file_name_with_offsets(FNAME, &offsets).unwrap();
+        assert_eq!(shifted_start, start - chrono::Duration::seconds(90));
+        // The end of the scan is after the anomaly period.
+        assert_eq!(shifted_end, end);
+
+        offsets.add(Satellite::G17, chrono::Duration::seconds(10));
+        assert_eq!(
+            offsets.apply(Satellite::G17, start),
+            start - chrono::Duration::seconds(80)
+        );
+
+        let mut clist = ClusterList::new(Satellite::G17, Sector::FULL, start, end, vec![]);
+        clist.apply_time_offsets(&offsets);
+        assert_eq!(clist.scan_start(), start - chrono::Duration::seconds(80));
+        assert_eq!(clist.scan_end(), end + chrono::Duration::seconds(10));
+    }
 }
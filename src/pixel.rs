@@ -1,8 +1,11 @@
 use crate::{
-    geo::{BoundingBox, Coord, Geo},
+    geo::{clip_to_convex_polygon, spherical_excess_area, BoundingBox, Coord, Ellipsoid, Geo},
     kml::KmlWriter,
-    satellite::{DataQualityFlagCode, MaskCode},
+    satellite::{DataQualityFlagCode, MaskCategoryCounts, MaskCode},
+    SatFireResult,
 };
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
 use std::{
     io::{Read, Write},
     mem::size_of,
@@ -10,8 +13,36 @@ use std::{
 
 const OVERLAP_FUDGE_FACTOR: f64 = 1.0e-2;
 
+/// The number of bytes used by the binary format for a single pixel, four coordinates, four other
+/// floating point values, and two 16 bit flags.
+const SERIALIZED_PIXEL_SIZE: usize =
+    4 * 2 * size_of::<f64>() + 4 * size_of::<f64>() + 2 * size_of::<i16>();
+
+/// The number of bytes stored after each pixel in a pixel list, the acquisition offset as an f32.
+const SERIALIZED_ACQUISITION_OFFSET_SIZE: usize = size_of::<f32>();
+
+/// The first bytes of a pixel list BLOB with a versioned header.
+const PIXEL_LIST_MAGIC: [u8; 4] = *b"SFPL";
+
+/// The first bytes of a pixel list BLOB with a versioned header and zstd compressed pixels.
+const COMPRESSED_PIXEL_LIST_MAGIC: [u8; 4] = *b"SFPZ";
+
+/// The version of the pixel list format written by [PixelList::binary_serialize].
+///
+/// Version 0 is the original format without the magic bytes and version. Version 1 added them,
+/// and version 2 added the acquisition offset after each pixel.
+const PIXEL_LIST_FORMAT_VERSION: u32 = 2;
+
+/// The number of bytes used by the legacy (version 0) pixel list header, just the length.
+const LEGACY_LIST_HEADER_SIZE: usize = size_of::<u64>();
+
+/// The number of bytes used by the pixel list header, the magic bytes, version, and length.
+const SERIALIZED_LIST_HEADER_SIZE: usize =
+    PIXEL_LIST_MAGIC.len() + size_of::<u32>() + size_of::<u64>();
+
 /// The coordinates describing the area of a pixel viewed from a GOES satellite.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Pixel {
     /// The upper left (northwest) corner point of the pixel
@@ -46,9 +77,63 @@ pub struct Pixel {
     ///
     /// See the satfire_satellite_dqf_code_to_string() function for reference.
     pub data_quality_flag: DataQualityFlagCode,
+    /// The estimated time this pixel was acquired, in seconds after the scan start.
+    ///
+    /// The imager sweeps across the Earth during a scan, so each pixel is observed at a slightly
+    /// different time. This is an estimate interpolated from the scan start and end times and the
+    /// row of the pixel in the image. It fits in what would otherwise be padding, so it doesn't
+    /// change the size of a Pixel. It is stored with each pixel by [PixelList::binary_serialize],
+    /// but pixels loaded from lists written before version 2 of that format have a value of 0.0,
+    /// which is the scan start time.
+    pub acquisition_offset: f32,
 }
 
+static_assertions::assert_eq_size!(Pixel, [u8; 104]);
+
 impl Pixel {
+    /// Get the radiative power in megawatts.
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+
+    /// Get the estimated area covered by the fire in square meters.
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Get the estimated temperature of the fire in Kelvin.
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Get the scan angle in degrees.
+    pub fn scan_angle(&self) -> f64 {
+        self.scan_angle
+    }
+
+    /// Get the mask code.
+    pub fn mask(&self) -> MaskCode {
+        self.mask_flag
+    }
+
+    /// Get the data quality flag.
+    pub fn dqf(&self) -> DataQualityFlagCode {
+        self.data_quality_flag
+    }
+
+    /// Get the corners in the order upper left, lower left, lower right, upper right.
+    pub fn corners(&self) -> [Coord; 4] {
+        [self.ul, self.ll, self.lr, self.ur]
+    }
+
+    /// Get the estimated time this pixel was acquired given the start time of its scan.
+    ///
+    /// See [Pixel::acquisition_offset].
+    pub fn acquisition_time(&self, scan_start: DateTime<Utc>) -> DateTime<Utc> {
+        scan_start
+            + Duration::milliseconds((self.acquisition_offset as f64 * 1000.0).round() as i64)
+    }
+
     fn max_merge(&mut self, other: &Pixel) {
         self.power = self.power.max(other.power);
         self.temperature = self.temperature.max(other.temperature);
@@ -87,38 +172,38 @@ impl Pixel {
         Ok(())
     }
 
-    fn read_bytes<R: Read>(r: &mut R) -> Self {
+    fn read_bytes<R: Read>(r: &mut R) -> Result<Self, std::io::Error> {
         let mut buf: [u8; 8] = [0; 8];
 
-        let mut read_coord = || -> Coord {
-            let _ = r.read_exact(&mut buf);
+        let mut read_coord = || -> Result<Coord, std::io::Error> {
+            r.read_exact(&mut buf)?;
             let lat = f64::from_le_bytes(buf);
-            let _ = r.read_exact(&mut buf);
+            r.read_exact(&mut buf)?;
             let lon = f64::from_le_bytes(buf);
-            Coord { lat, lon }
+            Ok(Coord { lat, lon })
         };
 
-        let ul = read_coord();
-        let ll = read_coord();
-        let lr = read_coord();
-        let ur = read_coord();
+        let ul = read_coord()?;
+        let ll = read_coord()?;
+        let lr = read_coord()?;
+        let ur = read_coord()?;
 
-        let _ = r.read_exact(&mut buf);
+        r.read_exact(&mut buf)?;
         let power = f64::from_le_bytes(buf);
-        let _ = r.read_exact(&mut buf);
+        r.read_exact(&mut buf)?;
         let area = f64::from_le_bytes(buf);
-        let _ = r.read_exact(&mut buf);
+        r.read_exact(&mut buf)?;
         let temperature = f64::from_le_bytes(buf);
-        let _ = r.read_exact(&mut buf);
+        r.read_exact(&mut buf)?;
         let scan_angle = f64::from_le_bytes(buf);
 
         let mut buf: [u8; 2] = [0; 2];
-        let _ = r.read_exact(&mut buf);
+        r.read_exact(&mut buf)?;
         let mask_flag = MaskCode(i16::from_le_bytes(buf));
-        let _ = r.read_exact(&mut buf);
+        r.read_exact(&mut buf)?;
         let data_quality_flag = DataQualityFlagCode(i16::from_le_bytes(buf));
 
-        Pixel {
+        Ok(Pixel {
             ul,
             ll,
             lr,
@@ -129,7 +214,8 @@ impl Pixel {
             scan_angle,
             mask_flag,
             data_quality_flag,
-        }
+            acquisition_offset: 0.0,
+        })
     }
 }
 
@@ -137,7 +223,8 @@ impl Geo for Pixel {
     /// Calculate the centroid of a Pixel.
     ///
     /// This function uses an algorithm that assumes the pixel is a quadrilateral, which is enforced
-    /// by the definition of the Pixel type.
+    /// by the definition of the Pixel type. If any of the corners are not finite, the centroid is
+    /// NaN.
     #[rustfmt::skip]
     fn centroid(&self) -> Coord {
         /* Steps to calculatule the centroid of a quadrilateral.
@@ -151,6 +238,12 @@ impl Geo for Pixel {
          */
         use crate::geo::{triangle_centroid, Line};
 
+        // A degenerate pixel with invalid coordinates doesn't have a centroid.
+        let corners = [self.ul, self.ll, self.lr, self.ur];
+        if corners.iter().any(|c| !c.lat.is_finite() || !c.lon.is_finite()) {
+            return Coord {lat: f64::NAN, lon: f64::NAN};
+        }
+
         let t1_c = triangle_centroid(self.ul, self.ll, self.lr);
         let t2_c = triangle_centroid(self.ul, self.ur, self.lr);
         let diag1_centroids = Line {start: t1_c, end: t2_c};
@@ -159,9 +252,14 @@ impl Geo for Pixel {
         let t4_c = triangle_centroid(self.lr, self.ur, self.ll);
         let diag2_centroids = Line {start: t3_c, end: t4_c};
 
-        let res = diag1_centroids.intersect(diag2_centroids, 1.0e-30).unwrap();
-
-        res.intersection
+        match diag1_centroids.intersect(diag2_centroids, 1.0e-30) {
+            Some(res) => res.intersection,
+            // A pixel collapsed to a line has parallel diagonals, fall back to the mean corner.
+            None => Coord {
+                lat: corners.iter().map(|c| c.lat).sum::<f64>() / 4.0,
+                lon: corners.iter().map(|c| c.lon).sum::<f64>() / 4.0,
+            },
+        }
     }
 
     #[rustfmt::skip]
@@ -190,6 +288,23 @@ impl Pixel {
             && self.ll.is_close(other.ll, eps)
     }
 
+    /// Calculate the area of the pixel on the ground from its corners, square meters.
+    ///
+    /// This is the area of the whole pixel, unlike the `area` field which is the area of the fire
+    /// in the pixel as reported in the file. The corners are joined by great circle arcs and the
+    /// area comes from the spherical excess, so the skewed quadrilaterals near the limb of the disk
+    /// don't need to be rectangles. It uses the [GRS80](Ellipsoid::GRS80) ellipsoid of the GOES
+    /// projection.
+    pub fn geographic_area_m2(&self) -> f64 {
+        self.geographic_area_m2_with_ellipsoid(&Ellipsoid::GRS80)
+    }
+
+    /// Calculate the area of the pixel on the ground from its corners on a sphere with the
+    /// [authalic radius](Ellipsoid::authalic_radius) of `ellipsoid`, square meters.
+    pub fn geographic_area_m2_with_ellipsoid(&self, ellipsoid: &Ellipsoid) -> f64 {
+        spherical_excess_area(&[self.ul, self.ll, self.lr, self.ur], ellipsoid)
+    }
+
     /// Determine if a coordinate is interior to a pixel.
     ///
     /// Interior means that it is NOT on the boundary. The eps parameter is used by an interanl line
@@ -320,6 +435,37 @@ impl Pixel {
         false
     }
 
+    /// Calculate the fraction of the smaller pixel covered by the intersection of the pixels.
+    ///
+    /// This is 0.0 if the pixels don't overlap and 1.0 if they are the same pixel or one is
+    /// completely inside the other. The intersection is found by clipping one pixel against the
+    /// other, which assumes both pixels are convex quadrilaterals. The `eps` parameter is used in
+    /// the same way as in [Pixel::overlap].
+    pub fn overlap_area_fraction(&self, other: &Pixel, eps: f64) -> f64 {
+        if self.approx_equal(other, eps) {
+            return 1.0;
+        }
+
+        if !self.bounding_box().overlap(&other.bounding_box(), eps) {
+            return 0.0;
+        }
+
+        let self_corners = [self.ul, self.ll, self.lr, self.ur];
+        let other_corners = [other.ul, other.ll, other.lr, other.ur];
+
+        let intersection = clip_to_convex_polygon(&self_corners, &other_corners, eps);
+        if intersection.len() < 3 {
+            return 0.0;
+        }
+
+        let smaller_area = self.geographic_area_m2().min(other.geographic_area_m2());
+        if smaller_area.is_nan() || smaller_area <= 0.0 {
+            return 0.0;
+        }
+
+        (spherical_excess_area(&intersection, &Ellipsoid::GRS80) / smaller_area).clamp(0.0, 1.0)
+    }
+
     /// Determine if satellite pixels are adjacent.
     ///
     /// Adjacent is defined as having at least one corner that is `eps` close to a coordinate in the
@@ -437,19 +583,31 @@ impl Pixel {
 
 /// A pixel list stores a list of Pixel objects.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PixelList(Vec<Pixel>);
 
 impl Geo for PixelList {
+    /// The centroid of the pixel centroids.
+    ///
+    /// Degenerate pixels with a centroid that isn't finite are skipped so they don't corrupt the
+    /// whole centroid. If there are no pixels with a finite centroid, the result is NaN.
     fn centroid(&self) -> Coord {
         let mut centroid = Coord { lat: 0.0, lon: 0.0 };
+        let mut count = 0;
         for pixel in &self.0 {
             let coord = pixel.centroid();
+            if !coord.lat.is_finite() || !coord.lon.is_finite() {
+                warn!("Skipping pixel with invalid centroid: {:?}", pixel);
+                continue;
+            }
+
             centroid.lat += coord.lat;
             centroid.lon += coord.lon;
+            count += 1;
         }
 
-        centroid.lat /= self.0.len() as f64;
-        centroid.lon /= self.0.len() as f64;
+        centroid.lat /= count as f64;
+        centroid.lon /= count as f64;
 
         centroid
     }
@@ -461,11 +619,13 @@ impl Geo for PixelList {
         let mut min_lon = std::f64::INFINITY;
         let mut max_lon = -std::f64::INFINITY;
 
+        // Use every corner so skewed pixels are always inside the box.
         for pixel in &self.0 {
-            min_lat = min_lat.min(pixel.ll.lat).min(pixel.lr.lat);
-            max_lat = max_lat.max(pixel.ul.lat).max(pixel.ur.lat);
-            min_lon = min_lon.min(pixel.ll.lon).min(pixel.lr.lon);
-            max_lon = max_lon.max(pixel.ul.lon).max(pixel.ur.lon);
+            let bbox = pixel.bounding_box();
+            min_lat = min_lat.min(bbox.ll.lat);
+            max_lat = max_lat.max(bbox.ur.lat);
+            min_lon = min_lon.min(bbox.ll.lon);
+            max_lon = max_lon.max(bbox.ur.lon);
         }
 
         BoundingBox {ll: Coord {lat: min_lat, lon: min_lon}, ur: Coord {lat: max_lat, lon: max_lon}}
@@ -478,6 +638,15 @@ impl Default for PixelList {
     }
 }
 
+impl<'a> IntoIterator for &'a PixelList {
+    type Item = &'a Pixel;
+    type IntoIter = std::slice::Iter<'a, Pixel>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 impl PixelList {
     /// Create a new PixelList
     pub fn new() -> Self {
@@ -518,8 +687,31 @@ impl PixelList {
             .sum()
     }
 
+    /// Count the pixels in each mask category.
+    pub fn mask_category_counts(&self) -> MaskCategoryCounts {
+        let mut counts = MaskCategoryCounts::new();
+        for pixel in &self.0 {
+            counts.add(pixel.mask_flag.category(), 1);
+        }
+
+        counts
+    }
+
+    /// Determine if a coordinate is interior to any of the pixels in the list.
+    ///
+    /// See [Pixel::contains_coord] for what interior means and how `eps` is used. The bounding box
+    /// of the whole list is checked first, then the pixels are checked in order until one of them
+    /// contains the coordinate.
+    pub fn contains_coord(&self, coord: Coord, eps: f64) -> bool {
+        if !self.bounding_box().contains_coord(coord, eps) {
+            return false;
+        }
+
+        self.0.iter().any(|pixel| pixel.contains_coord(coord, eps))
+    }
+
     /// Calculate the total fire area in a PixelList, square meters.
-    pub fn total_are(&self) -> f64 {
+    pub fn total_area(&self) -> f64 {
         self.0
             .iter()
             .filter(|p| !p.area.is_infinite() && !p.area.is_nan())
@@ -527,6 +719,24 @@ impl PixelList {
             .sum()
     }
 
+    /// Calculate the total fire area in a PixelList, square meters.
+    ///
+    /// This is the original, misspelled, name of [PixelList::total_area].
+    #[deprecated(since = "0.2.0", note = "use total_area instead")]
+    pub fn total_are(&self) -> f64 {
+        self.total_area()
+    }
+
+    /// Calculate the total area on the ground covered by the pixels in a PixelList from their
+    /// corners, square meters. See [Pixel::geographic_area_m2].
+    pub fn total_geographic_area_m2(&self) -> f64 {
+        self.0
+            .iter()
+            .map(|p| p.geographic_area_m2())
+            .filter(|area| area.is_finite())
+            .sum()
+    }
+
     /// Calculate the maximum fire temperature in a PixelList, kelvin.
     pub fn maximum_temperature(&self) -> f64 {
         self.0
@@ -562,10 +772,173 @@ impl PixelList {
         false
     }
 
+    /// Calculate the convex hull of all the corners of the pixels in this list.
+    ///
+    /// This uses Andrew's monotone chain algorithm. The result is a closed ring, so the first and
+    /// last points are the same, and the vertices are in counter-clockwise order. This is suitable
+    /// for passing vertex by vertex to [KmlWriter::linear_ring_add_vertex]. An empty list returns
+    /// an empty ring.
+    pub fn convex_hull(&self) -> Vec<Coord> {
+        // Cross product of the vectors o->a and o->b, positive for a counter-clockwise turn.
+        fn cross(o: &Coord, a: &Coord, b: &Coord) -> f64 {
+            (a.lon - o.lon) * (b.lat - o.lat) - (a.lat - o.lat) * (b.lon - o.lon)
+        }
+
+        let mut points: Vec<Coord> = self
+            .0
+            .iter()
+            .flat_map(|p| [p.ul, p.ll, p.lr, p.ur])
+            .filter(|c| c.lat.is_finite() && c.lon.is_finite())
+            .collect();
+
+        if points.is_empty() {
+            return points;
+        }
+
+        points.sort_by(|a, b| {
+            a.lon
+                .partial_cmp(&b.lon)
+                .unwrap()
+                .then(a.lat.partial_cmp(&b.lat).unwrap())
+        });
+        points.dedup_by(|a, b| a.lon == b.lon && a.lat == b.lat);
+
+        let mut hull: Vec<Coord> = Vec::with_capacity(points.len() + 1);
+
+        // Lower hull
+        for pnt in points.iter() {
+            while hull.len() >= 2 && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], pnt) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*pnt);
+        }
+
+        // Upper hull
+        let lower_len = hull.len() + 1;
+        for pnt in points.iter().rev().skip(1) {
+            while hull.len() >= lower_len
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], pnt) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*pnt);
+        }
+
+        // The upper hull loop ends by pushing the starting point again, which closes the ring. A
+        // single distinct point needs to be closed manually.
+        if hull.len() == 1 {
+            hull.push(hull[0]);
+        }
+
+        hull
+    }
+
+    /// The centroid of the pixel centroids, weighted by the power of each pixel.
+    ///
+    /// This better represents the most active part of a fire than the uniform
+    /// [centroid](Geo::centroid). Degenerate pixels and pixels without a finite power are skipped.
+    /// If the total power of the remaining pixels isn't positive, this falls back to the uniform
+    /// centroid.
+    pub fn power_weighted_centroid(&self) -> Coord {
+        let mut centroid = Coord { lat: 0.0, lon: 0.0 };
+        let mut total_power = 0.0;
+        for pixel in &self.0 {
+            let coord = pixel.centroid();
+            if !coord.lat.is_finite() || !coord.lon.is_finite() || !pixel.power.is_finite() {
+                continue;
+            }
+
+            centroid.lat += coord.lat * pixel.power;
+            centroid.lon += coord.lon * pixel.power;
+            total_power += pixel.power;
+        }
+
+        if total_power <= 0.0 {
+            return self.centroid();
+        }
+
+        centroid.lat /= total_power;
+        centroid.lon /= total_power;
+
+        centroid
+    }
+
+    /// The Jaccard similarity of the footprints of two pixel lists.
+    ///
+    /// This is the number of pixels in both lists divided by the number of pixels in either list,
+    /// where pixels are matched with [Pixel::approx_equal]. It is 1.0 for identical footprints and
+    /// 0.0 for disjoint footprints. Two empty lists are considered disjoint.
+    pub fn jaccard(&self, other: &PixelList, eps: f64) -> f64 {
+        let intersection = self
+            .0
+            .iter()
+            .filter(|pixel| other.0.iter().any(|o| pixel.approx_equal(o, eps)))
+            .count();
+
+        let union = self.0.len() + other.0.len() - intersection;
+
+        if union == 0 {
+            0.0
+        } else {
+            intersection as f64 / union as f64
+        }
+    }
+
+    /// Calculate the direction and distance a fire spread from a previous footprint.
+    ///
+    /// The pixels in this list that aren't in `previous` (as determined by
+    /// [Pixel::approx_equal]) are the newly burned pixels. The spread vector is from the centroid
+    /// of the `previous` footprint to the centroid of the newly burned pixels. The result is
+    /// (bearing, distance), with the great circle bearing in degrees clockwise from north and the
+    /// distance in meters. If there are no new pixels, or `previous` is empty, the fire didn't
+    /// spread and the result is (NaN, 0.0).
+    pub fn spread_vector(&self, previous: &PixelList, eps: f64) -> (f64, f64) {
+        let new_pixels = PixelList(
+            self.0
+                .iter()
+                .filter(|pixel| !previous.0.iter().any(|p| pixel.approx_equal(p, eps)))
+                .copied()
+                .collect(),
+        );
+
+        if new_pixels.is_empty() || previous.is_empty() {
+            return (f64::NAN, 0.0);
+        }
+
+        let from = previous.centroid();
+        let to = new_pixels.centroid();
+
+        (
+            from.bearing_to(to),
+            from.distance_meters_with_ellipsoid(to, &Ellipsoid::GRS80),
+        )
+    }
+
+    /// Clip the PixelList to a BoundingBox, keeping only the pixels with their centroid in the box.
+    ///
+    /// Unlike [crate::ClusterList::filter_box], which keeps or drops an entire cluster, this keeps
+    /// the portion of a fire that is inside the box so sums like [PixelList::total_power] can be
+    /// limited to a region.
+    pub fn clip_to_box(&self, bbox: BoundingBox) -> PixelList {
+        PixelList(
+            self.0
+                .iter()
+                .filter(|pixel| bbox.contains_coord(pixel.centroid(), 0.0))
+                .copied()
+                .collect(),
+        )
+    }
+
     pub fn pixels(&self) -> &[Pixel] {
         &self.0
     }
 
+    /// Get an iterator over the pixels.
+    pub fn iter(&self) -> impl Iterator<Item = &Pixel> {
+        self.0.iter()
+    }
+
     pub fn max_merge(&mut self, other: &PixelList) {
         for other_pixel in other.0.iter() {
             let mut is_new = true;
@@ -583,41 +956,266 @@ impl PixelList {
             }
         }
     }
+
+    /// Merge another list into this one like [PixelList::max_merge], then collapse duplicates.
+    ///
+    /// The same spot on the ground can be observed in more than one sector, e.g. CONUS and full
+    /// disk, and the pixels won't always line up within the tolerance used by `max_merge`. After
+    /// merging, any pixels that are [approx_equal](Pixel::approx_equal) within `eps` are combined
+    /// into a single pixel with the max power, area, and temperature of them all. This includes
+    /// duplicates that were already in this list.
+    pub fn max_merge_dedup(&mut self, other: &PixelList, eps: f64) {
+        self.max_merge(other);
+
+        let mut i = 0;
+        while i < self.0.len() {
+            let mut j = i + 1;
+            while j < self.0.len() {
+                if self.0[i].approx_equal(&self.0[j], eps) {
+                    let dup = self.0.swap_remove(j);
+                    self.0[i].max_merge(&dup);
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+    }
 }
 
 /*-------------------------------------------------------------------------------------------------
  *                                         Binary Format
  *-----------------------------------------------------------------------------------------------*/
+impl Pixel {
+    /// The number of bytes in the binary format of a single Pixel.
+    ///
+    /// See [Pixel::to_bytes] for the layout.
+    pub const fn serialized_size() -> usize {
+        SERIALIZED_PIXEL_SIZE
+    }
+
+    /// Encode a Pixel into the binary format used in the pixel BLOBs stored in the databases.
+    ///
+    /// All values are little endian and there is no padding.
+    ///
+    /// | offset | size | type | field             |
+    /// |--------|------|------|-------------------|
+    /// |      0 |    8 | f64  | ul.lat            |
+    /// |      8 |    8 | f64  | ul.lon            |
+    /// |     16 |    8 | f64  | ll.lat            |
+    /// |     24 |    8 | f64  | ll.lon            |
+    /// |     32 |    8 | f64  | lr.lat            |
+    /// |     40 |    8 | f64  | lr.lon            |
+    /// |     48 |    8 | f64  | ur.lat            |
+    /// |     56 |    8 | f64  | ur.lon            |
+    /// |     64 |    8 | f64  | power             |
+    /// |     72 |    8 | f64  | area              |
+    /// |     80 |    8 | f64  | temperature       |
+    /// |     88 |    8 | f64  | scan_angle        |
+    /// |     96 |    2 | i16  | mask_flag         |
+    /// |     98 |    2 | i16  | data_quality_flag |
+    ///
+    /// The [acquisition_offset](Pixel::acquisition_offset) is not part of the format, a
+    /// [PixelList] stores it after each pixel. See [PixelList::binary_serialize] for how a list of
+    /// pixels is stored. The same bytes are
+    /// produced on every platform, so the BLOBs can be shared between machines.
+    pub fn to_bytes(&self) -> [u8; SERIALIZED_PIXEL_SIZE] {
+        let mut bytes = [0; SERIALIZED_PIXEL_SIZE];
+
+        // Ignore write errors since the buffer is exactly the right size.
+        let _ = self.write_bytes(&mut &mut bytes[..]);
+
+        bytes
+    }
+
+    /// Decode a Pixel from the binary format described in [Pixel::to_bytes].
+    ///
+    /// The [acquisition_offset](Pixel::acquisition_offset) is set to 0.0. Returns an error if the
+    /// slice isn't exactly [Pixel::serialized_size] bytes long.
+    pub fn from_bytes(bytes: &[u8]) -> SatFireResult<Self> {
+        if bytes.len() != SERIALIZED_PIXEL_SIZE {
+            return Err(format!(
+                "Pixel should have {} bytes, but has {}",
+                SERIALIZED_PIXEL_SIZE,
+                bytes.len()
+            )
+            .into());
+        }
+
+        Ok(Self::read_bytes(&mut &bytes[..])?)
+    }
+}
+
 impl PixelList {
     /// Encode the PixelList into a binary format suitable for storing in a database.
+    ///
+    /// The list starts with the 4 bytes "SFPL", the format version as a little endian `u32`, and
+    /// the number of pixels as a little endian `u64`. Each pixel follows in the format described
+    /// in [Pixel::to_bytes], and then its [acquisition_offset](Pixel::acquisition_offset) as a
+    /// little endian `f32`.
+    ///
+    /// Lists written before the header was added are version 0 and start directly with the
+    /// number of pixels. They are still read by [PixelList::binary_deserialize]; a legacy list
+    /// would need over a billion pixels for its length to look like the magic bytes. Version 0 and
+    /// 1 lists don't have the acquisition offsets, so they are read as 0.0.
     pub fn binary_serialize(&self) -> Vec<u8> {
         // Ignore write errors since we're writing to a Vec<u8>
 
-        let mut output = Vec::with_capacity(size_of::<usize>() + size_of::<Pixel>() * self.0.len());
+        let mut output = Vec::with_capacity(
+            SERIALIZED_LIST_HEADER_SIZE
+                + serialized_record_size(PIXEL_LIST_FORMAT_VERSION) * self.0.len(),
+        );
+
+        let _ = output.write_all(&PIXEL_LIST_MAGIC);
+        let _ = output.write_all(&PIXEL_LIST_FORMAT_VERSION.to_le_bytes());
+        let _ = output.write_all(&(self.0.len() as u64).to_le_bytes());
+        let _ = self.write_pixels(&mut output);
+
+        output
+    }
 
-        let _ = output.write_all(&self.0.len().to_le_bytes());
+    /// Encode the PixelList into a compressed binary format suitable for storing in a database.
+    ///
+    /// This is the same as [PixelList::binary_serialize] except the magic bytes are "SFPZ" and
+    /// the pixels following the header are a single zstd frame. The corners of neighboring pixels
+    /// are nearly identical, so this is usually much smaller. Both formats are read by
+    /// [PixelList::binary_deserialize].
+    pub fn binary_serialize_compressed(&self) -> SatFireResult<Vec<u8>> {
+        let mut pixels =
+            Vec::with_capacity(serialized_record_size(PIXEL_LIST_FORMAT_VERSION) * self.0.len());
+        self.write_pixels(&mut pixels)?;
+        let pixels = zstd::bulk::compress(&pixels, 0)?;
+
+        let mut output = Vec::with_capacity(SERIALIZED_LIST_HEADER_SIZE + pixels.len());
+        output.write_all(&COMPRESSED_PIXEL_LIST_MAGIC)?;
+        output.write_all(&PIXEL_LIST_FORMAT_VERSION.to_le_bytes())?;
+        output.write_all(&(self.0.len() as u64).to_le_bytes())?;
+        output.write_all(&pixels)?;
+
+        Ok(output)
+    }
+
+    /// Write the pixels and their acquisition offsets in the current format.
+    fn write_pixels<W: Write>(&self, w: &mut W) -> Result<(), std::io::Error> {
         for pixel in &self.0 {
-            let _ = pixel.write_bytes(&mut output);
+            pixel.write_bytes(w)?;
+            w.write_all(&pixel.acquisition_offset.to_le_bytes())?;
         }
 
-        output
+        Ok(())
+    }
+
+    /// Read the header of a binary pixel list, returning the format version, whether the pixels
+    /// are compressed, and the length.
+    fn read_binary_header<R: Read>(r: &mut R) -> SatFireResult<(u32, bool, u64)> {
+        let mut buf: [u8; 8] = [0; 8];
+        r.read_exact(&mut buf)?;
+
+        let compressed = buf[..COMPRESSED_PIXEL_LIST_MAGIC.len()] == COMPRESSED_PIXEL_LIST_MAGIC;
+        if !compressed && buf[..PIXEL_LIST_MAGIC.len()] != PIXEL_LIST_MAGIC {
+            return Ok((0, false, u64::from_le_bytes(buf)));
+        }
+
+        let mut version: [u8; 4] = [0; 4];
+        version.copy_from_slice(&buf[PIXEL_LIST_MAGIC.len()..]);
+        let version = u32::from_le_bytes(version);
+        if version == 0 || version > PIXEL_LIST_FORMAT_VERSION {
+            return Err(format!("Unsupported pixel list format version: {}", version).into());
+        }
+
+        r.read_exact(&mut buf)?;
+        Ok((version, compressed, u64::from_le_bytes(buf)))
     }
 
     /// Deserialize an array of bytes into a PixelList.
     ///
-    pub fn binary_deserialize<R: Read>(r: &mut R) -> Self {
-        let mut buf: [u8; size_of::<usize>()] = [0; size_of::<usize>()];
-
-        let _ = r.read_exact(&mut buf);
-        let len = usize::from_le_bytes(buf);
+    /// The current format, compressed or not, and the legacy format without a header are
+    /// accepted, see [PixelList::binary_serialize] and [PixelList::binary_serialize_compressed].
+    /// Returns an error if the header is not valid or the reader runs out of bytes before all the
+    /// pixels are read. The decompressor may read past the end of a compressed list.
+    pub fn binary_deserialize<R: Read>(r: &mut R) -> SatFireResult<Self> {
+        let (version, compressed, len) = Self::read_binary_header(r)?;
+        let len = usize::try_from(len)?;
+
+        if compressed {
+            let mut decoder = zstd::stream::read::Decoder::new(r)?.single_frame();
+            Self::read_pixels(&mut decoder, len, version)
+        } else {
+            Self::read_pixels(r, len, version)
+        }
+    }
 
-        let mut data: Vec<Pixel> = Vec::with_capacity(len);
+    /// Read `len` pixels written with format `version`, see [PixelList::binary_serialize].
+    fn read_pixels<R: Read>(r: &mut R, len: usize, version: u32) -> SatFireResult<Self> {
+        // Don't trust the length for the allocation until the pixels have actually been read.
+        let mut data: Vec<Pixel> = Vec::with_capacity(len.min(1_024));
 
         for _ in 0..len {
-            data.push(Pixel::read_bytes(r));
+            let mut pixel = Pixel::read_bytes(r)?;
+            if version >= 2 {
+                let mut buf: [u8; SERIALIZED_ACQUISITION_OFFSET_SIZE] =
+                    [0; SERIALIZED_ACQUISITION_OFFSET_SIZE];
+                r.read_exact(&mut buf)?;
+                pixel.acquisition_offset = f32::from_le_bytes(buf);
+            }
+            data.push(pixel);
+        }
+
+        Ok(PixelList(data))
+    }
+
+    /// Deserialize an array of bytes into a PixelList, checking that it is well formed.
+    ///
+    /// Unlike [PixelList::binary_deserialize], this also returns an error if the number of pixels
+    /// in the header doesn't match the number of bytes, after decompressing them if needed.
+    pub fn try_binary_deserialize(bytes: &[u8]) -> SatFireResult<Self> {
+        if bytes.len() < LEGACY_LIST_HEADER_SIZE {
+            return Err(format!("Pixel list too short for header: {} bytes", bytes.len()).into());
+        }
+
+        let (version, compressed, len) = Self::read_binary_header(&mut &bytes[..])?;
+        let header_size = if version == 0 {
+            LEGACY_LIST_HEADER_SIZE
+        } else {
+            SERIALIZED_LIST_HEADER_SIZE
+        };
+
+        let expected_len = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_mul(serialized_record_size(version)))
+            .ok_or_else(|| format!("Pixel list length is too large: {}", len))?;
+
+        // Decompress into a buffer sized from the header, so a frame that decompresses to more
+        // than the header claims is an error instead of an unbounded allocation.
+        let decompressed;
+        let data = if compressed {
+            decompressed =
+                zstd::bulk::decompress(bytes.get(header_size..).unwrap_or(&[]), expected_len)?;
+            &decompressed[..]
+        } else {
+            bytes.get(header_size..).unwrap_or(&[])
+        };
+
+        if data.len() != expected_len {
+            return Err(format!(
+                "Pixel list of {} pixels should have {} bytes of data, but has {}",
+                len,
+                expected_len,
+                data.len()
+            )
+            .into());
         }
 
-        PixelList(data)
+        Self::read_pixels(&mut &data[..], len as usize, version)
+    }
+}
+
+/// The number of bytes used by each pixel in a pixel list written with format `version`.
+fn serialized_record_size(version: u32) -> usize {
+    if version >= 2 {
+        SERIALIZED_PIXEL_SIZE + SERIALIZED_ACQUISITION_OFFSET_SIZE
+    } else {
+        SERIALIZED_PIXEL_SIZE
     }
 }
 
@@ -625,27 +1223,67 @@ impl PixelList {
  *                                         KML Export
  *-----------------------------------------------------------------------------------------------*/
 
-impl PixelList {
-    fn kml_write_pixel_style<K: KmlWriter>(kml: &mut K, mut power: f64) {
-        const MAX_POWER: f64 = 3_000.0;
+/// How to choose the color of each pixel when writing a [PixelList] to KML.
+///
+/// Both ramps go from orange through red to white as the value goes from the bottom to the top of
+/// the range. Values outside the range get the color at the nearest end.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorRamp {
+    /// Color by fire power from 0 to 3,000 MW.
+    #[default]
+    ByPower,
+    /// Color by fire temperature from `min_k` to `max_k` Kelvin.
+    ///
+    /// Use [ColorRamp::by_temperature] to check the range. If `min_k` isn't less than `max_k`
+    /// every pixel gets the color at the bottom of the ramp.
+    ByTemperature { min_k: f64, max_k: f64 },
+}
+
+impl ColorRamp {
+    /// Color by fire temperature from `min_k` to `max_k` Kelvin.
+    ///
+    /// Returns an error unless `min_k` is less than `max_k` and both are finite.
+    pub fn by_temperature(min_k: f64, max_k: f64) -> SatFireResult<Self> {
+        if !min_k.is_finite() || !max_k.is_finite() || min_k >= max_k {
+            return Err(format!(
+                "Invalid temperature range for color ramp: {} to {}",
+                min_k, max_k
+            )
+            .into());
+        }
+
+        Ok(ColorRamp::ByTemperature { min_k, max_k })
+    }
+
+    /// Get the KML color of a pixel as a hex string in aabbggrr order.
+    pub fn kml_color(&self, pixel: &Pixel) -> String {
+        match *self {
+            ColorRamp::ByPower => Self::ramp_color(pixel.power, 0.0, 3_000.0),
+            ColorRamp::ByTemperature { min_k, max_k } => {
+                Self::ramp_color(pixel.temperature, min_k, max_k)
+            }
+        }
+    }
+
+    fn ramp_color(value: f64, min: f64, max: f64) -> String {
         const MAX_GREEN_FOR_ORANGE: f64 = 0.647;
-        const FULL_RED_POWER: f64 = MAX_POWER / 2.0;
+        let full_red = min + (max - min) / 2.0;
 
         let rd = 1.0;
         let gd;
         let mut bd = 0.0;
         let ad = 0.6;
 
-        if power.is_infinite() {
-            power = MAX_POWER;
+        if min.is_nan() || max.is_nan() || min >= max {
+            return Self::ramp_color(0.0, 0.0, 1.0);
         }
 
-        power = power.min(MAX_POWER);
+        let value = value.min(max).max(min);
 
-        if power <= FULL_RED_POWER {
-            gd = (FULL_RED_POWER - power) / FULL_RED_POWER * MAX_GREEN_FOR_ORANGE;
+        if value <= full_red {
+            gd = (full_red - value) / (full_red - min) * MAX_GREEN_FOR_ORANGE;
         } else {
-            gd = (power - FULL_RED_POWER) / (MAX_POWER - FULL_RED_POWER);
+            gd = (value - full_red) / (max - full_red);
             bd = gd;
         }
 
@@ -657,14 +1295,16 @@ impl PixelList {
         debug_assert!(ri < 256 && gi < 256 && bi < 256 && ai < 256);
         debug_assert!(ri >= 0 && gi >= 0 && bi >= 0 && ai >= 0);
 
-        let mut color: [u8; 9] = [0; 9];
-        let mut cursor = std::io::Cursor::new(&mut color[..]);
-        let _ = write!(cursor, "{:02X}{:02X}{:02X}{:02X}", ai, bi, gi, ri);
+        format!("{:02X}{:02X}{:02X}{:02X}", ai, bi, gi, ri)
+    }
+}
 
-        let color = std::str::from_utf8(&color[0..8]).unwrap();
+impl PixelList {
+    fn kml_write_pixel_style<K: KmlWriter>(kml: &mut K, pixel: &Pixel, ramp: ColorRamp) {
+        let color = ramp.kml_color(pixel);
 
         let _ = kml.start_style(None);
-        let _ = kml.create_poly_style(Some(color), true, false);
+        let _ = kml.create_poly_style(Some(&color), true, false);
         let _ = kml.finish_style();
     }
 
@@ -675,6 +1315,11 @@ impl PixelList {
     /// document.
     ///
     pub fn kml_write<K: KmlWriter>(&self, kml: &mut K) {
+        self.kml_write_with_ramp(kml, ColorRamp::default())
+    }
+
+    /// Write out a pixel list in KML format, coloring the pixels with the given [ColorRamp].
+    pub fn kml_write_with_ramp<K: KmlWriter>(&self, kml: &mut K, ramp: ColorRamp) {
         for pixel in &self.0 {
             let mut desc: [u8; 256] = [0; 256];
             let mut cursor = std::io::Cursor::new(&mut desc[..]);
@@ -704,7 +1349,7 @@ impl PixelList {
             let desc = unsafe { std::str::from_utf8_unchecked(&desc[..position]) };
             kml.start_placemark(None, Some(desc), None).unwrap();
 
-            Self::kml_write_pixel_style(kml, pixel.power);
+            Self::kml_write_pixel_style(kml, pixel, ramp);
             kml.start_polygon(true, true, Some("clampToGround"))
                 .unwrap();
             kml.polygon_start_outer_ring().unwrap();
@@ -733,22 +1378,18 @@ impl PixelList {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::testutil::pixel_with_corners;
 
     #[test]
     #[rustfmt::skip]
     fn test_satfire_pixel_centroid() {
-        let pxl = Pixel { 
-            ul: Coord {lat: 45.0, lon: -120.0},
-            ll: Coord {lat: 44.0, lon: -120.0},
-            lr: Coord {lat: 44.0, lon: -119.0},
-            ur: Coord {lat: 45.0, lon: -119.0},
-            power: 0.0,
-            area: 0.0,
-            temperature: 0.0,
-            scan_angle: 0.0,
-            mask_flag: MaskCode(0),
-            data_quality_flag: DataQualityFlagCode(0),
-        };
+        let pxl = pixel_with_corners(
+            Coord {lat: 45.0, lon: -120.0},
+            Coord {lat: 44.0, lon: -120.0},
+            Coord {lat: 44.0, lon: -119.0},
+            Coord {lat: 45.0, lon: -119.0},
+            0.0,
+        );
 
         let centroid = Coord {lat: 44.5, lon: -119.5};
         let centroid_calc = pxl.centroid();
@@ -759,31 +1400,21 @@ mod test {
     #[test]
     #[rustfmt::skip]
     fn test_satfire_pixels_approx_equal() {
-        let pxl1 = Pixel {
-            ul: Coord {lat: 45.0, lon: -120.0},
-            ll: Coord {lat: 44.0, lon: -120.0},
-            lr: Coord {lat: 44.0, lon: -119.0},
-            ur: Coord {lat: 45.0, lon: -119.0},
-            power: 0.0,
-            area: 0.0,
-            temperature: 0.0,
-            scan_angle: 0.0,
-            mask_flag: MaskCode(0),
-            data_quality_flag: DataQualityFlagCode(0),
-        };
-
-        let pxl2 = Pixel {
-            ul: Coord {lat: 45.0000002, lon: -120.0000002},
-            ll: Coord {lat: 44.0000002, lon: -119.9999998},
-            lr: Coord {lat: 43.9999998, lon: -119.0000002},
-            ur: Coord {lat: 44.9999998, lon: -118.9999998},
-            power: 0.0,
-            area: 0.0,
-            temperature: 0.0,
-            scan_angle: 0.0,
-            mask_flag: MaskCode(0),
-            data_quality_flag: DataQualityFlagCode(0),
-        };
+        let pxl1 = pixel_with_corners(
+            Coord {lat: 45.0, lon: -120.0},
+            Coord {lat: 44.0, lon: -120.0},
+            Coord {lat: 44.0, lon: -119.0},
+            Coord {lat: 45.0, lon: -119.0},
+            0.0,
+        );
+
+        let pxl2 = pixel_with_corners(
+            Coord {lat: 45.0000002, lon: -120.0000002},
+            Coord {lat: 44.0000002, lon: -119.9999998},
+            Coord {lat: 43.9999998, lon: -119.0000002},
+            Coord {lat: 44.9999998, lon: -118.9999998},
+            0.0,
+        );
 
         assert!(pxl1.approx_equal(&pxl1, 1.0e-6));
         assert!(pxl2.approx_equal(&pxl2, 1.0e-6));
@@ -795,27 +1426,14 @@ mod test {
     #[test]
     #[rustfmt::skip]
     fn test_satfire_pixel_contains_coord() {
-        let base = Pixel {
-            ul: Coord {lat: 0.0, lon: 0.0},
-            ll: Coord {lat: 0.0, lon: 0.0},
-            lr: Coord {lat: 0.0, lon: 0.0},
-            ur: Coord {lat: 0.0, lon: 0.0},
-            power: 0.0,
-            area: 0.0,
-            temperature: 0.0,
-            scan_angle: 0.0,
-            mask_flag: MaskCode(0),
-            data_quality_flag: DataQualityFlagCode(0),
-        };
-
         // This is a simple square of width & height 1 degree of latitude & longitude
-        let pxl1 = Pixel {
-            ul: Coord{lat: 45.0, lon: -120.0},
-            ll: Coord{lat: 44.0, lon: -120.0},
-            lr: Coord{lat: 44.0, lon: -119.0},
-            ur: Coord{lat: 45.0, lon: -119.0},
-            ..base
-        };
+        let pxl1 = pixel_with_corners(
+            Coord{lat: 45.0, lon: -120.0},
+            Coord{lat: 44.0, lon: -120.0},
+            Coord{lat: 44.0, lon: -119.0},
+            Coord{lat: 45.0, lon: -119.0},
+            0.0,
+        );
 
         let inside1 = Coord {lat: 44.5, lon: -119.5};
 
@@ -849,13 +1467,13 @@ mod test {
         assert!(!pxl1.contains_coord(boundary4, 1.0e-6));
 
         // This is a very skewed quadrilateral
-        let pxl2 = Pixel {
-            ul: Coord{lat: 3.0, lon: 2.0},
-            ll: Coord{lat: 0.0, lon: 0.0},
-            lr: Coord{lat: 2.0, lon: 2.0},
-            ur: Coord{lat: 5.0, lon: 4.0},
-            ..base
-        };
+        let pxl2 = pixel_with_corners(
+            Coord{lat: 3.0, lon: 2.0},
+            Coord{lat: 0.0, lon: 0.0},
+            Coord{lat: 2.0, lon: 2.0},
+            Coord{lat: 5.0, lon: 4.0},
+            0.0,
+        );
 
         let inside1 = Coord {lat: 2.5, lon: 2.0};
 
@@ -882,51 +1500,38 @@ mod test {
     #[test]
     #[rustfmt::skip]
     fn test_satfire_pixels_overlap() {
-        let base = Pixel {
-            ul: Coord {lat: 0.0, lon: 0.0},
-            ll: Coord {lat: 0.0, lon: 0.0},
-            lr: Coord {lat: 0.0, lon: 0.0},
-            ur: Coord {lat: 0.0, lon: 0.0},
-            power: 0.0,
-            area: 0.0,
-            temperature: 0.0,
-            scan_angle: 0.0,
-            mask_flag: MaskCode(0),
-            data_quality_flag: DataQualityFlagCode(0),
-        };
-
-        let pxl1 = Pixel {
-            ul: Coord{lat: 45.0, lon: -120.0},
-            ll: Coord{lat: 44.0, lon: -120.0},
-            lr: Coord{lat: 44.0, lon: -119.0},
-            ur: Coord{lat: 45.0, lon: -119.0},
-            ..base
-        };
-
-        let pxl2 = Pixel {
-            ul: Coord{lat: 45.5, lon: -120.5},
-            ll: Coord{lat: 44.5, lon: -120.5},
-            lr: Coord{lat: 44.5, lon: -119.5},
-            ur: Coord{lat: 45.5, lon: -119.5},
-            ..base
-        };
-
-        let pxl3 = Pixel {
-            ul: Coord{lat: 46.0, lon: -120.0},
-            ll: Coord{lat: 45.0, lon: -120.0},
-            lr: Coord{lat: 45.0, lon: -119.0},
-            ur: Coord{lat: 46.0, lon: -119.0},
-            ..base
-        };
+        let pxl1 = pixel_with_corners(
+            Coord{lat: 45.0, lon: -120.0},
+            Coord{lat: 44.0, lon: -120.0},
+            Coord{lat: 44.0, lon: -119.0},
+            Coord{lat: 45.0, lon: -119.0},
+            0.0,
+        );
+
+        let pxl2 = pixel_with_corners(
+            Coord{lat: 45.5, lon: -120.5},
+            Coord{lat: 44.5, lon: -120.5},
+            Coord{lat: 44.5, lon: -119.5},
+            Coord{lat: 45.5, lon: -119.5},
+            0.0,
+        );
+
+        let pxl3 = pixel_with_corners(
+            Coord{lat: 46.0, lon: -120.0},
+            Coord{lat: 45.0, lon: -120.0},
+            Coord{lat: 45.0, lon: -119.0},
+            Coord{lat: 46.0, lon: -119.0},
+            0.0,
+        );
 
         // The corners of pxl4 lie along the mid-points of pxl1. So they overlap.
-        let pxl4 = Pixel {
-            ul: Coord{lat: 45.0, lon: -119.5},
-            ll: Coord{lat: 44.5, lon: -120.0},
-            lr: Coord{lat: 44.0, lon: -119.5},
-            ur: Coord{lat: 44.5, lon: -119.0},
-            ..base
-        };
+        let pxl4 = pixel_with_corners(
+            Coord{lat: 45.0, lon: -119.5},
+            Coord{lat: 44.5, lon: -120.0},
+            Coord{lat: 44.0, lon: -119.5},
+            Coord{lat: 44.5, lon: -119.0},
+            0.0,
+        );
 
         // pixels are always overlapping themselves.
         assert!(pxl1.overlap(&pxl1, 1.0e-6));
@@ -970,92 +1575,164 @@ mod test {
 
     #[test]
     #[rustfmt::skip]
-    fn test_satfire_pixels_are_adjacent()
-    {
-        let base = Pixel {
-            ul: Coord {lat: 0.0, lon: 0.0},
-            ll: Coord {lat: 0.0, lon: 0.0},
-            lr: Coord {lat: 0.0, lon: 0.0},
-            ur: Coord {lat: 0.0, lon: 0.0},
-            power: 0.0,
-            area: 0.0,
-            temperature: 0.0,
-            scan_angle: 0.0,
-            mask_flag: MaskCode(0),
-            data_quality_flag: DataQualityFlagCode(0),
-        };
-
-        let pxl_nw = Pixel {
-            ul: Coord {lat: 46.0, lon: -121.0},
-            ll: Coord {lat: 45.0, lon: -121.0},
-            lr: Coord {lat: 45.0, lon: -120.0},
-            ur: Coord {lat: 46.0, lon: -120.0},
-            ..base
-        };
-
-        let pxl_nn = Pixel {
-            ul: Coord {lat: 46.0, lon: -120.0},
-            ll: Coord {lat: 45.0, lon: -120.0},
-            lr: Coord {lat: 45.0, lon: -119.0},
-            ur: Coord {lat: 46.0, lon: -119.0},
-            ..base
-        };
-
-        let pxl_ne = Pixel {
-            ul: Coord {lat: 46.0, lon: -119.0},
-            ll: Coord {lat: 45.0, lon: -119.0},
-            lr: Coord {lat: 45.0, lon: -118.0},
-            ur: Coord {lat: 46.0, lon: -118.0},
-            ..base
-        };
-
-        let pxl_ww = Pixel {
-            ul: Coord {lat: 45.0000002, lon: -121.0000002},
-            ll: Coord {lat: 44.0000002, lon: -120.9999998},
-            lr: Coord {lat: 43.9999998, lon: -120.0000002},
-            ur: Coord {lat: 44.9999998, lon: -119.9999998},
-            ..base
-        };
+    fn test_satfire_pixels_overlap_area_fraction() {
+        let pxl1 = pixel_with_corners(
+            Coord{lat: 45.0, lon: -120.0},
+            Coord{lat: 44.0, lon: -120.0},
+            Coord{lat: 44.0, lon: -119.0},
+            Coord{lat: 45.0, lon: -119.0},
+            0.0,
+        );
+
+        // Offset by half a pixel in both directions.
+        let pxl2 = pixel_with_corners(
+            Coord{lat: 45.5, lon: -120.5},
+            Coord{lat: 44.5, lon: -120.5},
+            Coord{lat: 44.5, lon: -119.5},
+            Coord{lat: 45.5, lon: -119.5},
+            0.0,
+        );
+
+        // Adjacent to pxl1.
+        let pxl3 = pixel_with_corners(
+            Coord{lat: 46.0, lon: -120.0},
+            Coord{lat: 45.0, lon: -120.0},
+            Coord{lat: 45.0, lon: -119.0},
+            Coord{lat: 46.0, lon: -119.0},
+            0.0,
+        );
+
+        // The corners of pxl4 lie along the mid-points of pxl1, so it is entirely inside.
+        let pxl4 = pixel_with_corners(
+            Coord{lat: 45.0, lon: -119.5},
+            Coord{lat: 44.5, lon: -120.0},
+            Coord{lat: 44.0, lon: -119.5},
+            Coord{lat: 44.5, lon: -119.0},
+            0.0,
+        );
+
+        // Offset by half a pixel to the east only.
+        let pxl5 = pixel_with_corners(
+            Coord{lat: 45.0, lon: -119.5},
+            Coord{lat: 44.0, lon: -119.5},
+            Coord{lat: 44.0, lon: -118.5},
+            Coord{lat: 45.0, lon: -118.5},
+            0.0,
+        );
+
+        // Far away.
+        let pxl6 = pixel_with_corners(
+            Coord{lat: 35.0, lon: -100.0},
+            Coord{lat: 34.0, lon: -100.0},
+            Coord{lat: 34.0, lon: -99.0},
+            Coord{lat: 35.0, lon: -99.0},
+            0.0,
+        );
+
+        let eps = 1.0e-6;
+
+        assert_eq!(pxl1.overlap_area_fraction(&pxl1, eps), 1.0);
+        assert_eq!(pxl4.overlap_area_fraction(&pxl4, eps), 1.0);
+
+        // A quarter of a pixel overlaps, a little less than that in area because it's the
+        // northern quarter of pxl1 and the southern quarter of pxl2.
+        for (a, b) in [(&pxl1, &pxl2), (&pxl2, &pxl1)] {
+            let fraction = a.overlap_area_fraction(b, eps);
+            assert!((fraction - 0.25).abs() < 0.01, "{}", fraction);
+        }
 
-        let pxl_00 = Pixel {
-            ul: Coord {lat: 45.0, lon: -120.0},
-            ll: Coord {lat: 44.0, lon: -120.0},
-            lr: Coord {lat: 44.0, lon: -119.0},
-            ur: Coord {lat: 45.0, lon: -119.0},
-            ..base
-        };
+        // Not exactly half, the great circle between the corners of the overlap bulges north a
+        // little less than the one along the top of the whole pixel.
+        for (a, b) in [(&pxl1, &pxl5), (&pxl5, &pxl1)] {
+            let fraction = a.overlap_area_fraction(b, eps);
+            assert!((fraction - 0.5).abs() < 1.0e-5, "{}", fraction);
+        }
 
-        let pxl_ee = Pixel {
-            ul: Coord {lat: 45.0, lon: -119.0},
-            ll: Coord {lat: 44.0, lon: -119.0},
-            lr: Coord {lat: 44.0, lon: -118.0},
-            ur: Coord {lat: 45.0, lon: -118.0},
-            ..base
-        };
+        // pxl4 is the smaller pixel and it is entirely inside pxl1.
+        assert!((pxl1.overlap_area_fraction(&pxl4, eps) - 1.0).abs() < 1.0e-6);
+        assert!((pxl4.overlap_area_fraction(&pxl1, eps) - 1.0).abs() < 1.0e-6);
 
-        let pxl_sw = Pixel {
-            ul: Coord {lat: 44.0, lon: -121.0},
-            ll: Coord {lat: 43.0, lon: -121.0},
-            lr: Coord {lat: 43.0, lon: -120.0},
-            ur: Coord {lat: 44.0, lon: -120.0},
-            ..base
-        };
+        assert!(pxl1.overlap_area_fraction(&pxl3, eps) < 1.0e-6);
+        assert!(pxl3.overlap_area_fraction(&pxl1, eps) < 1.0e-6);
 
-        let pxl_ss = Pixel {
-            ul: Coord {lat: 44.0, lon: -120.0},
-            ll: Coord {lat: 43.0, lon: -120.0},
-            lr: Coord {lat: 43.0, lon: -119.0},
-            ur: Coord {lat: 44.0, lon: -119.0},
-            ..base
-        };
+        assert_eq!(pxl1.overlap_area_fraction(&pxl6, eps), 0.0);
+    }
 
-        let pxl_se = Pixel {
-            ul: Coord {lat: 44.0, lon: -119.0},
-            ll: Coord {lat: 43.0, lon: -119.0},
-            lr: Coord {lat: 43.0, lon: -118.0},
-            ur: Coord {lat: 44.0, lon: -118.0},
-            ..base
-        };
+    #[test]
+    #[rustfmt::skip]
+    fn test_satfire_pixels_are_adjacent()
+    {
+        let pxl_nw = pixel_with_corners(
+            Coord {lat: 46.0, lon: -121.0},
+            Coord {lat: 45.0, lon: -121.0},
+            Coord {lat: 45.0, lon: -120.0},
+            Coord {lat: 46.0, lon: -120.0},
+            0.0,
+        );
+
+        let pxl_nn = pixel_with_corners(
+            Coord {lat: 46.0, lon: -120.0},
+            Coord {lat: 45.0, lon: -120.0},
+            Coord {lat: 45.0, lon: -119.0},
+            Coord {lat: 46.0, lon: -119.0},
+            0.0,
+        );
+
+        let pxl_ne = pixel_with_corners(
+            Coord {lat: 46.0, lon: -119.0},
+            Coord {lat: 45.0, lon: -119.0},
+            Coord {lat: 45.0, lon: -118.0},
+            Coord {lat: 46.0, lon: -118.0},
+            0.0,
+        );
+
+        let pxl_ww = pixel_with_corners(
+            Coord {lat: 45.0000002, lon: -121.0000002},
+            Coord {lat: 44.0000002, lon: -120.9999998},
+            Coord {lat: 43.9999998, lon: -120.0000002},
+            Coord {lat: 44.9999998, lon: -119.9999998},
+            0.0,
+        );
+
+        let pxl_00 = pixel_with_corners(
+            Coord {lat: 45.0, lon: -120.0},
+            Coord {lat: 44.0, lon: -120.0},
+            Coord {lat: 44.0, lon: -119.0},
+            Coord {lat: 45.0, lon: -119.0},
+            0.0,
+        );
+
+        let pxl_ee = pixel_with_corners(
+            Coord {lat: 45.0, lon: -119.0},
+            Coord {lat: 44.0, lon: -119.0},
+            Coord {lat: 44.0, lon: -118.0},
+            Coord {lat: 45.0, lon: -118.0},
+            0.0,
+        );
+
+        let pxl_sw = pixel_with_corners(
+            Coord {lat: 44.0, lon: -121.0},
+            Coord {lat: 43.0, lon: -121.0},
+            Coord {lat: 43.0, lon: -120.0},
+            Coord {lat: 44.0, lon: -120.0},
+            0.0,
+        );
+
+        let pxl_ss = pixel_with_corners(
+            Coord {lat: 44.0, lon: -120.0},
+            Coord {lat: 43.0, lon: -120.0},
+            Coord {lat: 43.0, lon: -119.0},
+            Coord {lat: 44.0, lon: -119.0},
+            0.0,
+        );
+
+        let pxl_se = pixel_with_corners(
+            Coord {lat: 44.0, lon: -119.0},
+            Coord {lat: 43.0, lon: -119.0},
+            Coord {lat: 43.0, lon: -118.0},
+            Coord {lat: 44.0, lon: -118.0},
+            0.0,
+        );
 
         // Pixels are not adjacent to themselves.
         assert!(!pxl_nw.is_adjacent_to(&pxl_nw, 1.0e-6));
@@ -1313,25 +1990,25 @@ mod test {
         // any vertices that aren't close aren't contained inside the other pixel.
 
         // This pixel is inside pxl_00, but it shares a common lower right corner
-        let sub_pxl_01 = Pixel {
-            ul: Coord {lat: 44.5, lon: -119.5},
-            ll: Coord {lat: 44.0, lon: -119.5},
-            lr: Coord {lat: 44.0, lon: -119.0},
-            ur: Coord {lat: 44.5, lon: -119.0},
-            ..base
-        };
+        let sub_pxl_01 = pixel_with_corners(
+            Coord {lat: 44.5, lon: -119.5},
+            Coord {lat: 44.0, lon: -119.5},
+            Coord {lat: 44.0, lon: -119.0},
+            Coord {lat: 44.5, lon: -119.0},
+            0.0,
+        );
 
         assert!(!pxl_00.is_adjacent_to(&sub_pxl_01, 1.0e-6));
         assert!(!sub_pxl_01.is_adjacent_to(&pxl_00, 1.0e-6));
 
         // This pixel overlaps pxl_00 and shares a right edge. These overlap, but aren't adjacent.
-        let sub_pxl_02 = Pixel {
-            ul: Coord {lat: 45.0, lon: -119.5},
-            ll: Coord {lat: 44.0, lon: -119.5},
-            lr: Coord {lat: 44.0, lon: -119.0},
-            ur: Coord {lat: 45.0, lon: -119.0},
-            ..base
-        };
+        let sub_pxl_02 = pixel_with_corners(
+            Coord {lat: 45.0, lon: -119.5},
+            Coord {lat: 44.0, lon: -119.5},
+            Coord {lat: 44.0, lon: -119.0},
+            Coord {lat: 45.0, lon: -119.0},
+            0.0,
+        );
 
         assert!(!pxl_00.is_adjacent_to(&sub_pxl_02, 1.0e-6));
         assert!(!sub_pxl_02.is_adjacent_to(&pxl_00, 1.0e-6));
@@ -1340,99 +2017,843 @@ mod test {
     #[rustfmt::skip]
     fn pixel_list_test_setup() -> PixelList {
 
-        let base = Pixel {
-            ul: Coord {lat: 0.0, lon: 0.0},
-            ll: Coord {lat: 0.0, lon: 0.0},
-            lr: Coord {lat: 0.0, lon: 0.0},
-            ur: Coord {lat: 0.0, lon: 0.0},
-            power: 0.0,
-            area: 0.0,
-            temperature: 0.0,
-            scan_angle: 0.0,
-            mask_flag: MaskCode(0),
-            data_quality_flag: DataQualityFlagCode(0),
-        };
+        let pixels = [
+            pixel_with_corners(
+                Coord {lat: 46.0, lon: -121.0},
+                Coord {lat: 45.0, lon: -121.0},
+                Coord {lat: 45.0, lon: -120.0},
+                Coord {lat: 46.0, lon: -120.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 46.0, lon: -120.0},
+                Coord {lat: 45.0, lon: -120.0},
+                Coord {lat: 45.0, lon: -119.0},
+                Coord {lat: 46.0, lon: -119.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 46.0, lon: -119.0},
+                Coord {lat: 45.0, lon: -119.0},
+                Coord {lat: 45.0, lon: -118.0},
+                Coord {lat: 46.0, lon: -118.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 45.0000002, lon: -121.0000002},
+                Coord {lat: 44.0000002, lon: -120.9999998},
+                Coord {lat: 43.9999998, lon: -120.0000002},
+                Coord {lat: 44.9999998, lon: -119.9999998},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 45.0, lon: -120.0},
+                Coord {lat: 44.0, lon: -120.0},
+                Coord {lat: 44.0, lon: -119.0},
+                Coord {lat: 45.0, lon: -119.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 45.0, lon: -119.0},
+                Coord {lat: 44.0, lon: -119.0},
+                Coord {lat: 44.0, lon: -118.0},
+                Coord {lat: 45.0, lon: -118.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 44.0, lon: -121.0},
+                Coord {lat: 43.0, lon: -121.0},
+                Coord {lat: 43.0, lon: -120.0},
+                Coord {lat: 44.0, lon: -120.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 44.0, lon: -120.0},
+                Coord {lat: 43.0, lon: -120.0},
+                Coord {lat: 43.0, lon: -119.0},
+                Coord {lat: 44.0, lon: -119.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 44.0, lon: -119.0},
+                Coord {lat: 43.0, lon: -119.0},
+                Coord {lat: 43.0, lon: -118.0},
+                Coord {lat: 44.0, lon: -118.0},
+                0.0,
+            ),
+        ];
+
+        let mut plist = PixelList::with_capacity(9);
+        for p in pixels {
+            plist.push(p);
+        }
+
+        plist
+    }
 
+    #[test]
+    #[rustfmt::skip]
+    fn satfire_pixel_list_test_convex_hull() {
+        // An L shaped set of pixels, the inside corner at (1, 1) is not on the hull.
         let pixels = [
-            Pixel {
-             ul: Coord {lat: 46.0, lon: -121.0},
-             ll: Coord {lat: 45.0, lon: -121.0},
-             lr: Coord {lat: 45.0, lon: -120.0},
-             ur: Coord {lat: 46.0, lon: -120.0},
-             ..base
-            },
+            pixel_with_corners(
+                Coord {lat: 1.0, lon: 0.0},
+                Coord {lat: 0.0, lon: 0.0},
+                Coord {lat: 0.0, lon: 1.0},
+                Coord {lat: 1.0, lon: 1.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 1.0, lon: 1.0},
+                Coord {lat: 0.0, lon: 1.0},
+                Coord {lat: 0.0, lon: 2.0},
+                Coord {lat: 1.0, lon: 2.0},
+                0.0,
+            ),
+
+            pixel_with_corners(
+                Coord {lat: 2.0, lon: 0.0},
+                Coord {lat: 1.0, lon: 0.0},
+                Coord {lat: 1.0, lon: 1.0},
+                Coord {lat: 2.0, lon: 1.0},
+                0.0,
+            ),
+        ];
 
-            Pixel {
-             ul: Coord {lat: 46.0, lon: -120.0},
-             ll: Coord {lat: 45.0, lon: -120.0},
-             lr: Coord {lat: 45.0, lon: -119.0},
-             ur: Coord {lat: 46.0, lon: -119.0},
-             ..base
-            },
+        let mut plist = PixelList::new();
+        for p in pixels {
+            plist.push(p);
+        }
 
-            Pixel {
-             ul: Coord {lat: 46.0, lon: -119.0},
-             ll: Coord {lat: 45.0, lon: -119.0},
-             lr: Coord {lat: 45.0, lon: -118.0},
-             ur: Coord {lat: 46.0, lon: -118.0},
-             ..base
-            },
+        let hull = plist.convex_hull();
 
-            Pixel {
-             ul: Coord {lat: 45.0000002, lon: -121.0000002},
-             ll: Coord {lat: 44.0000002, lon: -120.9999998},
-             lr: Coord {lat: 43.9999998, lon: -120.0000002},
-             ur: Coord {lat: 44.9999998, lon: -119.9999998},
-             ..base
-            },
+        let expected = [
+            Coord {lat: 0.0, lon: 0.0},
+            Coord {lat: 0.0, lon: 2.0},
+            Coord {lat: 1.0, lon: 2.0},
+            Coord {lat: 2.0, lon: 1.0},
+            Coord {lat: 2.0, lon: 0.0},
+            Coord {lat: 0.0, lon: 0.0},
+        ];
 
-            Pixel {
-             ul: Coord {lat: 45.0, lon: -120.0},
-             ll: Coord {lat: 44.0, lon: -120.0},
-             lr: Coord {lat: 44.0, lon: -119.0},
-             ur: Coord {lat: 45.0, lon: -119.0},
-             ..base
-            },
+        assert_eq!(hull.len(), expected.len());
+        for (h, e) in hull.iter().zip(expected.iter()) {
+            assert!(h.is_close(*e, 1.0e-12), "{:?} != {:?}", h, e);
+        }
 
-            Pixel {
-             ul: Coord {lat: 45.0, lon: -119.0},
-             ll: Coord {lat: 44.0, lon: -119.0},
-             lr: Coord {lat: 44.0, lon: -118.0},
-             ur: Coord {lat: 45.0, lon: -118.0},
-             ..base
+        assert!(PixelList::new().convex_hull().is_empty());
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_centroid_skips_degenerate_pixels() {
+        let mut plist = pixel_list_test_setup();
+        let expected = plist.centroid();
+        assert!(expected.lat.is_finite() && expected.lon.is_finite());
+
+        let degenerate = Pixel {
+            ul: Coord {
+                lat: f64::NAN,
+                lon: f64::NAN,
             },
+            ..plist.0[0]
+        };
+        assert!(degenerate.centroid().lat.is_nan());
+
+        plist.push(degenerate);
+        let centroid = plist.centroid();
+        assert!(centroid.is_close(expected, 1.0e-12));
+
+        assert!(PixelList::new().centroid().lat.is_nan());
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_color_ramps() {
+        let pixel = Pixel {
+            power: 100.0,
+            temperature: 1_400.0,
+            ..pixel_list_test_setup().0[0]
+        };
+
+        let by_power = ColorRamp::default();
+        let by_temperature = ColorRamp::by_temperature(500.0, 1_500.0).unwrap();
+        assert_eq!(by_power, ColorRamp::ByPower);
+        assert_eq!(
+            by_temperature,
+            ColorRamp::ByTemperature {
+                min_k: 500.0,
+                max_k: 1_500.0
+            }
+        );
 
+        assert!(ColorRamp::by_temperature(1_500.0, 500.0).is_err());
+        assert!(ColorRamp::by_temperature(500.0, 500.0).is_err());
+        assert!(ColorRamp::by_temperature(f64::NAN, 500.0).is_err());
+
+        // Built directly with a bad range, every pixel is the bottom of the ramp.
+        for (min_k, max_k) in [(500.0, 500.0), (1_500.0, 500.0), (f64::NAN, 500.0)] {
+            let ramp = ColorRamp::ByTemperature { min_k, max_k };
+            assert_eq!(ramp.kml_color(&pixel), "9900A4FF");
+        }
+
+        // A weak fire is orange by power, but a hot one is nearly white by temperature.
+        assert_eq!(by_power.kml_color(&pixel), "990099FF");
+        assert_eq!(by_temperature.kml_color(&pixel), "99CCCCFF");
+
+        let mut plist = PixelList::new();
+        plist.push(pixel);
+
+        let color_in_kml = |ramp: ColorRamp| {
+            let path = std::env::temp_dir().join(format!(
+                "satfire_test_color_ramps_{}.kml",
+                std::process::id()
+            ));
+            {
+                let mut kml = crate::KmlFile::new(&path).unwrap();
+                plist.kml_write_with_ramp(&mut kml, ramp);
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            let start = text.find("<color>").unwrap() + "<color>".len();
+            let end = text[start..].find("</color>").unwrap() + start;
+            text[start..end].to_owned()
+        };
+
+        assert_eq!(color_in_kml(by_power), by_power.kml_color(&pixel));
+        assert_eq!(
+            color_in_kml(by_temperature),
+            by_temperature.kml_color(&pixel)
+        );
+        assert_ne!(color_in_kml(by_power), color_in_kml(by_temperature));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn satfire_pixel_list_test_contains_coord() {
+        // The very skewed quadrilateral from test_satfire_pixel_contains_coord.
+        let skewed = pixel_with_corners(
+            Coord{lat: 3.0, lon: 2.0},
+            Coord{lat: 0.0, lon: 0.0},
+            Coord{lat: 2.0, lon: 2.0},
+            Coord{lat: 5.0, lon: 4.0},
+            0.0,
+        );
+
+        // This one leans the other way, so the upper left corner is west of both lower corners.
+        let leaning = pixel_with_corners(
+            Coord{lat: 13.0, lon: -1.0},
+            Coord{lat: 10.0, lon: 0.0},
+            Coord{lat: 10.0, lon: 2.0},
+            Coord{lat: 13.0, lon: 1.0},
+            0.0,
+        );
+
+        let mut plist = PixelList::new();
+        assert!(!plist.contains_coord(Coord {lat: 2.5, lon: 2.0}, 1.0e-6));
+
+        plist.push(skewed);
+        plist.push(leaning);
+
+        // Inside each pixel.
+        assert!(plist.contains_coord(Coord {lat: 2.5, lon: 2.0}, 1.0e-6));
+        assert!(plist.contains_coord(Coord {lat: 12.5, lon: -0.5}, 1.0e-6));
+
+        // Inside the bounding box of the list, but not inside any pixel.
+        assert!(!plist.contains_coord(Coord {lat: 2.0, lon: 1.0}, 1.0e-6));
+        assert!(!plist.contains_coord(Coord {lat: 7.0, lon: 1.0}, 1.0e-6));
+
+        // On the boundary of a pixel, or outside the box entirely.
+        assert!(!plist.contains_coord(Coord {lat: 1.0, lon: 1.0}, 1.0e-6));
+        assert!(!plist.contains_coord(Coord {lat: -1.5, lon: -119.5}, 1.0e-6));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn satfire_pixel_test_serde_round_trip() {
+        let pixel = Pixel {
+            power: 12.5,
+            area: 1_000.0,
+            temperature: 650.0,
+            scan_angle: 5.5,
+            mask_flag: MaskCode(10),
+            data_quality_flag: DataQualityFlagCode(0),
+            acquisition_offset: 42.0,
+            ..pixel_list_test_setup().0[0]
+        };
+
+        let json = serde_json::to_string(&pixel).unwrap();
+        assert!(json.contains(r#""mask_flag":10"#));
+
+        let back: Pixel = serde_json::from_str(&json).unwrap();
+        assert!(back.approx_equal(&pixel, 0.0));
+        assert_eq!(back.power, pixel.power);
+        assert_eq!(back.area, pixel.area);
+        assert_eq!(back.temperature, pixel.temperature);
+        assert_eq!(back.scan_angle, pixel.scan_angle);
+        assert_eq!(back.mask_flag.0, pixel.mask_flag.0);
+        assert_eq!(back.data_quality_flag.0, pixel.data_quality_flag.0);
+        assert_eq!(back.acquisition_offset, pixel.acquisition_offset);
+
+        let plist = pixel_list_test_setup();
+        let json = serde_json::to_string(&plist).unwrap();
+        let back: PixelList = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.len(), plist.len());
+        assert!(back
+            .iter()
+            .zip(plist.iter())
+            .all(|(a, b)| a.approx_equal(b, 0.0)));
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_max_merge_dedup() {
+        let conus = pixel_list_test_setup();
+
+        // The full disk view of the second pixel is a little off from the CONUS view, by more
+        // than the tolerance max_merge uses.
+        let shift = |c: Coord| Coord {
+            lat: c.lat + 0.02,
+            lon: c.lon - 0.02,
+        };
+        let p = conus.0[1];
+        let full_disk_pixel = Pixel {
+            ul: shift(p.ul),
+            ll: shift(p.ll),
+            lr: shift(p.lr),
+            ur: shift(p.ur),
+            power: p.power + 10.0,
+            area: p.area + 100.0,
+            temperature: p.temperature + 50.0,
+            ..p
+        };
+        let mut full_disk = PixelList::new();
+        full_disk.push(full_disk_pixel);
+
+        let mut merged = conus.clone();
+        merged.max_merge(&full_disk);
+        assert_eq!(merged.len(), conus.len() + 1);
+
+        let mut merged = conus.clone();
+        merged.max_merge_dedup(&full_disk, 0.05);
+        assert_eq!(merged.len(), conus.len());
+
+        let kept = merged.iter().find(|px| px.approx_equal(&p, 0.05)).unwrap();
+        assert_eq!(kept.power, full_disk_pixel.power);
+        assert_eq!(kept.area, full_disk_pixel.area);
+        assert_eq!(kept.temperature, full_disk_pixel.temperature);
+
+        // Duplicates already in the list are collapsed too.
+        let mut doubled = full_disk.clone();
+        doubled.push(p);
+        doubled.max_merge_dedup(&PixelList::new(), 0.05);
+        assert_eq!(doubled.len(), 1);
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_total_area() {
+        let mut plist = pixel_list_test_setup();
+        let expected: f64 = plist.iter().map(|p| p.area).sum();
+        assert!((plist.total_area() - expected).abs() < 1.0e-9);
+
+        // Pixels without a finite area are skipped.
+        plist.push(Pixel {
+            area: f64::NAN,
+            ..plist.0[0]
+        });
+        assert!((plist.total_area() - expected).abs() < 1.0e-9);
+
+        #[allow(deprecated)]
+        let old_name = plist.total_are();
+        assert_eq!(old_name, plist.total_area());
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_power_weighted_centroid() {
+        let west = Pixel {
+            power: 10.0,
+            ..pixel_list_test_setup().0[0]
+        };
+        let offset = |c: Coord| Coord {
+            lat: c.lat,
+            lon: c.lon + 1.0,
+        };
+        let east = Pixel {
+            ul: offset(west.ul),
+            ll: offset(west.ll),
+            lr: offset(west.lr),
+            ur: offset(west.ur),
+            power: 30.0,
+            ..west
+        };
+
+        let mut plist = PixelList::new();
+        plist.push(west);
+        plist.push(east);
+
+        // The geometric centroid is half way, the weighted one is 3/4 of the way to the east.
+        let uniform = plist.centroid();
+        let weighted = plist.power_weighted_centroid();
+        let west_lon = west.centroid().lon;
+        assert!((uniform.lon - (west_lon + 0.5)).abs() < 1.0e-12);
+        assert!((weighted.lon - (west_lon + 0.75)).abs() < 1.0e-12);
+        assert!((weighted.lat - uniform.lat).abs() < 1.0e-12);
+
+        // Pixels without a finite power are skipped.
+        plist.push(Pixel {
+            power: f64::NAN,
+            ..west
+        });
+        assert!(plist.power_weighted_centroid().is_close(weighted, 1.0e-12));
+
+        // Without any power it falls back to the geometric centroid.
+        let mut cold = PixelList::new();
+        cold.push(Pixel { power: 0.0, ..west });
+        cold.push(Pixel { power: 0.0, ..east });
+        assert!(cold.power_weighted_centroid().is_close(uniform, 1.0e-12));
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_spread_vector() {
+        const SIZE: f64 = 0.02;
+
+        let pixel_at = |x: f64, y: f64| {
+            pixel_with_corners(
+                Coord {
+                    lat: (y + 1.0) * SIZE,
+                    lon: x * SIZE,
+                },
+                Coord {
+                    lat: y * SIZE,
+                    lon: x * SIZE,
+                },
+                Coord {
+                    lat: y * SIZE,
+                    lon: (x + 1.0) * SIZE,
+                },
+                Coord {
+                    lat: (y + 1.0) * SIZE,
+                    lon: (x + 1.0) * SIZE,
+                },
+                10.0,
+            )
+        };
+
+        let mut previous = PixelList::new();
+        previous.push(pixel_at(0.0, 0.0));
+
+        // The fire spreads diagonally to the northeast.
+        let mut current = previous.clone();
+        current.push(pixel_at(1.0, 1.0));
+        current.push(pixel_at(2.0, 2.0));
+
+        let (bearing, distance) = current.spread_vector(&previous, 1.0e-6);
+        assert!((bearing - 45.0).abs() < 0.01, "bearing {}", bearing);
+
+        // From the center of the first pixel to the middle of the two new ones is 1.5 pixels in
+        // each direction.
+        let expected = Coord {
+            lat: 0.5 * SIZE,
+            lon: 0.5 * SIZE,
+        }
+        .distance_meters(Coord {
+            lat: 2.0 * SIZE,
+            lon: 2.0 * SIZE,
+        });
+        assert!((distance - expected).abs() < 1.0e-6);
+        assert!((distance - 4_717.0).abs() < 10.0);
+
+        let (bearing, distance) = previous.spread_vector(&previous, 1.0e-6);
+        assert!(bearing.is_nan());
+        assert_eq!(distance, 0.0);
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_jaccard() {
+        let plist = pixel_list_test_setup();
+        assert_eq!(plist.jaccard(&plist, 1.0e-6), 1.0);
+
+        let shift = |pixel: &Pixel| {
+            let shift_coord = |c: Coord| Coord {
+                lat: c.lat,
+                lon: c.lon + 10.0,
+            };
             Pixel {
-             ul: Coord {lat: 44.0, lon: -121.0},
-             ll: Coord {lat: 43.0, lon: -121.0},
-             lr: Coord {lat: 43.0, lon: -120.0},
-             ur: Coord {lat: 44.0, lon: -120.0},
-             ..base
+                ul: shift_coord(pixel.ul),
+                ll: shift_coord(pixel.ll),
+                lr: shift_coord(pixel.lr),
+                ur: shift_coord(pixel.ur),
+                ..*pixel
+            }
+        };
+        let mut disjoint = PixelList::new();
+        for pixel in &plist.0 {
+            disjoint.push(shift(pixel));
+        }
+        assert_eq!(plist.jaccard(&disjoint, 1.0e-6), 0.0);
+
+        // Three pixels in common, and one more only in the partial list.
+        let mut partial = PixelList::new();
+        for pixel in &plist.0[..3] {
+            partial.push(*pixel);
+        }
+        partial.push(disjoint.0[0]);
+        assert!((plist.jaccard(&partial, 1.0e-6) - 3.0 / 10.0).abs() < 1.0e-12);
+        assert!((partial.jaccard(&plist, 1.0e-6) - 3.0 / 10.0).abs() < 1.0e-12);
+
+        assert_eq!(PixelList::new().jaccard(&PixelList::new(), 1.0e-6), 0.0);
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_try_binary_deserialize() {
+        let plist = pixel_list_test_setup();
+        let buf = plist.binary_serialize();
+
+        let plist2 = PixelList::try_binary_deserialize(&buf).unwrap();
+        assert_eq!(plist2.len(), plist.len());
+
+        assert!(PixelList::try_binary_deserialize(&buf[..buf.len() - 1]).is_err());
+        assert!(PixelList::try_binary_deserialize(&buf[..4]).is_err());
+        assert!(PixelList::binary_deserialize(&mut &buf[..buf.len() - 1]).is_err());
+        assert!(PixelList::binary_deserialize(&mut &buf[..4]).is_err());
+
+        let mut extra = buf.clone();
+        extra.push(0);
+        assert!(PixelList::try_binary_deserialize(&extra).is_err());
+
+        let empty = PixelList::new().binary_serialize();
+        assert!(PixelList::try_binary_deserialize(&empty)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_legacy_binary_format() {
+        let plist = pixel_list_test_setup();
+
+        // Version 0 is just the length and the pixels.
+        let mut legacy = (plist.len() as u64).to_le_bytes().to_vec();
+        for pixel in plist.pixels() {
+            legacy.extend_from_slice(&pixel.to_bytes());
+        }
+
+        let from_slice = PixelList::try_binary_deserialize(&legacy).unwrap();
+        let from_reader = PixelList::binary_deserialize(&mut &legacy[..]).unwrap();
+        assert_eq!(from_slice.len(), plist.len());
+        assert_eq!(from_reader.len(), plist.len());
+        for ((p1, p2), p3) in plist.0.iter().zip(&from_slice.0).zip(&from_reader.0) {
+            assert!(p1.approx_equal(p2, 0.0));
+            assert!(p1.approx_equal(p3, 0.0));
+        }
+
+        // Reading an old BLOB and writing it back upgrades it to the current version.
+        assert_eq!(from_slice.binary_serialize(), plist.binary_serialize());
+
+        // Malformed input is an error instead of garbage.
+        assert!(PixelList::binary_deserialize(&mut &legacy[..legacy.len() - 1]).is_err());
+        assert!(PixelList::try_binary_deserialize(&legacy[..legacy.len() - 1]).is_err());
+
+        let mut future = plist.binary_serialize();
+        future[4] = 3;
+        assert!(PixelList::binary_deserialize(&mut &future[..]).is_err());
+        assert!(PixelList::try_binary_deserialize(&future).is_err());
+    }
+
+    #[test]
+    fn satfire_pixel_test_bytes_round_trip() {
+        let plist = pixel_list_test_setup();
+        let mut pixel = plist.0[0];
+        pixel.mask_flag = MaskCode(31);
+        pixel.data_quality_flag = DataQualityFlagCode(-1);
+
+        let mut written = Vec::new();
+        pixel.write_bytes(&mut written).unwrap();
+        assert_eq!(written.len(), Pixel::serialized_size());
+
+        let bytes = pixel.to_bytes();
+        assert_eq!(bytes.len(), Pixel::serialized_size());
+        assert_eq!(&bytes[..], &written[..]);
+        assert_eq!(&bytes[0..8], &pixel.ul.lat.to_le_bytes());
+        assert_eq!(&bytes[88..96], &pixel.scan_angle.to_le_bytes());
+        assert_eq!(&bytes[96..98], &31i16.to_le_bytes());
+        assert_eq!(&bytes[98..100], &(-1i16).to_le_bytes());
+
+        let pixel2 = Pixel::from_bytes(&bytes).unwrap();
+        assert!(pixel.approx_equal(&pixel2, f64::MIN));
+        assert_eq!(pixel2.mask_flag.0, 31);
+        assert_eq!(pixel2.data_quality_flag.0, -1);
+
+        assert!(Pixel::from_bytes(&bytes[1..]).is_err());
+        assert!(Pixel::from_bytes(&[0; 104]).is_err());
+
+        // A list is a header and then the pixels, each followed by its acquisition offset.
+        let buf = plist.binary_serialize();
+        assert_eq!(
+            buf.len(),
+            SERIALIZED_LIST_HEADER_SIZE + plist.len() * (Pixel::serialized_size() + 4)
+        );
+        assert_eq!(
+            &buf[SERIALIZED_LIST_HEADER_SIZE..][..Pixel::serialized_size()],
+            &plist.0[0].to_bytes()[..]
+        );
+    }
+
+    #[test]
+    fn satfire_pixel_test_accessors() {
+        let mut pixel = pixel_list_test_setup().0[0];
+        pixel.power = 12.5;
+        pixel.area = 300.0;
+        pixel.temperature = 650.0;
+        pixel.scan_angle = 4.25;
+        pixel.mask_flag = MaskCode(13);
+        pixel.data_quality_flag = DataQualityFlagCode(1);
+
+        assert_eq!(pixel.power(), 12.5);
+        assert_eq!(pixel.area(), 300.0);
+        assert_eq!(pixel.temperature(), 650.0);
+        assert_eq!(pixel.scan_angle(), 4.25);
+        assert_eq!(pixel.mask().0, 13);
+        assert_eq!(pixel.dqf().0, 1);
+
+        let [ul, ll, lr, ur] = pixel.corners();
+        assert!(ul.is_close(pixel.ul, 0.0) && ll.is_close(pixel.ll, 0.0));
+        assert!(lr.is_close(pixel.lr, 0.0) && ur.is_close(pixel.ur, 0.0));
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_clip_to_box() {
+        let plist = pixel_list_test_setup();
+
+        // Straddles the western and northern edges of the list.
+        let bbox = BoundingBox {
+            ll: Coord {
+                lat: 42.0,
+                lon: -120.2,
+            },
+            ur: Coord {
+                lat: 45.0,
+                lon: -117.0,
             },
+        };
 
-            Pixel {
-             ul: Coord {lat: 44.0, lon: -120.0},
-             ll: Coord {lat: 43.0, lon: -120.0},
-             lr: Coord {lat: 43.0, lon: -119.0},
-             ur: Coord {lat: 44.0, lon: -119.0},
-             ..base
+        let clipped = plist.clip_to_box(bbox);
+        assert_eq!(clipped.len(), 4);
+        for pixel in clipped.pixels() {
+            assert!(bbox.contains_coord(pixel.centroid(), 0.0));
+            assert!(plist.pixels().iter().any(|p| p.approx_equal(pixel, 0.0)));
+        }
+
+        // The original list is untouched.
+        assert!(plist.len() > clipped.len());
+
+        // A box away from the list keeps nothing.
+        let far_away = BoundingBox {
+            ll: Coord { lat: 0.0, lon: 0.0 },
+            ur: Coord { lat: 1.0, lon: 1.0 },
+        };
+        assert!(plist.clip_to_box(far_away).is_empty());
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_iter() {
+        let plist = pixel_list_test_setup();
+
+        assert_eq!(plist.iter().count(), plist.len());
+        for (a, b) in plist.iter().zip(plist.pixels()) {
+            assert!(a.approx_equal(b, 0.0));
+        }
+
+        let mut total_power = 0.0;
+        for pixel in &plist {
+            total_power += pixel.power;
+        }
+        assert_eq!(total_power, plist.total_power());
+
+        assert!(PixelList::new().iter().next().is_none());
+        assert!(PixelList::new().is_empty());
+    }
+
+    #[test]
+    fn satfire_pixel_test_geographic_area_m2() {
+        let pixel = pixel_with_corners(
+            Coord { lat: 1.0, lon: 0.0 },
+            Coord { lat: 0.0, lon: 0.0 },
+            Coord { lat: 0.0, lon: 1.0 },
+            Coord { lat: 1.0, lon: 1.0 },
+            0.0,
+        );
+
+        // The analytic area of a one degree box, whose top edge is a parallel instead of the
+        // great circle used here, so they agree very closely but not exactly.
+        let expected = crate::geo::lat_lon_cell_area(0.0, 1.0, 1.0, &Ellipsoid::GRS80);
+        let area = pixel.geographic_area_m2();
+        assert!((area - expected).abs() < 1.0e-4 * expected);
+
+        // A skewed pixel is the sum of the triangles on either side of a diagonal.
+        let skewed = Pixel {
+            ul: Coord { lat: 1.3, lon: 0.4 },
+            ur: Coord { lat: 0.9, lon: 1.6 },
+            ..pixel
+        };
+        let halves = spherical_excess_area(&[skewed.ul, skewed.ll, skewed.lr], &Ellipsoid::GRS80)
+            + spherical_excess_area(&[skewed.ul, skewed.lr, skewed.ur], &Ellipsoid::GRS80);
+        let skewed_area = skewed.geographic_area_m2();
+        assert!((skewed_area - halves).abs() < 1.0e-6 * halves);
+    }
+
+    #[test]
+    fn satfire_pixel_test_total_geographic_area_m2() {
+        let plist = pixel_list_test_setup();
+
+        let sum: f64 = plist.pixels().iter().map(|p| p.geographic_area_m2()).sum();
+        assert!(sum > 0.0);
+        assert!((plist.total_geographic_area_m2() - sum).abs() < 1.0e-6);
+
+        // The pixels come from the GOES projection, so the default is its ellipsoid.
+        let pixel = plist.0[0];
+        assert_eq!(
+            pixel.geographic_area_m2(),
+            pixel.geographic_area_m2_with_ellipsoid(&Ellipsoid::GRS80)
+        );
+
+        let mut bad = plist.0[0];
+        bad.ul.lat = f64::NAN;
+        let mut with_bad = plist.clone();
+        with_bad.push(bad);
+        assert!((with_bad.total_geographic_area_m2() - sum).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_binary_layout_is_portable() {
+        let mut pixel = pixel_with_corners(
+            Coord { lat: 1.0, lon: 2.0 },
+            Coord {
+                lat: 0.5,
+                lon: -2.0,
             },
+            Coord { lat: 0.0, lon: 0.0 },
+            Coord { lat: 0.0, lon: 0.0 },
+            0.0,
+        );
+        pixel.mask_flag = MaskCode(0x0102);
+        pixel.data_quality_flag = DataQualityFlagCode(-1);
+        pixel.acquisition_offset = 30.0;
+        let mut plist = PixelList::new();
+        plist.push(pixel);
+
+        // Written out by hand so this checks the exact bytes regardless of the host.
+        let mut expected = b"SFPL".to_vec();
+        expected.extend_from_slice(&[2, 0, 0, 0]);
+        expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0xf0, 0x3f]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0x00, 0x40]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0xe0, 0x3f]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0x00, 0xc0]);
+        expected.extend_from_slice(&[0; 40]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0x40, 0x8f, 0x40]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0xc0, 0x82, 0x40]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0x14, 0x40]);
+        expected.extend_from_slice(&[0x02, 0x01, 0xff, 0xff]);
+        expected.extend_from_slice(&[0, 0, 0xf0, 0x41]);
 
-            Pixel {
-             ul: Coord {lat: 44.0, lon: -119.0},
-             ll: Coord {lat: 43.0, lon: -119.0},
-             lr: Coord {lat: 43.0, lon: -118.0},
-             ur: Coord {lat: 44.0, lon: -118.0},
-             ..base
+        let buf = plist.binary_serialize();
+        assert_eq!(buf, expected);
+
+        let plist2 = PixelList::try_binary_deserialize(&buf).unwrap();
+        assert_eq!(plist2.len(), 1);
+        assert!(plist2.0[0].approx_equal(&pixel, 0.0));
+        assert_eq!(plist2.0[0].mask_flag.0, 0x0102);
+        assert_eq!(plist2.0[0].data_quality_flag.0, -1);
+        assert_eq!(plist2.0[0].acquisition_offset, 30.0);
+        assert_eq!(plist2.binary_serialize(), buf);
+
+        // Version 1 is the same without the acquisition offsets, which are read as 0.0.
+        let mut version_1 = expected[..expected.len() - 4].to_vec();
+        version_1[4] = 1;
+        let plist1 = PixelList::try_binary_deserialize(&version_1).unwrap();
+        let from_reader = PixelList::binary_deserialize(&mut &version_1[..]).unwrap();
+        assert!(plist1.0[0].approx_equal(&pixel, 0.0));
+        assert_eq!(plist1.0[0].acquisition_offset, 0.0);
+        assert_eq!(from_reader.0[0].acquisition_offset, 0.0);
+    }
+
+    #[test]
+    fn satfire_pixel_list_test_compressed_binary_round_trip() {
+        use crate::testutil::{PixelGrid, SyntheticRng};
+
+        // A 2 km grid near the edge of the CONUS sector with varied powers, like a large fire.
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
             },
-        ];
+            0.02,
+        );
+        let mut rng = SyntheticRng::new(1265);
+        let mut plist = PixelList::new();
+        for row in 0..20 {
+            for col in 0..25 {
+                plist.push(grid.pixel(col, row, rng.range(1.0, 500.0)));
+            }
+        }
 
-        let mut plist = PixelList::with_capacity(9);
-        for p in pixels {
-            plist.push(p);
+        let uncompressed = plist.binary_serialize();
+        let compressed = plist.binary_serialize_compressed().unwrap();
+        assert_eq!(&compressed[..4], b"SFPZ");
+        let ratio = compressed.len() as f64 / uncompressed.len() as f64;
+        assert!(
+            ratio < 0.5,
+            "compressed to {:.2} of the original size",
+            ratio
+        );
+
+        for buf in [&uncompressed, &compressed] {
+            let from_slice = PixelList::try_binary_deserialize(buf).unwrap();
+            let from_reader = PixelList::binary_deserialize(&mut &buf[..]).unwrap();
+            assert_eq!(from_slice.len(), plist.len());
+            assert_eq!(from_reader.len(), plist.len());
+            for ((p1, p2), p3) in plist.0.iter().zip(&from_slice.0).zip(&from_reader.0) {
+                assert!(p1.approx_equal(p2, 0.0));
+                assert!(p1.approx_equal(p3, 0.0));
+            }
+
+            // Reading a BLOB always writes it back uncompressed.
+            assert_eq!(from_slice.binary_serialize(), uncompressed);
         }
 
-        plist
+        let empty = PixelList::new().binary_serialize_compressed().unwrap();
+        assert!(PixelList::try_binary_deserialize(&empty)
+            .unwrap()
+            .is_empty());
+
+        // Malformed input is an error instead of garbage.
+        assert!(PixelList::try_binary_deserialize(&compressed[..compressed.len() - 1]).is_err());
+        assert!(PixelList::binary_deserialize(&mut &compressed[..compressed.len() - 1]).is_err());
+
+        let mut wrong_len = compressed.clone();
+        wrong_len[8] += 1;
+        assert!(PixelList::try_binary_deserialize(&wrong_len).is_err());
+        assert!(PixelList::binary_deserialize(&mut &wrong_len[..]).is_err());
+
+        // More pixels in the frame than the header claims, and a header claiming far too many.
+        let mut wrong_len = compressed.clone();
+        wrong_len[8] -= 1;
+        assert!(PixelList::try_binary_deserialize(&wrong_len).is_err());
+
+        let mut wrong_len = compressed.clone();
+        wrong_len[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(PixelList::try_binary_deserialize(&wrong_len).is_err());
+
+        let mut future = compressed;
+        future[4] = 3;
+        assert!(PixelList::try_binary_deserialize(&future).is_err());
     }
 
     #[test]
@@ -1443,7 +2864,7 @@ mod test {
         let buf = plist.binary_serialize();
         let mut cursor = std::io::Cursor::new(buf);
 
-        let plist2 = PixelList::binary_deserialize(&mut cursor);
+        let plist2 = PixelList::binary_deserialize(&mut cursor).unwrap();
 
         for (p1, p2) in plist.0.into_iter().zip(plist2.0.into_iter()) {
             assert!(p1.approx_equal(&p2, f64::MIN));
@@ -0,0 +1,402 @@
+//! Quality control of the fire detections.
+//!
+//! The GOES fire detection product has two quality fields for every pixel. The Mask field is a
+//! detailed category, like "good_fire_pixel", "temporally_filtered_saturated_fire_pixel", or one
+//! of many reasons a pixel was not processed. The DQF (data quality flag) field is a summary of
+//! the same quality control analysis with only a handful of values. Every fire pixel, whether it
+//! was temporally filtered or not, has a DQF of 0 ("good_quality_fire_pixel_qf"), so the DQF
+//! alone can't tell a high probability fire from a low probability one, and the Mask alone is
+//! harder to use for picking out good quality data.
+//!
+//! [ClusterQc] combines them in two steps:
+//!
+//!  1. When a file is loaded, only pixels with an accepted DQF are used to build clusters. By
+//!     default only DQF 0 is accepted, so every pixel in a cluster is a fire pixel.
+//!  2. A cluster is kept if at least one of its pixels has an accepted Mask code and the maximum
+//!     scan angle of the cluster is below a threshold. By default the accepted Mask codes are the
+//!     good, saturated, cloud contaminated, high probability, and medium probability fire pixels
+//!     (10-14) and their temporally filtered counterparts (30-34). The low probability fire pixels
+//!     (15 and 35) are not accepted on their own, but they are kept when they are part of a
+//!     cluster with a more certain pixel.
+//!
+//! Some locations are always hot, like gas flares, volcanoes, and factories. These can be removed
+//! with [ExclusionZones], which drop any cluster with a centroid inside one of the zones.
+
+use crate::{
+    cluster::Cluster,
+    geo::{Coord, Ellipsoid, Geo},
+    pixel::Pixel,
+    satellite::{DataQualityFlagCode, MaskCode, Satellite},
+    SatFireResult,
+};
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+/// The Mask codes [ClusterQc] accepts by default.
+const DEFAULT_MASK_CODES: [i16; 10] = [
+    10, // good_fire_pixel
+    11, // saturated_fire_pixel
+    12, // cloud_contaminated_fire_pixel
+    13, // high_probability_fire_pixel
+    14, // medium_probability_fire_pixel
+    30, // temporally_filtered_good_fire_pixel
+    31, // temporally_filtered_saturated_fire_pixel
+    32, // temporally_filtered_cloud_contaminated_fire_pixel
+    33, // temporally_filtered_high_probability_fire_pixel
+    34, // temporally_filtered_medium_probability_fire_pixel
+];
+
+/// The DQF codes [ClusterQc] accepts by default.
+const DEFAULT_DQF_CODES: [i16; 1] = [
+    0, // good_quality_fire_pixel_qf
+];
+
+/// How far inside the limb of the Earth, in degrees of scan angle, [ClusterQc] stops accepting
+/// clusters by default. Beyond this the pixels get so large that the detections aren't very
+/// useful.
+///
+/// The limb is at about 8.7 degrees, and 8.3 degrees was picked by visual inspection of a graph of
+/// cluster power vs max scan angle.
+const LIMB_SCAN_ANGLE_MARGIN: f64 = 0.4;
+
+/// The quality control applied to pixels and clusters.
+///
+/// Pixels are selected by their DQF when a file is loaded, then a cluster is kept if any of its
+/// pixels has an accepted Mask code and its scan angle is not too large. For example, to also keep
+/// clusters made up of only low probability fire pixels:
+///
+/// ```
+/// use satfire::{ClusterQc, MaskCode};
+///
+/// let qc = ClusterQc::default().accept_masks([MaskCode(15), MaskCode(35)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClusterQc {
+    mask_codes: Vec<i16>,
+    dqf_codes: Vec<i16>,
+    max_scan_angle: f64,
+    exclusion_zones: ExclusionZones,
+}
+
+impl Default for ClusterQc {
+    fn default() -> Self {
+        ClusterQc {
+            mask_codes: DEFAULT_MASK_CODES.to_vec(),
+            dqf_codes: DEFAULT_DQF_CODES.to_vec(),
+            max_scan_angle: Satellite::iter()
+                .map(ClusterQc::default_max_scan_angle)
+                .fold(f64::INFINITY, f64::min),
+            exclusion_zones: ExclusionZones::default(),
+        }
+    }
+}
+
+impl ClusterQc {
+    /// Accept these Mask codes in addition to the ones already accepted.
+    pub fn accept_masks<I: IntoIterator<Item = MaskCode>>(mut self, codes: I) -> Self {
+        for MaskCode(code) in codes {
+            if !self.mask_codes.contains(&code) {
+                self.mask_codes.push(code);
+            }
+        }
+        self
+    }
+
+    /// Stop accepting these Mask codes.
+    pub fn reject_masks<I: IntoIterator<Item = MaskCode>>(mut self, codes: I) -> Self {
+        for MaskCode(code) in codes {
+            self.mask_codes.retain(|&c| c != code);
+        }
+        self
+    }
+
+    /// Accept these DQF codes in addition to the ones already accepted.
+    pub fn accept_dqfs<I: IntoIterator<Item = DataQualityFlagCode>>(mut self, codes: I) -> Self {
+        for DataQualityFlagCode(code) in codes {
+            if !self.dqf_codes.contains(&code) {
+                self.dqf_codes.push(code);
+            }
+        }
+        self
+    }
+
+    /// Stop accepting these DQF codes.
+    pub fn reject_dqfs<I: IntoIterator<Item = DataQualityFlagCode>>(mut self, codes: I) -> Self {
+        for DataQualityFlagCode(code) in codes {
+            self.dqf_codes.retain(|&c| c != code);
+        }
+        self
+    }
+
+    /// The default maximum scan angle, in degrees, of a cluster to keep from `sat`.
+    ///
+    /// This is a little inside the [limb](Satellite::limb_scan_angle) of the Earth. The default
+    /// [ClusterQc] uses the smallest of these for all the satellites.
+    pub fn default_max_scan_angle(sat: Satellite) -> f64 {
+        sat.limb_scan_angle() - LIMB_SCAN_ANGLE_MARGIN
+    }
+
+    /// Set the maximum scan angle, in degrees, of a cluster to keep.
+    pub fn max_scan_angle(mut self, max_scan_angle: f64) -> Self {
+        self.max_scan_angle = max_scan_angle;
+        self
+    }
+
+    /// Drop clusters with a centroid in any of these zones.
+    pub fn exclusion_zones(mut self, zones: ExclusionZones) -> Self {
+        self.exclusion_zones = zones;
+        self
+    }
+
+    /// Is this DQF code accepted?
+    pub fn accepts_dqf(&self, dqf: DataQualityFlagCode) -> bool {
+        self.dqf_codes.contains(&dqf.0)
+    }
+
+    /// Is this Mask code accepted?
+    pub fn accepts_mask(&self, mask: MaskCode) -> bool {
+        self.mask_codes.contains(&mask.0)
+    }
+
+    /// Does this pixel pass both the Mask and DQF checks?
+    pub fn accepts_pixel(&self, pixel: &Pixel) -> bool {
+        self.accepts_mask(pixel.mask_flag) && self.accepts_dqf(pixel.data_quality_flag)
+    }
+
+    /// Should this cluster be kept?
+    ///
+    /// At least one pixel must pass both the Mask and DQF checks, the maximum scan angle must be
+    /// below the threshold, and the centroid must not be in an exclusion zone.
+    pub fn is_keeper(&self, cluster: &Cluster) -> bool {
+        let keep_pixel_criteria = cluster
+            .pixels()
+            .pixels()
+            .iter()
+            .any(|pixel| self.accepts_pixel(pixel));
+
+        let scan_angle_criteria = cluster.max_scan_angle() < self.max_scan_angle;
+
+        keep_pixel_criteria
+            && scan_angle_criteria
+            && !self.exclusion_zones.contains(cluster.centroid())
+    }
+}
+
+/// A list of circular zones around known persistent heat sources.
+///
+/// A zone file has one zone per line as `lat, lon, radius` with the radius in meters. Blank lines
+/// and anything after a `#` are ignored.
+///
+/// ```text
+/// # Gas flares
+/// 47.95, -103.35, 1500
+/// 48.12, -103.02, 1500  # near Williston
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExclusionZones {
+    zones: Vec<(Coord, f64)>,
+}
+
+impl ExclusionZones {
+    /// Create an empty list of zones.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a zone with a radius in meters.
+    pub fn add(&mut self, center: Coord, radius: f64) {
+        self.zones.push((center, radius));
+    }
+
+    /// Load a zone file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> SatFireResult<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read zones {}: {}", path.display(), err))?;
+
+        Self::parse(&text)
+            .map_err(|err| format!("Invalid zones {}: {}", path.display(), err).into())
+    }
+
+    /// Parse the contents of a zone file.
+    pub fn parse(text: &str) -> SatFireResult<Self> {
+        let mut zones = Self::new();
+
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let vals = line
+                .split(',')
+                .map(|val| val.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| format!("line {}: {}", line_num + 1, err))?;
+
+            match vals[..] {
+                [lat, lon, radius] if radius >= 0.0 => zones.add(Coord { lat, lon }, radius),
+                _ => return Err(format!("line {}: expected lat, lon, radius", line_num + 1).into()),
+            }
+        }
+
+        Ok(zones)
+    }
+
+    /// Is this point inside any of the zones?
+    pub fn contains(&self, coord: Coord) -> bool {
+        self.zones.iter().any(|&(center, radius)| {
+            center.distance_meters_with_ellipsoid(coord, &Ellipsoid::GRS80) <= radius
+        })
+    }
+
+    /// Get the number of zones.
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    /// Are there no zones?
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        pixel::PixelList,
+        testutil::{cluster_from_pixels, PixelGrid},
+    };
+
+    /// The test pixels are all the same 0.02 degree pixel centered on 45 N 120 W.
+    const GRID: PixelGrid = PixelGrid {
+        origin: Coord {
+            lat: 45.01,
+            lon: -120.01,
+        },
+        cell_size: 0.02,
+    };
+
+    fn pixel(mask: i16, dqf: i16) -> Pixel {
+        let mut pixel = GRID.pixel(0, 0, 10.0);
+        pixel.mask_flag = MaskCode(mask);
+        pixel.data_quality_flag = DataQualityFlagCode(dqf);
+        pixel
+    }
+
+    fn cluster(pixels: &[(i16, i16)]) -> Cluster {
+        let mut plist = PixelList::new();
+        for &(mask, dqf) in pixels {
+            plist.push(pixel(mask, dqf));
+        }
+        cluster_from_pixels(plist)
+    }
+
+    #[test]
+    fn test_default_qc_mask_and_dqf() {
+        let qc = ClusterQc::default();
+
+        // (mask, dqf, accepted)
+        let cases = [
+            (10, 0, true),
+            (14, 0, true),
+            (30, 0, true),
+            (34, 0, true),
+            (15, 0, false),
+            (35, 0, false),
+            (10, 1, false),
+            (30, 2, false),
+            (100, 1, false),
+            (200, 2, false),
+        ];
+
+        for (mask, dqf, accepted) in cases {
+            assert_eq!(
+                qc.accepts_pixel(&pixel(mask, dqf)),
+                accepted,
+                "mask {} dqf {}",
+                mask,
+                dqf
+            );
+            assert_eq!(qc.is_keeper(&cluster(&[(mask, dqf)])), accepted);
+        }
+
+        // A low probability pixel is kept along with a more certain one.
+        assert!(qc.is_keeper(&cluster(&[(15, 0), (35, 0), (31, 0)])));
+        assert!(!qc.is_keeper(&cluster(&[(15, 0), (35, 0)])));
+
+        // But the certain one has to have a good DQF too.
+        assert!(!qc.is_keeper(&cluster(&[(15, 0), (31, 3)])));
+    }
+
+    #[test]
+    fn test_adjusted_qc() {
+        let qc = ClusterQc::default()
+            .accept_masks([MaskCode(15)])
+            .reject_masks([MaskCode(30), MaskCode(31)])
+            .accept_dqfs([DataQualityFlagCode(2)]);
+
+        assert!(qc.accepts_pixel(&pixel(15, 0)));
+        assert!(qc.accepts_pixel(&pixel(15, 2)));
+        assert!(!qc.accepts_pixel(&pixel(35, 0)));
+        assert!(!qc.accepts_pixel(&pixel(30, 0)));
+        assert!(!qc.accepts_pixel(&pixel(31, 2)));
+        assert!(qc.accepts_pixel(&pixel(32, 2)));
+        assert!(!qc.accepts_pixel(&pixel(10, 1)));
+
+        let qc = qc.reject_dqfs([DataQualityFlagCode(0)]);
+        assert!(!qc.accepts_pixel(&pixel(10, 0)));
+        assert!(qc.accepts_pixel(&pixel(10, 2)));
+
+        let hot = cluster(&[(10, 2)]);
+        assert!(qc.is_keeper(&hot));
+        assert!(!qc.clone().max_scan_angle(5.0).is_keeper(&hot));
+    }
+
+    #[test]
+    fn test_default_max_scan_angle() {
+        for sat in Satellite::iter() {
+            let max_scan_angle = ClusterQc::default_max_scan_angle(sat);
+            assert!(max_scan_angle < sat.limb_scan_angle());
+            assert!((max_scan_angle - 8.3).abs() < 0.01);
+        }
+
+        let mut plist = PixelList::new();
+        plist.push(Pixel {
+            scan_angle: 8.5,
+            ..pixel(10, 0)
+        });
+        let near_limb = cluster_from_pixels(plist);
+        assert!(ClusterQc::default().is_keeper(&cluster(&[(10, 0)])));
+        assert!(!ClusterQc::default().is_keeper(&near_limb));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_exclusion_zones() {
+        let zones = ExclusionZones::parse(concat!(
+            "# A flare\n",
+            "45.0, -120.0, 2000\n",
+            "\n",
+            "  30.0, -100.0, 500  # a refinery\n",
+        ))
+        .unwrap();
+        assert_eq!(zones.len(), 2);
+
+        assert!(zones.contains(Coord {lat: 45.0, lon: -120.0}));
+        assert!(zones.contains(Coord {lat: 45.01, lon: -120.0}));
+        assert!(!zones.contains(Coord {lat: 45.03, lon: -120.0}));
+        assert!(!zones.contains(Coord {lat: 30.01, lon: -100.0}));
+
+        // The test pixel is centered on the first zone.
+        let flare = cluster(&[(10, 0)]);
+        assert!(ClusterQc::default().is_keeper(&flare));
+        assert!(!ClusterQc::default().exclusion_zones(zones).is_keeper(&flare));
+
+        assert!(ExclusionZones::parse("").unwrap().is_empty());
+        assert!(ExclusionZones::parse("45.0, -120.0").is_err());
+        assert!(ExclusionZones::parse("45.0, -120.0, -1").is_err());
+        assert!(ExclusionZones::parse("45.0, west, 10").is_err());
+    }
+}
@@ -1,10 +1,18 @@
 /*! Contains all the information about satellites. */
 
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
 use strum::{Display, EnumIter};
 
+/// The GRS80 ellipsoid and geostationary altitude used by every GOES-R series satellite.
+///
+/// See the GOES-R Product Definition and User's Guide (PUG), Volume 5, section 4.2.8.
+pub(crate) const GOES_SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+pub(crate) const GOES_SEMI_MINOR_AXIS: f64 = 6_356_752.314_14;
+pub(crate) const GOES_PERSPECTIVE_POINT_HEIGHT: f64 = 35_786_023.0;
+
 /** The GOES satellites this library works with. */
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Satellite {
     /// GOES-16 (formerly GOES-R), or commonly known as GOES East
     G16,
@@ -40,6 +48,45 @@ impl Satellite {
         None
     }
 
+    /// Get the longitude of the nadir point of the satellite's fixed grid projection in degrees.
+    ///
+    /// These are the nominal GOES-East and GOES-West positions used for the
+    /// `longitude_of_projection_origin` in the data files.
+    pub fn longitude_of_projection_origin(&self) -> f64 {
+        use Satellite::*;
+
+        match self {
+            G16 => -75.0,
+            G17 | G18 => -137.0,
+        }
+    }
+
+    /// Get the nominal longitude of the point on the equator directly below the satellite in
+    /// degrees.
+    ///
+    /// This is the operational station of the satellite, the `nominal_satellite_subpoint_lon` in
+    /// the data files. It is close to, but not exactly, the
+    /// [longitude of the projection origin](Satellite::longitude_of_projection_origin).
+    pub fn sub_satellite_longitude(&self) -> f64 {
+        use Satellite::*;
+
+        match self {
+            G16 => -75.2,
+            G17 | G18 => -137.2,
+        }
+    }
+
+    /// Get the scan angle, in degrees from nadir, of the limb of the Earth as seen from the
+    /// satellite.
+    ///
+    /// This is the angle to a line tangent to the equator from the geostationary orbit. It is the
+    /// same for every satellite at the nominal altitude, regardless of the sub-satellite longitude.
+    /// Pixels near this angle are very large and badly distorted.
+    pub fn limb_scan_angle(&self) -> f64 {
+        let orbit_radius = GOES_SEMI_MAJOR_AXIS + GOES_PERSPECTIVE_POINT_HEIGHT;
+        (GOES_SEMI_MAJOR_AXIS / orbit_radius).asin().to_degrees()
+    }
+
     /// Get the date and time (in UTC) that the satellite became operational.
     ///
     /// This is the time that the satellite was officially declared operational after all checkouts
@@ -73,6 +120,7 @@ impl Satellite {
 
 /** The satellite scan sectors this library recognizes. */
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Sector {
     /// This is the full disk sector that includes the full viewable disk of the Earth.
     FULL,
@@ -100,6 +148,50 @@ impl Sector {
         }
     }
 
+    /// The usual time between the starts of consecutive scans of the sector.
+    ///
+    /// These are for ABI scan mode 6, the default since April 2019: 10 minutes for the full disk,
+    /// 5 minutes for the CONUS sector, and 1 minute for each meso-sector. See
+    /// [Sector::nominal_cadence_at] for older scans.
+    pub fn nominal_cadence(&self) -> Duration {
+        use Sector::*;
+
+        match self {
+            FULL => Duration::minutes(10),
+            CONUS => Duration::minutes(5),
+            MESO1 | MESO2 => Duration::minutes(1),
+        }
+    }
+
+    /// The usual time between the starts of consecutive scans of the sector at a given time.
+    ///
+    /// Before every GOES-R series satellite switched to scan mode 6 on April 2, 2019, they used
+    /// scan mode 3 where the full disk was scanned every 15 minutes. The CONUS and meso-sector
+    /// cadences are the same in both modes. The switch happened on the same day for all of the
+    /// satellites, so this only depends on the time.
+    pub fn nominal_cadence_at(&self, scan_start: DateTime<Utc>) -> Duration {
+        if self.is_full_disk() && scan_start < scan_mode_6_start() {
+            Duration::minutes(15)
+        } else {
+            self.nominal_cadence()
+        }
+    }
+
+    /// Is this the full disk sector?
+    pub fn is_full_disk(&self) -> bool {
+        matches!(self, Sector::FULL)
+    }
+
+    /// Is this the CONUS sector?
+    pub fn is_conus(&self) -> bool {
+        matches!(self, Sector::CONUS)
+    }
+
+    /// Is this one of the meso-sectors?
+    pub fn is_meso(&self) -> bool {
+        matches!(self, Sector::MESO1 | Sector::MESO2)
+    }
+
     /// Scan the string for the occurrence of a sector name and return first one found.
     ///
     /// Note that in some cases either of the meso-sectors can be represented by "FDCM", such as in
@@ -124,9 +216,25 @@ impl Sector {
     }
 }
 
+/// The start of the day that ABI scan mode 6 became the default.
+fn scan_mode_6_start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2019, 4, 2, 0, 0, 0).unwrap()
+}
+
 /// Parse the satellite, sector, scan start time, and scan end time from a file name
 pub fn parse_satellite_description_from_file_name(
     fname: &str,
+) -> Option<(Satellite, Sector, DateTime<Utc>, DateTime<Utc>)> {
+    parse_satellite_description_from_file_name_with_offsets(fname, &SatelliteTimeOffsets::new())
+}
+
+/// Parse the satellite, sector, scan start time, and scan end time from a file name, and correct
+/// the times with an offset table.
+///
+/// See [parse_satellite_description_from_file_name] and [SatelliteTimeOffsets].
+pub fn parse_satellite_description_from_file_name_with_offsets(
+    fname: &str,
+    offsets: &SatelliteTimeOffsets,
 ) -> Option<(Satellite, Sector, DateTime<Utc>, DateTime<Utc>)> {
     let sat = Satellite::string_contains_satellite(fname)?;
     let sector = Sector::string_contains_sector(fname)?;
@@ -134,11 +242,92 @@ pub fn parse_satellite_description_from_file_name(
     let start_time = crate::start_time_from_file_name(fname)?;
     let end_time = crate::end_time_from_file_name(fname)?;
 
-    Some((sat, sector, start_time, end_time))
+    Some((
+        sat,
+        sector,
+        offsets.apply(sat, start_time),
+        offsets.apply(sat, end_time),
+    ))
+}
+
+/// A table of clock corrections for the times in the file names of each satellite.
+///
+/// If a satellite's published timestamps are known to be off, for instance during an anomaly,
+/// the correction is added to the parsed times so its scans line up with the other satellites.
+/// A correction can apply to all the scans from a satellite or only to the scans that start
+/// during a period. If more than one correction applies to a time, they are all added together.
+#[derive(Debug, Clone, Default)]
+pub struct SatelliteTimeOffsets {
+    offsets: Vec<TimeOffset>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimeOffset {
+    sat: Satellite,
+    /// The period the correction applies to, or `None` for all the time.
+    period: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    offset: Duration,
+}
+
+impl SatelliteTimeOffsets {
+    /// Create an empty table, it doesn't change any times.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a correction to all the times from a satellite.
+    pub fn add(&mut self, sat: Satellite, offset: Duration) {
+        self.offsets.push(TimeOffset {
+            sat,
+            period: None,
+            offset,
+        });
+    }
+
+    /// Add a correction to the times from a satellite from `start` up to, but not including,
+    /// `end`.
+    pub fn add_for_period(
+        &mut self,
+        sat: Satellite,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        offset: Duration,
+    ) {
+        self.offsets.push(TimeOffset {
+            sat,
+            period: Some((start, end)),
+            offset,
+        });
+    }
+
+    /// Get the total correction for a time from a satellite.
+    ///
+    /// Periods are matched against the uncorrected time.
+    pub fn offset(&self, sat: Satellite, time: DateTime<Utc>) -> Duration {
+        self.offsets
+            .iter()
+            .filter(|entry| entry.sat == sat)
+            .filter(|entry| match entry.period {
+                Some((start, end)) => time >= start && time < end,
+                None => true,
+            })
+            .fold(Duration::zero(), |total, entry| total + entry.offset)
+    }
+
+    /// Apply the correction to a time from a satellite.
+    pub fn apply(&self, sat: Satellite, time: DateTime<Utc>) -> DateTime<Utc> {
+        time + self.offset(sat, time)
+    }
+
+    /// Are there no corrections in the table?
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
 }
 
 /// Represents a code from the Mask field of the NetCDF files.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MaskCode(pub i16);
 
 impl MaskCode {
@@ -200,6 +389,94 @@ impl MaskCode {
             _ => "unknown code",
         }
     }
+
+    /// Get the broad category of a fire pixel mask code.
+    ///
+    /// Temporally filtered detections are in the same category as the unfiltered ones.
+    pub fn category(self) -> MaskCategory {
+        match self.0 {
+            10 | 30 => MaskCategory::Good,
+            11 | 31 => MaskCategory::Saturated,
+            12 | 32 => MaskCategory::CloudContaminated,
+            13 | 33 => MaskCategory::HighProbability,
+            14 | 34 => MaskCategory::MediumProbability,
+            15 | 35 => MaskCategory::LowProbability,
+            _ => MaskCategory::Other,
+        }
+    }
+}
+
+/// The broad categories of fire pixel [MaskCode]s, used for quality control summaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
+pub enum MaskCategory {
+    /// A good quality fire pixel.
+    Good,
+    /// A saturated fire pixel.
+    Saturated,
+    /// A cloud contaminated fire pixel.
+    CloudContaminated,
+    /// A high probability fire pixel.
+    HighProbability,
+    /// A medium probability fire pixel.
+    MediumProbability,
+    /// A low probability fire pixel.
+    LowProbability,
+    /// Any other mask code, these aren't expected in a cluster.
+    Other,
+}
+
+impl MaskCategory {
+    /// The number of categories.
+    pub const COUNT: usize = 7;
+
+    /// The name of the column in the clusters table that stores the number of pixels in this
+    /// category.
+    pub(crate) fn column_name(self) -> &'static str {
+        match self {
+            MaskCategory::Good => "num_good_pixels",
+            MaskCategory::Saturated => "num_saturated_pixels",
+            MaskCategory::CloudContaminated => "num_cloud_pixels",
+            MaskCategory::HighProbability => "num_high_probability_pixels",
+            MaskCategory::MediumProbability => "num_medium_probability_pixels",
+            MaskCategory::LowProbability => "num_low_probability_pixels",
+            MaskCategory::Other => "num_other_pixels",
+        }
+    }
+}
+
+/// A histogram of the number of pixels in each [MaskCategory].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaskCategoryCounts {
+    counts: [u64; MaskCategory::COUNT],
+}
+
+impl MaskCategoryCounts {
+    /// Create a histogram with no pixels in it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add pixels to a category.
+    pub fn add(&mut self, category: MaskCategory, num_pixels: u64) {
+        self.counts[category as usize] += num_pixels;
+    }
+
+    /// Add all the counts from another histogram to this one.
+    pub fn merge(&mut self, other: &MaskCategoryCounts) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+    }
+
+    /// Get the number of pixels in a category.
+    pub fn get(&self, category: MaskCategory) -> u64 {
+        self.counts[category as usize]
+    }
+
+    /// Get the number of pixels in all the categories.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
 }
 
 /// Represents a code from the DQF (Data Quality Flag) field of the NetCDF file.
@@ -210,6 +487,7 @@ impl MaskCode {
 ///
 /// [doc_url]: (https://www.goes-r.gov/products/docs/PUG-L2+-vol5.pdf)
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataQualityFlagCode(pub i16);
 
 impl DataQualityFlagCode {
@@ -227,3 +505,51 @@ impl DataQualityFlagCode {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_satellite_geometry() {
+        for sat in Satellite::iter() {
+            // The documented limb of the Earth is at about 8.7 degrees.
+            assert!((sat.limb_scan_angle() - 8.7).abs() < 0.01);
+
+            let drift = sat.sub_satellite_longitude() - sat.longitude_of_projection_origin();
+            assert!(drift.abs() < 0.5);
+        }
+
+        assert_eq!(Satellite::G16.sub_satellite_longitude(), -75.2);
+        assert_eq!(Satellite::G18.sub_satellite_longitude(), -137.2);
+    }
+
+    #[test]
+    fn test_sector_cadence_and_classification() {
+        let mode_3: DateTime<Utc> = "2018-08-01T12:00:00Z".parse().unwrap();
+        let mode_6: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+
+        for sector in Sector::iter() {
+            let (cadence, old_cadence, full, conus, meso) = match sector {
+                Sector::FULL => (10, 15, true, false, false),
+                Sector::CONUS => (5, 5, false, true, false),
+                Sector::MESO1 | Sector::MESO2 => (1, 1, false, false, true),
+            };
+
+            assert_eq!(sector.nominal_cadence(), Duration::minutes(cadence));
+            assert_eq!(
+                sector.nominal_cadence_at(mode_6),
+                Duration::minutes(cadence)
+            );
+            assert_eq!(
+                sector.nominal_cadence_at(mode_3),
+                Duration::minutes(old_cadence)
+            );
+
+            assert_eq!(sector.is_full_disk(), full);
+            assert_eq!(sector.is_conus(), conus);
+            assert_eq!(sector.is_meso(), meso);
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Synthetic data generators for tests and benchmarks.
+//!
+//! This module is only available with the `testutil` feature enabled, or in this crate's own unit
+//! tests. Nothing here needs libnetcdf or real satellite data. All the random generators take a
+//! [SyntheticRng] so the data is reproducible from a seed.
+
+use crate::{
+    cluster::{clusters_from_fire_points, Cluster, ClusterList, Connectivity},
+    fire::{Fire, FireList},
+    firesatimage::FirePoint,
+    geo::{BoundingBox, Coord},
+    pixel::{Pixel, PixelList},
+    satellite::{DataQualityFlagCode, MaskCode, Satellite, Sector},
+};
+use chrono::{DateTime, Duration, Utc};
+
+/// A small and fast pseudo random number generator (SplitMix64).
+///
+/// This is NOT suitable for anything but generating test data.
+#[derive(Debug, Clone)]
+pub struct SyntheticRng(u64);
+
+impl SyntheticRng {
+    /// Create a generator, the same seed always produces the same sequence.
+    pub fn new(seed: u64) -> Self {
+        SyntheticRng(seed)
+    }
+
+    /// Get the next value in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a value uniformly distributed from 0 up to, but not including, 1.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Get a value uniformly distributed from `min` up to, but not including, `max`.
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}
+
+/// A regular latitude-longitude grid of square pixels, like a (very) simplified satellite image.
+///
+/// Columns increase to the east and rows increase to the south, the same as in a satellite image.
+#[derive(Debug, Clone, Copy)]
+pub struct PixelGrid {
+    /// The upper left (northwest) corner of the pixel in column 0 and row 0.
+    pub origin: Coord,
+    /// The width and height of a pixel in degrees.
+    pub cell_size: f64,
+}
+
+impl PixelGrid {
+    /// Create a new grid.
+    pub fn new(origin: Coord, cell_size: f64) -> Self {
+        PixelGrid { origin, cell_size }
+    }
+
+    /// Create the pixel at this column and row with a good quality fire detection.
+    ///
+    /// The temperature and area are fixed, only the power varies.
+    pub fn pixel(&self, col: isize, row: isize, power: f64) -> Pixel {
+        let top = self.origin.lat - row as f64 * self.cell_size;
+        let bottom = top - self.cell_size;
+        let left = self.origin.lon + col as f64 * self.cell_size;
+        let right = left + self.cell_size;
+
+        pixel_with_corners(
+            Coord {
+                lat: top,
+                lon: left,
+            },
+            Coord {
+                lat: bottom,
+                lon: left,
+            },
+            Coord {
+                lat: bottom,
+                lon: right,
+            },
+            Coord {
+                lat: top,
+                lon: right,
+            },
+            power,
+        )
+    }
+
+    /// Create a rectangular block of pixels `width` columns wide and `height` rows tall with its
+    /// upper left pixel at `col` and `row`.
+    ///
+    /// Blocks on the same grid overlap if they share any cells, and are adjacent if they touch.
+    pub fn block(
+        &self,
+        col: isize,
+        row: isize,
+        width: usize,
+        height: usize,
+        power: f64,
+    ) -> PixelList {
+        let mut pixels = PixelList::new();
+        for r in row..(row + height as isize) {
+            for c in col..(col + width as isize) {
+                pixels.push(self.pixel(c, r, power));
+            }
+        }
+        pixels
+    }
+}
+
+/// Create a pixel with these upper left, lower left, lower right, and upper right corners and a
+/// good quality fire detection.
+///
+/// This is for pixels that don't line up on a [PixelGrid], like skewed or partially overlapping
+/// pixels. The other properties are the same as from [PixelGrid::pixel].
+pub fn pixel_with_corners(ul: Coord, ll: Coord, lr: Coord, ur: Coord, power: f64) -> Pixel {
+    Pixel {
+        ul,
+        ll,
+        lr,
+        ur,
+        power,
+        area: 1_000.0,
+        temperature: 600.0,
+        scan_angle: 5.0,
+        mask_flag: MaskCode(10),
+        data_quality_flag: DataQualityFlagCode(0),
+        acquisition_offset: 0.0,
+    }
+}
+
+/// Assemble a Cluster from pixels, calculating the aggregate properties the same way clustering
+/// a file does.
+pub fn cluster_from_pixels(pixels: PixelList) -> Cluster {
+    let mut power = 0.0;
+    let mut area = 0.0;
+    let mut max_temp = 0.0f64;
+    let mut max_scan_angle = 0.0f64;
+
+    for pixel in pixels.pixels() {
+        if pixel.power.is_finite() {
+            power += pixel.power;
+        }
+        if pixel.area.is_finite() {
+            area += pixel.area;
+        }
+        if pixel.temperature.is_finite() {
+            max_temp = max_temp.max(pixel.temperature);
+        }
+        max_scan_angle = max_scan_angle.max(pixel.scan_angle);
+    }
+
+    Cluster::new(power, area, max_temp, max_scan_angle, pixels)
+}
+
+/// Assemble a ClusterList for a single scan.
+pub fn cluster_list(
+    satellite: Satellite,
+    sector: Sector,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    clusters: Vec<Cluster>,
+) -> ClusterList {
+    ClusterList::new(satellite, sector, start, end, clusters)
+}
+
+/// Assemble a Fire from pixels that was observed from `first_observed` to `last_observed`.
+///
+/// Together with [PixelGrid::block] this allows creating fires with a controlled overlap and time
+/// span.
+pub fn fire_from_pixels(
+    id: u64,
+    pixels: PixelList,
+    satellite: Satellite,
+    first_observed: DateTime<Utc>,
+    last_observed: DateTime<Utc>,
+) -> Fire {
+    let max_power = pixels
+        .pixels()
+        .iter()
+        .map(|p| p.power)
+        .filter(|p| p.is_finite())
+        .fold(0.0, f64::max);
+    let max_temperature = pixels
+        .pixels()
+        .iter()
+        .map(|p| p.temperature)
+        .filter(|t| t.is_finite())
+        .fold(0.0, f64::max);
+
+    Fire::new(
+        first_observed,
+        last_observed,
+        max_power,
+        max_temperature,
+        id,
+        pixels,
+        satellite,
+        0,
+    )
+}
+
+/// The fire detections from a synthetic scan.
+#[derive(Debug, Clone)]
+pub struct SyntheticScan {
+    points: Vec<FirePoint>,
+}
+
+impl SyntheticScan {
+    /// Randomly place fire pixels in an image with `cols` columns and `rows` rows.
+    ///
+    /// The `density` is the fraction, from 0 to 1, of the pixels in the image that are fire
+    /// detections. Each fire pixel has a power from 1 to 100 MW.
+    pub fn random(
+        grid: PixelGrid,
+        cols: usize,
+        rows: usize,
+        density: f64,
+        rng: &mut SyntheticRng,
+    ) -> Self {
+        let num_cells = cols * rows;
+        let num_points = (num_cells as f64 * density.clamp(0.0, 1.0)).round() as usize;
+
+        let mut cells: Vec<usize> = (0..num_points)
+            .map(|_| (rng.next_u64() % num_cells.max(1) as u64) as usize)
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+
+        let points = cells
+            .into_iter()
+            .map(|cell| {
+                let x = (cell % cols) as isize;
+                let y = (cell / cols) as isize;
+                FirePoint {
+                    pixel: grid.pixel(x, y, rng.range(1.0, 100.0)),
+                    x,
+                    y,
+                }
+            })
+            .collect();
+
+        SyntheticScan { points }
+    }
+
+    /// Get the number of fire pixels in the scan.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Are there no fire pixels in this scan?
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Group the fire pixels into clusters, the same way [ClusterList::from_file] does.
+    pub fn into_cluster_list(
+        self,
+        satellite: Satellite,
+        sector: Sector,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        connectivity: Connectivity,
+    ) -> ClusterList {
+        let clusters = clusters_from_fire_points(self.points, connectivity);
+        ClusterList::new(satellite, sector, start, end, clusters)
+    }
+}
+
+/// Randomly place `num_fires` square fires in a region.
+///
+/// Each fire is `size` by `size` pixels, on a grid with pixels `cell_size` degrees wide, and has
+/// a fire id from 1 up to `num_fires`. The `overlap` is the fraction, from 0 to 1, of the fires
+/// that are deliberately placed overlapping the fire created just before them, so at least that
+/// many mergers are possible. The other fires may also overlap by chance, which is more likely as
+/// more fires are crowded into a smaller region. All the fires were observed for one hour starting
+/// at `start`.
+pub fn random_fires(
+    region: BoundingBox,
+    num_fires: usize,
+    size: usize,
+    cell_size: f64,
+    overlap: f64,
+    start: DateTime<Utc>,
+    rng: &mut SyntheticRng,
+) -> FireList {
+    let fire_width = size as f64 * cell_size;
+    let mut fires = FireList::new();
+    let mut prev_origin: Option<Coord> = None;
+
+    for id in 1..=num_fires as u64 {
+        let origin = match prev_origin {
+            Some(prev) if rng.next_f64() < overlap => Coord {
+                lat: prev.lat - fire_width / 2.0,
+                lon: prev.lon + fire_width / 2.0,
+            },
+            _ => Coord {
+                lat: rng.range(region.ll.lat + fire_width, region.ur.lat),
+                lon: rng.range(region.ll.lon, region.ur.lon - fire_width),
+            },
+        };
+        prev_origin = Some(origin);
+
+        let grid = PixelGrid::new(origin, cell_size);
+        let mut pixels = PixelList::new();
+        for row in 0..size as isize {
+            for col in 0..size as isize {
+                pixels.push(grid.pixel(col, row, rng.range(1.0, 100.0)));
+            }
+        }
+
+        fires.add_fire(fire_from_pixels(
+            id,
+            pixels,
+            Satellite::G17,
+            start,
+            start + Duration::hours(1),
+        ));
+    }
+
+    fires
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_generators() {
+        let mut rng = SyntheticRng::new(42);
+        let mut rng2 = SyntheticRng::new(42);
+        for _ in 0..100 {
+            let val = rng.next_f64();
+            assert!((0.0..1.0).contains(&val));
+            assert_eq!(val, rng2.next_f64());
+        }
+
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 50.0,
+                lon: -125.0,
+            },
+            0.02,
+        );
+        let scan = SyntheticScan::random(grid, 1_000, 500, 0.01, &mut rng);
+        // Some cells might be picked twice.
+        assert!(scan.len() <= 5_000 && scan.len() > 4_900);
+
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let clist = scan.into_cluster_list(
+            Satellite::G17,
+            Sector::CONUS,
+            start,
+            start + Duration::minutes(5),
+            Connectivity::Eight,
+        );
+        let num_pixels: usize = clist.clusters().iter().map(|c| c.pixel_count()).sum();
+        assert!(num_pixels > 4_900);
+        assert!(clist.len() < num_pixels);
+
+        let region = BoundingBox {
+            ll: Coord {
+                lat: 30.0,
+                lon: -125.0,
+            },
+            ur: Coord {
+                lat: 50.0,
+                lon: -100.0,
+            },
+        };
+        let mut fires = random_fires(region, 100, 3, 0.02, 0.5, start, &mut rng);
+        assert_eq!(fires.len(), 100);
+
+        let mut merged_away = FireList::new();
+        let num_merged = fires.merge_fires(&mut merged_away);
+        assert!(num_merged >= 30);
+        assert_eq!(fires.len() + merged_away.len(), 100);
+
+        let mut fires = random_fires(region, 100, 3, 0.02, 0.0, start, &mut rng);
+        assert!(fires.merge_fires(&mut FireList::new()) < 5);
+    }
+
+    #[test]
+    fn test_cluster_list_database_round_trip() {
+        use crate::{ClusterDatabase, Geo};
+
+        let grid = PixelGrid::new(
+            Coord {
+                lat: 45.0,
+                lon: -120.0,
+            },
+            0.02,
+        );
+        let start: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let end = start + Duration::minutes(5);
+
+        let clusters = vec![
+            cluster_from_pixels(grid.block(0, 0, 2, 2, 10.0)),
+            cluster_from_pixels(grid.block(10, 5, 3, 1, 25.0)),
+            cluster_from_pixels(grid.block(40, 40, 1, 1, 5.0)),
+        ];
+        let clist = cluster_list(Satellite::G17, Sector::CONUS, start, end, clusters.clone());
+
+        let db = ClusterDatabase::connect(":memory:").unwrap();
+        db.prepare_to_add_clusters().unwrap().add(clist).unwrap();
+
+        let region = BoundingBox {
+            ll: Coord {
+                lat: 40.0,
+                lon: -125.0,
+            },
+            ur: Coord {
+                lat: 50.0,
+                lon: -115.0,
+            },
+        };
+        let mut query = db
+            .query_clusters(
+                Some(Satellite::G17),
+                Some(Sector::CONUS),
+                start,
+                end,
+                region,
+            )
+            .unwrap();
+        let mut rows: Vec<_> = query.rows().unwrap().map(Result::unwrap).collect();
+        rows.sort_by(|a, b| a.power.total_cmp(&b.power));
+        assert_eq!(rows.len(), 3);
+
+        let mut expected = clusters;
+        expected.sort_by(|a, b| a.total_power().total_cmp(&b.total_power()));
+
+        for (row, cluster) in rows.iter().zip(expected.iter()) {
+            assert_eq!(row.start, start);
+            assert_eq!(row.end, end);
+            assert_eq!(row.power, cluster.total_power());
+            assert_eq!(row.area, cluster.total_area());
+            assert_eq!(row.max_temperature, cluster.max_temperature());
+            assert_eq!(row.pixels.len(), cluster.pixel_count());
+            assert!(row.centroid.is_close(cluster.centroid(), 1.0e-9));
+            for (p1, p2) in row.pixels.pixels().iter().zip(cluster.pixels().pixels()) {
+                assert!(p1.approx_equal(p2, 1.0e-12));
+            }
+        }
+
+        // Overlapping and adjacent blocks make fires that merge, time spans are as given.
+        let first = fire_from_pixels(
+            1,
+            grid.block(0, 0, 3, 3, 10.0),
+            Satellite::G17,
+            start,
+            start + Duration::hours(2),
+        );
+        assert_eq!(first.duration(), Duration::hours(2));
+
+        let mut fires = FireList::new();
+        fires.add_fire(first);
+        fires.add_fire(fire_from_pixels(
+            2,
+            grid.block(2, 2, 3, 3, 20.0),
+            Satellite::G17,
+            start,
+            end,
+        ));
+        fires.add_fire(fire_from_pixels(
+            3,
+            grid.block(5, 4, 1, 1, 30.0),
+            Satellite::G17,
+            start,
+            end,
+        ));
+        fires.add_fire(fire_from_pixels(
+            4,
+            grid.block(20, 20, 1, 1, 40.0),
+            Satellite::G17,
+            start,
+            end,
+        ));
+        assert_eq!(fires.merge_fires(&mut FireList::new()), 2);
+        assert_eq!(fires.len(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! The time zone used when showing times to people.
+//!
+//! Times are always stored and passed around in UTC. Reports meant for people, such as the
+//! descriptions in KML files, can show them in an [OutputTimeZone] instead, which defaults to UTC.
+//! The zone is passed explicitly to the code doing the formatting, see [OutputTime]. Named zones
+//! from the IANA time zone database follow daylight saving time.
+
+use crate::SatFireResult;
+use chrono::{DateTime, FixedOffset, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt::{self, Display};
+
+/// A time zone for showing times, either a named zone or a fixed offset from UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputTimeZone {
+    /// A zone from the IANA time zone database, like "America/Denver".
+    Named(Tz),
+    /// A fixed offset from UTC that never changes.
+    Fixed(FixedOffset),
+}
+
+impl OutputTimeZone {
+    /// The offset from UTC in this zone at `time`.
+    pub fn offset_at(&self, time: DateTime<Utc>) -> FixedOffset {
+        match self {
+            OutputTimeZone::Named(tz) => tz.offset_from_utc_datetime(&time.naive_utc()).fix(),
+            OutputTimeZone::Fixed(offset) => *offset,
+        }
+    }
+}
+
+impl Default for OutputTimeZone {
+    fn default() -> Self {
+        OutputTimeZone::Fixed(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+impl From<Tz> for OutputTimeZone {
+    fn from(tz: Tz) -> Self {
+        OutputTimeZone::Named(tz)
+    }
+}
+
+impl From<FixedOffset> for OutputTimeZone {
+    fn from(offset: FixedOffset) -> Self {
+        OutputTimeZone::Fixed(offset)
+    }
+}
+
+impl Display for OutputTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            OutputTimeZone::Named(tz) => write!(f, "{}", tz.name()),
+            OutputTimeZone::Fixed(offset) if offset.local_minus_utc() == 0 => write!(f, "UTC"),
+            OutputTimeZone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+/// Parse a time zone name like "America/Denver", or an offset accepted by [parse_utc_offset].
+pub fn parse_time_zone(zone_str: &str) -> SatFireResult<OutputTimeZone> {
+    if let Ok(offset) = parse_utc_offset(zone_str) {
+        return Ok(OutputTimeZone::Fixed(offset));
+    }
+
+    let zone_str = zone_str.trim();
+    zone_str
+        .parse::<Tz>()
+        .map(OutputTimeZone::Named)
+        .map_err(|_| format!("Invalid time zone: {}", zone_str).into())
+}
+
+/// Parse an offset from UTC like "-06", "+05:30", or "UTC".
+pub fn parse_utc_offset(offset_str: &str) -> SatFireResult<FixedOffset> {
+    let offset_str = offset_str.trim();
+    if offset_str.eq_ignore_ascii_case("utc") || offset_str.eq_ignore_ascii_case("z") {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+
+    let (sign, rest) = match offset_str.as_bytes().first() {
+        Some(b'+') => (1, &offset_str[1..]),
+        Some(b'-') => (-1, &offset_str[1..]),
+        _ => (1, offset_str),
+    };
+
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+
+    // Only plain digits, parse would accept another sign like "+-6".
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_number(hours) || !is_number(minutes) {
+        return Err(format!("Invalid UTC offset: {}", offset_str).into());
+    }
+    let (hours, minutes) = (hours.parse::<i32>()?, minutes.parse::<i32>()?);
+
+    if !(0..60).contains(&minutes) {
+        return Err(format!("Invalid minutes in UTC offset: {}", offset_str).into());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .ok_or_else(|| format!("UTC offset out of range: {}", offset_str).into())
+}
+
+/// A UTC time that is displayed in a time zone.
+///
+/// In UTC this displays the same as a `DateTime<Utc>`, otherwise the local time is followed by
+/// the offset from UTC in effect at that time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputTime(pub DateTime<Utc>, pub OutputTimeZone);
+
+impl Display for OutputTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let OutputTime(time, zone) = self;
+        let offset = zone.offset_at(*time);
+        if offset.local_minus_utc() == 0 {
+            write!(f, "{}", time)
+        } else {
+            write!(f, "{}", time.with_timezone(&offset))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("UTC").unwrap().local_minus_utc(), 0);
+        assert_eq!(
+            parse_utc_offset("-06").unwrap().local_minus_utc(),
+            -6 * 3600
+        );
+        assert_eq!(parse_utc_offset("7").unwrap().local_minus_utc(), 7 * 3600);
+        assert_eq!(
+            parse_utc_offset("+05:30").unwrap().local_minus_utc(),
+            5 * 3600 + 30 * 60
+        );
+
+        assert!(parse_utc_offset("-06:75").is_err());
+        assert!(parse_utc_offset("+25").is_err());
+        assert!(parse_utc_offset("MDT").is_err());
+        assert!(parse_utc_offset("+-6").is_err());
+        assert!(parse_utc_offset("-+06:00").is_err());
+        assert!(parse_utc_offset("+06:-30").is_err());
+        assert!(parse_utc_offset("+").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_zone() {
+        assert_eq!(
+            parse_time_zone("America/Denver").unwrap(),
+            OutputTimeZone::Named(Tz::America__Denver)
+        );
+        assert_eq!(
+            parse_time_zone("-06").unwrap(),
+            OutputTimeZone::Fixed(FixedOffset::west_opt(6 * 3600).unwrap())
+        );
+        assert_eq!(parse_time_zone("UTC").unwrap(), OutputTimeZone::default());
+
+        assert!(parse_time_zone("America/Nowhere").is_err());
+        assert!(parse_time_zone("+25").is_err());
+
+        assert_eq!(
+            parse_time_zone("America/Denver").unwrap().to_string(),
+            "America/Denver"
+        );
+        assert_eq!(parse_time_zone("-06").unwrap().to_string(), "-06:00");
+        assert_eq!(OutputTimeZone::default().to_string(), "UTC");
+    }
+
+    #[test]
+    fn test_output_time() {
+        let summer: DateTime<Utc> = "2021-08-01T12:00:00Z".parse().unwrap();
+        let winter: DateTime<Utc> = "2021-12-01T12:00:00Z".parse().unwrap();
+        let mountain = parse_time_zone("-06").unwrap();
+        let denver = parse_time_zone("America/Denver").unwrap();
+        let utc = OutputTimeZone::default();
+
+        assert_eq!(
+            OutputTime(summer, mountain).to_string(),
+            "2021-08-01 06:00:00 -06:00"
+        );
+        assert_eq!(
+            OutputTime(winter, mountain).to_string(),
+            "2021-12-01 06:00:00 -06:00"
+        );
+        assert_eq!(
+            OutputTime(summer, utc).to_string(),
+            "2021-08-01 12:00:00 UTC"
+        );
+
+        // Named zones follow daylight saving time.
+        assert_eq!(
+            OutputTime(summer, denver).to_string(),
+            "2021-08-01 06:00:00 -06:00"
+        );
+        assert_eq!(
+            OutputTime(winter, denver).to_string(),
+            "2021-12-01 05:00:00 -07:00"
+        );
+    }
+}